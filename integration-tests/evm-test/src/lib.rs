@@ -1,52 +1,459 @@
 use ethers::{
+    abi::Detokenize,
+    contract::{abigen, ContractError, EthError, FunctionCall},
     core::k256::ecdsa::SigningKey,
     middleware::SignerMiddleware,
     providers::{Http, Middleware, Provider},
     signers::{LocalWallet, Signer},
-    types::{Address, U256, U64},
-    utils::{hex, Anvil},
+    types::{transaction::eip2718::TypedTransaction, Address, BlockNumber, U256, U64},
+    utils::{hex, Anvil, AnvilInstance},
 };
-use stark_evm_adapter::{
-    annotated_proof::AnnotatedProof,
-    annotation_parser::{split_fri_merkle_statements, SplitProofs},
-    ContractFunctionCall,
+use futures::{Future, StreamExt};
+use madara_prover_rpc_server::evm_adapter::split_annotated_proof;
+use madara_prover_rpc_server::facts::{
+    output_segment_values, parse_bootloader_output, FactError, FactTopology, TaskOutput,
+    TASK_OUTPUT_HEADER_SIZE,
 };
-use std::{convert::TryFrom, fs, path::PathBuf, str::FromStr, sync::Arc};
+use madara_prover_rpc_server::public_input::public_memory_pages;
+use serde::Serialize;
+use stark_evm_adapter::annotation_parser::SplitProofs;
+use std::{convert::TryFrom, fs, panic::AssertUnwindSafe, path::PathBuf, str::FromStr, sync::Arc};
+use stone_prover_sdk::models::PublicInput;
+
+abigen!(
+    MemoryPageFactRegistry,
+    r#"[
+        function registerContinuousMemoryPage(uint256 startAddr, uint256[] values, uint256 z, uint256 alpha, uint256 prime) external returns (bytes32, uint256, uint256)
+    ]"#
+);
+
+type EthSigner = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+/// Default number of statement transactions waiting to be mined at the same time.
+pub const DEFAULT_STATEMENT_PARALLELISM: usize = 8;
+
+/// Prime of the Cairo field, used by the memory page fact registry.
+const CAIRO_PRIME: &str = "800000000000011000000000000000000000000000000000000000000000001";
+
+fn parse_hex_u256(value: &str) -> Option<U256> {
+    U256::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+/// Addresses of the SHARP verifier contracts used to verify split proofs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifierContracts {
+    /// Merkle statement contract, verifies trace decommitments.
+    pub merkle: Address,
+    /// FRI statement contract, verifies FRI decommitments.
+    pub fri: Address,
+    /// GPS statement verifier, verifies the main proof.
+    pub gps: Address,
+    /// Memory page fact registry, registers the continuous memory pages of the main proof.
+    /// Only required if the proof has continuous memory pages.
+    pub memory_page_registry: Option<Address>,
+}
+
+impl VerifierContracts {
+    /// SHARP verifier contracts on Ethereum mainnet.
+    pub fn mainnet() -> Self {
+        Self::from_addresses(
+            "0x5899Efea757E0Dbd6d114b3375C23D7540f65fa4",
+            "0x3E6118DA317f7A433031F03bB71ab870d87dd2DD",
+            "0x47312450B3Ac8b5b8e247a6bB6d523e7605bDb60",
+            Some("0xFD14567eaf9ba941cB8c8a94eEC14831ca7fD1b4"),
+        )
+    }
+
+    /// SHARP verifier contracts on Sepolia.
+    pub fn sepolia() -> Self {
+        Self::from_addresses(
+            "0x5d07afFAfc8721Ef3dEe4D11A2D1484CBf6A9dDf",
+            "0x55d049b4C82807808E76e61a08C6764bbf2ffB55",
+            "0x07ec0D28e50322Eb0C159B9090ecF3aeA8346DFe",
+            None,
+        )
+    }
+
+    fn from_addresses(
+        merkle: &str,
+        fri: &str,
+        gps: &str,
+        memory_page_registry: Option<&str>,
+    ) -> Self {
+        Self {
+            merkle: Address::from_str(merkle).unwrap(),
+            fri: Address::from_str(fri).unwrap(),
+            gps: Address::from_str(gps).unwrap(),
+            memory_page_registry: memory_page_registry
+                .map(|address| Address::from_str(address).unwrap()),
+        }
+    }
+}
+
+impl Default for VerifierContracts {
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+/// Gas used by each verification transaction.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct VerificationReport {
+    /// Name and gas used of each transaction, in the order they were sent.
+    pub items: Vec<(String, u64)>,
+    pub total_gas: u64,
+}
+
+impl VerificationReport {
+    pub fn add(&mut self, name: String, gas_used: u64) {
+        self.items.push((name, gas_used));
+        self.total_gas += gas_used;
+    }
+}
+
+impl std::fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name_width = self
+            .items
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["Total".len()])
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f, "{:<name_width$}  {:>12}", "Call", "Gas used")?;
+        for (name, gas_used) in &self.items {
+            writeln!(f, "{name:<name_width$}  {gas_used:>12}")?;
+        }
+        write!(f, "{:<name_width$}  {:>12}", "Total", self.total_gas)
+    }
+}
+
+/// Public data of a bootloader proof, required to verify its main proof on L1.
+#[derive(Clone, Debug)]
+pub struct BootloaderPublicData {
+    pub public_input: PublicInput,
+    /// Fact topology of each task, in the order of the tasks.
+    pub fact_topologies: Vec<FactTopology>,
+    /// Output of each task, in the order of the tasks.
+    pub task_outputs: Vec<TaskOutput>,
+}
+
+impl BootloaderPublicData {
+    /// Reads the output of the tasks from the output segment of the public input, for proofs
+    /// whose task outputs were not returned by the prover.
+    pub fn from_public_input(
+        public_input: PublicInput,
+        fact_topologies: Vec<FactTopology>,
+    ) -> Result<Self, FactError> {
+        let bootloader_output = output_segment_values(&public_input)?;
+        let task_outputs = parse_bootloader_output(&bootloader_output)?;
+
+        Ok(Self {
+            public_input,
+            fact_topologies,
+            task_outputs,
+        })
+    }
+}
+
+/// Builds the task metadata expected by the GPS statement verifier:
+/// `[n_tasks, (output_size, program_hash, n_tree_pairs, tree_structure...)*]`.
+///
+/// The output size of a task includes its header (size and program hash), as in the
+/// bootloader output.
+pub fn build_task_metadata(
+    fact_topologies: &[FactTopology],
+    task_outputs: &[TaskOutput],
+) -> Result<Vec<U256>, FactError> {
+    if task_outputs.len() != fact_topologies.len() {
+        return Err(FactError::TopologyCountMismatch {
+            expected: task_outputs.len(),
+            actual: fact_topologies.len(),
+        });
+    }
+
+    let mut task_metadata = vec![U256::from(task_outputs.len())];
+    for (task_output, fact_topology) in task_outputs.iter().zip(fact_topologies) {
+        task_metadata.push(U256::from(
+            task_output.output.len() + TASK_OUTPUT_HEADER_SIZE,
+        ));
+        task_metadata.push(U256::from_big_endian(
+            &task_output.program_hash.to_bytes_be(),
+        ));
+        task_metadata.push(U256::from(fact_topology.tree_structure.len() / 2));
+        task_metadata.extend(fact_topology.tree_structure.iter().map(|x| U256::from(*x)));
+    }
+
+    Ok(task_metadata)
+}
+
+/// A memory page other than the main page, spanning consecutive addresses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContinuousMemoryPage {
+    pub start_address: u32,
+    pub values: Vec<U256>,
+}
+
+/// Returns the continuous memory pages of the public memory, i.e. all pages except page 0
+/// which is registered by the GPS statement verifier itself.
+pub fn continuous_memory_pages(
+    public_input: &PublicInput,
+) -> Result<Vec<ContinuousMemoryPage>, Box<dyn std::error::Error>> {
+    let mut pages = public_memory_pages(public_input);
+    pages.remove(&0);
+
+    pages
+        .into_iter()
+        .map(|(page, mut entries)| {
+            entries.sort_by_key(|entry| entry.address);
+            let start_address = entries[0].address;
+            let values = entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    if entry.address != start_address + i as u32 {
+                        return Err(format!("Memory page {page} is not continuous").into());
+                    }
+                    parse_hex_u256(&entry.value)
+                        .ok_or_else(|| FactError::InvalidMemoryValue(entry.address).into())
+                })
+                .collect::<Result<_, Box<dyn std::error::Error>>>()?;
+
+            Ok(ContinuousMemoryPage {
+                start_address,
+                values,
+            })
+        })
+        .collect()
+}
+
+/// Ethereum node against which proofs are verified.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EthEndpoint {
+    /// Spawn an anvil node forking a network.
+    Fork(ForkOptions),
+    /// Use an already-running node (ex: anvil or hardhat) at this URL.
+    Existing(String),
+}
+
+/// Network forked by anvil.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForkOptions {
+    /// RPC URL of the network to fork.
+    pub rpc_url: String,
+    /// Block to fork from, the latest block if not set. Pinning the block makes the runs
+    /// reproducible and lets anvil reuse the state it cached in a previous run.
+    pub block_number: Option<u64>,
+    /// Directory where anvil caches the state of the fork.
+    pub cache_path: Option<PathBuf>,
+}
+
+impl ForkOptions {
+    pub fn new(rpc_url: impl Into<String>) -> Self {
+        Self {
+            rpc_url: rpc_url.into(),
+            block_number: None,
+            cache_path: None,
+        }
+    }
+}
+
+impl From<String> for ForkOptions {
+    fn from(rpc_url: String) -> Self {
+        Self::new(rpc_url)
+    }
+}
+
+impl From<&str> for ForkOptions {
+    fn from(rpc_url: &str) -> Self {
+        Self::new(rpc_url)
+    }
+}
+
+/// Forking can take a while with a slow RPC endpoint.
+const ANVIL_STARTUP_TIMEOUT_MS: u64 = 60_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AnvilError {
+    #[error(
+        "Could not run {program}: {source}. Install anvil with foundryup, see \
+        https://book.getfoundry.sh/getting-started/installation"
+    )]
+    NotInstalled {
+        program: String,
+        source: std::io::Error,
+    },
+    #[error("anvil failed to start, check that the fork RPC URL is reachable: {0}")]
+    StartupFailed(String),
+}
+
+/// Spawns the anvil node forking the network.
+#[derive(Clone, Debug)]
+pub struct AnvilLauncher {
+    /// Path of the anvil binary, looked up in `PATH` by default.
+    program: String,
+    fork: ForkOptions,
+}
+
+impl AnvilLauncher {
+    pub fn new(fork: ForkOptions) -> Self {
+        Self {
+            program: "anvil".to_string(),
+            fork,
+        }
+    }
+
+    pub fn with_program(mut self, program: impl Into<String>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Arguments passed to anvil, in addition to the port chosen by `ethers`.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["--fork-url".to_string(), self.fork.rpc_url.clone()];
+        if let Some(block_number) = self.fork.block_number {
+            args.extend(["--fork-block-number".to_string(), block_number.to_string()]);
+        }
+        if let Some(cache_path) = &self.fork.cache_path {
+            args.extend([
+                "--cache-path".to_string(),
+                cache_path.to_string_lossy().into_owned(),
+            ]);
+        }
+        args
+    }
+
+    /// Spawns anvil and waits until it accepts connections. The node is stopped when the
+    /// returned instance is dropped.
+    pub fn spawn(&self) -> Result<AnvilInstance, AnvilError> {
+        // `Anvil::spawn` panics if the binary is missing, check it first for a clear error
+        std::process::Command::new(&self.program)
+            .arg("--version")
+            .output()
+            .map_err(|source| AnvilError::NotInstalled {
+                program: self.program.clone(),
+                source,
+            })?;
+
+        let anvil = Anvil::at(&self.program)
+            .args(self.args())
+            .timeout(ANVIL_STARTUP_TIMEOUT_MS);
+        std::panic::catch_unwind(AssertUnwindSafe(|| anvil.spawn())).map_err(|panic| {
+            let reason = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown error".to_string());
+            AnvilError::StartupFailed(reason)
+        })
+    }
+}
 
 /// Verify a proof file against Ethereum SHARP contracts.
 ///
 /// See lib.rs for more details
 pub async fn verify_annotated_proof_with_l1(
     annotated_proof_file: &PathBuf,
-    mainnet_rpc: String,
-) -> Result<(), Box<dyn std::error::Error>> {
+    endpoint: EthEndpoint,
+    contracts: &VerifierContracts,
+    signer_key: Option<SigningKey>,
+    statement_parallelism: usize,
+    dry_run: bool,
+) -> Result<VerificationReport, Box<dyn std::error::Error>> {
     let proof_str = fs::read_to_string(annotated_proof_file)?;
-    let annotated_proof: AnnotatedProof = serde_json::from_str(proof_str.as_str())?;
+    let annotated_proof: serde_json::Value = serde_json::from_str(proof_str.as_str())?;
 
     // generate split proofs
-    let split_proofs: SplitProofs = split_fri_merkle_statements(annotated_proof.clone()).unwrap();
+    let split_proofs: SplitProofs = split_annotated_proof(annotated_proof)?;
+
+    verify_split_proofs_with_l1(
+        &split_proofs,
+        endpoint,
+        contracts,
+        signer_key,
+        None,
+        statement_parallelism,
+        dry_run,
+    )
+    .await
+}
+
+/// Test private key from anvil node, only funded on nodes spawned by anvil.
+const ANVIL_PRIVATE_KEY: &str =
+    "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
 
-    verify_split_proofs_with_l1(&split_proofs, mainnet_rpc).await
+/// Parses a hex-encoded private key, with or without `0x` prefix.
+pub fn parse_signing_key(private_key: &str) -> Result<SigningKey, String> {
+    let key_bytes = hex::decode(private_key).map_err(|e| format!("invalid private key: {e}"))?;
+    SigningKey::from_slice(&key_bytes).map_err(|e| format!("invalid private key: {e}"))
 }
 
+/// Verify split proofs against the SHARP verifier contracts deployed on `endpoint`.
+///
+/// Transactions are signed with `signer_key`. If not specified, a dev account of the anvil
+/// node is used, which is only possible when forking.
+///
+/// If `bootloader_data` is specified, the continuous memory pages of the proof are registered
+/// and the main proof is verified with the metadata of the bootloader tasks, see
+/// `build_task_metadata`. Otherwise, the proof is assumed to have no task, which only holds
+/// for proofs that do not run the bootloader, ex: the annotated proof fixture.
+///
+/// The Merkle and FRI statements are independent: up to `statement_parallelism` of their
+/// transactions wait to be mined at the same time. The main proof depends on all of them and
+/// is only verified once they are registered.
+///
+/// In dry-run mode, each call is executed with `eth_call` directly against the endpoint: no
+/// anvil node is spawned and no transaction is sent. As calls do not modify the state, the main
+/// proof only verifies if its statements are already registered on chain.
 pub async fn verify_split_proofs_with_l1(
     split_proofs: &SplitProofs,
-    mainnet_rpc: String,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let anvil = Some(Anvil::new().fork(mainnet_rpc).spawn());
-    let endpoint = anvil.as_ref().unwrap().endpoint();
-    let provider = Provider::<Http>::try_from(endpoint.as_str())?;
-
-    // a trick to make anvil process lives in the whole main function
-    if anvil.is_some() {
-        println!("Anvil is running.");
+    endpoint: EthEndpoint,
+    contracts: &VerifierContracts,
+    signer_key: Option<SigningKey>,
+    bootloader_data: Option<&BootloaderPublicData>,
+    statement_parallelism: usize,
+    dry_run: bool,
+) -> Result<VerificationReport, Box<dyn std::error::Error>> {
+    // The main proof is rejected if the statements of its FRI layers are not registered
+    if split_proofs.fri_merkle_statements.is_empty() {
+        return Err("The split proof has no FRI statement, see `fri_inner_layer_count`".into());
     }
 
-    // test private key from anvil node
-    let from_key_bytes =
-        hex::decode("0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d").unwrap();
+    let (task_metadata, memory_pages) = match bootloader_data {
+        Some(bootloader_data) => (
+            build_task_metadata(
+                &bootloader_data.fact_topologies,
+                &bootloader_data.task_outputs,
+            )?,
+            continuous_memory_pages(&bootloader_data.public_input)?,
+        ),
+        None => (vec![U256::zero()], vec![]),
+    };
+
+    // Calls do not need a funded account in dry-run mode
+    let from_signing_key = match (signer_key, &endpoint) {
+        (Some(signer_key), _) => signer_key,
+        (None, EthEndpoint::Fork(_)) => parse_signing_key(ANVIL_PRIVATE_KEY)?,
+        (None, EthEndpoint::Existing(_)) if dry_run => parse_signing_key(ANVIL_PRIVATE_KEY)?,
+        (None, EthEndpoint::Existing(_)) => {
+            return Err("A signer key is required to use an existing node".into());
+        }
+    };
+
+    // Keep the anvil process alive until the end of the function
+    let (_anvil, endpoint) = match endpoint {
+        EthEndpoint::Fork(fork) if dry_run => (None, fork.rpc_url),
+        EthEndpoint::Fork(fork) => {
+            let anvil = AnvilLauncher::new(fork).spawn()?;
+            let endpoint = anvil.endpoint();
+            println!("Anvil is running.");
+            (Some(anvil), endpoint)
+        }
+        EthEndpoint::Existing(endpoint) => (None, endpoint),
+    };
+    let provider = Provider::<Http>::try_from(endpoint.as_str())?;
 
-    let from_signing_key = SigningKey::from_bytes(from_key_bytes.as_slice().into()).unwrap();
     let from_wallet: LocalWallet = LocalWallet::from(from_signing_key);
     println!("Test wallet address: {:?}", from_wallet.address());
 
@@ -56,51 +463,824 @@ pub async fn verify_split_proofs_with_l1(
         from_wallet.with_chain_id(chain_id),
     ));
 
-    // start verifying all split proofs
+    let mut executor = CallExecutor::new(dry_run);
+
+    // start verifying all split proofs. Transactions are sent together once all the
+    // statements are built, dry-run calls are executed right away.
+    let mut statement_txs = vec![];
+
     println!("Verifying trace decommitments:");
-    let contract_address = Address::from_str("0x5899Efea757E0Dbd6d114b3375C23D7540f65fa4").unwrap();
     for i in 0..split_proofs.merkle_statements.len() {
         let key = format!("Trace {}", i);
         let trace_merkle = split_proofs.merkle_statements.get(&key).unwrap();
+        let call = trace_merkle.verify(contracts.merkle, signer.clone());
 
-        let call = trace_merkle.verify(contract_address, signer.clone());
-
-        assert_call(call, &key).await?;
+        if dry_run {
+            executor.execute(call, key).await?;
+        } else {
+            statement_txs.push((key, call.tx));
+        }
     }
 
     println!("Verifying FRI decommitments:");
-    let contract_address = Address::from_str("0x3E6118DA317f7A433031F03bB71ab870d87dd2DD").unwrap();
     for (i, fri_statement) in split_proofs.fri_merkle_statements.iter().enumerate() {
-        let call = fri_statement.verify(contract_address, signer.clone());
+        let call = fri_statement.verify(contracts.fri, signer.clone());
+        let key = format!("FRI statement: {}", i);
 
-        assert_call(call, &format!("FRI statement: {}", i)).await?;
+        if dry_run {
+            executor.execute(call, key).await?;
+        } else {
+            statement_txs.push((key, call.tx));
+        }
+    }
+
+    executor
+        .send_statements(&signer, statement_txs, statement_parallelism)
+        .await?;
+
+    if !memory_pages.is_empty() {
+        println!("Registering memory pages:");
+        let contract_address = contracts
+            .memory_page_registry
+            .ok_or("The proof has continuous memory pages but no memory page registry is set")?;
+        let registry = MemoryPageFactRegistry::new(contract_address, signer.clone());
+
+        // The interaction elements are the last two values of the Cairo auxiliary input
+        let aux_input = &split_proofs.main_proof.public_input;
+        let (z, alpha) = match aux_input.as_slice() {
+            [.., z, alpha] => (*z, *alpha),
+            _ => return Err("The main proof has no interaction elements".into()),
+        };
+        let prime = parse_hex_u256(CAIRO_PRIME).unwrap();
+
+        for (i, page) in memory_pages.into_iter().enumerate() {
+            let call = registry.register_continuous_memory_page(
+                U256::from(page.start_address),
+                page.values,
+                z,
+                alpha,
+                prime,
+            );
+            executor
+                .execute(call, format!("Memory page: {}", i + 1))
+                .await?;
+        }
     }
 
     println!("Verifying main proof:");
-    let contract_address = Address::from_str("0x47312450B3Ac8b5b8e247a6bB6d523e7605bDb60").unwrap();
+    let contract_address = contracts.gps;
 
-    let task_metadata = vec![U256::zero()];
     let call = split_proofs
         .main_proof
         .verify(contract_address, signer, task_metadata);
 
-    assert_call(call, "Main proof").await?;
+    executor.execute(call, "Main proof".to_string()).await?;
 
-    Ok(())
+    executor.finish()
+}
+
+/// Executes the verification calls, either as transactions or with `eth_call`
+/// in dry-run mode.
+struct CallExecutor {
+    dry_run: bool,
+    report: VerificationReport,
+    /// Calls that reverted in dry-run mode, with their revert reason.
+    reverted_calls: Vec<String>,
+}
+
+impl CallExecutor {
+    fn new(dry_run: bool) -> Self {
+        Self {
+            dry_run,
+            report: VerificationReport::default(),
+            reverted_calls: vec![],
+        }
+    }
+
+    /// Sends independent transactions with consecutive nonces, without waiting for the
+    /// previous ones to be mined.
+    async fn send_statements(
+        &mut self,
+        signer: &Arc<EthSigner>,
+        txs: Vec<(String, TypedTransaction)>,
+        parallelism: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match txs.first() {
+            Some((name, tx)) => check_balance(signer, tx, name).await?,
+            None => return Ok(()),
+        }
+        let first_nonce = signer
+            .get_transaction_count(signer.address(), Some(BlockNumber::Pending.into()))
+            .await?;
+
+        submit_statements(
+            txs,
+            first_nonce,
+            parallelism,
+            |tx, nonce| send_statement(signer.clone(), tx, nonce),
+            &mut self.report,
+        )
+        .await
+    }
+
+    async fn execute<D: Detokenize>(
+        &mut self,
+        call: FunctionCall<Arc<EthSigner>, EthSigner, D>,
+        name: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.dry_run {
+            let gas_used = assert_call(call, &name).await?;
+            self.report.add(name, gas_used);
+            return Ok(());
+        }
+
+        match call.call().await {
+            Ok(_) => {
+                let estimated_gas = call.estimate_gas().await?;
+                println!("Call succeeded: {}", name);
+                self.report.add(name, estimated_gas.as_u64());
+            }
+            Err(e) => {
+                let reason = revert_reason(&e);
+                println!("Call reverted: {}: {}", name, reason);
+                self.reverted_calls.push(format!("{}: {}", name, reason));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the report, or an error listing the calls that reverted in dry-run mode.
+    fn finish(self) -> Result<VerificationReport, Box<dyn std::error::Error>> {
+        if !self.reverted_calls.is_empty() {
+            return Err(format!(
+                "{} call(s) reverted:\n{}",
+                self.reverted_calls.len(),
+                self.reverted_calls.join("\n")
+            )
+            .into());
+        }
+        Ok(self.report)
+    }
 }
 
-async fn assert_call(
-    call: ContractFunctionCall,
+fn revert_reason<M: Middleware>(error: &ContractError<M>) -> String {
+    match error.as_revert() {
+        Some(revert_data) => decode_revert_reason(revert_data),
+        None => error.to_string(),
+    }
+}
+
+/// Decodes the revert data of a call, falling back to the raw data if it is not
+/// an `Error(string)` revert.
+fn decode_revert_reason(revert_data: &[u8]) -> String {
+    String::decode_with_selector(revert_data)
+        .unwrap_or_else(|| format!("0x{}", hex::encode(revert_data)))
+}
+
+/// Checks that the signer account can pay for the call, to fail with a clear error
+/// instead of an RPC error when using an unfunded account.
+async fn check_balance(
+    client: &EthSigner,
+    tx: &TypedTransaction,
     name: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let estimated_gas = client.estimate_gas(tx, None).await?;
+    let gas_price = client.get_gas_price().await?;
+    let estimated_cost = estimated_gas * gas_price;
+    let balance = client.get_balance(client.address(), None).await?;
+
+    if balance < estimated_cost {
+        return Err(format!(
+            "Account {:?} cannot pay for {}: balance is {} wei, estimated cost is {} wei",
+            client.address(),
+            name,
+            balance,
+            estimated_cost
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Sends the call, checks that it succeeded and returns the gas it used.
+async fn assert_call<D: Detokenize>(
+    call: FunctionCall<Arc<EthSigner>, EthSigner, D>,
+    name: &str,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    check_balance(&call.client, &call.tx, name).await?;
     let pending_tx = call.send().await?;
-    let mined_tx = pending_tx.await?;
+    let mined_tx = pending_tx.await?.unwrap();
     assert_eq!(
         U64::from(1),
-        mined_tx.unwrap().status.unwrap(),
+        mined_tx.status.unwrap(),
         "tx failed: {}",
         name
     );
     println!("Verified: {}", name);
+    Ok(mined_tx.gas_used.unwrap_or_default().as_u64())
+}
+
+/// Failure of a statement transaction.
+#[derive(Debug)]
+enum StatementError {
+    /// The transaction was mined but reverted.
+    Reverted(String),
+    /// The transaction could not be sent or its receipt could not be fetched. The
+    /// transactions with the next nonces cannot be mined.
+    Failed(String),
+}
+
+/// Sends a statement transaction with the specified nonce and returns the gas it used.
+async fn send_statement(
+    signer: Arc<EthSigner>,
+    mut tx: TypedTransaction,
+    nonce: U256,
+) -> Result<u64, StatementError> {
+    tx.set_nonce(nonce);
+    let pending_tx = signer
+        .send_transaction(tx, None)
+        .await
+        .map_err(|e| StatementError::Failed(e.to_string()))?;
+    let tx_hash = pending_tx.tx_hash();
+    let mined_tx = pending_tx
+        .await
+        .map_err(|e| StatementError::Failed(e.to_string()))?
+        .ok_or_else(|| StatementError::Failed(format!("transaction {tx_hash:?} was dropped")))?;
+
+    if mined_tx.status != Some(U64::from(1)) {
+        return Err(StatementError::Reverted(format!(
+            "transaction {tx_hash:?} reverted"
+        )));
+    }
+    Ok(mined_tx.gas_used.unwrap_or_default().as_u64())
+}
+
+/// Submits the statements with `submit`, with consecutive nonces starting at `first_nonce`.
+/// Up to `parallelism` statements are pending at the same time.
+///
+/// The gas used by each statement is added to `report` in the order of `statements`. All the
+/// statements that reverted are reported in the error.
+async fn submit_statements<T, F, Fut>(
+    statements: Vec<(String, T)>,
+    first_nonce: U256,
+    parallelism: usize,
+    submit: F,
+    report: &mut VerificationReport,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Fn(T, U256) -> Fut,
+    Fut: Future<Output = Result<u64, StatementError>>,
+{
+    let mut results = futures::stream::iter(statements.into_iter().enumerate())
+        .map(|(i, (name, statement))| {
+            let result = submit(statement, first_nonce + i);
+            async move { (name, result.await) }
+        })
+        .buffered(parallelism.max(1));
+
+    let mut reverted_statements = vec![];
+    while let Some((name, result)) = results.next().await {
+        match result {
+            Ok(gas_used) => {
+                println!("Verified: {}", name);
+                report.add(name, gas_used);
+            }
+            Err(StatementError::Reverted(reason)) => {
+                println!("Reverted: {}: {}", name, reason);
+                reverted_statements.push(format!("{}: {}", name, reason));
+            }
+            Err(StatementError::Failed(e)) => {
+                return Err(format!("Could not verify {}: {}", name, e).into());
+            }
+        }
+    }
+
+    if !reverted_statements.is_empty() {
+        return Err(format!(
+            "{} statement(s) reverted:\n{}",
+            reverted_statements.len(),
+            reverted_statements.join("\n")
+        )
+        .into());
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use cairo_vm::Felt252;
+    use stone_prover_sdk::json::read_json_from_file;
+    use stone_prover_sdk::models::PublicMemoryEntry;
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    fn fibonacci_public_input() -> PublicInput {
+        read_json_from_file(get_test_case_file_path(
+            "bootloader/programs/fibonacci/output/air_public_input.json",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_anvil_args() {
+        let launcher = AnvilLauncher::new("http://localhost:8545".into());
+        assert_eq!(launcher.args(), vec!["--fork-url", "http://localhost:8545"]);
+
+        let launcher = AnvilLauncher::new(ForkOptions {
+            block_number: Some(19000000),
+            cache_path: Some(PathBuf::from("/tmp/anvil-cache")),
+            ..ForkOptions::new("http://localhost:8545")
+        });
+        assert_eq!(
+            launcher.args(),
+            vec![
+                "--fork-url",
+                "http://localhost:8545",
+                "--fork-block-number",
+                "19000000",
+                "--cache-path",
+                "/tmp/anvil-cache",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_anvil_not_installed() {
+        let launcher =
+            AnvilLauncher::new("http://localhost:8545".into()).with_program("/nonexistent/anvil");
+
+        let error = launcher.spawn().unwrap_err();
+
+        assert!(matches!(error, AnvilError::NotInstalled { .. }));
+        assert!(error.to_string().contains("foundryup"));
+    }
+
+    #[test]
+    fn test_build_task_metadata() {
+        let public_input = fibonacci_public_input();
+        // The fibonacci program writes no output
+        let fact_topologies = vec![FactTopology::single_page(0)];
+        let bootloader_data =
+            BootloaderPublicData::from_public_input(public_input, fact_topologies).unwrap();
+
+        let task_metadata = build_task_metadata(
+            &bootloader_data.fact_topologies,
+            &bootloader_data.task_outputs,
+        )
+        .unwrap();
+
+        let program_hash =
+            parse_hex_u256("0x43b17e9592f33142246af4c06cd2b574b460dd1f718d76b51341175a62b220f")
+                .unwrap();
+        let expected_task_metadata = vec![
+            U256::from(1),
+            U256::from(2),
+            program_hash,
+            U256::from(1),
+            U256::from(1),
+            U256::from(0),
+        ];
+        assert_eq!(task_metadata, expected_task_metadata);
+    }
+
+    #[test]
+    fn test_build_task_metadata_multiple_tasks() {
+        let task_outputs = vec![
+            TaskOutput {
+                program_hash: Felt252::from(0x1234),
+                output: vec![Felt252::from(1), Felt252::from(2), Felt252::from(3)],
+            },
+            TaskOutput {
+                program_hash: Felt252::from(0x5678),
+                output: vec![],
+            },
+        ];
+        let fact_topologies = vec![
+            FactTopology {
+                tree_structure: vec![2, 0, 0, 2],
+                page_sizes: vec![1, 2],
+            },
+            FactTopology::single_page(0),
+        ];
+
+        let task_metadata = build_task_metadata(&fact_topologies, &task_outputs).unwrap();
+
+        let expected_task_metadata: Vec<U256> = [2, 5, 0x1234, 2, 2, 0, 0, 2, 2, 0x5678, 1, 1, 0]
+            .into_iter()
+            .map(U256::from)
+            .collect();
+        assert_eq!(task_metadata, expected_task_metadata);
+    }
+
+    #[test]
+    fn test_build_task_metadata_topology_mismatch() {
+        let public_input = fibonacci_public_input();
+        let bootloader_data =
+            BootloaderPublicData::from_public_input(public_input, vec![]).unwrap();
+        let result = build_task_metadata(
+            &bootloader_data.fact_topologies,
+            &bootloader_data.task_outputs,
+        );
+        assert!(matches!(
+            result,
+            Err(FactError::TopologyCountMismatch {
+                expected: 1,
+                actual: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn test_continuous_memory_pages() {
+        let mut public_input = fibonacci_public_input();
+        assert!(continuous_memory_pages(&public_input).unwrap().is_empty());
+
+        for (address, value) in [(101, "0x2"), (100, "0x1"), (102, "0x3")] {
+            public_input.public_memory.push(PublicMemoryEntry {
+                address,
+                value: value.to_string(),
+                page: 1,
+            });
+        }
+
+        let pages = continuous_memory_pages(&public_input).unwrap();
+        assert_eq!(
+            pages,
+            vec![ContinuousMemoryPage {
+                start_address: 100,
+                values: vec![U256::from(1), U256::from(2), U256::from(3)],
+            }]
+        );
+
+        public_input.public_memory.push(PublicMemoryEntry {
+            address: 200,
+            value: "0x4".to_string(),
+            page: 1,
+        });
+        assert!(continuous_memory_pages(&public_input).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_split_proof_without_fri_statements() {
+        let annotated_proof = read_json_from_file(get_test_case_file_path(
+            "bootloader/empty_bootloader_proof/annotated_proof.json",
+        ))
+        .unwrap();
+        let mut split_proofs = split_annotated_proof(annotated_proof).unwrap();
+        split_proofs.fri_merkle_statements.clear();
+
+        // Rejected before connecting to the endpoint
+        let result = verify_split_proofs_with_l1(
+            &split_proofs,
+            EthEndpoint::Existing("http://127.0.0.1:1".to_string()),
+            &VerifierContracts::mainnet(),
+            None,
+            None,
+            1,
+            true,
+        )
+        .await;
+
+        let error = result.err().expect("The split proof was verified");
+        assert!(error.to_string().contains("no FRI statement"), "{error}");
+    }
+
+    #[test]
+    fn test_mainnet_contracts() {
+        let contracts = VerifierContracts::mainnet();
+
+        assert_eq!(
+            contracts.merkle,
+            Address::from_str("0x5899Efea757E0Dbd6d114b3375C23D7540f65fa4").unwrap()
+        );
+        assert_eq!(
+            contracts.fri,
+            Address::from_str("0x3E6118DA317f7A433031F03bB71ab870d87dd2DD").unwrap()
+        );
+        assert_eq!(
+            contracts.gps,
+            Address::from_str("0x47312450B3Ac8b5b8e247a6bB6d523e7605bDb60").unwrap()
+        );
+        assert_eq!(VerifierContracts::default(), contracts);
+    }
+
+    #[test]
+    fn test_verification_report() {
+        let mut report = VerificationReport::default();
+        report.add("Trace 0".to_string(), 1_000);
+        report.add("FRI statement: 0".to_string(), 2_500);
+        report.add("Main proof".to_string(), 3_000_000);
+
+        assert_eq!(report.total_gas, 3_003_500);
+        assert_eq!(
+            report
+                .items
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Trace 0", "FRI statement: 0", "Main proof"]
+        );
+
+        let table = report.to_string();
+        assert!(table.lines().next().unwrap().starts_with("Call"));
+        assert!(table.lines().last().unwrap().ends_with("3003500"));
+
+        let report_json = serde_json::to_value(&report).unwrap();
+        assert_eq!(report_json["total_gas"], 3_003_500);
+        assert_eq!(report_json["items"][2][0], "Main proof");
+    }
+
+    #[test]
+    fn test_decode_revert_reason() {
+        // Error("Invalid proof")
+        let revert_data = hex::decode(
+            "0x08c379a0\
+             0000000000000000000000000000000000000000000000000000000000000020\
+             000000000000000000000000000000000000000000000000000000000000000d\
+             496e76616c69642070726f6f6600000000000000000000000000000000000000",
+        )
+        .unwrap();
+        assert_eq!(decode_revert_reason(&revert_data), "Invalid proof");
+
+        // Custom errors are reported as raw data
+        assert_eq!(
+            decode_revert_reason(&[0xde, 0xad, 0xbe, 0xef]),
+            "0xdeadbeef"
+        );
+    }
+
+    #[test]
+    fn test_dry_run_executor_reports_reverted_calls() {
+        let mut executor = CallExecutor::new(true);
+        executor.report.add("Trace 0".to_string(), 1_000);
+        executor
+            .reverted_calls
+            .push("Main proof: Invalid proof".to_string());
+
+        let error = executor.finish().unwrap_err().to_string();
+        assert!(error.contains("1 call(s) reverted"));
+        assert!(error.contains("Main proof: Invalid proof"));
+    }
+
+    /// JSON-RPC node that records the methods it is called with. Calls succeed and return
+    /// a zero word, transactions are rejected.
+    async fn spawn_stub_node() -> (String, Arc<Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let methods = Arc::new(Mutex::new(vec![]));
+
+        let recorded_methods = methods.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let methods = recorded_methods.clone();
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    loop {
+                        let mut content_length = 0;
+                        loop {
+                            let mut line = String::new();
+                            if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+                                return;
+                            }
+                            let line = line.trim_end().to_ascii_lowercase();
+                            if line.is_empty() {
+                                break;
+                            }
+                            if let Some(value) = line.strip_prefix("content-length:") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                        let mut body = vec![0u8; content_length];
+                        stream.read_exact(&mut body).await.unwrap();
+
+                        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                        let method = request["method"].as_str().unwrap().to_string();
+                        methods.lock().unwrap().push(method.clone());
+                        let result = match method.as_str() {
+                            "eth_call" => serde_json::json!(format!("0x{}", "0".repeat(64))),
+                            "eth_sendTransaction" | "eth_sendRawTransaction" => {
+                                serde_json::Value::Null
+                            }
+                            _ => serde_json::json!("0x1"),
+                        };
+                        let response = serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": request["id"],
+                            "result": result,
+                        })
+                        .to_string();
+                        let http_response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                             Content-Length: {}\r\n\r\n{}",
+                            response.len(),
+                            response
+                        );
+                        if stream
+                            .get_mut()
+                            .write_all(http_response.as_bytes())
+                            .await
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        (endpoint, methods)
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_sends_no_transaction() {
+        let annotated_proof = read_json_from_file(get_test_case_file_path(
+            "bootloader/empty_bootloader_proof/annotated_proof.json",
+        ))
+        .unwrap();
+        let split_proofs = split_annotated_proof(annotated_proof).unwrap();
+        let (endpoint, methods) = spawn_stub_node().await;
+
+        let report = verify_split_proofs_with_l1(
+            &split_proofs,
+            EthEndpoint::Existing(endpoint),
+            &VerifierContracts::mainnet(),
+            None,
+            None,
+            DEFAULT_STATEMENT_PARALLELISM,
+            true,
+        )
+        .await
+        .unwrap();
+
+        let n_calls =
+            split_proofs.merkle_statements.len() + split_proofs.fri_merkle_statements.len() + 1;
+        assert_eq!(report.items.len(), n_calls);
+
+        let methods = methods.lock().unwrap();
+        assert_eq!(
+            methods
+                .iter()
+                .filter(|method| *method == "eth_call")
+                .count(),
+            n_calls
+        );
+        assert!(
+            !methods.iter().any(|method| method.starts_with("eth_send")),
+            "{methods:?}"
+        );
+    }
+
+    /// Records the nonce of each statement and the number of statements pending at the same
+    /// time. Statements are `Some(gas_used)`, or `None` to revert.
+    #[derive(Default)]
+    struct MockSubmitter {
+        nonces: Mutex<Vec<(String, U256)>>,
+        pending: AtomicUsize,
+        max_pending: AtomicUsize,
+    }
+
+    impl MockSubmitter {
+        async fn submit(
+            &self,
+            (name, gas_used): (String, Option<u64>),
+            nonce: U256,
+        ) -> Result<u64, StatementError> {
+            self.nonces.lock().unwrap().push((name, nonce));
+            let pending = self.pending.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_pending.fetch_max(pending, Ordering::SeqCst);
+
+            // The first statements are mined last
+            let delay = 10 - self.nonces.lock().unwrap().len();
+            for _ in 0..delay {
+                tokio::task::yield_now().await;
+            }
+
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            gas_used.ok_or_else(|| StatementError::Reverted("reverted".to_string()))
+        }
+    }
+
+    fn statements(gas_used: &[Option<u64>]) -> Vec<(String, (String, Option<u64>))> {
+        gas_used
+            .iter()
+            .enumerate()
+            .map(|(i, gas_used)| {
+                let name = format!("Trace {i}");
+                (name.clone(), (name, *gas_used))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_submit_statements() {
+        let submitter = MockSubmitter::default();
+        let mut report = VerificationReport::default();
+
+        submit_statements(
+            statements(&[Some(100), Some(200), Some(300), Some(400), Some(500)]),
+            U256::from(7),
+            2,
+            |statement, nonce| submitter.submit(statement, nonce),
+            &mut report,
+        )
+        .await
+        .unwrap();
+
+        // Consecutive nonces, in the order of the statements
+        let nonces = submitter.nonces.into_inner().unwrap();
+        assert_eq!(
+            nonces,
+            (0u64..5)
+                .map(|i| (format!("Trace {i}"), U256::from(7 + i)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(submitter.max_pending.load(Ordering::SeqCst), 2);
+
+        // The report follows the order of the statements, not the order they were mined in
+        assert_eq!(
+            report.items,
+            (0u64..5)
+                .map(|i| (format!("Trace {i}"), 100 * (i + 1)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(report.total_gas, 1_500);
+    }
+
+    #[tokio::test]
+    async fn test_submit_statements_reports_reverted_statements() {
+        let submitter = MockSubmitter::default();
+        let mut report = VerificationReport::default();
+
+        let error = submit_statements(
+            statements(&[Some(100), None, Some(300), None]),
+            U256::zero(),
+            4,
+            |statement, nonce| submitter.submit(statement, nonce),
+            &mut report,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert_eq!(
+            error,
+            "2 statement(s) reverted:\nTrace 1: reverted\nTrace 3: reverted"
+        );
+        assert_eq!(report.total_gas, 400);
+    }
+
+    #[tokio::test]
+    async fn test_submit_statements_stops_on_failure() {
+        let mut report = VerificationReport::default();
+
+        let error = submit_statements(
+            statements(&[Some(100), None, Some(300)]),
+            U256::zero(),
+            1,
+            |(_, gas_used), _| async move {
+                gas_used.ok_or_else(|| StatementError::Failed("connection reset".to_string()))
+            },
+            &mut report,
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+
+        assert_eq!(error, "Could not verify Trace 1: connection reset");
+        assert_eq!(report.items, vec![("Trace 0".to_string(), 100)]);
+    }
+
+    #[test]
+    fn test_parse_signing_key() {
+        let signing_key = parse_signing_key(ANVIL_PRIVATE_KEY).unwrap();
+        let wallet = LocalWallet::from(signing_key);
+
+        // Second dev account of anvil
+        assert_eq!(
+            wallet.address(),
+            Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_signing_key() {
+        assert!(parse_signing_key("0x1234").is_err());
+        assert!(parse_signing_key("not hex").is_err());
+    }
+
+    #[test]
+    fn test_sepolia_contracts_differ_from_mainnet() {
+        let mainnet = VerifierContracts::mainnet();
+        let sepolia = VerifierContracts::sepolia();
+
+        assert_ne!(sepolia.merkle, mainnet.merkle);
+        assert_ne!(sepolia.fri, mainnet.fri);
+        assert_ne!(sepolia.gps, mainnet.gps);
+    }
+}
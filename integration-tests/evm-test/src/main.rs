@@ -1,5 +1,12 @@
-use clap::Parser;
+use clap::{ArgGroup, Parser, ValueEnum};
+use ethers::core::k256::ecdsa::SigningKey;
+use ethers::types::Address;
+use ethers::utils::to_checksum;
+use evm_adapter::{
+    parse_signing_key, EthEndpoint, ForkOptions, VerifierContracts, DEFAULT_STATEMENT_PARALLELISM,
+};
 use std::path::PathBuf;
+use std::str::FromStr;
 
 /// Binary borrowed from `stark-evm-adapter` used to test a split proof against in-production
 /// SHARP provers on Ethereum.
@@ -19,17 +26,326 @@ use std::path::PathBuf;
 // CLI Args
 #[derive(Parser, Debug)]
 #[command(version, about)]
+#[command(group(ArgGroup::new("eth_endpoint").args(["mainnet_rpc", "endpoint"])))]
 struct Args {
     #[arg(short, long)]
     annotated_proof: PathBuf,
 
-    // TODO: support FORKED_MAINNET_RPC and set up proper arg group
-    #[arg(short, long, required = true)]
-    mainnet_rpc: String,
+    /// RPC endpoint of the network to fork, must match `--network`.
+    /// Defaults to the FORKED_MAINNET_RPC environment variable.
+    #[arg(short, long)]
+    mainnet_rpc: Option<String>,
+
+    /// Block to fork from. Defaults to the latest block.
+    #[arg(long, conflicts_with = "endpoint")]
+    fork_block_number: Option<u64>,
+
+    /// Directory where anvil caches the state of the fork, reused across runs forking
+    /// the same block.
+    #[arg(long, conflicts_with = "endpoint")]
+    fork_cache_path: Option<PathBuf>,
+
+    /// URL of an already-running node (ex: anvil or hardhat) to use instead of forking.
+    #[arg(short, long)]
+    endpoint: Option<String>,
+
+    /// Network whose verifier contracts are used.
+    #[arg(short, long, value_enum, default_value_t = Network::Mainnet)]
+    network: Network,
+
+    /// Overrides the address of the Merkle statement contract.
+    #[arg(long, value_parser = parse_checksummed_address)]
+    merkle_address: Option<Address>,
+
+    /// Overrides the address of the FRI statement contract.
+    #[arg(long, value_parser = parse_checksummed_address)]
+    fri_address: Option<Address>,
+
+    /// Overrides the address of the GPS statement verifier contract.
+    #[arg(long, value_parser = parse_checksummed_address)]
+    gps_address: Option<Address>,
+
+    /// Overrides the address of the memory page fact registry contract.
+    #[arg(long, value_parser = parse_checksummed_address)]
+    memory_page_registry_address: Option<Address>,
+
+    /// Hex-encoded private key of the account sending the verification transactions.
+    /// Defaults to the PRIVATE_KEY environment variable, then to an anvil dev account
+    /// when forking.
+    #[arg(long)]
+    private_key: Option<String>,
+
+    /// Only simulates the verification calls with `eth_call` against the RPC endpoint,
+    /// without spawning anvil or sending transactions.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Maximum number of Merkle and FRI statement transactions waiting to be mined at the
+    /// same time. 1 sends them one after the other.
+    #[arg(long, default_value_t = DEFAULT_STATEMENT_PARALLELISM)]
+    parallelism: usize,
+
+    /// Writes the gas usage report to this file, as JSON.
+    #[arg(long)]
+    report_out: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Network {
+    Mainnet,
+    Sepolia,
+}
+
+/// Parses an address, requiring it to be EIP-55 checksummed.
+fn parse_checksummed_address(value: &str) -> Result<Address, String> {
+    let address = Address::from_str(value).map_err(|e| format!("invalid address: {e}"))?;
+    let checksummed = to_checksum(&address, None);
+    if checksummed != value {
+        return Err(format!(
+            "address is not checksummed, expected {checksummed}"
+        ));
+    }
+    Ok(address)
+}
+
+const FORKED_MAINNET_RPC_ENV: &str = "FORKED_MAINNET_RPC";
+const PRIVATE_KEY_ENV: &str = "PRIVATE_KEY";
+
+impl Args {
+    /// Selects the node to use, by order of precedence: `--endpoint`, `--mainnet-rpc`,
+    /// then the RPC URL from the environment.
+    fn eth_endpoint(&self, env_rpc: Option<String>) -> Result<EthEndpoint, String> {
+        if let Some(endpoint) = &self.endpoint {
+            return Ok(EthEndpoint::Existing(endpoint.clone()));
+        }
+
+        self.mainnet_rpc
+            .clone()
+            .or(env_rpc)
+            .map(|rpc_url| {
+                EthEndpoint::Fork(ForkOptions {
+                    block_number: self.fork_block_number,
+                    cache_path: self.fork_cache_path.clone(),
+                    ..ForkOptions::new(rpc_url)
+                })
+            })
+            .ok_or_else(|| {
+                format!(
+                    "No Ethereum node specified: set --mainnet-rpc, the {FORKED_MAINNET_RPC_ENV} \
+                    environment variable or --endpoint"
+                )
+            })
+    }
+
+    /// Selects the signer key: `--private-key` takes precedence over the key from the environment.
+    fn signer_key(&self, env_key: Option<String>) -> Result<Option<SigningKey>, String> {
+        self.private_key
+            .clone()
+            .or(env_key)
+            .map(|private_key| parse_signing_key(&private_key))
+            .transpose()
+    }
+
+    fn verifier_contracts(&self) -> VerifierContracts {
+        let contracts = match self.network {
+            Network::Mainnet => VerifierContracts::mainnet(),
+            Network::Sepolia => VerifierContracts::sepolia(),
+        };
+
+        VerifierContracts {
+            merkle: self.merkle_address.unwrap_or(contracts.merkle),
+            fri: self.fri_address.unwrap_or(contracts.fri),
+            gps: self.gps_address.unwrap_or(contracts.gps),
+            memory_page_registry: self
+                .memory_page_registry_address
+                .or(contracts.memory_page_registry),
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
-    evm_adapter::verify_annotated_proof_with_l1(&args.annotated_proof, args.mainnet_rpc).await
+    let endpoint = args.eth_endpoint(std::env::var(FORKED_MAINNET_RPC_ENV).ok())?;
+    let signer_key = args.signer_key(std::env::var(PRIVATE_KEY_ENV).ok())?;
+    let contracts = args.verifier_contracts();
+    let report = evm_adapter::verify_annotated_proof_with_l1(
+        &args.annotated_proof,
+        endpoint,
+        &contracts,
+        signer_key,
+        args.parallelism,
+        args.dry_run,
+    )
+    .await?;
+
+    println!("{report}");
+    if let Some(report_out) = &args.report_out {
+        std::fs::write(report_out, serde_json::to_string_pretty(&report)?)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_ARGS: [&str; 5] = [
+        "test_proof_against_l1_sharp_contracts",
+        "--annotated-proof",
+        "proof.json",
+        "--mainnet-rpc",
+        "http://localhost:8545",
+    ];
+
+    fn parse_args(extra_args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(BASE_ARGS.iter().chain(extra_args))
+    }
+
+    fn parse_endpoint_args(extra_args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(BASE_ARGS[..3].iter().chain(extra_args))
+    }
+
+    const ENV_RPC: &str = "http://env-rpc:8545";
+
+    #[test]
+    fn test_mainnet_rpc_takes_precedence_over_env() {
+        let args = parse_args(&[]).unwrap();
+        assert_eq!(
+            args.eth_endpoint(Some(ENV_RPC.to_string())),
+            Ok(EthEndpoint::Fork("http://localhost:8545".into()))
+        );
+    }
+
+    #[test]
+    fn test_env_rpc_is_used_as_fallback() {
+        let args = parse_endpoint_args(&[]).unwrap();
+        assert_eq!(
+            args.eth_endpoint(Some(ENV_RPC.to_string())),
+            Ok(EthEndpoint::Fork(ENV_RPC.into()))
+        );
+    }
+
+    #[test]
+    fn test_fork_options() {
+        let args = parse_args(&[
+            "--fork-block-number",
+            "19000000",
+            "--fork-cache-path",
+            "cache",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.eth_endpoint(None),
+            Ok(EthEndpoint::Fork(ForkOptions {
+                rpc_url: "http://localhost:8545".to_string(),
+                block_number: Some(19000000),
+                cache_path: Some(PathBuf::from("cache")),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_fork_block_number_conflicts_with_endpoint() {
+        let result = parse_endpoint_args(&[
+            "--endpoint",
+            "http://localhost:8546",
+            "--fork-block-number",
+            "19000000",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_existing_endpoint_takes_precedence_over_env() {
+        let args = parse_endpoint_args(&["--endpoint", "http://localhost:8546"]).unwrap();
+        assert_eq!(
+            args.eth_endpoint(Some(ENV_RPC.to_string())),
+            Ok(EthEndpoint::Existing("http://localhost:8546".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_missing_endpoint() {
+        let args = parse_endpoint_args(&[]).unwrap();
+        assert!(args.eth_endpoint(None).is_err());
+    }
+
+    #[test]
+    fn test_endpoint_conflicts_with_mainnet_rpc() {
+        let result = parse_args(&["--endpoint", "http://localhost:8546"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_default_network_is_mainnet() {
+        let args = parse_args(&[]).unwrap();
+        assert_eq!(args.verifier_contracts(), VerifierContracts::mainnet());
+    }
+
+    #[test]
+    fn test_sepolia_network() {
+        let args = parse_args(&["--network", "sepolia"]).unwrap();
+        assert_eq!(args.verifier_contracts(), VerifierContracts::sepolia());
+    }
+
+    #[test]
+    fn test_explicit_addresses() {
+        let gps_address = "0x07ec0D28e50322Eb0C159B9090ecF3aeA8346DFe";
+        let args = parse_args(&["--gps-address", gps_address]).unwrap();
+
+        let contracts = args.verifier_contracts();
+        let mainnet = VerifierContracts::mainnet();
+        assert_eq!(contracts.merkle, mainnet.merkle);
+        assert_eq!(contracts.fri, mainnet.fri);
+        assert_eq!(contracts.gps, Address::from_str(gps_address).unwrap());
+    }
+
+    #[test]
+    fn test_non_checksummed_address_is_rejected() {
+        let result = parse_args(&[
+            "--merkle-address",
+            "0x5899efea757e0dbd6d114b3375c23d7540f65fa4",
+        ]);
+        assert!(result.is_err());
+    }
+
+    // First and second dev accounts of anvil
+    const CLI_PRIVATE_KEY: &str =
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ENV_PRIVATE_KEY: &str =
+        "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    #[test]
+    fn test_private_key_takes_precedence_over_env() {
+        let args = parse_args(&["--private-key", CLI_PRIVATE_KEY]).unwrap();
+        let signer_key = args.signer_key(Some(ENV_PRIVATE_KEY.to_string())).unwrap();
+        assert_eq!(
+            signer_key,
+            Some(parse_signing_key(CLI_PRIVATE_KEY).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_env_private_key_is_used_as_fallback() {
+        let args = parse_args(&[]).unwrap();
+        let signer_key = args.signer_key(Some(ENV_PRIVATE_KEY.to_string())).unwrap();
+        assert_eq!(
+            signer_key,
+            Some(parse_signing_key(ENV_PRIVATE_KEY).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_no_private_key() {
+        let args = parse_args(&[]).unwrap();
+        assert_eq!(args.signer_key(None), Ok(None));
+    }
+
+    #[test]
+    fn test_invalid_private_key() {
+        let args = parse_args(&["--private-key", "0x1234"]).unwrap();
+        assert!(args.signer_key(None).is_err());
+    }
 }
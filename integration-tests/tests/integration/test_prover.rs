@@ -1,15 +1,51 @@
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::{
+        BuiltinHintProcessor, HintFunc,
+    };
+    use cairo_vm::hint_processor::hint_processor_definition::HintReference;
+    use cairo_vm::serde::deserialize_program::ApTracking;
+    use cairo_vm::types::exec_scope::ExecutionScopes;
+    use cairo_vm::vm::errors::hint_errors::HintError;
+    use cairo_vm::vm::vm_core::VirtualMachine;
+    use cairo_vm::Felt252;
     use rstest::rstest;
 
+    use madara_prover_rpc_client::auth::AuthToken;
+    use madara_prover_rpc_client::config::ClientConfig;
+    use madara_prover_rpc_client::demo::run_demo;
+    use madara_prover_rpc_client::proof::StoneProof;
+    use madara_prover_rpc_client::retry::RetryPolicy;
     use madara_prover_rpc_client::services::prover::prover_proto::prover_client::ProverClient;
+    use madara_prover_rpc_client::services::prover::prover_proto::ExecutionRequest;
     use madara_prover_rpc_client::services::prover::{
-        execute_and_prove, execute_program, prove_execution,
+        estimate_proof, execute_and_prove, execute_and_prove_with_retry, execute_program,
+        execute_program_streamed, execute_program_to_files, execute_program_with_retry,
+        prove_execution, prove_from_files,
+    };
+    use madara_prover_rpc_server::artifacts::{read_artifacts_from_dir, write_artifacts_to_dir};
+    use madara_prover_rpc_server::hints::register_hint_profile;
+    use madara_prover_rpc_server::limiter::{
+        ESTIMATED_WAIT_HEADER, PROOF_CLASS_HEADER, QUEUE_POSITION_HEADER,
     };
+    use madara_prover_rpc_server::services::format_verifier_error;
+    use madara_prover_rpc_server::services::prover::run_cairo_program_in_proof_mode;
+    use madara_prover_rpc_server::ServerConfig;
+    use stone_prover_sdk::models::Proof;
+    use stone_prover_sdk::verifier::run_verifier_async;
     use test_cases::get_test_case_file_path;
-    use test_fixtures::{parsed_prover_test_case, ParsedProverTestCase};
+    use test_fixtures::{assert_or_update_proof, parsed_prover_test_case, ParsedProverTestCase};
 
-    use crate::integration::toolkit::{prover_client_server, RpcServer};
+    use crate::integration::toolkit::{
+        flaky_prover_client_server, prover_client_server, prover_client_server_over_tcp,
+        prover_client_server_tcp, prover_client_server_with_file_roots,
+        prover_client_server_with_proof_limit, prover_client_server_with_reserved_slot,
+        token_protected_prover_server, RpcServer,
+    };
 
     type RpcClient = ProverClient<tonic::transport::Channel>;
 
@@ -26,6 +62,174 @@ mod tests {
         assert!(result.is_ok(), "{:?}", result);
     }
 
+    fn fast_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_over_tcp(#[future] prover_client_server_tcp: (RpcClient, RpcServer)) {
+        let (mut client, _server) = prover_client_server_tcp.await;
+
+        let program_path = get_test_case_file_path("fibonacci/fibonacci_compiled.json");
+        let program_content = std::fs::read(program_path).unwrap();
+
+        let result = execute_program(&mut client, program_content).await;
+
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_retry() {
+        let (mut client, _server) = flaky_prover_client_server(1).await;
+        let program_path = get_test_case_file_path("fibonacci/fibonacci_compiled.json");
+        let program_content = std::fs::read(program_path).unwrap();
+
+        // Without retries, the call fails on the first error
+        let status = execute_program(&mut client, program_content.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unavailable);
+
+        let (mut client, _server) = flaky_prover_client_server(1).await;
+        let result =
+            execute_program_with_retry(&mut client, program_content, &fast_retry_policy()).await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_with_retry(
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = flaky_prover_client_server(2).await;
+
+        let proof = execute_and_prove_with_retry(
+            &mut client,
+            test_case.compiled_program,
+            Some(test_case.prover_config),
+            Some(test_case.prover_parameters),
+            "retried-proof",
+            &fast_retry_policy(),
+        )
+        .await
+        .unwrap();
+
+        assert_or_update_proof(&proof, &test_case.proof_file);
+    }
+
+    #[tokio::test]
+    async fn test_execute_with_token() {
+        let (channel, _server) = token_protected_prover_server("secret").await;
+        let program_path = get_test_case_file_path("fibonacci/fibonacci_compiled.json");
+        let program_content = std::fs::read(program_path).unwrap();
+
+        // The service helpers accept authenticated clients
+        let mut client =
+            ProverClient::with_token(channel.clone(), AuthToken::new("secret").unwrap());
+        let result = execute_program(&mut client, program_content.clone()).await;
+        assert!(result.is_ok(), "{:?}", result);
+
+        let mut plain_client = ProverClient::new(channel.clone());
+        let status = execute_program(&mut plain_client, program_content.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let mut wrong_token_client =
+            ProverClient::with_token(channel, AuthToken::new("wrong").unwrap());
+        let status = execute_program(&mut wrong_token_client, program_content)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_demo(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+
+        let report = run_demo(&mut client, "fibonacci").await.unwrap();
+
+        let expected_proof_size = serde_json::to_vec(&test_case.proof).unwrap().len();
+        assert_eq!(report.proof_size, expected_proof_size);
+        assert_eq!(report.suggested_layout, "plain");
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_failing_assert(#[future] prover_client_server: (RpcClient, RpcServer)) {
+        let (mut client, _server) = prover_client_server.await;
+
+        let program_path = get_test_case_file_path("failing-assert/failing_assert_compiled.json");
+        let program_content = std::fs::read(program_path).unwrap();
+
+        let status = execute_program(&mut client, program_content)
+            .await
+            .expect_err("execution of a failing assert should fail");
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(
+            status.message().contains("ASSERT_EQ"),
+            "{}",
+            status.message()
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_streamed(#[future] prover_client_server: (RpcClient, RpcServer)) {
+        let (mut client, _server) = prover_client_server.await;
+
+        let program_path = get_test_case_file_path("fibonacci/fibonacci_compiled.json");
+        let program_content = std::fs::read(program_path).unwrap();
+
+        let expected = execute_program(&mut client, program_content.clone())
+            .await
+            .unwrap();
+        let streamed = execute_program_streamed(&mut client, program_content)
+            .await
+            .unwrap();
+
+        assert_eq!(streamed.public_input, expected.public_input);
+        assert_eq!(streamed.memory, expected.memory);
+        assert_eq!(streamed.trace, expected.trace);
+        assert_eq!(streamed.memory_sha256, expected.memory_sha256);
+        assert_eq!(streamed.trace_sha256, expected.trace_sha256);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_streamed_to_files(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+
+        let program_path = get_test_case_file_path("fibonacci/fibonacci_compiled.json");
+        let program_content = std::fs::read(program_path).unwrap();
+        let output_dir = tempfile::tempdir().unwrap();
+        let memory_file = output_dir.path().join("memory.bin");
+        let trace_file = output_dir.path().join("trace.bin");
+
+        let expected = execute_program(&mut client, program_content.clone())
+            .await
+            .unwrap();
+        let public_input =
+            execute_program_to_files(&mut client, program_content, &memory_file, &trace_file)
+                .await
+                .unwrap();
+
+        assert_eq!(public_input, expected.public_input);
+        assert_eq!(std::fs::read(memory_file).unwrap(), expected.memory);
+        assert_eq!(std::fs::read(trace_file).unwrap(), expected.trace);
+    }
+
     #[rstest]
     #[tokio::test]
     async fn test_prove(
@@ -41,14 +245,98 @@ mod tests {
             test_case.memory,
             test_case.trace,
             test_case.prover_config,
-            test_case.prover_parameters,
+            Some(test_case.prover_parameters),
         )
         .await;
 
         assert!(result.is_ok(), "{:?}", result);
 
         let proof = result.unwrap();
-        assert_eq!(proof.proof_hex, test_case.proof.proof_hex);
+        assert_or_update_proof(&proof, &test_case.proof_file);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_prove_from_files(
+        #[future] prover_client_server_with_file_roots: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server_with_file_roots.await;
+
+        let proof = prove_from_files(
+            &mut client,
+            &get_test_case_file_path("fibonacci/fibonacci_public_input.json"),
+            &get_test_case_file_path("fibonacci/fibonacci_private_input.json"),
+            &get_test_case_file_path("fibonacci/fibonacci_memory.bin"),
+            &get_test_case_file_path("fibonacci/fibonacci_trace.bin"),
+            test_case.prover_config,
+            Some(test_case.prover_parameters),
+        )
+        .await
+        .unwrap();
+
+        assert_or_update_proof(&proof, &test_case.proof_file);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_prove_from_files_outside_roots(
+        #[future] prover_client_server_with_file_roots: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server_with_file_roots.await;
+
+        // Files outside of the roots of the server are rejected, even if they exist
+        let status = prove_from_files(
+            &mut client,
+            &get_test_case_file_path("fibonacci/fibonacci_public_input.json"),
+            &get_test_case_file_path("fibonacci/fibonacci_private_input.json"),
+            &get_test_case_file_path("fibonacci/fibonacci_memory.bin"),
+            &get_test_case_file_path("bootloader/programs/fibonacci/program.json"),
+            test_case.prover_config,
+            Some(test_case.prover_parameters),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert!(status.message().starts_with("trace_path"));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_prove_moved_artifacts(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+        let artifacts = run_cairo_program_in_proof_mode(
+            &test_case.compiled_program,
+            "starknet_with_keccak",
+            None,
+        )
+        .unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        let original_dir = root.path().join("original");
+        let moved_dir = root.path().join("moved");
+        std::fs::create_dir(&original_dir).unwrap();
+        write_artifacts_to_dir(&artifacts, &original_dir).unwrap();
+        std::fs::rename(&original_dir, &moved_dir).unwrap();
+        let artifacts = read_artifacts_from_dir(&moved_dir).unwrap();
+
+        let proof = prove_execution(
+            &mut client,
+            artifacts.public_input,
+            artifacts.private_input,
+            artifacts.memory,
+            artifacts.trace,
+            test_case.prover_config,
+            Some(test_case.prover_parameters),
+        )
+        .await
+        .unwrap();
+
+        assert_or_update_proof(&proof, &test_case.proof_file);
     }
 
     #[rstest]
@@ -81,6 +369,392 @@ mod tests {
         assert!(result.is_ok(), "{:?}", result);
 
         let proof = result.unwrap();
-        assert_eq!(proof.proof_hex, test_case.proof.proof_hex);
+        assert_or_update_proof(&proof, &test_case.proof_file);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_with_max_fri_step(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+        let fri_step_list = |parameters: &serde_json::Value| -> Vec<u64> {
+            serde_json::from_value(parameters["stark"]["fri"]["fri_step_list"].clone()).unwrap()
+        };
+        let default_fri_step_list =
+            fri_step_list(&serde_json::to_value(&test_case.prover_parameters).unwrap());
+
+        let request = ExecutionRequest {
+            program: test_case.compiled_program,
+            max_fri_step: Some(3),
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        let proof: serde_json::Value = serde_json::from_str(&response.proof).unwrap();
+        let fri_step_list = fri_step_list(&proof["proof_parameters"]);
+        assert!(fri_step_list.iter().all(|step| *step <= 3));
+        assert_eq!(
+            fri_step_list.iter().sum::<u64>(),
+            default_fri_step_list.iter().sum::<u64>()
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_deterministic_proofs(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+        let request = ExecutionRequest {
+            program: test_case.compiled_program,
+            prover_config: Some(serde_json::to_string(&test_case.prover_config).unwrap()),
+            prover_parameters: Some(serde_json::to_string(&test_case.prover_parameters).unwrap()),
+            deterministic: true,
+            ..Default::default()
+        };
+
+        let mut responses = vec![];
+        for _ in 0..2 {
+            let response = client
+                .execute_and_prove(request.clone())
+                .await
+                .unwrap()
+                .into_inner();
+            responses.push(response);
+        }
+
+        let proof_hex = |proof: &str| {
+            let proof: serde_json::Value = serde_json::from_str(proof).unwrap();
+            proof["proof_hex"].as_str().unwrap().to_string()
+        };
+        assert_eq!(responses[0].input_digest, responses[1].input_digest);
+        assert_eq!(
+            proof_hex(&responses[0].proof),
+            proof_hex(&responses[1].proof)
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_verify_proof_from_response(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+        let request = ExecutionRequest {
+            program: test_case.compiled_program,
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        let proof = StoneProof::from_json_str(&response.proof).unwrap();
+        let working_dir = tempfile::tempdir().unwrap();
+        let proof_file = working_dir.path().join("proof.json");
+        proof.write_to_file(&proof_file).unwrap();
+
+        let result = run_verifier_async(&proof_file).await;
+        assert!(result.is_ok(), "{:?}", result);
+    }
+
+    /// Runs the verifier on `proof_content` and returns the status code of the failure.
+    async fn verifier_status_code(proof_content: &[u8]) -> tonic::Code {
+        let working_dir = tempfile::tempdir().unwrap();
+        let proof_file = working_dir.path().join("proof.json");
+        std::fs::write(&proof_file, proof_content).unwrap();
+
+        let error = run_verifier_async(&proof_file)
+            .await
+            .expect_err("The verifier accepted the proof");
+        format_verifier_error(error).code()
+    }
+
+    #[tokio::test]
+    async fn test_verify_bit_flipped_proof() {
+        let proof_file = get_test_case_file_path("fibonacci/fibonacci_proof.json");
+        let mut proof: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(proof_file).unwrap()).unwrap();
+        let mut proof_hex: Vec<u8> = proof["proof_hex"].as_str().unwrap().as_bytes().to_vec();
+        let index = proof_hex.len() / 2;
+        let digit = (proof_hex[index] as char).to_digit(16).unwrap();
+        proof_hex[index] = std::char::from_digit(digit ^ 1, 16).unwrap() as u8;
+        proof["proof_hex"] = String::from_utf8(proof_hex).unwrap().into();
+
+        let code = verifier_status_code(&serde_json::to_vec(&proof).unwrap()).await;
+        assert_eq!(code, tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_verify_truncated_proof() {
+        let proof_file = get_test_case_file_path("fibonacci/fibonacci_proof.json");
+        let proof_content = std::fs::read(proof_file).unwrap();
+
+        let code = verifier_status_code(&proof_content[..proof_content.len() / 2]).await;
+        assert_eq!(code, tonic::Code::InvalidArgument);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_estimate_proof(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+        let nb_steps = test_case.public_input.n_steps;
+
+        let estimate = estimate_proof(
+            &mut client,
+            nb_steps,
+            test_case.public_input.layout.clone(),
+            Some(test_case.prover_parameters),
+        )
+        .await
+        .unwrap();
+
+        let proof_size = serde_json::to_string(&test_case.proof).unwrap().len() as u64;
+        assert!(estimate.proof_size_bytes > proof_size / 10);
+        assert!(estimate.proof_size_bytes < proof_size * 10);
+
+        // Parameters generated by the server
+        let larger_estimate = estimate_proof(&mut client, nb_steps * 4, String::new(), None)
+            .await
+            .unwrap();
+        assert!(larger_estimate.proof_size_bytes > estimate.proof_size_bytes);
+        assert!(larger_estimate.estimated_duration_ms > estimate.estimated_duration_ms);
+
+        let status = estimate_proof(&mut client, 0, String::new(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_over_limit(
+        #[future] prover_client_server_with_proof_limit: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (client, _server) = prover_client_server_with_proof_limit.await;
+        let (mut first_client, mut second_client) = (client.clone(), client);
+
+        let (first, second) = tokio::join!(
+            execute_and_prove(
+                &mut first_client,
+                test_case.compiled_program.clone(),
+                None,
+                None
+            ),
+            execute_and_prove(&mut second_client, test_case.compiled_program, None, None),
+        );
+
+        // Only one of the proofs can run, the other one is rejected
+        let status = match (first, second) {
+            (Ok(_), Err(status)) | (Err(status), Ok(_)) => status,
+            results => panic!("expected a single proof to be rejected, got {:?}", results),
+        };
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(status.metadata().get(QUEUE_POSITION_HEADER).unwrap(), "1");
+        assert!(status.metadata().get(ESTIMATED_WAIT_HEADER).is_some());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_small_proof_runs_while_large_slot_is_taken(
+        #[future] prover_client_server_with_reserved_slot: (RpcClient, RpcServer),
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        let (client, _server) = prover_client_server_with_reserved_slot.await;
+        let (mut first_client, mut second_client, mut small_client) =
+            (client.clone(), client.clone(), client);
+        let small_request = ExecutionRequest {
+            program: test_case.compiled_program.clone(),
+            small_proof: true,
+            ..Default::default()
+        };
+
+        let (first, second, small) = tokio::join!(
+            execute_and_prove(
+                &mut first_client,
+                test_case.compiled_program.clone(),
+                None,
+                None
+            ),
+            execute_and_prove(
+                &mut second_client,
+                test_case.compiled_program.clone(),
+                None,
+                None
+            ),
+            small_client.execute_and_prove(small_request),
+        );
+
+        // The large proofs share a single slot: one of them is rejected
+        let status = match (first, second) {
+            (Ok(_), Err(status)) | (Err(status), Ok(_)) => status,
+            results => panic!(
+                "expected a single large proof to be rejected, got {:?}",
+                results
+            ),
+        };
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(status.metadata().get(PROOF_CLASS_HEADER).unwrap(), "large");
+
+        // The small proof runs in the reserved slot
+        let response = small.unwrap().into_inner();
+        let proof: Proof = serde_json::from_str(&response.proof).unwrap();
+        assert_or_update_proof(&proof, &test_case.proof_file);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_with_keepalive(
+        #[from(parsed_prover_test_case)] test_case: ParsedProverTestCase,
+    ) {
+        // Aggressive keepalive settings: pings are sent every second, both while the proof
+        // runs and while the connection is idle, and must be acknowledged within a second.
+        let server_config = ServerConfig {
+            http2_keepalive_interval_secs: 1,
+            http2_keepalive_timeout_secs: 1,
+            tcp_keepalive_secs: 1,
+            ..Default::default()
+        };
+        let client_config = ClientConfig {
+            http2_keepalive_interval: Some(Duration::from_secs(1)),
+            keepalive_timeout: Duration::from_secs(1),
+            keepalive_while_idle: true,
+            tcp_keepalive: Some(Duration::from_secs(1)),
+            timeout: None,
+        };
+        let (mut client, _server) =
+            prover_client_server_over_tcp(server_config, client_config).await;
+
+        let proof = execute_and_prove(&mut client, test_case.compiled_program.clone(), None, None)
+            .await
+            .unwrap();
+        assert_or_update_proof(&proof, &test_case.proof_file);
+
+        // The connection survives an idle period
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let proof = execute_and_prove(&mut client, test_case.compiled_program, None, None)
+            .await
+            .unwrap();
+        assert_or_update_proof(&proof, &test_case.proof_file);
+    }
+
+    fn toy_hint(
+        _vm: &mut VirtualMachine,
+        _exec_scopes: &mut ExecutionScopes,
+        _ids_data: &HashMap<String, HintReference>,
+        _ap_tracking: &ApTracking,
+        _constants: &HashMap<String, Felt252>,
+    ) -> Result<(), HintError> {
+        Ok(())
+    }
+
+    fn toy_hint_processor() -> BuiltinHintProcessor {
+        let mut hint_processor = BuiltinHintProcessor::new_empty();
+        hint_processor.add_hint(
+            "toy_hint()".to_string(),
+            Rc::new(HintFunc(Box::new(toy_hint))),
+        );
+        hint_processor
+    }
+
+    fn panicking_hint(
+        _vm: &mut VirtualMachine,
+        _exec_scopes: &mut ExecutionScopes,
+        _ids_data: &HashMap<String, HintReference>,
+        _ap_tracking: &ApTracking,
+        _constants: &HashMap<String, Felt252>,
+    ) -> Result<(), HintError> {
+        panic!("injected fault");
+    }
+
+    /// Fault injection: a hint processor that panics when running the toy hint.
+    fn panicking_hint_processor() -> BuiltinHintProcessor {
+        let mut hint_processor = BuiltinHintProcessor::new_empty();
+        hint_processor.add_hint(
+            "toy_hint()".to_string(),
+            Rc::new(HintFunc(Box::new(panicking_hint))),
+        );
+        hint_processor
+    }
+
+    fn custom_hint_execution_request(hint_profile: Option<&str>) -> ExecutionRequest {
+        let program_path = get_test_case_file_path("custom-hint/custom_hint_compiled.json");
+        let program = std::fs::read(program_path).unwrap();
+
+        ExecutionRequest {
+            program,
+            hint_profile: hint_profile.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_with_custom_hint_profile(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+    ) {
+        register_hint_profile("toy", toy_hint_processor);
+        let (mut client, _server) = prover_client_server.await;
+
+        let result = client
+            .execute(custom_hint_execution_request(Some("toy")))
+            .await;
+        assert!(result.is_ok(), "{:?}", result);
+
+        // The default profile does not know about the toy hint
+        let result = client.execute(custom_hint_execution_request(None)).await;
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_with_unknown_hint_profile(
+        #[future] prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let (mut client, _server) = prover_client_server.await;
+
+        let result = client
+            .execute(custom_hint_execution_request(Some("unknown")))
+            .await;
+
+        let status = result.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_panic_in_handler(#[future] prover_client_server: (RpcClient, RpcServer)) {
+        register_hint_profile("panicking", panicking_hint_processor);
+        let (mut client, _server) = prover_client_server.await;
+
+        let status = client
+            .execute(custom_hint_execution_request(Some("panicking")))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Internal);
+        assert!(
+            status.message().contains("injected fault"),
+            "{}",
+            status.message()
+        );
+
+        // The server keeps serving, on the same connection
+        let program_path = get_test_case_file_path("fibonacci/fibonacci_compiled.json");
+        let program_content = std::fs::read(program_path).unwrap();
+        let result = execute_program(&mut client, program_content).await;
+        assert!(result.is_ok(), "{:?}", result);
     }
 }
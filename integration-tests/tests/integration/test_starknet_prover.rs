@@ -2,13 +2,26 @@
 mod tests {
     type RpcClient = StarknetProverClient<tonic::transport::Channel>;
 
-    use crate::integration::toolkit::{starknet_prover_client_server, RpcServer};
-    use madara_prover_rpc_client::services::starknet_prover::execute_and_prove;
+    use crate::integration::toolkit::{
+        starknet_prover_client_server, starknet_prover_client_server_tcp,
+        starknet_prover_client_server_with_policy, starknet_prover_client_server_with_storage,
+        RpcServer,
+    };
+    use madara_prover_rpc_client::services::starknet_prover::starknet_prover_proto::{
+        StarknetExecutionRequest, StarknetProverResponse,
+    };
+    use madara_prover_rpc_client::services::starknet_prover::{
+        execute_and_prove, execute_and_prove_compressed, execute_and_prove_with_public_input,
+        get_stored_proof, StarknetProveOptions,
+    };
+    use cairo_vm::Felt252;
+    use madara_prover_rpc_server::facts::{FactTopology, TaskOutput};
     use rstest::rstest;
-    use stone_prover_sdk::models::Proof;
+    use stone_prover_sdk::models::{Proof, ProverConfig, ProverParameters, PublicInput};
     use stone_prover_sdk::json::read_json_from_file;
     use madara_prover_rpc_client::services::starknet_prover::starknet_prover_proto::starknet_prover_client::StarknetProverClient;
     use test_cases::get_test_case_file_path;
+    use test_fixtures::assert_or_update_proof;
 
     #[rstest]
     #[tokio::test]
@@ -19,7 +32,6 @@ mod tests {
         let program_file = test_case_dir.join("program.json");
         let proof_file = test_case_dir.join("output/proof.json");
         let program_bytes = std::fs::read(program_file).unwrap();
-        let expected_proof: Proof = read_json_from_file(proof_file).unwrap();
 
         let (mut client, _server) = starknet_prover_client_server.await;
 
@@ -27,39 +39,413 @@ mod tests {
         let pies = vec![];
         let split_proof = false;
 
-        let result = execute_and_prove(&mut client, programs, pies, split_proof).await;
+        let result = execute_and_prove(&mut client, programs, pies, split_proof, None, None).await;
 
         assert!(result.is_ok(), "{:?}", result);
 
         let proof = result.unwrap();
-        assert_eq!(proof.proof_hex, expected_proof.proof_hex);
+        assert_or_update_proof(&proof, &proof_file);
         assert!(proof.split_proofs.is_none());
     }
 
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_over_tcp(
+        #[future] starknet_prover_client_server_tcp: (RpcClient, RpcServer),
+    ) {
+        let test_case_dir = get_test_case_file_path("bootloader/programs/fibonacci");
+        let program_bytes = std::fs::read(test_case_dir.join("program.json")).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server_tcp.await;
+
+        let proof = execute_and_prove(&mut client, vec![program_bytes], vec![], false, None, None)
+            .await
+            .unwrap();
+
+        assert_or_update_proof(&proof, &test_case_dir.join("output/proof.json"));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_compressed(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let test_case_dir = get_test_case_file_path("bootloader/programs/fibonacci");
+        let program_bytes = std::fs::read(test_case_dir.join("program.json")).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes.clone()],
+            compress_proof: true,
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(response.proof.is_empty());
+        assert!(!response.compressed_proof.is_empty());
+        assert!(response.compressed_proof.len() < response.proof_size as usize);
+
+        let proof = execute_and_prove(
+            &mut client,
+            vec![program_bytes.clone()],
+            vec![],
+            false,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let decompressed_proof = execute_and_prove_compressed(
+            &mut client,
+            vec![program_bytes],
+            vec![],
+            StarknetProveOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            serde_json::to_value(decompressed_proof).unwrap(),
+            serde_json::to_value(proof).unwrap()
+        );
+    }
+
+    /// Returns the values written to the output segment, in order.
+    fn output_segment(public_input: &PublicInput) -> Vec<String> {
+        let output_segment = &public_input.memory_segments["output"];
+        let mut output_entries: Vec<_> = public_input
+            .public_memory
+            .iter()
+            .filter(|entry| {
+                entry.address >= output_segment.begin_addr
+                    && entry.address < output_segment.stop_ptr
+            })
+            .collect();
+        output_entries.sort_by_key(|entry| entry.address);
+
+        output_entries
+            .into_iter()
+            .map(|entry| entry.value.clone())
+            .collect()
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_returns_public_input(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let test_case_dir = get_test_case_file_path("bootloader/programs/fibonacci");
+        let program_bytes = std::fs::read(test_case_dir.join("program.json")).unwrap();
+        let expected_public_input: PublicInput =
+            read_json_from_file(test_case_dir.join("output/air_public_input.json")).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let (_proof, public_input) = execute_and_prove_with_public_input(
+            &mut client,
+            vec![program_bytes],
+            vec![],
+            false,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let output = output_segment(&public_input);
+        assert!(!output.is_empty());
+        assert_eq!(output, output_segment(&expected_public_input));
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_returns_program_hashes(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let test_case_dir = get_test_case_file_path("bootloader/programs/fibonacci");
+        let program_bytes = std::fs::read(test_case_dir.join("program.json")).unwrap();
+        let expected_public_input: PublicInput =
+            read_json_from_file(test_case_dir.join("output/air_public_input.json")).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes],
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        // The program hash of the first task follows the bootloader header and the task size
+        let expected_program_hash = &output_segment(&expected_public_input)[4];
+        assert_eq!(response.program_hashes.len(), 1);
+        assert_eq!(
+            Felt252::from_hex(&response.program_hashes[0]).unwrap(),
+            Felt252::from_hex(expected_program_hash).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_returns_task_outputs(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let pie_file = get_test_case_file_path("bootloader/pies/fibonacci/cairo_pie.zip");
+        let pie_bytes = std::fs::read(pie_file).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let request = StarknetExecutionRequest {
+            pies: vec![pie_bytes],
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.task_outputs.len(), 1);
+        let task_output = &response.task_outputs[0];
+        assert_eq!(task_output.program_hash, response.program_hashes[0]);
+        let output: Vec<Felt252> = task_output
+            .output
+            .iter()
+            .map(|value| Felt252::from_hex(value).unwrap())
+            .collect();
+        assert_eq!(output, vec![Felt252::from(10u64), Felt252::from(144u64)]);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_stores_proof(
+        #[future] starknet_prover_client_server_with_storage: (
+            RpcClient,
+            RpcServer,
+            tempfile::TempDir,
+        ),
+    ) {
+        let program_file = get_test_case_file_path("bootloader/programs/fibonacci/program.json");
+        let program_bytes = std::fs::read(program_file).unwrap();
+
+        let (mut client, _server, _storage_dir) = starknet_prover_client_server_with_storage.await;
+
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes],
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        let storage_ref = response.storage_ref.expect("proof should be stored");
+        let stored_proof = get_stored_proof(&mut client, storage_ref).await.unwrap();
+
+        assert_eq!(stored_proof.proof, response.proof);
+        assert!(stored_proof.public_input.is_some());
+        assert!(stored_proof.annotated_proof.is_none());
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_stored_proof_not_found(
+        #[future] starknet_prover_client_server_with_storage: (
+            RpcClient,
+            RpcServer,
+            tempfile::TempDir,
+        ),
+    ) {
+        let (mut client, _server, _storage_dir) = starknet_prover_client_server_with_storage.await;
+
+        let status = get_stored_proof(&mut client, "unknown".to_string())
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_get_stored_proof_without_storage(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let status = get_stored_proof(&mut client, "unknown".to_string())
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_with_config_and_parameters(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let test_case_dir = get_test_case_file_path("bootloader/programs/fibonacci");
+        let program_bytes = std::fs::read(test_case_dir.join("program.json")).unwrap();
+        let prover_config: ProverConfig =
+            read_json_from_file(test_case_dir.join("cpu_air_prover_config.json")).unwrap();
+        let prover_parameters: ProverParameters =
+            read_json_from_file(test_case_dir.join("cpu_air_params.json")).unwrap();
+        let proof_file = test_case_dir.join("output/proof.json");
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let proof = execute_and_prove(
+            &mut client,
+            vec![program_bytes],
+            vec![],
+            false,
+            Some(prover_config),
+            Some(prover_parameters),
+        )
+        .await
+        .unwrap();
+
+        assert_or_update_proof(&proof, &proof_file);
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_invalid_parameters(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let program_file = get_test_case_file_path("bootloader/programs/fibonacci/program.json");
+        let program_bytes = std::fs::read(program_file).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes],
+            prover_parameters: Some("not parameters".to_string()),
+            ..Default::default()
+        };
+        let status = client.execute_and_prove(request).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[rstest]
+    #[case::split_proof(true, None, "allow_split_proof")]
+    #[case::user_parameters(false, Some("{}".to_string()), "allow_user_parameters")]
+    #[case::layout(false, None, "allowed_layouts: recursive")]
+    #[tokio::test]
+    async fn test_execute_and_prove_rejected_by_policy(
+        #[future] starknet_prover_client_server_with_policy: (RpcClient, RpcServer),
+        #[case] split_proof: bool,
+        #[case] prover_parameters: Option<String>,
+        #[case] policy_setting: &str,
+    ) {
+        let program_file = get_test_case_file_path("bootloader/programs/fibonacci/program.json");
+        let program_bytes = std::fs::read(program_file).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server_with_policy.await;
+
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes],
+            split_proof,
+            prover_parameters,
+            ..Default::default()
+        };
+        let status = client.execute_and_prove(request).await.unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert!(
+            status.message().contains(policy_setting),
+            "{}",
+            status.message()
+        );
+    }
+
+    /// Public data of the bootloader proof, from the fact topologies and task outputs returned
+    /// by the server.
+    fn bootloader_public_data(
+        response: &StarknetProverResponse,
+    ) -> evm_adapter::BootloaderPublicData {
+        let fact_topologies = response
+            .fact_topologies
+            .iter()
+            .map(|fact_topology| FactTopology {
+                tree_structure: fact_topology
+                    .tree_structure
+                    .iter()
+                    .map(|x| *x as usize)
+                    .collect(),
+                page_sizes: fact_topology
+                    .page_sizes
+                    .iter()
+                    .map(|x| *x as usize)
+                    .collect(),
+            })
+            .collect();
+        let task_outputs = response
+            .task_outputs
+            .iter()
+            .map(|task_output| TaskOutput {
+                program_hash: Felt252::from_hex(&task_output.program_hash).unwrap(),
+                output: task_output
+                    .output
+                    .iter()
+                    .map(|value| Felt252::from_hex(value).unwrap())
+                    .collect(),
+            })
+            .collect();
+
+        evm_adapter::BootloaderPublicData {
+            public_input: serde_json::from_str(&response.public_input).unwrap(),
+            fact_topologies,
+            task_outputs,
+        }
+    }
+
     #[ignore = "needs RPC URL"] // see "<redacted>" below
     #[rstest]
+    #[case::anvil_key(None, evm_adapter::DEFAULT_STATEMENT_PARALLELISM)]
+    // First dev account of anvil
+    #[case::explicit_key(
+        Some("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"),
+        evm_adapter::DEFAULT_STATEMENT_PARALLELISM
+    )]
+    #[case::sequential(None, 1)]
     #[tokio::test]
     async fn test_execute_and_prove_and_split(
         #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+        #[case] signer_key: Option<&str>,
+        #[case] statement_parallelism: usize,
     ) {
         let test_case_dir = get_test_case_file_path("bootloader/programs/fibonacci");
         let program_file = test_case_dir.join("program.json");
         let proof_file = test_case_dir.join("output/proof.json");
         let program_bytes = std::fs::read(program_file).unwrap();
-        let expected_proof: Proof = read_json_from_file(proof_file).unwrap();
 
         let (mut client, _server) = starknet_prover_client_server.await;
 
-        let programs = vec![program_bytes];
-        let pies = vec![];
-        let split_proof = true;
-
-        let result = execute_and_prove(&mut client, programs, pies, split_proof).await;
-
-        assert!(result.is_ok(), "{:?}", result);
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes],
+            split_proof: true,
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
 
-        let proof = result.unwrap();
-        assert_eq!(proof.proof_hex, expected_proof.proof_hex);
+        let proof: Proof = serde_json::from_str(&response.proof).unwrap();
+        assert_or_update_proof(&proof, &proof_file);
+        // The main proof is verified with the metadata of the tasks returned by the server
+        let bootloader_data = bootloader_public_data(&response);
 
         assert!(proof.split_proofs.is_some());
         let split_proofs = proof.split_proofs.unwrap();
@@ -67,9 +453,115 @@ mod tests {
         assert!(split_proofs.fri_merkle_statements.len() > 0);
 
         let private_url = "<redacted>";
-        evm_adapter::verify_split_proofs_with_l1(&split_proofs, private_url.into())
+        let report = evm_adapter::verify_split_proofs_with_l1(
+            &split_proofs,
+            evm_adapter::EthEndpoint::Fork(private_url.into()),
+            &evm_adapter::VerifierContracts::mainnet(),
+            signer_key.map(|key| evm_adapter::parse_signing_key(key).unwrap()),
+            Some(&bootloader_data),
+            statement_parallelism,
+            false,
+        )
+        .await
+        .unwrap();
+
+        let n_calls =
+            split_proofs.merkle_statements.len() + split_proofs.fri_merkle_statements.len() + 1;
+        assert_eq!(report.items.len(), n_calls);
+        assert!(report.total_gas > 0);
+        // The statements are reported in order, before the main proof that depends on them
+        assert_eq!(report.items[0].0, "Trace 0");
+        assert_eq!(report.items[n_calls - 1].0, "Main proof");
+    }
+
+    #[ignore = "needs RPC URL"] // see "<redacted>" below
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_and_verify_bootloader_proof(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let program_file = get_test_case_file_path("bootloader/programs/fibonacci/program.json");
+        let program_bytes = std::fs::read(program_file).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes],
+            split_proof: true,
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
             .await
-            .unwrap();
+            .unwrap()
+            .into_inner();
+
+        let proof: Proof = serde_json::from_str(&response.proof).unwrap();
+        let split_proofs = proof.split_proofs.unwrap();
+        // The fact topologies recorded by the bootloader
+        let bootloader_data = bootloader_public_data(&response);
+
+        let private_url = "<redacted>";
+        evm_adapter::verify_split_proofs_with_l1(
+            &split_proofs,
+            evm_adapter::EthEndpoint::Fork(private_url.into()),
+            &evm_adapter::VerifierContracts::mainnet(),
+            None,
+            Some(&bootloader_data),
+            evm_adapter::DEFAULT_STATEMENT_PARALLELISM,
+            false,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn test_execute_and_prove_multiple_tasks(
+        #[future] starknet_prover_client_server: (RpcClient, RpcServer),
+    ) {
+        let program_file = get_test_case_file_path("bootloader/programs/fibonacci/program.json");
+        let pie_file = get_test_case_file_path("bootloader/pies/fibonacci/cairo_pie.zip");
+        let program_bytes = std::fs::read(program_file).unwrap();
+        let pie_bytes = std::fs::read(pie_file).unwrap();
+
+        let (mut client, _server) = starknet_prover_client_server.await;
+
+        let request = StarknetExecutionRequest {
+            programs: vec![program_bytes.clone(), program_bytes],
+            pies: vec![pie_bytes],
+            // Splitting the proof runs the verifier on it
+            split_proof: true,
+            ..Default::default()
+        };
+        let response = client
+            .execute_and_prove(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        let proof: Proof = serde_json::from_str(&response.proof).unwrap();
+        assert!(proof.split_proofs.is_some());
+
+        // The tasks add no output pages: one single-page topology per task, covering its output
+        let output_sizes: Vec<u64> = response
+            .task_outputs
+            .iter()
+            .map(|task_output| task_output.output.len() as u64)
+            .collect();
+        assert_eq!(output_sizes, vec![0, 2, 0]);
+        assert_eq!(response.fact_topologies.len(), 3);
+        for (fact_topology, output_size) in response.fact_topologies.iter().zip(output_sizes) {
+            assert_eq!(fact_topology.tree_structure, vec![1, 0]);
+            assert_eq!(fact_topology.page_sizes, vec![output_size]);
+        }
+
+        // The fact of a task only depends on its program and output
+        let fibonacci_fact = "0x61fedf74dd5aea0e5db4b64b5a53f8b3dd2df4ece2e38a5f94cf49596894d288";
+        assert_eq!(response.fact_hashes.len(), 3);
+        assert_eq!(response.fact_hashes[0], fibonacci_fact);
+        assert_eq!(response.fact_hashes[2], fibonacci_fact);
+        assert_ne!(response.fact_hashes[1], fibonacci_fact);
     }
 
     #[ignore = "this test takes ~5 minutes to run"]
@@ -82,7 +574,6 @@ mod tests {
         let os_pie_file = test_case_dir.join("os.zip");
         let proof_file = test_case_dir.join("output/proof.json");
         let pie_bytes = std::fs::read(os_pie_file).unwrap();
-        let expected_proof: Proof = read_json_from_file(proof_file).unwrap();
 
         let (mut client, _server) = starknet_prover_client_server.await;
 
@@ -90,12 +581,12 @@ mod tests {
         let pies = vec![pie_bytes];
         let split_proof = false;
 
-        let result = execute_and_prove(&mut client, programs, pies, split_proof).await;
+        let result = execute_and_prove(&mut client, programs, pies, split_proof, None, None).await;
 
         assert!(result.is_ok(), "{:?}", result);
 
         let proof = result.unwrap();
-        assert_eq!(proof.proof_hex, expected_proof.proof_hex);
+        assert_or_update_proof(&proof, &proof_file);
     }
 
     #[rstest]
@@ -107,7 +598,6 @@ mod tests {
         let os_pie_file = test_case_dir.join("cairo_pie.zip");
         let proof_file = test_case_dir.join("output/proof.json");
         let pie_bytes = std::fs::read(os_pie_file).unwrap();
-        let expected_proof: Proof = read_json_from_file(proof_file).unwrap();
 
         let (mut client, _server) = starknet_prover_client_server.await;
 
@@ -115,11 +605,11 @@ mod tests {
         let pies = vec![pie_bytes];
         let split_proof = false;
 
-        let result = execute_and_prove(&mut client, programs, pies, split_proof).await;
+        let result = execute_and_prove(&mut client, programs, pies, split_proof, None, None).await;
 
         assert!(result.is_ok(), "{:?}", result);
 
         let proof = result.unwrap();
-        assert_eq!(proof.proof_hex, expected_proof.proof_hex);
+        assert_or_update_proof(&proof, &proof_file);
     }
 }
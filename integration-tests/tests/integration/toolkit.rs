@@ -1,15 +1,27 @@
+use madara_prover_rpc_client::config::ClientConfig;
 use madara_prover_rpc_client::services::prover::prover_proto::prover_client::ProverClient;
 use madara_prover_rpc_client::services::starknet_prover::starknet_prover_proto::starknet_prover_client::StarknetProverClient;
 use madara_prover_rpc_server::error::ServerError;
-use madara_prover_rpc_server::{run_grpc_server, BindAddress};
+use madara_prover_rpc_server::services::prover::prover_proto::prover_server::ProverServer;
+use madara_prover_rpc_server::services::prover::ProverService;
+use madara_prover_rpc_server::listener::ServerListener;
+use madara_prover_rpc_server::policy::ProverPolicy;
+use madara_prover_rpc_server::{run_grpc_server, run_grpc_server_with_listener, ServerConfig};
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 use rstest::fixture;
+use test_cases::get_test_case_file_path;
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::net::UnixStream;
 use tokio::task::JoinHandle;
-use tonic::transport::{Endpoint, Uri};
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Endpoint, Server, Uri};
+use tonic::Status;
 use tower::service_fn;
 
 pub type RpcServer = JoinHandle<Result<(), ServerError>>;
@@ -30,20 +42,26 @@ fn generate_socket_path() -> PathBuf {
 /// The client and server communicate over a Unix socket.
 async fn rpc_client_server<T>(
     client_factory: fn(tonic::transport::Channel) -> T,
+    config: ServerConfig,
 ) -> (T, RpcServer) {
     let unix_socket_client = generate_socket_path();
     let unix_socket_server = unix_socket_client.clone();
 
     let server_task = tokio::spawn(async move {
-        run_grpc_server(BindAddress::UnixSocket(unix_socket_server.as_path())).await
+        let config = ServerConfig {
+            unix_socket: Some(unix_socket_server),
+            ..config
+        };
+        run_grpc_server(config).await
     });
 
     // TODO: attempt to declare the client until the server responds instead of this (slow) sleep
     tokio::time::sleep(Duration::from_secs(1)).await;
 
     // Note that the URI parameter is ignored.
-    let channel = Endpoint::try_from("http://[::]:65535")
-        .unwrap()
+    let endpoint = Endpoint::try_from("http://[::]:65535").unwrap();
+    let channel = ClientConfig::default()
+        .apply(endpoint)
         .connect_with_connector(service_fn(move |_: Uri| {
             UnixStream::connect(unix_socket_client.clone())
         }))
@@ -54,13 +72,217 @@ async fn rpc_client_server<T>(
     (client, server_task)
 }
 
+/// Binds a TCP listener on a port chosen by the OS, so that tests can run TCP servers
+/// concurrently. The listener is passed to the server, no other process can take the port
+/// in between.
+pub fn bind_ephemeral_tcp() -> (std::net::TcpListener, SocketAddr) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    (listener, address)
+}
+
+/// Incoming connections of a listener from `bind_ephemeral_tcp`, for custom servers.
+fn tcp_incoming(listener: std::net::TcpListener) -> TcpIncoming {
+    listener.set_nonblocking(true).unwrap();
+    let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+    TcpIncoming::from_listener(listener, false, None).unwrap()
+}
+
+/// Starts an RPC server and client communicating over TCP, on a free port.
+async fn rpc_client_server_over_tcp<T>(
+    client_factory: fn(tonic::transport::Channel) -> T,
+    config: ServerConfig,
+    client_config: ClientConfig,
+) -> (T, RpcServer) {
+    let (listener, address) = bind_ephemeral_tcp();
+    let listener = ServerListener::from_std_tcp(listener).unwrap();
+    let server_task = tokio::spawn(run_grpc_server_with_listener(config, listener));
+
+    // The port is already bound: the connection waits for the server to accept it
+    let channel = client_config
+        .connect(format!("http://{address}"))
+        .await
+        .unwrap();
+    (client_factory(channel), server_task)
+}
+
+/// Starts a prover server and client communicating over TCP.
+pub async fn prover_client_server_over_tcp(
+    config: ServerConfig,
+    client_config: ClientConfig,
+) -> (ProverClient<tonic::transport::Channel>, RpcServer) {
+    rpc_client_server_over_tcp(ProverClient::new, config, client_config).await
+}
+
+/// Starts a prover server over TCP that rejects its first `failures` requests
+/// with `UNAVAILABLE`.
+pub async fn flaky_prover_client_server(
+    failures: usize,
+) -> (ProverClient<tonic::transport::Channel>, RpcServer) {
+    let remaining_failures = Arc::new(AtomicUsize::new(failures));
+    let interceptor = move |request: tonic::Request<()>| {
+        let fail = remaining_failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+        if fail {
+            return Err(Status::unavailable("Flaky server"));
+        }
+        Ok(request)
+    };
+
+    let (listener, bind_address) = bind_ephemeral_tcp();
+    let incoming = tcp_incoming(listener);
+    let server_task = tokio::spawn(async move {
+        Server::builder()
+            .add_service(ProverServer::with_interceptor(
+                ProverService::default(),
+                interceptor,
+            ))
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(ServerError::from)
+    });
+
+    let channel = ClientConfig::default()
+        .connect(format!("http://{bind_address}"))
+        .await
+        .unwrap();
+    (ProverClient::new(channel), server_task)
+}
+
+/// Starts a prover server over TCP that only accepts requests authenticated with
+/// the bearer token `token`. Returns a channel to the server, to build clients with or
+/// without the token.
+pub async fn token_protected_prover_server(token: &str) -> (tonic::transport::Channel, RpcServer) {
+    let expected_authorization = format!("Bearer {token}");
+    let interceptor =
+        move |request: tonic::Request<()>| match request.metadata().get("authorization") {
+            Some(authorization) if authorization == expected_authorization.as_str() => Ok(request),
+            _ => Err(Status::unauthenticated("Invalid or missing token")),
+        };
+
+    let (listener, bind_address) = bind_ephemeral_tcp();
+    let incoming = tcp_incoming(listener);
+    let server_task = tokio::spawn(async move {
+        Server::builder()
+            .add_service(ProverServer::with_interceptor(
+                ProverService::default(),
+                interceptor,
+            ))
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(ServerError::from)
+    });
+
+    let channel = ClientConfig::default()
+        .connect(format!("http://{bind_address}"))
+        .await
+        .unwrap();
+    (channel, server_task)
+}
+
 #[fixture]
 pub async fn prover_client_server() -> (ProverClient<tonic::transport::Channel>, RpcServer) {
-    rpc_client_server(ProverClient::new).await
+    rpc_client_server(ProverClient::new, ServerConfig::default()).await
+}
+
+/// Prover client and server, with `ProveFromFiles` allowed to read the fibonacci test case.
+#[fixture]
+pub async fn prover_client_server_with_file_roots(
+) -> (ProverClient<tonic::transport::Channel>, RpcServer) {
+    let config = ServerConfig {
+        prove_from_files: true,
+        prove_from_files_roots: vec![get_test_case_file_path("fibonacci")],
+        ..Default::default()
+    };
+    rpc_client_server(ProverClient::new, config).await
+}
+
+/// Prover client and server, with a single proof running at a time and no queue.
+#[fixture]
+pub async fn prover_client_server_with_proof_limit(
+) -> (ProverClient<tonic::transport::Channel>, RpcServer) {
+    let config = ServerConfig {
+        max_concurrent_proofs: Some(NonZeroUsize::MIN),
+        max_queued_proofs: 0,
+        ..Default::default()
+    };
+    rpc_client_server(ProverClient::new, config).await
+}
+
+/// Prover client and server with two proof slots, one of them reserved to small proofs,
+/// and no queue.
+#[fixture]
+pub async fn prover_client_server_with_reserved_slot(
+) -> (ProverClient<tonic::transport::Channel>, RpcServer) {
+    let config = ServerConfig {
+        max_concurrent_proofs: NonZeroUsize::new(2),
+        max_queued_proofs: 0,
+        reserved_small_proofs: 1,
+        ..Default::default()
+    };
+    rpc_client_server(ProverClient::new, config).await
+}
+
+/// Same as `prover_client_server`, over TCP.
+#[fixture]
+pub async fn prover_client_server_tcp() -> (ProverClient<tonic::transport::Channel>, RpcServer) {
+    rpc_client_server_over_tcp(
+        ProverClient::new,
+        ServerConfig::default(),
+        ClientConfig::default(),
+    )
+    .await
 }
 
 #[fixture]
 pub async fn starknet_prover_client_server(
 ) -> (StarknetProverClient<tonic::transport::Channel>, RpcServer) {
-    rpc_client_server(StarknetProverClient::new).await
+    rpc_client_server(StarknetProverClient::new, ServerConfig::default()).await
+}
+
+/// Same as `starknet_prover_client_server`, over TCP.
+#[fixture]
+pub async fn starknet_prover_client_server_tcp(
+) -> (StarknetProverClient<tonic::transport::Channel>, RpcServer) {
+    rpc_client_server_over_tcp(
+        StarknetProverClient::new,
+        ServerConfig::default(),
+        ClientConfig::default(),
+    )
+    .await
+}
+
+/// Starknet prover client and server, with a policy forbidding split proofs, user-provided
+/// parameters and layouts other than `recursive`.
+#[fixture]
+pub async fn starknet_prover_client_server_with_policy(
+) -> (StarknetProverClient<tonic::transport::Channel>, RpcServer) {
+    let config = ServerConfig {
+        policy: ProverPolicy {
+            allowed_layouts: Some(vec!["recursive".to_string()]),
+            max_n_steps: None,
+            allow_split_proof: false,
+            allow_user_parameters: false,
+            allow_small_proof: true,
+        },
+        ..Default::default()
+    };
+    rpc_client_server(StarknetProverClient::new, config).await
+}
+
+/// Starknet prover client and server, with proofs stored in a temporary directory.
+#[fixture]
+pub async fn starknet_prover_client_server_with_storage() -> (
+    StarknetProverClient<tonic::transport::Channel>,
+    RpcServer,
+    tempfile::TempDir,
+) {
+    let storage_dir = tempfile::tempdir().unwrap();
+    let config = ServerConfig {
+        proof_storage_dir: Some(storage_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let (client, server) = rpc_client_server(StarknetProverClient::new, config).await;
+    (client, server, storage_dir)
 }
@@ -0,0 +1,53 @@
+use sha2::{Digest, Sha256};
+
+/// SHA-256 digest of data received in chunks, ex: the memory and trace of an execution.
+#[derive(Debug, Clone, Default)]
+pub struct Sha256Checksum(Sha256);
+
+impl Sha256Checksum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, chunk: impl AsRef<[u8]>) {
+        self.0.update(chunk);
+    }
+
+    /// Returns the digest as a lowercase hex string, like `sha256sum`.
+    pub fn finalize(self) -> String {
+        self.0
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+/// Returns the SHA-256 digest of `data` as a lowercase hex string.
+pub fn sha256_hex(data: impl AsRef<[u8]>) -> String {
+    let mut checksum = Sha256Checksum::new();
+    checksum.update(data);
+    checksum.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(sha256_hex(b"abc"), ABC_SHA256);
+    }
+
+    #[test]
+    fn test_chunked_checksum() {
+        let mut checksum = Sha256Checksum::new();
+        for chunk in [&b"a"[..], b"", b"bc"] {
+            checksum.update(chunk);
+        }
+
+        assert_eq!(checksum.finalize(), ABC_SHA256);
+    }
+}
@@ -0,0 +1,4 @@
+//! Code shared by the client and the server of the Prover API, and by their build scripts.
+
+pub mod checksum;
+pub mod proto_ext;
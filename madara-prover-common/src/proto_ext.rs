@@ -0,0 +1,100 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// `bytes` fields of the protobuf messages, serialized as base64 strings by serde. Used by
+/// the build scripts of the client and the server.
+pub const BYTES_FIELDS: &[&str] = &[
+    ".prover.ExecutionRequest.program",
+    ".prover.ExecutionResponse.public_input",
+    ".prover.ExecutionResponse.memory",
+    ".prover.ExecutionResponse.trace",
+    ".prover.ExecutionHeader.public_input",
+    ".prover.ExecutionChunk.data",
+    ".prover.ProverRequest.public_input",
+    ".prover.ProverRequest.memory",
+    ".prover.ProverRequest.trace",
+    ".starknet_prover.StarknetProverResponse.compressed_proof",
+];
+/// `repeated bytes` fields, serialized as lists of base64 strings.
+pub const REPEATED_BYTES_FIELDS: &[&str] = &[
+    ".starknet_prover.StarknetExecutionRequest.programs",
+    ".starknet_prover.StarknetExecutionRequest.pies",
+];
+
+/// JSON serialization of the protobuf messages, for logging and golden tests.
+pub trait MessageExt {
+    fn to_json(&self) -> serde_json::Result<String>;
+}
+
+impl<T: prost::Message + Serialize> MessageExt for T {
+    fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Serializes `bytes` fields as base64 strings.
+pub mod base64_bytes {
+    use super::*;
+
+    pub fn serialize<T: AsRef<[u8]>, S: Serializer>(
+        bytes: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, T: From<Vec<u8>>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD
+            .decode(encoded)
+            .map(T::from)
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Serializes `repeated bytes` fields as lists of base64 strings.
+pub mod base64_bytes_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(values: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|bytes| STANDARD.encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Vec<u8>>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|encoded| STANDARD.decode(encoded).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_bytes() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Message {
+            #[serde(with = "base64_bytes")]
+            data: Vec<u8>,
+            #[serde(with = "base64_bytes_list")]
+            chunks: Vec<Vec<u8>>,
+        }
+
+        let message = Message {
+            data: vec![1, 2, 3],
+            chunks: vec![vec![4, 5]],
+        };
+
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, r#"{"data":"AQID","chunks":["BAU="]}"#);
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+    }
+}
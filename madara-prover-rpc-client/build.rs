@@ -1,7 +1,30 @@
+use madara_prover_common::proto_ext::{BYTES_FIELDS, REPEATED_BYTES_FIELDS};
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let builder = tonic_build::configure()
+    let mut builder = tonic_build::configure()
         .protoc_arg("--experimental_allow_proto3_optional")
-        .build_server(false);
+        // Avoid copying execution artifacts between messages
+        .bytes([
+            ".prover.ExecutionResponse.memory",
+            ".prover.ExecutionResponse.trace",
+            ".prover.ProverRequest.memory",
+            ".prover.ProverRequest.trace",
+        ])
+        .build_server(false)
+        // Serialize the messages as JSON for logging and golden tests
+        .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]");
+    for field in BYTES_FIELDS {
+        builder = builder.field_attribute(
+            field,
+            r#"#[serde(with = "crate::proto_ext::base64_bytes")]"#,
+        );
+    }
+    for field in REPEATED_BYTES_FIELDS {
+        builder = builder.field_attribute(
+            field,
+            r#"#[serde(with = "crate::proto_ext::base64_bytes_list")]"#,
+        );
+    }
     builder.compile(
         &[
             "../protocols/prover.proto",
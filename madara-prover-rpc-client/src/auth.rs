@@ -0,0 +1,164 @@
+use std::fmt;
+use std::path::Path;
+
+use tonic::metadata::AsciiMetadataValue;
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::transport::Channel;
+use tonic::{Request, Status};
+
+use crate::services::prover::prover_proto::prover_client::ProverClient;
+use crate::services::starknet_prover::starknet_prover_proto::starknet_prover_client::StarknetProverClient;
+
+/// Environment variable read by `AuthToken::from_env`.
+pub const AUTH_TOKEN_ENV_VAR: &str = "MADARA_PROVER_AUTH_TOKEN";
+
+const AUTHORIZATION_HEADER: &str = "authorization";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthTokenError {
+    #[error("{AUTH_TOKEN_ENV_VAR} is not set")]
+    MissingEnvVar,
+    #[error("Could not read the token file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("The token must be a non-empty string of visible ASCII characters")]
+    InvalidToken,
+}
+
+/// Bearer token sent to the server. The token is redacted from the `Debug` output.
+#[derive(Clone)]
+pub struct AuthToken {
+    /// `authorization` metadata value, with the `Bearer` prefix.
+    header_value: AsciiMetadataValue,
+}
+
+impl fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuthToken(<redacted>)")
+    }
+}
+
+impl AuthToken {
+    pub fn new(token: &str) -> Result<Self, AuthTokenError> {
+        if token.is_empty() || !token.chars().all(|c| c.is_ascii_graphic()) {
+            return Err(AuthTokenError::InvalidToken);
+        }
+        let header_value = format!("Bearer {token}")
+            .parse()
+            .map_err(|_| AuthTokenError::InvalidToken)?;
+        Ok(Self { header_value })
+    }
+
+    /// Reads the token from the `MADARA_PROVER_AUTH_TOKEN` environment variable.
+    pub fn from_env() -> Result<Self, AuthTokenError> {
+        let token = std::env::var(AUTH_TOKEN_ENV_VAR).map_err(|_| AuthTokenError::MissingEnvVar)?;
+        Self::new(token.trim())
+    }
+
+    /// Reads the token from a file, ignoring surrounding whitespace.
+    pub fn from_file(path: &Path) -> Result<Self, AuthTokenError> {
+        let token = std::fs::read_to_string(path)?;
+        Self::new(token.trim())
+    }
+}
+
+/// Adds the `authorization` metadata to every request.
+#[derive(Debug, Clone)]
+pub struct AuthInterceptor {
+    token: AuthToken,
+}
+
+impl AuthInterceptor {
+    pub fn new(token: AuthToken) -> Self {
+        Self { token }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        request
+            .metadata_mut()
+            .insert(AUTHORIZATION_HEADER, self.token.header_value.clone());
+        Ok(request)
+    }
+}
+
+/// Channel that authenticates every request.
+pub type AuthenticatedChannel = InterceptedService<Channel, AuthInterceptor>;
+
+impl ProverClient<AuthenticatedChannel> {
+    /// Creates a client that sends `token` with every request.
+    pub fn with_token(channel: Channel, token: AuthToken) -> Self {
+        ProverClient::with_interceptor(channel, AuthInterceptor::new(token))
+    }
+}
+
+impl StarknetProverClient<AuthenticatedChannel> {
+    /// Creates a client that sends `token` with every request.
+    pub fn with_token(channel: Channel, token: AuthToken) -> Self {
+        StarknetProverClient::with_interceptor(channel, AuthInterceptor::new(token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tonic::Code;
+
+    use crate::services::prover::list_recent_jobs;
+
+    use super::*;
+
+    #[test]
+    fn test_interceptor_adds_token() {
+        let mut interceptor = AuthInterceptor::new(AuthToken::new("secret").unwrap());
+
+        let request = interceptor.call(Request::new(())).unwrap();
+
+        assert_eq!(
+            request.metadata().get(AUTHORIZATION_HEADER).unwrap(),
+            "Bearer secret"
+        );
+    }
+
+    #[test]
+    fn test_token_is_redacted() {
+        let token = AuthToken::new("secret").unwrap();
+
+        let debug_output = format!("{:?}", AuthInterceptor::new(token));
+
+        assert!(!debug_output.contains("secret"), "{debug_output}");
+    }
+
+    #[test]
+    fn test_invalid_tokens() {
+        for token in ["", "two words", "line\nbreak", "non-ascii-é"] {
+            assert!(matches!(
+                AuthToken::new(token),
+                Err(AuthTokenError::InvalidToken)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_token_from_file() {
+        let mut token_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(token_file, "secret").unwrap();
+
+        let token = AuthToken::from_file(token_file.path()).unwrap();
+
+        assert_eq!(&token.header_value, "Bearer secret");
+    }
+
+    #[tokio::test]
+    async fn test_helpers_accept_authenticated_clients() {
+        // Nothing listens on the port: the call fails, but goes through the helper
+        let channel = Channel::from_static("http://127.0.0.1:1").connect_lazy();
+        let mut client = ProverClient::with_token(channel, AuthToken::new("secret").unwrap());
+
+        let status = list_recent_jobs(&mut client, 1).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::Unavailable);
+    }
+}
@@ -0,0 +1,86 @@
+//! SHA-256 checksums of the execution artifacts, to detect truncated or corrupted transfers.
+//!
+//! The server verifies the checksums of the memory and trace of `Prove` requests, and
+//! returns the checksums of the memory and trace of executions.
+
+use tonic::Status;
+
+pub use madara_prover_common::checksum::{sha256_hex, Sha256Checksum};
+
+use crate::services::prover::prover_proto::ExecutionResponse;
+
+/// Compares the digest of downloaded data to the checksum sent by the server. Servers that
+/// do not send checksums are trusted.
+pub fn check_sha256(field: &str, actual: &str, expected: &str) -> Result<(), Status> {
+    if !expected.is_empty() && !actual.eq_ignore_ascii_case(expected) {
+        return Err(Status::data_loss(format!(
+            "{field} checksum mismatch: expected SHA-256 {expected}, got {actual}"
+        )));
+    }
+    Ok(())
+}
+
+/// Verifies the memory and trace of an execution against the checksums of the server.
+pub fn verify_execution_response(response: &ExecutionResponse) -> Result<(), Status> {
+    check_sha256(
+        "Memory",
+        &sha256_hex(&response.memory),
+        &response.memory_sha256,
+    )?;
+    check_sha256(
+        "Trace",
+        &sha256_hex(&response.trace),
+        &response.trace_sha256,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    fn execution_response(memory: &[u8], trace: &[u8]) -> ExecutionResponse {
+        ExecutionResponse {
+            public_input: vec![],
+            memory: memory.to_vec().into(),
+            trace: trace.to_vec().into(),
+            builtin_usage: None,
+            memory_sha256: sha256_hex(memory),
+            trace_sha256: sha256_hex(trace),
+        }
+    }
+
+    #[test]
+    fn test_verify_execution_response() {
+        let response = execution_response(b"memory", b"trace");
+        assert!(verify_execution_response(&response).is_ok());
+
+        // Older servers do not send checksums
+        let response = ExecutionResponse {
+            memory_sha256: String::new(),
+            trace_sha256: String::new(),
+            ..execution_response(b"memory", b"trace")
+        };
+        assert!(verify_execution_response(&response).is_ok());
+    }
+
+    #[test]
+    fn test_truncated_trace() {
+        let response = ExecutionResponse {
+            trace: b"tra".to_vec().into(),
+            ..execution_response(b"memory", b"trace")
+        };
+
+        let status = verify_execution_response(&response).unwrap_err();
+        assert_eq!(status.code(), Code::DataLoss);
+        assert_eq!(
+            status.message(),
+            format!(
+                "Trace checksum mismatch: expected SHA-256 {}, got {}",
+                sha256_hex(b"trace"),
+                sha256_hex(b"tra")
+            )
+        );
+    }
+}
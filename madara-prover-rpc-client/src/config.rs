@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use tonic::transport::{Channel, Endpoint};
+
+/// Interval between HTTP/2 keepalive pings. Proofs can run for several minutes without
+/// any data sent on the connection, the pings prevent NATs and load balancers from
+/// dropping it.
+pub const DEFAULT_HTTP2_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+pub const DEFAULT_TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+
+/// Connection settings of the clients.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// Interval between HTTP/2 keepalive pings, `None` disables the pings.
+    pub http2_keepalive_interval: Option<Duration>,
+    /// Time to wait for the acknowledgement of a keepalive ping before closing the connection.
+    pub keepalive_timeout: Duration,
+    /// Send keepalive pings even when there is no request in progress.
+    pub keepalive_while_idle: bool,
+    /// Interval between TCP keepalive probes, `None` disables TCP keepalive.
+    pub tcp_keepalive: Option<Duration>,
+    /// Maximum duration of a request.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            http2_keepalive_interval: Some(DEFAULT_HTTP2_KEEPALIVE_INTERVAL),
+            keepalive_timeout: DEFAULT_KEEPALIVE_TIMEOUT,
+            keepalive_while_idle: false,
+            tcp_keepalive: Some(DEFAULT_TCP_KEEPALIVE),
+            timeout: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Applies the settings to an endpoint, ex: one connected through a Unix socket.
+    pub fn apply(&self, mut endpoint: Endpoint) -> Endpoint {
+        if let Some(interval) = self.http2_keepalive_interval {
+            endpoint = endpoint
+                .http2_keep_alive_interval(interval)
+                .keep_alive_timeout(self.keepalive_timeout)
+                .keep_alive_while_idle(self.keepalive_while_idle);
+        }
+        if let Some(timeout) = self.timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        endpoint.tcp_keepalive(self.tcp_keepalive)
+    }
+
+    /// Connects to the server at `uri`, ex: `http://[::1]:8080`.
+    pub async fn connect(&self, uri: String) -> Result<Channel, tonic::transport::Error> {
+        let endpoint = Endpoint::from_shared(uri)?;
+        self.apply(endpoint).connect().await
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use cairo_vm::air_private_input::{AirPrivateInput, AirPrivateInputSerializable};
+use serde::de::DeserializeOwned;
+use stone_prover_sdk::json::read_json_from_file;
+use stone_prover_sdk::models::{ProverConfig, ProverParameters};
+use test_cases::get_test_case_file_path;
+use tonic::Status;
+
+use crate::services::prover::prover_proto::prover_client::ProverClient;
+use crate::services::prover::{execute_and_prove, execute_program, prove_execution_response};
+use crate::services::GrpcTransport;
+use crate::validation::{prover_config_from_file, prover_parameters_from_file};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DemoError {
+    #[error("Could not read test case file {}: {1}", .0.display())]
+    TestCase(PathBuf, String),
+    #[error(transparent)]
+    Rpc(#[from] Status),
+}
+
+/// Inputs of a test case of the `test-cases` crate, ex: `fibonacci`.
+pub struct DemoTestCase {
+    pub compiled_program: Vec<u8>,
+    pub private_input: AirPrivateInput,
+    pub prover_config: ProverConfig,
+    pub prover_parameters: ProverParameters,
+}
+
+fn read_test_case_json<T: DeserializeOwned>(path: PathBuf) -> Result<T, DemoError> {
+    read_json_from_file(&path).map_err(|e| DemoError::TestCase(path, e.to_string()))
+}
+
+impl DemoTestCase {
+    /// Loads the `<name>/<name>_compiled.json` program and its private input, prover config
+    /// and prover parameters.
+    pub fn load(name: &str) -> Result<Self, DemoError> {
+        let file_path = |filename: &str| get_test_case_file_path(&format!("{name}/{filename}"));
+
+        let program_path = file_path(&format!("{name}_compiled.json"));
+        let compiled_program = std::fs::read(&program_path)
+            .map_err(|e| DemoError::TestCase(program_path, e.to_string()))?;
+        let private_input: AirPrivateInputSerializable =
+            read_test_case_json(file_path(&format!("{name}_private_input.json")))?;
+
+        // Validated before sending the requests to report typos precisely
+        let prover_config_path = file_path("cpu_air_prover_config.json");
+        let prover_config = prover_config_from_file(&prover_config_path)
+            .map_err(|e| DemoError::TestCase(prover_config_path, e.to_string()))?;
+        let prover_parameters_path = file_path("cpu_air_params.json");
+        let prover_parameters = prover_parameters_from_file(&prover_parameters_path)
+            .map_err(|e| DemoError::TestCase(prover_parameters_path, e.to_string()))?;
+
+        Ok(Self {
+            compiled_program,
+            private_input: private_input.into(),
+            prover_config,
+            prover_parameters,
+        })
+    }
+}
+
+/// Durations of the calls made by `run_demo`.
+#[derive(Debug, Clone)]
+pub struct DemoReport {
+    pub execute: Duration,
+    pub prove: Duration,
+    pub execute_and_prove: Duration,
+    /// Size of the proof returned by `ExecuteAndProve`, serialized as JSON.
+    pub proof_size: usize,
+    /// Cheapest layout with the builtins of the program, as suggested by the server.
+    pub suggested_layout: String,
+}
+
+/// Executes the program of the test case, proves the execution, then does both at once
+/// with `ExecuteAndProve`.
+pub async fn run_demo<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    test_case_name: &str,
+) -> Result<DemoReport, DemoError> {
+    let test_case = DemoTestCase::load(test_case_name)?;
+
+    let started_at = Instant::now();
+    let execution = execute_program(client, test_case.compiled_program).await?;
+    let execute = started_at.elapsed();
+    let suggested_layout = execution
+        .builtin_usage
+        .as_ref()
+        .map(|builtin_usage| builtin_usage.suggested_layout.clone())
+        .unwrap_or_default();
+
+    let started_at = Instant::now();
+    prove_execution_response(
+        client,
+        execution,
+        test_case.private_input,
+        test_case.prover_config,
+        Some(test_case.prover_parameters),
+    )
+    .await?;
+    let prove = started_at.elapsed();
+
+    // The inputs were consumed by the previous calls
+    let test_case = DemoTestCase::load(test_case_name)?;
+    let started_at = Instant::now();
+    let proof = execute_and_prove(
+        client,
+        test_case.compiled_program,
+        Some(test_case.prover_config),
+        Some(test_case.prover_parameters),
+    )
+    .await?;
+    let execute_and_prove = started_at.elapsed();
+
+    let proof_size = serde_json::to_vec(&proof)
+        .map_err(|e| Status::internal(format!("Could not serialize the proof: {e}")))?
+        .len();
+
+    Ok(DemoReport {
+        execute,
+        prove,
+        execute_and_prove,
+        proof_size,
+        suggested_layout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_test_case() {
+        let test_case = DemoTestCase::load("fibonacci").unwrap();
+        assert!(!test_case.compiled_program.is_empty());
+    }
+
+    #[test]
+    fn test_load_unknown_test_case() {
+        let result = DemoTestCase::load("unknown");
+        assert!(matches!(result, Err(DemoError::TestCase(_, _))));
+    }
+}
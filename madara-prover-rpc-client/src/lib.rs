@@ -1 +1,12 @@
+pub mod auth;
+pub mod checksum;
+pub mod config;
+#[cfg(feature = "demo")]
+pub mod demo;
+pub mod proof;
+pub mod proto_ext;
+pub mod retry;
 pub mod services;
+pub mod validation;
+#[cfg(feature = "local-verify")]
+pub mod verify;
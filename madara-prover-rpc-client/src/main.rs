@@ -0,0 +1,38 @@
+use clap::Parser;
+use madara_prover_rpc_client::config::ClientConfig;
+use madara_prover_rpc_client::demo::run_demo;
+use madara_prover_rpc_client::services::prover::prover_proto::prover_client::ProverClient;
+
+/// Demonstrates the Prover API: executes a test case program, proves it, then does both
+/// at once, and prints the duration of each call.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// URI of the server.
+    #[arg(long, default_value = "http://[::1]:8080")]
+    endpoint: String,
+
+    /// Name of the test case, in the `test-cases` crate.
+    #[arg(long, default_value = "fibonacci")]
+    test_case: String,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let channel = ClientConfig::default().connect(args.endpoint).await?;
+    let mut client = ProverClient::new(channel);
+
+    let report = run_demo(&mut client, &args.test_case).await?;
+
+    println!("Execute: {:.2?}", report.execute);
+    println!("Prove: {:.2?}", report.prove);
+    println!("ExecuteAndProve: {:.2?}", report.execute_and_prove);
+    println!("Proof size: {} bytes", report.proof_size);
+    if !report.suggested_layout.is_empty() {
+        println!("Suggested layout: {}", report.suggested_layout);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use stone_prover_sdk::models::Proof;
+
+/// A proof in the format written by `cpu_air_prover`.
+///
+/// `Proof` only models a subset of the fields of the proof file. The other fields are kept
+/// in `extra_fields`, so that the proof can be written back and passed to `cpu_air_verifier`.
+#[derive(Serialize, Deserialize)]
+pub struct StoneProof {
+    #[serde(flatten)]
+    pub proof: Proof,
+    #[serde(flatten)]
+    pub extra_fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl StoneProof {
+    /// Parses a proof file, ex: the `proof` field of a `ProverResponse`.
+    pub fn from_json_str(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Serializes the proof with all the fields of the original file. The keys may be
+    /// ordered differently.
+    pub fn to_stone_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Writes the proof to a file that can be passed to `cpu_air_verifier --in_file`.
+    pub fn write_to_file(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_stone_json()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_cases::load_test_case_file;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let proof_file = load_test_case_file("fibonacci/fibonacci_proof.json");
+
+        let proof = StoneProof::from_json_str(&proof_file).unwrap();
+
+        let expected: serde_json::Value = serde_json::from_str(&proof_file).unwrap();
+        let round_trip: serde_json::Value =
+            serde_json::from_str(&proof.to_stone_json().unwrap()).unwrap();
+        assert_eq!(round_trip, expected);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_kept() {
+        let mut proof_file: serde_json::Value =
+            serde_json::from_str(&load_test_case_file("fibonacci/fibonacci_proof.json")).unwrap();
+        proof_file["future_field"] = serde_json::json!({"value": 1});
+
+        let proof = StoneProof::from_json_str(&proof_file.to_string()).unwrap();
+
+        assert_eq!(
+            proof.extra_fields["future_field"],
+            serde_json::json!({"value": 1})
+        );
+        let round_trip: serde_json::Value =
+            serde_json::from_str(&proof.to_stone_json().unwrap()).unwrap();
+        assert_eq!(round_trip, proof_file);
+    }
+}
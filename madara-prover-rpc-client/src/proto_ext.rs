@@ -0,0 +1,31 @@
+pub use madara_prover_common::proto_ext::{base64_bytes, base64_bytes_list, MessageExt};
+
+#[cfg(test)]
+mod tests {
+    use crate::services::starknet_prover::starknet_prover_proto::StarknetExecutionRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_starknet_execution_request_json() {
+        let request = StarknetExecutionRequest {
+            programs: vec![vec![1, 2, 3]],
+            pies: vec![vec![4, 5]],
+            split_proof: true,
+            prover_config: Some("{}".to_string()),
+            prover_parameters: None,
+            compress_proof: false,
+            reuse_annotations: false,
+            small_proof: false,
+        };
+
+        let json = request.to_json().unwrap();
+        assert_eq!(
+            json,
+            r#"{"programs":["AQID"],"pies":["BAU="],"split_proof":true,"prover_config":"{}","prover_parameters":null,"compress_proof":false,"reuse_annotations":false,"small_proof":false}"#
+        );
+
+        let deserialized: StarknetExecutionRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, request);
+    }
+}
@@ -0,0 +1,159 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tonic::{Code, Status};
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Retry policy of the idempotent client calls.
+///
+/// Only calls that can safely be replayed accept a policy: executions, and proofs sent with
+/// an idempotency key so that the server does not start a second proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each subsequent retry.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Status codes that trigger a retry. Connection failures and resets are reported
+    /// as `UNAVAILABLE`.
+    pub retry_on: Vec<Code>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            retry_on: vec![Code::Unavailable],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn no_retry() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay before the specified retry, starting at 1.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+
+    fn should_retry(&self, status: &Status) -> bool {
+        self.retry_on.contains(&status.code())
+    }
+
+    /// Runs `call` until it succeeds, fails with a status that is not retried or runs out
+    /// of attempts. Returns the result of the last attempt.
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T, Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(status) if attempt < self.max_attempts && self.should_retry(&status) => {
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    /// Fails with `code` for the first `failures` calls.
+    async fn flaky_call(calls: &AtomicU32, failures: u32, code: Code) -> Result<u32, Status> {
+        let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if call <= failures {
+            return Err(Status::new(code, "flaky"));
+        }
+        Ok(call)
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), DEFAULT_MAX_BACKOFF);
+        assert_eq!(policy.backoff(100), DEFAULT_MAX_BACKOFF);
+    }
+
+    #[tokio::test]
+    async fn test_retry_until_success() {
+        let calls = AtomicU32::new(0);
+
+        let result = fast_policy()
+            .run(|| flaky_call(&calls, 2, Code::Unavailable))
+            .await;
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_max_attempts() {
+        let calls = AtomicU32::new(0);
+
+        let status = fast_policy()
+            .run(|| flaky_call(&calls, 5, Code::Unavailable))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), DEFAULT_MAX_ATTEMPTS);
+    }
+
+    #[tokio::test]
+    async fn test_other_codes_are_not_retried() {
+        let calls = AtomicU32::new(0);
+
+        let status = fast_policy()
+            .run(|| flaky_call(&calls, 1, Code::InvalidArgument))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_retry() {
+        let calls = AtomicU32::new(0);
+
+        let result = RetryPolicy::no_retry()
+            .run(|| flaky_call(&calls, 1, Code::Unavailable))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
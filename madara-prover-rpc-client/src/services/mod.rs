@@ -1,2 +1,26 @@
+use tonic::body::BoxBody;
+use tonic::client::GrpcService;
+use tonic::codegen::{Body, Bytes, StdError};
+
 pub mod prover;
 pub mod starknet_prover;
+
+/// Transport of the clients accepted by the service helpers: a `Channel`, or a channel
+/// wrapped in interceptors like the clients created with `with_token`.
+pub trait GrpcTransport:
+    GrpcService<
+    BoxBody,
+    Error: Into<StdError>,
+    ResponseBody: Body<Data = Bytes, Error: Into<StdError> + Send> + Send + 'static,
+>
+{
+}
+
+impl<T> GrpcTransport for T where
+    T: GrpcService<
+        BoxBody,
+        Error: Into<StdError>,
+        ResponseBody: Body<Data = Bytes, Error: Into<StdError> + Send> + Send + 'static,
+    >
+{
+}
@@ -1,31 +1,188 @@
+use std::io::Write;
+use std::path::Path;
+
 use cairo_vm::air_private_input::AirPrivateInput;
-use tonic::Status;
+use prost::bytes::Bytes;
+use tonic::metadata::AsciiMetadataValue;
+use tonic::{Status, Streaming};
 
 use stone_prover_sdk::models::{Proof, ProverConfig, ProverParameters, PublicInput};
 
+use crate::checksum::{check_sha256, sha256_hex, verify_execution_response, Sha256Checksum};
+use crate::retry::RetryPolicy;
+use crate::services::GrpcTransport;
+use prover_proto::execution_stream_response::Message;
 use prover_proto::prover_client::ProverClient;
-use prover_proto::{ExecutionRequest, ExecutionResponse, ProverRequest, ProverResponse};
+use prover_proto::{
+    Capabilities, EstimateProofRequest, EstimateProofResponse, ExecutionChunkKind, ExecutionHeader,
+    ExecutionRequest, ExecutionResponse, ExecutionStreamResponse, GetCapabilitiesRequest,
+    JobSummary, ListRecentJobsRequest, ProveFromFilesRequest, ProverRequest, ProverResponse,
+};
 
 pub mod prover_proto {
     tonic::include_proto!("prover");
 }
 
-/// Execute a program in proof mode and retrieve the execution artifacts.
-pub async fn execute_program(
-    client: &mut ProverClient<tonic::transport::Channel>,
+/// Request metadata key of the idempotency key of `ExecuteAndProve` requests.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Execute a program in proof mode and retrieve the execution artifacts. The memory and trace
+/// are verified against the checksums of the server.
+pub async fn execute_program<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
     program_content: Vec<u8>,
 ) -> Result<ExecutionResponse, Status> {
     let request = tonic::Request::new(ExecutionRequest {
         program: program_content,
-        prover_config: None,
-        prover_parameters: None,
+        ..Default::default()
+    });
+    let response = client.execute(request).await?.into_inner();
+    verify_execution_response(&response)?;
+    Ok(response)
+}
+
+/// Same as `execute_program`, retrying the call according to `retry_policy`.
+pub async fn execute_program_with_retry<T: GrpcTransport + Clone>(
+    client: &mut ProverClient<T>,
+    program_content: Vec<u8>,
+    retry_policy: &RetryPolicy,
+) -> Result<ExecutionResponse, Status> {
+    retry_policy
+        .run(|| {
+            let mut client = client.clone();
+            let program_content = program_content.clone();
+            async move { execute_program(&mut client, program_content).await }
+        })
+        .await
+}
+
+/// Reads an `ExecuteStreamed` response, writing the memory and trace chunks to the specified
+/// writers as they arrive. Returns the header of the stream, once the memory and trace are
+/// verified against its checksums.
+pub async fn consume_execution_stream<M: Write, T: Write>(
+    mut stream: Streaming<ExecutionStreamResponse>,
+    memory_writer: &mut M,
+    trace_writer: &mut T,
+) -> Result<ExecutionHeader, Status> {
+    let write_error = |e: std::io::Error| Status::internal(format!("Could not write chunk: {}", e));
+
+    let header = match stream.message().await? {
+        Some(ExecutionStreamResponse {
+            message: Some(Message::Header(header)),
+        }) => header,
+        _ => return Err(Status::internal("Expected an execution header")),
+    };
+
+    let mut memory_size = 0u64;
+    let mut trace_size = 0u64;
+    let mut memory_checksum = Sha256Checksum::new();
+    let mut trace_checksum = Sha256Checksum::new();
+
+    while let Some(response) = stream.message().await? {
+        let chunk = match response.message {
+            Some(Message::Chunk(chunk)) => chunk,
+            _ => return Err(Status::internal("Expected an execution chunk")),
+        };
+
+        let (size, checksum, writer): (&mut u64, &mut Sha256Checksum, &mut dyn Write) =
+            match chunk.kind() {
+                ExecutionChunkKind::Memory if trace_size == 0 => (
+                    &mut memory_size,
+                    &mut memory_checksum,
+                    &mut *memory_writer as &mut dyn Write,
+                ),
+                ExecutionChunkKind::Memory => {
+                    return Err(Status::internal("Received a memory chunk after the trace"))
+                }
+                ExecutionChunkKind::Trace => (
+                    &mut trace_size,
+                    &mut trace_checksum,
+                    &mut *trace_writer as &mut dyn Write,
+                ),
+            };
+        if chunk.offset != *size {
+            return Err(Status::internal(format!(
+                "Unexpected chunk offset: expected {}, got {}",
+                size, chunk.offset
+            )));
+        }
+        writer.write_all(&chunk.data).map_err(write_error)?;
+        checksum.update(&chunk.data);
+        *size += chunk.data.len() as u64;
+    }
+
+    if memory_size != header.memory_size || trace_size != header.trace_size {
+        return Err(Status::internal(format!(
+            "Incomplete execution stream: received {}/{} memory bytes and {}/{} trace bytes",
+            memory_size, header.memory_size, trace_size, header.trace_size
+        )));
+    }
+    check_sha256("Memory", &memory_checksum.finalize(), &header.memory_sha256)?;
+    check_sha256("Trace", &trace_checksum.finalize(), &header.trace_sha256)?;
+
+    memory_writer.flush().map_err(write_error)?;
+    trace_writer.flush().map_err(write_error)?;
+
+    Ok(header)
+}
+
+async fn execute_program_streamed_raw<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    program_content: Vec<u8>,
+) -> Result<Streaming<ExecutionStreamResponse>, Status> {
+    let request = tonic::Request::new(ExecutionRequest {
+        program: program_content,
+        ..Default::default()
     });
     client
-        .execute(request)
+        .execute_streamed(request)
         .await
         .map(|response| response.into_inner())
 }
 
+/// Execute a program in proof mode and retrieve the execution artifacts in chunks.
+pub async fn execute_program_streamed<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    program_content: Vec<u8>,
+) -> Result<ExecutionResponse, Status> {
+    let stream = execute_program_streamed_raw(client, program_content).await?;
+
+    let mut memory = vec![];
+    let mut trace = vec![];
+    let header = consume_execution_stream(stream, &mut memory, &mut trace).await?;
+
+    Ok(ExecutionResponse {
+        public_input: header.public_input,
+        memory: memory.into(),
+        trace: trace.into(),
+        builtin_usage: header.builtin_usage,
+        memory_sha256: header.memory_sha256,
+        trace_sha256: header.trace_sha256,
+    })
+}
+
+/// Execute a program in proof mode and write the memory and trace directly to the specified files.
+/// Returns the public input, serialized as JSON.
+pub async fn execute_program_to_files<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    program_content: Vec<u8>,
+    memory_file: &Path,
+    trace_file: &Path,
+) -> Result<Vec<u8>, Status> {
+    let open_file = |path: &Path| {
+        std::fs::File::create(path)
+            .map(std::io::BufWriter::new)
+            .map_err(|e| Status::internal(format!("Could not create {}: {}", path.display(), e)))
+    };
+    let mut memory_writer = open_file(memory_file)?;
+    let mut trace_writer = open_file(trace_file)?;
+
+    let stream = execute_program_streamed_raw(client, program_content).await?;
+    let header = consume_execution_stream(stream, &mut memory_writer, &mut trace_writer).await?;
+
+    Ok(header.public_input)
+}
+
 fn unpack_prover_response(prover_result: Result<ProverResponse, Status>) -> Result<Proof, Status> {
     match prover_result {
         Ok(prover_response) => serde_json::from_str(&prover_response.proof)
@@ -34,39 +191,114 @@ fn unpack_prover_response(prover_result: Result<ProverResponse, Status>) -> Resu
     }
 }
 
-/// Prove the execution of a program.
-pub async fn prove_execution(
-    client: &mut ProverClient<tonic::transport::Channel>,
-    public_input: PublicInput,
+fn prover_request(
+    public_input: Vec<u8>,
     private_input: AirPrivateInput,
-    memory: Vec<u8>,
-    trace: Vec<u8>,
+    memory: Bytes,
+    trace: Bytes,
     prover_config: ProverConfig,
-    prover_parameters: ProverParameters,
-) -> Result<Proof, Status> {
-    let public_input_str = serde_json::to_string(&public_input).unwrap();
+    prover_parameters: Option<ProverParameters>,
+) -> ProverRequest {
     let private_input_str =
         serde_json::to_string(&private_input.to_serializable("".to_string(), "".to_string()))
             .unwrap();
     let prover_config_str = serde_json::to_string(&prover_config).unwrap();
-    let prover_parameters_str = serde_json::to_string(&prover_parameters).unwrap();
+    let prover_parameters_str =
+        prover_parameters.map(|params| serde_json::to_string(&params).unwrap());
 
-    let request = tonic::Request::new(ProverRequest {
-        public_input: public_input_str,
+    ProverRequest {
+        public_input,
         private_input: private_input_str,
+        memory_sha256: Some(sha256_hex(&memory)),
+        trace_sha256: Some(sha256_hex(&trace)),
         memory,
         trace,
         prover_config: prover_config_str,
         prover_parameters: prover_parameters_str,
-    });
-    let prover_response = client.prove(request).await;
+    }
+}
+
+async fn prove<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    request: ProverRequest,
+) -> Result<Proof, Status> {
+    let prover_response = client.prove(tonic::Request::new(request)).await;
+    let prover_result = prover_response.map(|response| response.into_inner());
+    unpack_prover_response(prover_result)
+}
+
+/// Prove the execution of a program. The server generates the prover parameters if
+/// `prover_parameters` is not set.
+pub async fn prove_execution<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    public_input: PublicInput,
+    private_input: AirPrivateInput,
+    memory: Vec<u8>,
+    trace: Vec<u8>,
+    prover_config: ProverConfig,
+    prover_parameters: Option<ProverParameters>,
+) -> Result<Proof, Status> {
+    let public_input_bytes = serde_json::to_vec(&public_input).unwrap();
+    let request = prover_request(
+        public_input_bytes,
+        private_input,
+        memory.into(),
+        trace.into(),
+        prover_config,
+        prover_parameters,
+    );
+    prove(client, request).await
+}
+
+/// Prove the output of `execute_program`.
+///
+/// The public input, memory and trace returned by the server are forwarded as-is, without
+/// being parsed or copied on the client.
+pub async fn prove_execution_response<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    execution: ExecutionResponse,
+    private_input: AirPrivateInput,
+    prover_config: ProverConfig,
+    prover_parameters: Option<ProverParameters>,
+) -> Result<Proof, Status> {
+    let request = prover_request(
+        execution.public_input,
+        private_input,
+        execution.memory,
+        execution.trace,
+        prover_config,
+        prover_parameters,
+    );
+    prove(client, request).await
+}
+
+/// Prove execution artifacts already on the filesystem of the server, ex: written by a
+/// co-located runner. The paths are absolute paths on the server, within the roots it allows.
+pub async fn prove_from_files<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    public_input_path: &Path,
+    private_input_path: &Path,
+    memory_path: &Path,
+    trace_path: &Path,
+    prover_config: ProverConfig,
+    prover_parameters: Option<ProverParameters>,
+) -> Result<Proof, Status> {
+    let request = ProveFromFilesRequest {
+        public_input_path: public_input_path.to_string_lossy().into_owned(),
+        private_input_path: private_input_path.to_string_lossy().into_owned(),
+        memory_path: memory_path.to_string_lossy().into_owned(),
+        trace_path: trace_path.to_string_lossy().into_owned(),
+        prover_config: serde_json::to_string(&prover_config).unwrap(),
+        prover_parameters: prover_parameters.map(|params| serde_json::to_string(&params).unwrap()),
+    };
+    let prover_response = client.prove_from_files(tonic::Request::new(request)).await;
     let prover_result = prover_response.map(|response| response.into_inner());
     unpack_prover_response(prover_result)
 }
 
 /// Execute and prove a program.
-pub async fn execute_and_prove(
-    client: &mut ProverClient<tonic::transport::Channel>,
+pub async fn execute_and_prove<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
     program_content: Vec<u8>,
     prover_config: Option<ProverConfig>,
     prover_parameters: Option<ProverParameters>,
@@ -80,6 +312,7 @@ pub async fn execute_and_prove(
         program: program_content,
         prover_config: serialized_prover_config,
         prover_parameters: serialized_prover_parameters,
+        ..Default::default()
     };
 
     let prover_result = client
@@ -88,3 +321,222 @@ pub async fn execute_and_prove(
         .map(|response| response.into_inner());
     unpack_prover_response(prover_result)
 }
+
+/// Same as `execute_and_prove`, retrying the call according to `retry_policy`.
+///
+/// Every attempt carries `idempotency_key`: if an attempt fails after the server started
+/// the proof, the next attempt waits for it instead of starting another one.
+pub async fn execute_and_prove_with_retry<T: GrpcTransport + Clone>(
+    client: &mut ProverClient<T>,
+    program_content: Vec<u8>,
+    prover_config: Option<ProverConfig>,
+    prover_parameters: Option<ProverParameters>,
+    idempotency_key: &str,
+    retry_policy: &RetryPolicy,
+) -> Result<Proof, Status> {
+    let idempotency_key: AsciiMetadataValue = idempotency_key
+        .parse()
+        .map_err(|_| Status::invalid_argument("Invalid idempotency key"))?;
+    let request = ExecutionRequest {
+        program: program_content,
+        prover_config: prover_config.map(|config| serde_json::to_string(&config).unwrap()),
+        prover_parameters: prover_parameters.map(|params| serde_json::to_string(&params).unwrap()),
+        ..Default::default()
+    };
+
+    let prover_result = retry_policy
+        .run(|| {
+            let mut client = client.clone();
+            let mut request = tonic::Request::new(request.clone());
+            request
+                .metadata_mut()
+                .insert(IDEMPOTENCY_KEY_HEADER, idempotency_key.clone());
+            async move {
+                client
+                    .execute_and_prove(request)
+                    .await
+                    .map(|response| response.into_inner())
+            }
+        })
+        .await;
+    unpack_prover_response(prover_result)
+}
+
+/// Estimate the size and proving time of the proof of an execution of `nb_steps` steps.
+/// The parameters are generated by the server if not specified.
+pub async fn estimate_proof<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    nb_steps: u32,
+    layout: String,
+    prover_parameters: Option<ProverParameters>,
+) -> Result<EstimateProofResponse, Status> {
+    let request = EstimateProofRequest {
+        nb_steps,
+        layout,
+        prover_parameters: prover_parameters.map(|params| serde_json::to_string(&params).unwrap()),
+        max_fri_step: None,
+        last_layer_degree_bound: None,
+    };
+
+    client
+        .estimate_proof(request)
+        .await
+        .map(|response| response.into_inner())
+}
+
+/// Lists the last `limit` jobs completed by the server, most recent first. Lists all the
+/// jobs kept by the server if `limit` is 0.
+pub async fn list_recent_jobs<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+    limit: u32,
+) -> Result<Vec<JobSummary>, Status> {
+    client
+        .list_recent_jobs(ListRecentJobsRequest { limit })
+        .await
+        .map(|response| response.into_inner().jobs)
+}
+
+/// Returns what the server can prove, ex: the versions of its prover binaries.
+pub async fn get_capabilities<T: GrpcTransport>(
+    client: &mut ProverClient<T>,
+) -> Result<Capabilities, Status> {
+    client
+        .get_capabilities(GetCapabilitiesRequest {})
+        .await
+        .map(|response| response.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use stone_prover_sdk::json::read_json_from_file;
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    #[test]
+    fn test_prover_request_does_not_copy_artifacts() {
+        let public_input = br#"{"layout": "starknet_with_keccak"}"#.to_vec();
+        let public_input_ptr = public_input.as_ptr();
+        let memory = Bytes::from(vec![1u8; 40]);
+        let trace = Bytes::from(vec![2u8; 32]);
+
+        let request = prover_request(
+            public_input,
+            AirPrivateInput(HashMap::new()),
+            memory.clone(),
+            trace.clone(),
+            ProverConfig::default(),
+            Some(
+                read_json_from_file(get_test_case_file_path("fibonacci/cpu_air_params.json"))
+                    .unwrap(),
+            ),
+        );
+
+        // The public input bytes are moved into the request, not re-serialized
+        assert_eq!(request.public_input.as_ptr(), public_input_ptr);
+        // The memory and trace buffers are shared, not copied
+        assert_eq!(request.memory.as_ptr(), memory.as_ptr());
+        assert_eq!(request.trace.as_ptr(), trace.as_ptr());
+        assert_eq!(request.trace_sha256, Some(sha256_hex(&trace)));
+    }
+
+    /// Number of JSON passes over the public input on the way from the execution to the prover.
+    #[derive(Debug, Default, PartialEq)]
+    struct SerdePasses {
+        serializations: usize,
+        parses: usize,
+    }
+
+    /// Public input of the fibonacci test case, with its public memory repeated to
+    /// `n_entries` entries.
+    fn large_public_input(n_entries: usize) -> PublicInput {
+        let mut public_input: serde_json::Value = read_json_from_file(get_test_case_file_path(
+            "fibonacci/fibonacci_public_input.json",
+        ))
+        .unwrap();
+        let entries = public_input["public_memory"].as_array().unwrap().clone();
+        public_input["public_memory"] = entries.into_iter().cycle().take(n_entries).collect();
+        serde_json::from_value(public_input).unwrap()
+    }
+
+    /// Sends the public input from the execution to the prover through `forward`, which turns
+    /// the `ExecutionResponse` bytes into the `ProverRequest` bytes. Returns the duration of
+    /// the flow and the public input parsed by the prover.
+    fn execute_to_prove(
+        public_input: &PublicInput,
+        passes: &mut SerdePasses,
+        forward: impl FnOnce(Vec<u8>, &mut SerdePasses) -> Vec<u8>,
+    ) -> (Duration, PublicInput) {
+        let start = Instant::now();
+
+        // Server: ExecutionResponse
+        let execution_public_input = serde_json::to_vec(public_input).unwrap();
+        passes.serializations += 1;
+
+        let request = prover_request(
+            forward(execution_public_input, passes),
+            AirPrivateInput(HashMap::new()),
+            Bytes::new(),
+            Bytes::new(),
+            ProverConfig::default(),
+            Some(
+                read_json_from_file(get_test_case_file_path("fibonacci/cpu_air_params.json"))
+                    .unwrap(),
+            ),
+        );
+
+        // Server: Prove
+        let prover_public_input: PublicInput =
+            serde_json::from_slice(&request.public_input).unwrap();
+        passes.parses += 1;
+
+        (start.elapsed(), prover_public_input)
+    }
+
+    /// Benchmarks the execute -> prove flow against the flow that parsed the public input on
+    /// the client and serialized it again for the `ProverRequest`.
+    #[test]
+    fn test_public_input_serialization_benchmark() {
+        let public_input = large_public_input(200_000);
+
+        let mut passes = SerdePasses::default();
+        let (duration, prover_public_input) =
+            execute_to_prove(&public_input, &mut passes, |public_input, _| public_input);
+
+        let mut reserialized_passes = SerdePasses::default();
+        let (reserialized_duration, _) = execute_to_prove(
+            &public_input,
+            &mut reserialized_passes,
+            |public_input, passes| {
+                let parsed: PublicInput = serde_json::from_slice(&public_input).unwrap();
+                passes.parses += 1;
+                let public_input = serde_json::to_vec(&parsed).unwrap();
+                passes.serializations += 1;
+                public_input
+            },
+        );
+
+        assert_eq!(prover_public_input.public_memory.len(), 200_000);
+        assert_eq!(
+            passes,
+            SerdePasses {
+                serializations: 1,
+                parses: 1
+            }
+        );
+        assert_eq!(
+            reserialized_passes,
+            SerdePasses {
+                serializations: 2,
+                parses: 2
+            }
+        );
+        assert!(
+            duration < reserialized_duration,
+            "Forwarded public input: {duration:?}, re-serialized: {reserialized_duration:?}"
+        );
+    }
+}
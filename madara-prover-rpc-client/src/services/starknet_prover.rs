@@ -1,40 +1,203 @@
+use std::io::Read;
+
+use flate2::read::GzDecoder;
 use tonic::Status;
 
-use starknet_prover_proto::{StarknetExecutionRequest, StarknetProverResponse};
-use stone_prover_sdk::models::Proof;
+use starknet_prover_proto::{
+    GetStoredProofRequest, StarknetExecutionRequest, StarknetProverResponse, StoredProofResponse,
+};
+use stone_prover_sdk::models::{Proof, ProverConfig, ProverParameters, PublicInput};
 
 use crate::services::starknet_prover::starknet_prover_proto::starknet_prover_client::StarknetProverClient;
+use crate::services::GrpcTransport;
 
 pub mod starknet_prover_proto {
     tonic::include_proto!("starknet_prover");
 }
 
+/// Upper bound of the buffer allocated upfront to decompress a proof.
+const MAX_PREALLOCATED_PROOF_SIZE: u64 = 1 << 30;
+
+/// Decompresses a proof compressed with gzip by the server.
+fn decompress_proof(compressed_proof: &[u8], proof_size: u64) -> std::io::Result<String> {
+    let mut proof = String::with_capacity(proof_size.min(MAX_PREALLOCATED_PROOF_SIZE) as usize);
+    GzDecoder::new(compressed_proof).read_to_string(&mut proof)?;
+    Ok(proof)
+}
+
 fn unpack_prover_response(
     prover_result: Result<StarknetProverResponse, Status>,
 ) -> Result<Proof, Status> {
-    match prover_result {
-        Ok(prover_response) => serde_json::from_str(&prover_response.proof)
-            .map_err(|e| Status::internal(format!("Could not read prover output: {}", e))),
-        Err(status) => Err(status),
-    }
+    let prover_response = prover_result?;
+    let proof = if prover_response.compressed_proof.is_empty() {
+        prover_response.proof
+    } else {
+        decompress_proof(
+            &prover_response.compressed_proof,
+            prover_response.proof_size,
+        )
+        .map_err(|e| Status::internal(format!("Could not decompress the proof: {}", e)))?
+    };
+
+    serde_json::from_str(&proof)
+        .map_err(|e| Status::internal(format!("Could not read prover output: {}", e)))
+}
+
+/// Options of the Starknet `ExecuteAndProve` requests.
+#[derive(Default)]
+pub struct StarknetProveOptions {
+    /// Split the proof to verify it on L1, see the `evm_adapter` of the server.
+    pub split_proof: bool,
+    /// With `split_proof`, split the proof with the annotations embedded by the prover
+    /// instead of running the verifier, if they match the proof.
+    pub reuse_annotations: bool,
+    /// Generated by the server if not specified.
+    pub prover_config: Option<ProverConfig>,
+    /// Generated by the server if not specified.
+    pub prover_parameters: Option<ProverParameters>,
 }
 
 /// Execute programs/PIEs with the Starknet bootloader and generate a proof.
-pub async fn execute_and_prove(
-    client: &mut StarknetProverClient<tonic::transport::Channel>,
+pub async fn execute_and_prove<T: GrpcTransport>(
+    client: &mut StarknetProverClient<T>,
     programs: Vec<Vec<u8>>,
     pies: Vec<Vec<u8>>,
     split_proof: bool,
+    prover_config: Option<ProverConfig>,
+    prover_parameters: Option<ProverParameters>,
+) -> Result<Proof, Status> {
+    let options = StarknetProveOptions {
+        split_proof,
+        prover_config,
+        prover_parameters,
+        ..Default::default()
+    };
+    let prover_result = call_execute_and_prove(client, programs, pies, options, false).await;
+    unpack_prover_response(prover_result)
+}
+
+/// Same as `execute_and_prove`, with the proof compressed by the server. Recommended for
+/// large proofs, ex: the proofs of the Starknet OS.
+pub async fn execute_and_prove_compressed<T: GrpcTransport>(
+    client: &mut StarknetProverClient<T>,
+    programs: Vec<Vec<u8>>,
+    pies: Vec<Vec<u8>>,
+    options: StarknetProveOptions,
 ) -> Result<Proof, Status> {
+    let prover_result = call_execute_and_prove(client, programs, pies, options, true).await;
+    unpack_prover_response(prover_result)
+}
+
+/// Same as `execute_and_prove`, also returns the public input of the bootloader run.
+pub async fn execute_and_prove_with_public_input<T: GrpcTransport>(
+    client: &mut StarknetProverClient<T>,
+    programs: Vec<Vec<u8>>,
+    pies: Vec<Vec<u8>>,
+    split_proof: bool,
+    prover_config: Option<ProverConfig>,
+    prover_parameters: Option<ProverParameters>,
+) -> Result<(Proof, PublicInput), Status> {
+    let options = StarknetProveOptions {
+        split_proof,
+        prover_config,
+        prover_parameters,
+        ..Default::default()
+    };
+    let prover_response = call_execute_and_prove(client, programs, pies, options, false).await?;
+    let public_input = serde_json::from_str(&prover_response.public_input)
+        .map_err(|e| Status::internal(format!("Could not read public input: {}", e)))?;
+    let proof = unpack_prover_response(Ok(prover_response))?;
+
+    Ok((proof, public_input))
+}
+
+async fn call_execute_and_prove<T: GrpcTransport>(
+    client: &mut StarknetProverClient<T>,
+    programs: Vec<Vec<u8>>,
+    pies: Vec<Vec<u8>>,
+    options: StarknetProveOptions,
+    compress_proof: bool,
+) -> Result<StarknetProverResponse, Status> {
+    let StarknetProveOptions {
+        split_proof,
+        reuse_annotations,
+        prover_config,
+        prover_parameters,
+    } = options;
     let request = StarknetExecutionRequest {
         programs,
         pies,
         split_proof,
+        prover_config: prover_config.map(|config| serde_json::to_string(&config).unwrap()),
+        prover_parameters: prover_parameters.map(|params| serde_json::to_string(&params).unwrap()),
+        compress_proof,
+        reuse_annotations,
+        small_proof: false,
     };
 
-    let prover_result = client
+    client
         .execute_and_prove(request)
         .await
-        .map(|response| response.into_inner());
-    unpack_prover_response(prover_result)
+        .map(|response| response.into_inner())
+}
+
+/// Retrieve a proof archived by the server, using the storage reference returned
+/// by `ExecuteAndProve`.
+pub async fn get_stored_proof<T: GrpcTransport>(
+    client: &mut StarknetProverClient<T>,
+    storage_ref: String,
+) -> Result<StoredProofResponse, Status> {
+    let request = GetStoredProofRequest { storage_ref };
+
+    client
+        .get_stored_proof(request)
+        .await
+        .map(|response| response.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use test_cases::load_test_case_file;
+
+    use super::*;
+
+    #[test]
+    fn test_unpack_compressed_proof() {
+        let proof = load_test_case_file("bootloader/programs/fibonacci/output/proof.json");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(proof.as_bytes()).unwrap();
+
+        let uncompressed_response = StarknetProverResponse {
+            proof: proof.clone(),
+            proof_size: proof.len() as u64,
+            ..Default::default()
+        };
+        let compressed_response = StarknetProverResponse {
+            compressed_proof: encoder.finish().unwrap(),
+            proof_size: proof.len() as u64,
+            ..Default::default()
+        };
+
+        let expected_proof = unpack_prover_response(Ok(uncompressed_response)).unwrap();
+        let decompressed_proof = unpack_prover_response(Ok(compressed_response)).unwrap();
+        assert_eq!(
+            serde_json::to_value(decompressed_proof).unwrap(),
+            serde_json::to_value(expected_proof).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_unpack_invalid_compressed_proof() {
+        let response = StarknetProverResponse {
+            compressed_proof: b"not gzip".to_vec(),
+            ..Default::default()
+        };
+
+        let status = unpack_prover_response(Ok(response)).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Internal);
+    }
 }
@@ -0,0 +1,287 @@
+//! Validation of the prover configuration and parameters files before they are sent to the
+//! server.
+//!
+//! The server only reports that it "Could not read prover parameters". Parsing the files on
+//! the client side gives the path of the invalid value, and a suggestion for misspelled
+//! field names, which the prover would otherwise silently ignore if they are optional.
+
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use serde_path_to_error::Segment;
+use stone_prover_sdk::models::{ProverConfig, ProverParameters};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ValidationError {
+    #[error("Could not read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid JSON: {0}")]
+    Syntax(serde_json::Error),
+    #[error("Unknown field `{path}`{}", suggestion_hint(.suggestion))]
+    UnknownField {
+        path: String,
+        suggestion: Option<String>,
+    },
+    #[error("Invalid value at `{path}`: {message}")]
+    InvalidValue { path: String, message: String },
+}
+
+fn suggestion_hint(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(field) => format!(", did you mean `{field}`?"),
+        None => String::new(),
+    }
+}
+
+/// Parses prover parameters, ex: the content of a `cpu_air_params.json` file.
+pub fn prover_parameters_from_json_str(json: &str) -> Result<ProverParameters, ValidationError> {
+    from_json_str(json)
+}
+
+/// Parses a prover configuration, ex: the content of a `cpu_air_prover_config.json` file.
+pub fn prover_config_from_json_str(json: &str) -> Result<ProverConfig, ValidationError> {
+    from_json_str(json)
+}
+
+/// Reads and validates a prover parameters file.
+pub fn prover_parameters_from_file(path: &Path) -> Result<ProverParameters, ValidationError> {
+    prover_parameters_from_json_str(&read_file(path)?)
+}
+
+/// Reads and validates a prover configuration file.
+pub fn prover_config_from_file(path: &Path) -> Result<ProverConfig, ValidationError> {
+    prover_config_from_json_str(&read_file(path)?)
+}
+
+fn read_file(path: &Path) -> Result<String, ValidationError> {
+    std::fs::read_to_string(path).map_err(|source| ValidationError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Deserializes `json` and checks that it has no unknown fields.
+///
+/// Unknown fields are the fields of the input that are dropped when the parsed value is
+/// serialized back.
+pub fn from_json_str<T: DeserializeOwned + Serialize>(json: &str) -> Result<T, ValidationError> {
+    let value: Value = serde_json::from_str(json).map_err(ValidationError::Syntax)?;
+
+    let parsed: T = serde_path_to_error::deserialize(&value).map_err(|e| {
+        let path = path_segments(e.path());
+        let message = e.into_inner().to_string();
+        missing_field_error(&value, &path, &message).unwrap_or(ValidationError::InvalidValue {
+            path: join_path(&path),
+            message,
+        })
+    })?;
+
+    let known = serde_json::to_value(&parsed).map_err(ValidationError::Syntax)?;
+    match find_unknown_field(&value, &known, &mut vec![]) {
+        Some(error) => Err(error),
+        None => Ok(parsed),
+    }
+}
+
+fn path_segments(path: &serde_path_to_error::Path) -> Vec<String> {
+    path.iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.clone()),
+            Segment::Enum { variant } => Some(variant.clone()),
+            Segment::Unknown => None,
+        })
+        .collect()
+}
+
+fn join_path(path: &[String]) -> String {
+    if path.is_empty() {
+        ".".to_string()
+    } else {
+        path.join(".")
+    }
+}
+
+/// A required field that is missing is usually misspelled: reports the closest field of
+/// the input object as unknown.
+fn missing_field_error(value: &Value, path: &[String], message: &str) -> Option<ValidationError> {
+    let missing_field = message.strip_prefix("missing field `")?.strip_suffix('`')?;
+
+    let mut object = value;
+    for segment in path {
+        object = match object {
+            Value::Object(map) => map.get(segment)?,
+            Value::Array(values) => values.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    let field = closest_match(missing_field, object.as_object()?.keys())?;
+    let mut field_path = path.to_vec();
+    field_path.push(field.clone());
+    Some(ValidationError::UnknownField {
+        path: join_path(&field_path),
+        suggestion: Some(missing_field.to_string()),
+    })
+}
+
+fn find_unknown_field(
+    value: &Value,
+    known: &Value,
+    path: &mut Vec<String>,
+) -> Option<ValidationError> {
+    match (value, known) {
+        (Value::Object(fields), Value::Object(known_fields)) => {
+            for (field, field_value) in fields {
+                path.push(field.clone());
+                match known_fields.get(field) {
+                    Some(known_value) => {
+                        if let Some(error) = find_unknown_field(field_value, known_value, path) {
+                            return Some(error);
+                        }
+                    }
+                    // Optional fields set to null may not be serialized back
+                    None if field_value.is_null() => {}
+                    None => {
+                        return Some(ValidationError::UnknownField {
+                            path: join_path(path),
+                            suggestion: closest_match(field, known_fields.keys()).cloned(),
+                        })
+                    }
+                }
+                path.pop();
+            }
+            None
+        }
+        (Value::Array(values), Value::Array(known_values)) => {
+            for (index, (value, known_value)) in values.iter().zip(known_values).enumerate() {
+                path.push(index.to_string());
+                if let Some(error) = find_unknown_field(value, known_value, path) {
+                    return Some(error);
+                }
+                path.pop();
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+/// Returns the candidate closest to `field`, if it is close enough to be a typo.
+fn closest_match<'a>(
+    field: &str,
+    candidates: impl Iterator<Item = &'a String>,
+) -> Option<&'a String> {
+    let max_distance = std::cmp::max(2, field.len() / 3);
+    candidates
+        .filter(|candidate| candidate.as_str() != field)
+        .map(|candidate| (edit_distance(field, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            row[j + 1] = substitution.min(previous_row[j + 1] + 1).min(row[j] + 1);
+        }
+        previous_row = row;
+    }
+
+    previous_row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use test_cases::load_test_case_file;
+
+    use super::*;
+
+    fn fibonacci_parameters() -> Value {
+        serde_json::from_str(&load_test_case_file("fibonacci/cpu_air_params.json")).unwrap()
+    }
+
+    #[test]
+    fn test_valid_files() {
+        assert!(prover_parameters_from_json_str(&load_test_case_file(
+            "fibonacci/cpu_air_params.json"
+        ))
+        .is_ok());
+        assert!(prover_config_from_json_str(&load_test_case_file(
+            "fibonacci/cpu_air_prover_config.json"
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn test_misspelled_fri_step_list() {
+        let mut parameters = fibonacci_parameters();
+        let fri = parameters["stark"]["fri"].as_object_mut().unwrap();
+        let fri_step_list = fri.remove("fri_step_list").unwrap();
+        fri.insert("fri_steps_list".to_string(), fri_step_list);
+
+        let error = prover_parameters_from_json_str(&parameters.to_string()).unwrap_err();
+
+        assert!(matches!(
+            &error,
+            ValidationError::UnknownField { path, suggestion }
+                if path == "stark.fri.fri_steps_list" && suggestion.as_deref() == Some("fri_step_list")
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Unknown field `stark.fri.fri_steps_list`, did you mean `fri_step_list`?"
+        );
+    }
+
+    #[test]
+    fn test_extra_field() {
+        let mut parameters = fibonacci_parameters();
+        parameters["stark"]["fri"]["fri_step_lists"] = serde_json::json!([4, 4, 4, 1]);
+
+        let error = prover_parameters_from_json_str(&parameters.to_string()).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Unknown field `stark.fri.fri_step_lists`, did you mean `fri_step_list`?"
+        );
+    }
+
+    #[test]
+    fn test_wrong_type() {
+        let mut parameters = fibonacci_parameters();
+        parameters["stark"]["fri"]["n_queries"] = serde_json::json!("18");
+
+        let error = prover_parameters_from_json_str(&parameters.to_string()).unwrap_err();
+
+        assert!(matches!(
+            &error,
+            ValidationError::InvalidValue { path, .. } if path == "stark.fri.n_queries"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let error = prover_config_from_json_str("{").unwrap_err();
+        assert!(matches!(error, ValidationError::Syntax(_)));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("fri_step_list", "fri_step_list"), 0);
+        assert_eq!(edit_distance("fri_steps_list", "fri_step_list"), 1);
+        assert_eq!(edit_distance("n_queries", "n_querys"), 2);
+        assert_eq!(edit_distance("", "field"), 5);
+    }
+}
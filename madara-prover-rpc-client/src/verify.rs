@@ -0,0 +1,65 @@
+use std::process::ExitStatus;
+
+use stone_prover_sdk::error::VerifierError;
+use stone_prover_sdk::models::Proof;
+use stone_prover_sdk::verifier::run_verifier_with_annotations_async;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LocalVerifyError {
+    #[error("Could not serialize the proof: {0}")]
+    Serialize(#[from] serde_json::Error),
+    #[error("Could not run the verifier: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Verifier run failed ({status}): {stderr}")]
+    Verification { status: ExitStatus, stderr: String },
+}
+
+impl From<VerifierError> for LocalVerifyError {
+    fn from(e: VerifierError) -> Self {
+        match e {
+            VerifierError::CommandError(verifier_output) => Self::Verification {
+                status: verifier_output.status,
+                stderr: String::from_utf8_lossy(&verifier_output.stderr).into_owned(),
+            },
+            VerifierError::IoError(io_error) => Self::Io(io_error),
+        }
+    }
+}
+
+/// Verifies a proof with `cpu_air_verifier`, without going through the server.
+///
+/// The verifier is run by the SDK, from `PATH`. Returns the annotations produced by the
+/// verifier, one per line.
+pub async fn verify_locally(proof: &Proof) -> Result<Vec<String>, LocalVerifyError> {
+    let working_dir = tempfile::tempdir()?;
+    let proof_file = working_dir.path().join("proof.json");
+    let annotations_file = working_dir.path().join("annotations.txt");
+    let extra_annotations_file = working_dir.path().join("extra_annotations.txt");
+    std::fs::write(&proof_file, serde_json::to_vec(proof)?)?;
+
+    run_verifier_with_annotations_async(&proof_file, &annotations_file, &extra_annotations_file)
+        .await?;
+
+    let annotations = std::fs::read_to_string(annotations_file)?;
+    Ok(annotations.lines().map(str::to_string).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use stone_prover_sdk::json::read_json_from_file;
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    fn fibonacci_proof() -> Proof {
+        read_json_from_file(get_test_case_file_path("fibonacci/fibonacci_proof.json")).unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "needs cpu_air_verifier in PATH"]
+    async fn test_verify_fibonacci_proof() {
+        let annotations = verify_locally(&fibonacci_proof()).await.unwrap();
+
+        assert!(!annotations.is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+//! Persistence of execution artifacts, in the format expected by `cpu_air_prover` and the
+//! Python toolchain.
+//!
+//! The private input references the memory and trace files by path. These paths are written
+//! relative to the directory of the private input file, so that the artifacts can be moved
+//! or consumed from another working directory.
+
+use std::path::{Component, Path, PathBuf};
+
+use cairo_vm::air_private_input::AirPrivateInputSerializable;
+use stone_prover_sdk::cairo_vm::ExecutionArtifacts;
+
+pub const PUBLIC_INPUT_FILE: &str = "air_public_input.json";
+pub const PRIVATE_INPUT_FILE: &str = "air_private_input.json";
+pub const MEMORY_FILE: &str = "memory.bin";
+pub const TRACE_FILE: &str = "trace.bin";
+
+/// Keys of the file paths in a serialized private input.
+const PRIVATE_INPUT_PATH_KEYS: [&str; 2] = ["trace_path", "memory_path"];
+
+#[derive(thiserror::Error, Debug)]
+pub enum ArtifactsError {
+    #[error("Could not access {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Could not parse {}: {source}", path.display())]
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// Returns `path` relative to `base_dir`, or `path` itself if it is not inside `base_dir`.
+pub fn relative_path(path: &Path, base_dir: &Path) -> PathBuf {
+    path.strip_prefix(base_dir)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Resolves a path read from a private input file against the directory of that file.
+/// Absolute paths are kept as-is.
+pub fn resolve_path(path: &Path, private_input_dir: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    // Drop the leading `./` written by the Python toolchain
+    let path: PathBuf = path
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect();
+    private_input_dir.join(path)
+}
+
+fn rewrite_private_input_paths(
+    private_input: &mut serde_json::Value,
+    rewrite: impl Fn(&Path) -> PathBuf,
+) {
+    for key in PRIVATE_INPUT_PATH_KEYS {
+        if let Some(path) = private_input[key].as_str() {
+            let path = rewrite(Path::new(path));
+            private_input[key] = serde_json::Value::String(path.to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// Rewrites the trace and memory paths of a serialized private input relative to
+/// `private_input_dir`, the directory where the private input is written.
+pub fn relativize_private_input_paths(
+    private_input: &mut serde_json::Value,
+    private_input_dir: &Path,
+) {
+    rewrite_private_input_paths(private_input, |path| relative_path(path, private_input_dir));
+}
+
+/// Resolves the relative trace and memory paths of a serialized private input against
+/// `private_input_dir`, the directory the private input was read from.
+pub fn resolve_private_input_paths(
+    private_input: &mut serde_json::Value,
+    private_input_dir: &Path,
+) {
+    rewrite_private_input_paths(private_input, |path| resolve_path(path, private_input_dir));
+}
+
+fn write_file(path: &Path, content: &[u8]) -> Result<(), ArtifactsError> {
+    std::fs::write(path, content).map_err(|source| ArtifactsError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, ArtifactsError> {
+    std::fs::read(path).map_err(|source| ArtifactsError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, ArtifactsError> {
+    serde_json::from_slice(&read_file(path)?).map_err(|source| ArtifactsError::Json {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn to_json_bytes<T: serde::Serialize>(path: &Path, value: &T) -> Result<Vec<u8>, ArtifactsError> {
+    serde_json::to_vec_pretty(value).map_err(|source| ArtifactsError::Json {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Writes the public input, private input, memory and trace files of an execution to `dir`.
+pub fn write_artifacts_to_dir(
+    artifacts: &ExecutionArtifacts,
+    dir: &Path,
+) -> Result<(), ArtifactsError> {
+    let public_input_file = dir.join(PUBLIC_INPUT_FILE);
+    let private_input_file = dir.join(PRIVATE_INPUT_FILE);
+    let memory_file = dir.join(MEMORY_FILE);
+    let trace_file = dir.join(TRACE_FILE);
+
+    let private_input = artifacts.private_input.to_serializable(
+        trace_file.to_string_lossy().into_owned(),
+        memory_file.to_string_lossy().into_owned(),
+    );
+    let mut private_input =
+        serde_json::to_value(private_input).map_err(|source| ArtifactsError::Json {
+            path: private_input_file.clone(),
+            source,
+        })?;
+    relativize_private_input_paths(&mut private_input, dir);
+
+    write_file(
+        &public_input_file,
+        &to_json_bytes(&public_input_file, &artifacts.public_input)?,
+    )?;
+    write_file(
+        &private_input_file,
+        &to_json_bytes(&private_input_file, &private_input)?,
+    )?;
+    write_file(&memory_file, &artifacts.memory)?;
+    write_file(&trace_file, &artifacts.trace)?;
+
+    Ok(())
+}
+
+/// Reads the artifacts written by `write_artifacts_to_dir`. The memory and trace files are
+/// located from the paths of the private input.
+pub fn read_artifacts_from_dir(dir: &Path) -> Result<ExecutionArtifacts, ArtifactsError> {
+    let private_input_file = dir.join(PRIVATE_INPUT_FILE);
+
+    let public_input = read_json(&dir.join(PUBLIC_INPUT_FILE))?;
+    let mut private_input: serde_json::Value = read_json(&private_input_file)?;
+    resolve_private_input_paths(&mut private_input, dir);
+
+    let path_of = |key: &'static str| {
+        private_input[key]
+            .as_str()
+            .map(PathBuf::from)
+            .ok_or_else(|| ArtifactsError::Json {
+                path: private_input_file.clone(),
+                source: serde::de::Error::missing_field(key),
+            })
+    };
+    let memory = read_file(&path_of("memory_path")?)?;
+    let trace = read_file(&path_of("trace_path")?)?;
+
+    let private_input: AirPrivateInputSerializable = serde_json::from_value(private_input)
+        .map_err(|source| ArtifactsError::Json {
+            path: private_input_file,
+            source,
+        })?;
+
+    Ok(ExecutionArtifacts {
+        public_input,
+        private_input: private_input.into(),
+        memory,
+        trace,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use test_cases::get_test_case_file_path;
+
+    use crate::services::prover::run_cairo_program_in_proof_mode;
+
+    use super::*;
+
+    fn fibonacci_artifacts() -> ExecutionArtifacts {
+        let program =
+            std::fs::read(get_test_case_file_path("fibonacci/fibonacci_compiled.json")).unwrap();
+        run_cairo_program_in_proof_mode(&program, "starknet_with_keccak", None).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        let dir = Path::new("/artifacts");
+        assert_eq!(
+            resolve_path(Path::new("./trace.bin"), dir),
+            PathBuf::from("/artifacts/trace.bin")
+        );
+        assert_eq!(
+            resolve_path(Path::new("output/memory.bin"), dir),
+            PathBuf::from("/artifacts/output/memory.bin")
+        );
+        assert_eq!(
+            resolve_path(Path::new("/tmp/trace.bin"), dir),
+            PathBuf::from("/tmp/trace.bin")
+        );
+    }
+
+    #[test]
+    fn test_relativize_private_input_paths() {
+        let mut private_input = serde_json::json!({
+            "trace_path": "/artifacts/trace.bin",
+            "memory_path": "/elsewhere/memory.bin",
+            "pedersen": [],
+        });
+
+        relativize_private_input_paths(&mut private_input, Path::new("/artifacts"));
+
+        assert_eq!(private_input["trace_path"], "trace.bin");
+        assert_eq!(private_input["memory_path"], "/elsewhere/memory.bin");
+        assert_eq!(private_input["pedersen"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_artifacts_survive_move() {
+        let artifacts = fibonacci_artifacts();
+        let root = tempfile::tempdir().unwrap();
+        let original_dir = root.path().join("original");
+        let moved_dir = root.path().join("moved");
+        std::fs::create_dir(&original_dir).unwrap();
+
+        write_artifacts_to_dir(&artifacts, &original_dir).unwrap();
+        std::fs::rename(&original_dir, &moved_dir).unwrap();
+
+        let private_input: serde_json::Value =
+            read_json(&moved_dir.join(PRIVATE_INPUT_FILE)).unwrap();
+        assert_eq!(private_input["trace_path"], TRACE_FILE);
+        assert_eq!(private_input["memory_path"], MEMORY_FILE);
+
+        let read_artifacts = read_artifacts_from_dir(&moved_dir).unwrap();
+        assert_eq!(read_artifacts.memory, artifacts.memory);
+        assert_eq!(read_artifacts.trace, artifacts.trace);
+        assert_eq!(
+            serde_json::to_value(&read_artifacts.public_input).unwrap(),
+            serde_json::to_value(&artifacts.public_input).unwrap()
+        );
+        let serializable = |artifacts: &ExecutionArtifacts| {
+            let private_input = artifacts
+                .private_input
+                .to_serializable(String::new(), String::new());
+            serde_json::to_value(private_input).unwrap()
+        };
+        assert_eq!(serializable(&read_artifacts), serializable(&artifacts));
+    }
+
+    #[test]
+    fn test_missing_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let error = read_artifacts_from_dir(dir.path()).unwrap_err();
+
+        assert!(
+            matches!(error, ArtifactsError::Io { path, .. } if path.ends_with(PUBLIC_INPUT_FILE))
+        );
+    }
+}
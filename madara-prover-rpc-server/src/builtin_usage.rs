@@ -0,0 +1,235 @@
+use stone_prover_sdk::models::PublicInput;
+
+use crate::public_input::{layout_builtins, EXECUTION_SEGMENT, PROGRAM_SEGMENT};
+
+/// Layouts suggested by `suggest_layout`, from the cheapest to prove to the most expensive.
+const SUGGESTED_LAYOUTS: &[&str] = &[
+    "plain",
+    "small",
+    "recursive",
+    "recursive_large_output",
+    "starknet",
+    "starknet_with_keccak",
+];
+
+/// Returns the number of memory cells used by one instance of a builtin.
+fn cells_per_instance(builtin: &str) -> Option<u64> {
+    let cells = match builtin {
+        "output" | "range_check" => 1,
+        "pedersen" => 3,
+        "ecdsa" => 2,
+        "bitwise" => 5,
+        "ec_op" => 7,
+        "keccak" => 16,
+        "poseidon" => 6,
+        _ => return None,
+    };
+    Some(cells)
+}
+
+/// Returns the number of steps per instance of a builtin in a layout, `None` if the number
+/// of instances is not bounded by the number of steps (ex: output).
+fn builtin_ratio(layout: &str, builtin: &str) -> Option<u64> {
+    let ratio = match (layout, builtin) {
+        ("small", "pedersen" | "range_check") => 8,
+        ("small", "ecdsa") => 512,
+        ("recursive" | "recursive_large_output", "pedersen") => 128,
+        ("recursive" | "recursive_large_output", "range_check" | "bitwise" | "poseidon") => 8,
+        ("starknet" | "starknet_with_keccak", "pedersen" | "poseidon") => 32,
+        ("starknet" | "starknet_with_keccak", "range_check") => 16,
+        ("starknet" | "starknet_with_keccak", "bitwise") => 64,
+        ("starknet" | "starknet_with_keccak", "ec_op") => 1024,
+        ("starknet" | "starknet_with_keccak", "ecdsa" | "keccak") => 2048,
+        _ => return None,
+    };
+    Some(ratio)
+}
+
+/// Usage of a builtin by an execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuiltinUsage {
+    pub name: String,
+    /// Number of memory cells of the builtin segment.
+    pub segment_size: u64,
+    /// Number of instances of the builtin, `segment_size` if the size of its instances is
+    /// unknown.
+    pub instances: u64,
+}
+
+/// Builtins used by an execution, to choose the layout of its proof.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuiltinUsageReport {
+    /// Builtins with a non-empty segment, sorted by name.
+    pub builtins: Vec<BuiltinUsage>,
+    pub n_steps: u32,
+    pub rc_min: u32,
+    pub rc_max: u32,
+}
+
+impl BuiltinUsageReport {
+    /// Reads the builtin segments of the public input of an execution. In proof mode, the
+    /// segments of the builtins of the layout that the program does not use are empty.
+    pub fn from_public_input(public_input: &PublicInput) -> Self {
+        let mut builtins: Vec<BuiltinUsage> = public_input
+            .memory_segments
+            .iter()
+            .filter(|(name, _)| *name != PROGRAM_SEGMENT && *name != EXECUTION_SEGMENT)
+            .map(|(name, segment)| {
+                let segment_size = segment.stop_ptr.saturating_sub(segment.begin_addr) as u64;
+                let instances = match cells_per_instance(name) {
+                    Some(cells) => segment_size.div_ceil(cells),
+                    None => segment_size,
+                };
+                BuiltinUsage {
+                    name: name.clone(),
+                    segment_size,
+                    instances,
+                }
+            })
+            .filter(|builtin| builtin.segment_size > 0)
+            .collect();
+        builtins.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+        Self {
+            builtins,
+            n_steps: public_input.n_steps,
+            rc_min: public_input.rc_min,
+            rc_max: public_input.rc_max,
+        }
+    }
+
+    /// Whether `layout` has all the builtins of the execution.
+    fn supported_by(&self, layout: &str) -> bool {
+        let layout_builtins = layout_builtins(layout).unwrap_or_default();
+        self.builtins
+            .iter()
+            .all(|builtin| layout_builtins.contains(&builtin.name.as_str()))
+    }
+
+    /// Whether the builtin instances of the execution fit in its number of steps with the
+    /// ratios of `layout`. Otherwise, the prover pads the trace with more steps.
+    fn fits_in(&self, layout: &str) -> bool {
+        self.builtins.iter().all(|builtin| {
+            builtin_ratio(layout, &builtin.name).map_or(true, |ratio| {
+                builtin.instances * ratio <= self.n_steps as u64
+            })
+        })
+    }
+}
+
+/// Returns the cheapest layout able to prove an execution, preferring the layouts in which
+/// its builtins fit without adding steps. Returns `None` if no layout has all its builtins.
+///
+/// The number of steps of the execution includes the padding of its layout: it may be
+/// smaller in the suggested layout.
+pub fn suggest_layout(report: &BuiltinUsageReport) -> Option<&'static str> {
+    let supported_layouts: Vec<&str> = SUGGESTED_LAYOUTS
+        .iter()
+        .copied()
+        .filter(|layout| report.supported_by(layout))
+        .collect();
+
+    supported_layouts
+        .iter()
+        .copied()
+        .find(|layout| report.fits_in(layout))
+        .or_else(|| supported_layouts.first().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::{fixture, rstest};
+    use stone_prover_sdk::json::read_json_from_file;
+    use stone_prover_sdk::models::MemorySegmentAddresses;
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    #[fixture]
+    fn public_input() -> PublicInput {
+        read_json_from_file(get_test_case_file_path(
+            "fibonacci/fibonacci_public_input.json",
+        ))
+        .unwrap()
+    }
+
+    /// Makes the execution use `size` cells of the segment of `builtin`.
+    fn use_builtin(public_input: &mut PublicInput, builtin: &str, size: u32) {
+        let segment = public_input.memory_segments.get_mut(builtin).unwrap();
+        segment.stop_ptr = segment.begin_addr + size;
+    }
+
+    #[rstest]
+    fn test_fibonacci_report(public_input: PublicInput) {
+        let report = BuiltinUsageReport::from_public_input(&public_input);
+
+        // Executed in starknet_with_keccak, without using any builtin
+        assert_eq!(report.builtins, vec![]);
+        assert_eq!(report.n_steps, public_input.n_steps);
+        assert_eq!(report.rc_min, public_input.rc_min);
+        assert_eq!(report.rc_max, public_input.rc_max);
+        assert_eq!(suggest_layout(&report), Some("plain"));
+    }
+
+    #[rstest]
+    fn test_pedersen_report(mut public_input: PublicInput) {
+        use_builtin(&mut public_input, "output", 2);
+        use_builtin(&mut public_input, "pedersen", 30);
+
+        let report = BuiltinUsageReport::from_public_input(&public_input);
+
+        assert_eq!(
+            report.builtins,
+            vec![
+                BuiltinUsage {
+                    name: "output".to_string(),
+                    segment_size: 2,
+                    instances: 2,
+                },
+                BuiltinUsage {
+                    name: "pedersen".to_string(),
+                    segment_size: 30,
+                    instances: 10,
+                },
+            ]
+        );
+        assert_eq!(suggest_layout(&report), Some("small"));
+    }
+
+    #[rstest]
+    fn test_suggestion_follows_ratios(mut public_input: PublicInput) {
+        public_input.n_steps = 1024;
+        use_builtin(&mut public_input, "pedersen", 3 * 64);
+        let report = BuiltinUsageReport::from_public_input(&public_input);
+        // 64 hashes need 8192 steps in recursive, but only 512 in small
+        assert_eq!(suggest_layout(&report), Some("small"));
+
+        use_builtin(&mut public_input, "bitwise", 5);
+        let report = BuiltinUsageReport::from_public_input(&public_input);
+        // No layout with bitwise fits 64 hashes in 1024 steps, the cheapest one is suggested
+        assert_eq!(suggest_layout(&report), Some("recursive"));
+    }
+
+    #[rstest]
+    fn test_suggestion_with_keccak(mut public_input: PublicInput) {
+        use_builtin(&mut public_input, "keccak", 16);
+        let report = BuiltinUsageReport::from_public_input(&public_input);
+
+        assert_eq!(suggest_layout(&report), Some("starknet_with_keccak"));
+    }
+
+    #[rstest]
+    fn test_no_layout_for_unknown_builtin(mut public_input: PublicInput) {
+        public_input.memory_segments.insert(
+            "range_check96".to_string(),
+            MemorySegmentAddresses {
+                begin_addr: 9000,
+                stop_ptr: 9010,
+            },
+        );
+        let report = BuiltinUsageReport::from_public_input(&public_input);
+
+        assert_eq!(report.builtins[0].instances, 10);
+        assert_eq!(suggest_layout(&report), None);
+    }
+}
@@ -1,11 +1,72 @@
+use std::collections::HashMap;
+
+use cairo_vm::vm::errors::cairo_run_errors::CairoRunError;
+use cairo_vm::vm::errors::runner_errors::RunnerError;
+use cairo_vm::vm::errors::vm_exception::VmException;
 use stone_prover_sdk::cairo_vm::ExecutionError;
-use tonic::Status;
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+
+/// Domain of the `ErrorInfo` details attached to Cairo execution errors.
+pub const CAIRO_ERROR_DOMAIN: &str = "cairo-vm";
+
+/// Builds the error details of a VM exception: the pc and source location of the failing
+/// instruction, and the Cairo traceback if any.
+fn vm_exception_details(vm_exception: &VmException) -> ErrorDetails {
+    let mut metadata = HashMap::from([("pc".to_string(), vm_exception.pc.to_string())]);
+    if let Some(location) = &vm_exception.inst_location {
+        metadata.insert("file".to_string(), location.input_file.filename.clone());
+        metadata.insert("line".to_string(), location.start_line.to_string());
+        metadata.insert("column".to_string(), location.start_col.to_string());
+    }
+    if let Some(error_attr_value) = &vm_exception.error_attr_value {
+        metadata.insert("error_attr".to_string(), error_attr_value.clone());
+    }
+
+    let mut details =
+        ErrorDetails::with_error_info("CAIRO_EXECUTION_FAILED", CAIRO_ERROR_DOMAIN, metadata);
+    let stack_entries: Vec<String> = vm_exception
+        .traceback
+        .as_deref()
+        .map(|traceback| traceback.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    details.set_debug_info(stack_entries, vm_exception.inner_exc.to_string());
+
+    details
+}
+
+/// Returns whether the error is caused by the program itself, ex: a failed assertion,
+/// rather than by the server.
+fn is_program_error(cairo_run_error: &CairoRunError) -> bool {
+    match cairo_run_error {
+        CairoRunError::Program(_)
+        | CairoRunError::VirtualMachine(_)
+        | CairoRunError::MemoryError(_)
+        | CairoRunError::VmException(_)
+        | CairoRunError::Runner(RunnerError::MissingMain) => true,
+        _ => false,
+    }
+}
+
+pub fn cairo_run_error_to_status(cairo_run_error: CairoRunError) -> Status {
+    let message = format!("Failed to run Cairo program: {}", cairo_run_error);
+    if !is_program_error(&cairo_run_error) {
+        return Status::internal(message);
+    }
+
+    match &cairo_run_error {
+        CairoRunError::VmException(vm_exception) => Status::with_error_details(
+            Code::InvalidArgument,
+            message,
+            vm_exception_details(vm_exception),
+        ),
+        _ => Status::invalid_argument(message),
+    }
+}
 
 pub fn execution_error_to_status(execution_error: ExecutionError) -> Status {
     match execution_error {
-        ExecutionError::RunFailed(cairo_run_error) => {
-            Status::internal(format!("Failed to run Cairo program: {}", cairo_run_error))
-        }
+        ExecutionError::RunFailed(cairo_run_error) => cairo_run_error_to_status(cairo_run_error),
         ExecutionError::GeneratePublicInput(public_input_error) => Status::internal(format!(
             "Failed to generate public input: {}",
             public_input_error
@@ -27,3 +88,51 @@ pub fn execution_error_to_status(execution_error: ExecutionError) -> Status {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cairo_vm::vm::errors::trace_errors::TraceError;
+    use test_cases::get_test_case_file_path;
+
+    use crate::services::prover::run_cairo_program_in_proof_mode;
+
+    use super::*;
+
+    #[test]
+    fn test_failed_assert_is_invalid_argument() {
+        let program_path = get_test_case_file_path("failing-assert/failing_assert_compiled.json");
+        let program = std::fs::read(program_path).unwrap();
+
+        let status = run_cairo_program_in_proof_mode(&program, "starknet", None).unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status.message().contains("ASSERT_EQ"),
+            "{}",
+            status.message()
+        );
+
+        let details = status.get_error_details();
+        let error_info = details.error_info().expect("missing error info");
+        assert_eq!(error_info.domain, CAIRO_ERROR_DOMAIN);
+        assert!(error_info.metadata.contains_key("pc"));
+        assert!(error_info.metadata["file"].ends_with("failing_assert.cairo"));
+        assert_eq!(error_info.metadata["line"], "6");
+
+        let debug_info = details.debug_info().expect("missing debug info");
+        assert!(debug_info.detail.contains("ASSERT_EQ"));
+    }
+
+    #[test]
+    fn test_invalid_program_is_invalid_argument() {
+        let status =
+            run_cairo_program_in_proof_mode(b"not a program", "starknet", None).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn test_trace_error_is_internal() {
+        let status = cairo_run_error_to_status(CairoRunError::Trace(TraceError::TraceNotEnabled));
+        assert_eq!(status.code(), Code::Internal);
+    }
+}
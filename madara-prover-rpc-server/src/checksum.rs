@@ -0,0 +1,48 @@
+use tonic::Status;
+
+pub use madara_prover_common::checksum::{sha256_hex, Sha256Checksum};
+
+/// Checks `data` against the checksum provided by the client, if any.
+///
+/// A mismatch is reported with `DATA_LOSS` and both digests: the data was most likely
+/// truncated or corrupted in transit.
+pub fn verify_sha256(field: &str, data: &[u8], expected: Option<&str>) -> Result<(), Status> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let actual = sha256_hex(data);
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(Status::data_loss(format!(
+            "{field} checksum mismatch: expected SHA-256 {expected}, got {actual} \
+            ({} bytes received)",
+            data.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_verify_sha256() {
+        assert!(verify_sha256("Trace", b"abc", None).is_ok());
+        assert!(verify_sha256("Trace", b"abc", Some(ABC_SHA256)).is_ok());
+        assert!(verify_sha256("Trace", b"abc", Some(&ABC_SHA256.to_uppercase())).is_ok());
+
+        let status = verify_sha256("Trace", b"ab", Some(ABC_SHA256)).unwrap_err();
+        assert_eq!(status.code(), Code::DataLoss);
+        assert_eq!(
+            status.message(),
+            format!(
+                "Trace checksum mismatch: expected SHA-256 {ABC_SHA256}, got {} (2 bytes received)",
+                sha256_hex(b"ab")
+            )
+        );
+    }
+}
@@ -0,0 +1,792 @@
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tonic::transport::{Certificate, Identity, ServerTlsConfig};
+
+use crate::error::ServerError;
+use crate::history::{JobHistory, DEFAULT_JOB_HISTORY_SIZE};
+use crate::input_limits::{
+    InputLimits, DEFAULT_MAX_MEMORY_SIZE, DEFAULT_MAX_PIE_SIZE, DEFAULT_MAX_PIE_UNCOMPRESSED_SIZE,
+    DEFAULT_MAX_PROGRAM_SIZE, DEFAULT_MAX_TRACE_SIZE,
+};
+use crate::limiter::{ProofLimiter, DEFAULT_SMALL_PROOF_MAX_N_STEPS};
+use crate::local_files::FileRoots;
+use crate::parameters::{ParameterPreset, ParameterPresets};
+use crate::policy::ProverPolicy;
+use crate::storage::{FilesystemStorage, ProofStorage};
+use crate::workspace::{WorkspaceTracker, DEFAULT_WORKSPACE_TTL_SECS};
+use crate::BindAddress;
+
+/// Prefix of the environment variables that override the configuration file.
+pub const ENV_PREFIX: &str = "MADARA_PROVER_";
+
+/// Proofs can run for several minutes without any data sent on the connection.
+/// Keepalive pings prevent NATs and load balancers from dropping these connections.
+pub const DEFAULT_HTTP2_KEEPALIVE_INTERVAL_SECS: u64 = 30;
+pub const DEFAULT_HTTP2_KEEPALIVE_TIMEOUT_SECS: u64 = 20;
+pub const DEFAULT_TCP_KEEPALIVE_SECS: u64 = 60;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Could not read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Invalid config file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid value for {name}: {value}")]
+    InvalidEnvVar { name: String, value: String },
+}
+
+/// Server configuration.
+///
+/// Values are taken from, in order of precedence: command-line flags, `MADARA_PROVER_*`
+/// environment variables, the TOML configuration file and the defaults below.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// TCP address to listen on. Ignored if `unix_socket` is set at the same or a higher
+    /// precedence level: a TCP address set by a higher level disables a lower-level socket.
+    pub bind_address: SocketAddr,
+    /// Listen on this Unix socket instead of TCP.
+    pub unix_socket: Option<PathBuf>,
+    /// PEM certificate chain and private key of the server. The server serves TLS if both
+    /// are set, and plaintext if neither is.
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    /// PEM certificates of the CAs signing the client certificates. If set, clients must
+    /// authenticate with a certificate. Requires `tls_cert` and `tls_key`.
+    pub tls_client_ca: Option<PathBuf>,
+    /// Directory of the `cpu_air_prover` and `cpu_air_verifier` binaries, searched before
+    /// the directories of `PATH`.
+    pub prover_bin_dir: Option<PathBuf>,
+    /// Directory where the proofs generated by the Starknet prover are archived.
+    pub proof_storage_dir: Option<PathBuf>,
+    /// Maximum size of a request or response message, in bytes.
+    pub max_message_size: Option<usize>,
+    /// Maximum duration of a request, in seconds.
+    pub request_timeout_secs: Option<u64>,
+    /// Maximum number of concurrent requests on each connection.
+    pub concurrency_limit: Option<usize>,
+    /// Minimum free space on the filesystem of the prover working directories, in bytes.
+    /// The server reports itself as not serving below this threshold.
+    pub min_free_disk_space: Option<u64>,
+    /// Maximum number of proofs running at the same time, across all services.
+    pub max_concurrent_proofs: Option<NonZeroUsize>,
+    /// Maximum number of proofs waiting for `max_concurrent_proofs` to run. Requests over
+    /// this limit are rejected with `RESOURCE_EXHAUSTED`.
+    pub max_queued_proofs: usize,
+    /// Number of the `max_concurrent_proofs` slots reserved to small proofs, so that they
+    /// do not wait behind large ones. At least one slot is left to large proofs.
+    pub reserved_small_proofs: usize,
+    /// Maximum number of steps of a small proof. `ExecuteAndProve` requests are large unless
+    /// they set `small_proof`.
+    pub small_proof_max_n_steps: u32,
+    /// Interval between HTTP/2 keepalive pings, in seconds. 0 disables the pings.
+    pub http2_keepalive_interval_secs: u64,
+    /// Time to wait for the acknowledgement of a keepalive ping before closing the
+    /// connection, in seconds.
+    pub http2_keepalive_timeout_secs: u64,
+    /// Interval between TCP keepalive probes, in seconds. 0 disables TCP keepalive.
+    pub tcp_keepalive_secs: u64,
+    /// Number of completed jobs kept in memory and returned by `ListRecentJobs`.
+    /// 0 disables the history.
+    pub job_history_size: usize,
+    /// Refuse to start if the prover binaries are not in `PATH`. Otherwise, the server
+    /// starts and reports itself as not serving.
+    pub require_prover_at_startup: bool,
+    /// Age after which the working directories left behind by the prover are removed, in
+    /// seconds. 0 disables the removal.
+    pub workspace_ttl_secs: u64,
+    /// Maximum size of a program, in bytes. Larger programs are rejected before parsing.
+    pub max_program_size: u64,
+    /// Maximum size of a zipped PIE, in bytes.
+    pub max_pie_size: u64,
+    /// Maximum size of the files of a PIE once extracted, in bytes, as declared by the
+    /// archive. Protects against zip bombs.
+    pub max_pie_uncompressed_size: u64,
+    /// Maximum size of the memory and trace of a `Prove` request, in bytes.
+    pub max_memory_size: u64,
+    pub max_trace_size: u64,
+    /// OTLP endpoint receiving the request traces, ex: `http://localhost:4317`. Takes
+    /// precedence over `OTEL_EXPORTER_OTLP_ENDPOINT`. Requires the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+    /// Prover parameter presets, keyed by layout. Replace the built-in presets of the
+    /// same layouts.
+    pub parameter_presets: HashMap<String, ParameterPreset>,
+    /// Enable the `ProveFromFiles` RPC, which proves artifacts read from the filesystem of
+    /// the server, for co-located deployments.
+    pub prove_from_files: bool,
+    /// Directories from which `ProveFromFiles` can read, as absolute paths.
+    pub prove_from_files_roots: Vec<PathBuf>,
+    /// Layouts and features allowed on this deployment.
+    pub policy: ProverPolicy,
+    /// Storage backend for the proofs, takes precedence over `proof_storage_dir`.
+    /// Only settable programmatically, ex: to use an S3 backend.
+    #[serde(skip)]
+    pub proof_storage: Option<Arc<dyn ProofStorage>>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: "[::1]:8080".parse().unwrap(),
+            unix_socket: None,
+            tls_cert: None,
+            tls_key: None,
+            tls_client_ca: None,
+            prover_bin_dir: None,
+            proof_storage_dir: None,
+            max_message_size: None,
+            request_timeout_secs: None,
+            concurrency_limit: None,
+            min_free_disk_space: None,
+            max_concurrent_proofs: None,
+            max_queued_proofs: 0,
+            reserved_small_proofs: 0,
+            small_proof_max_n_steps: DEFAULT_SMALL_PROOF_MAX_N_STEPS,
+            http2_keepalive_interval_secs: DEFAULT_HTTP2_KEEPALIVE_INTERVAL_SECS,
+            http2_keepalive_timeout_secs: DEFAULT_HTTP2_KEEPALIVE_TIMEOUT_SECS,
+            tcp_keepalive_secs: DEFAULT_TCP_KEEPALIVE_SECS,
+            job_history_size: DEFAULT_JOB_HISTORY_SIZE,
+            require_prover_at_startup: false,
+            workspace_ttl_secs: DEFAULT_WORKSPACE_TTL_SECS,
+            max_program_size: DEFAULT_MAX_PROGRAM_SIZE,
+            max_pie_size: DEFAULT_MAX_PIE_SIZE,
+            max_pie_uncompressed_size: DEFAULT_MAX_PIE_UNCOMPRESSED_SIZE,
+            max_memory_size: DEFAULT_MAX_MEMORY_SIZE,
+            max_trace_size: DEFAULT_MAX_TRACE_SIZE,
+            otlp_endpoint: None,
+            parameter_presets: HashMap::new(),
+            prove_from_files: false,
+            prove_from_files_roots: vec![],
+            policy: ProverPolicy::default(),
+            proof_storage: None,
+        }
+    }
+}
+
+fn parse_var<T: FromStr>(name: &str, value: String) -> Result<T, ConfigError> {
+    value.parse().map_err(|_| ConfigError::InvalidEnvVar {
+        name: name.to_string(),
+        value,
+    })
+}
+
+/// Parses prover parameter presets from a JSON object keyed by layout, ex:
+/// `{"recursive": {"last_layer_degree_bound": 128, "log_n_cosets": 4, "n_queries": 24,
+/// "proof_of_work_bits": 20}}`.
+pub fn parse_parameter_presets(
+    value: &str,
+) -> Result<HashMap<String, ParameterPreset>, serde_json::Error> {
+    serde_json::from_str(value)
+}
+
+fn non_zero_duration(secs: u64) -> Option<Duration> {
+    (secs != 0).then_some(Duration::from_secs(secs))
+}
+
+impl ServerConfig {
+    /// Parses a TOML configuration. Missing fields are set to their default value.
+    pub fn from_toml(content: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let content = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&content)
+    }
+
+    /// Overrides the configuration with the `MADARA_PROVER_*` environment variables.
+    pub fn apply_env(self) -> Result<Self, ConfigError> {
+        self.apply_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Overrides the configuration with the variables returned by `get_var`, a stand-in
+    /// for `std::env::var`.
+    pub fn apply_vars<F>(mut self, get_var: F) -> Result<Self, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |key: &str| {
+            let name = format!("{ENV_PREFIX}{key}");
+            get_var(&name).map(|value| (name, value))
+        };
+
+        if let Some((name, value)) = var("BIND_ADDRESS") {
+            self.listen_on_tcp(parse_var(&name, value)?);
+        }
+        if let Some((_, value)) = var("UNIX_SOCKET") {
+            self.unix_socket = Some(value.into());
+        }
+        if let Some((_, value)) = var("TLS_CERT") {
+            self.tls_cert = Some(value.into());
+        }
+        if let Some((_, value)) = var("TLS_KEY") {
+            self.tls_key = Some(value.into());
+        }
+        if let Some((_, value)) = var("TLS_CLIENT_CA") {
+            self.tls_client_ca = Some(value.into());
+        }
+        if let Some((_, value)) = var("PROVER_BIN_DIR") {
+            self.prover_bin_dir = Some(value.into());
+        }
+        if let Some((_, value)) = var("PROOF_STORAGE_DIR") {
+            self.proof_storage_dir = Some(value.into());
+        }
+        if let Some((name, value)) = var("MAX_MESSAGE_SIZE") {
+            self.max_message_size = Some(parse_var(&name, value)?);
+        }
+        if let Some((name, value)) = var("REQUEST_TIMEOUT_SECS") {
+            self.request_timeout_secs = Some(parse_var(&name, value)?);
+        }
+        if let Some((name, value)) = var("CONCURRENCY_LIMIT") {
+            self.concurrency_limit = Some(parse_var(&name, value)?);
+        }
+        if let Some((name, value)) = var("MIN_FREE_DISK_SPACE") {
+            self.min_free_disk_space = Some(parse_var(&name, value)?);
+        }
+        if let Some((name, value)) = var("MAX_CONCURRENT_PROOFS") {
+            self.max_concurrent_proofs = Some(parse_var(&name, value)?);
+        }
+        if let Some((name, value)) = var("MAX_QUEUED_PROOFS") {
+            self.max_queued_proofs = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("RESERVED_SMALL_PROOFS") {
+            self.reserved_small_proofs = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("SMALL_PROOF_MAX_N_STEPS") {
+            self.small_proof_max_n_steps = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("HTTP2_KEEPALIVE_INTERVAL_SECS") {
+            self.http2_keepalive_interval_secs = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("HTTP2_KEEPALIVE_TIMEOUT_SECS") {
+            self.http2_keepalive_timeout_secs = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("TCP_KEEPALIVE_SECS") {
+            self.tcp_keepalive_secs = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("JOB_HISTORY_SIZE") {
+            self.job_history_size = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("REQUIRE_PROVER_AT_STARTUP") {
+            self.require_prover_at_startup = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("WORKSPACE_TTL_SECS") {
+            self.workspace_ttl_secs = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("MAX_PROGRAM_SIZE") {
+            self.max_program_size = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("MAX_PIE_SIZE") {
+            self.max_pie_size = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("MAX_PIE_UNCOMPRESSED_SIZE") {
+            self.max_pie_uncompressed_size = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("MAX_MEMORY_SIZE") {
+            self.max_memory_size = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("MAX_TRACE_SIZE") {
+            self.max_trace_size = parse_var(&name, value)?;
+        }
+        if let Some((_, value)) = var("OTLP_ENDPOINT") {
+            self.otlp_endpoint = Some(value);
+        }
+        if let Some((name, value)) = var("PARAMETER_PRESETS") {
+            self.parameter_presets = parse_parameter_presets(&value)
+                .map_err(|_| ConfigError::InvalidEnvVar { name, value })?;
+        }
+        if let Some((name, value)) = var("PROVE_FROM_FILES") {
+            self.prove_from_files = parse_var(&name, value)?;
+        }
+        if let Some((_, value)) = var("PROVE_FROM_FILES_ROOTS") {
+            self.prove_from_files_roots = std::env::split_paths(&value).collect();
+        }
+        if let Some((_, value)) = var("POLICY_ALLOWED_LAYOUTS") {
+            self.policy.allowed_layouts = Some(
+                value
+                    .split(',')
+                    .map(|layout| layout.trim().to_string())
+                    .filter(|layout| !layout.is_empty())
+                    .collect(),
+            );
+        }
+        if let Some((name, value)) = var("POLICY_MAX_N_STEPS") {
+            self.policy.max_n_steps = Some(parse_var(&name, value)?);
+        }
+        if let Some((name, value)) = var("POLICY_ALLOW_SPLIT_PROOF") {
+            self.policy.allow_split_proof = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("POLICY_ALLOW_USER_PARAMETERS") {
+            self.policy.allow_user_parameters = parse_var(&name, value)?;
+        }
+        if let Some((name, value)) = var("POLICY_ALLOW_SMALL_PROOF") {
+            self.policy.allow_small_proof = parse_var(&name, value)?;
+        }
+
+        Ok(self)
+    }
+
+    /// Listens on a TCP address, overriding a Unix socket set by a lower precedence level.
+    pub fn listen_on_tcp(&mut self, bind_address: SocketAddr) {
+        self.bind_address = bind_address;
+        self.unix_socket = None;
+    }
+
+    pub fn bind_address(&self) -> BindAddress<'_> {
+        match &self.unix_socket {
+            Some(socket_path) => BindAddress::UnixSocket(socket_path),
+            None => BindAddress::Tcp(self.bind_address),
+        }
+    }
+
+    /// TLS settings of the server, `None` to serve plaintext. Fails if only one of
+    /// `tls_cert` and `tls_key` is set, or if a file cannot be read.
+    pub fn tls_config(&self) -> Result<Option<ServerTlsConfig>, ServerError> {
+        let (tls_cert, tls_key) = match (&self.tls_cert, &self.tls_key) {
+            (Some(tls_cert), Some(tls_key)) => (tls_cert, tls_key),
+            (None, None) if self.tls_client_ca.is_none() => return Ok(None),
+            (None, None) => {
+                return Err(ServerError::InvalidTlsConfig(
+                    "tls_client_ca requires tls_cert and tls_key",
+                ))
+            }
+            _ => {
+                return Err(ServerError::InvalidTlsConfig(
+                    "tls_cert and tls_key must be set together",
+                ))
+            }
+        };
+
+        let read_pem = |path: &Path| {
+            std::fs::read(path).map_err(|source| ServerError::TlsFile {
+                path: path.to_path_buf(),
+                source,
+            })
+        };
+        let identity = Identity::from_pem(read_pem(tls_cert)?, read_pem(tls_key)?);
+        let mut tls_config = ServerTlsConfig::new().identity(identity);
+        if let Some(tls_client_ca) = &self.tls_client_ca {
+            tls_config = tls_config.client_ca_root(Certificate::from_pem(read_pem(tls_client_ca)?));
+        }
+        Ok(Some(tls_config))
+    }
+
+    /// Directories in which the prover binaries are looked up: `prover_bin_dir`, then the
+    /// directories of `path`, a `PATH`-like list of directories.
+    pub fn prover_search_path(&self, path: &OsStr) -> Result<OsString, ServerError> {
+        let Some(prover_bin_dir) = &self.prover_bin_dir else {
+            return Ok(path.to_os_string());
+        };
+        let dirs = std::iter::once(prover_bin_dir.clone()).chain(std::env::split_paths(path));
+        std::env::join_paths(dirs).map_err(|e| ServerError::InvalidProverBinDir {
+            path: prover_bin_dir.clone(),
+            reason: e.to_string(),
+        })
+    }
+
+    pub fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout_secs.map(Duration::from_secs)
+    }
+
+    pub fn http2_keepalive_interval(&self) -> Option<Duration> {
+        non_zero_duration(self.http2_keepalive_interval_secs)
+    }
+
+    pub fn http2_keepalive_timeout(&self) -> Option<Duration> {
+        non_zero_duration(self.http2_keepalive_timeout_secs)
+    }
+
+    pub fn tcp_keepalive(&self) -> Option<Duration> {
+        non_zero_duration(self.tcp_keepalive_secs)
+    }
+
+    /// Limiter shared by the services if the number of concurrent proofs is limited.
+    pub fn proof_limiter(&self) -> Option<Arc<ProofLimiter>> {
+        self.max_concurrent_proofs.map(|max_concurrent_proofs| {
+            Arc::new(
+                ProofLimiter::new(max_concurrent_proofs, self.max_queued_proofs)
+                    .with_reserved_slots(self.reserved_small_proofs, self.small_proof_max_n_steps),
+            )
+        })
+    }
+
+    /// History of the completed jobs, shared by the services.
+    pub fn job_history(&self) -> Arc<JobHistory> {
+        Arc::new(JobHistory::new(self.job_history_size))
+    }
+
+    pub fn workspace_ttl(&self) -> Option<Duration> {
+        non_zero_duration(self.workspace_ttl_secs)
+    }
+
+    /// Size limits of the inputs, shared by the services.
+    pub fn input_limits(&self) -> InputLimits {
+        InputLimits {
+            max_program_size: self.max_program_size,
+            max_pie_size: self.max_pie_size,
+            max_pie_uncompressed_size: self.max_pie_uncompressed_size,
+            max_memory_size: self.max_memory_size,
+            max_trace_size: self.max_trace_size,
+        }
+    }
+
+    /// Tracker of the prover working directories, shared by the services.
+    pub fn workspace_tracker(&self) -> Arc<WorkspaceTracker> {
+        Arc::new(match self.workspace_ttl() {
+            Some(ttl) => WorkspaceTracker::new(ttl),
+            None => WorkspaceTracker::default(),
+        })
+    }
+
+    /// The built-in parameter presets, with the overrides of the configuration.
+    pub fn parameter_presets(&self) -> ParameterPresets {
+        ParameterPresets::default().with_overrides(self.parameter_presets.clone())
+    }
+
+    /// Roots of the files readable by `ProveFromFiles`, `None` if the RPC is disabled. Fails
+    /// if a root is not an absolute path to a directory.
+    pub fn prove_from_files_roots(&self) -> Result<Option<FileRoots>, ServerError> {
+        if !self.prove_from_files {
+            return Ok(None);
+        }
+        FileRoots::new(self.prove_from_files_roots.clone()).map(Some)
+    }
+
+    /// The storage backend to use for proofs, if any.
+    pub fn proof_storage(&self) -> Option<Arc<dyn ProofStorage>> {
+        self.proof_storage.clone().or_else(|| {
+            self.proof_storage_dir
+                .as_ref()
+                .map(|dir| Arc::new(FilesystemStorage::new(dir)) as Arc<dyn ProofStorage>)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE_CONFIG: &str = include_str!("../config.example.toml");
+
+    fn vars(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_parse_example_config() {
+        let config = ServerConfig::from_toml(EXAMPLE_CONFIG).unwrap();
+
+        assert_eq!(config.bind_address, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.unix_socket, None);
+        assert_eq!(
+            config.proof_storage_dir,
+            Some(PathBuf::from("/var/lib/madara-prover/proofs"))
+        );
+        assert_eq!(config.max_message_size, Some(512 * 1024 * 1024));
+        assert_eq!(config.request_timeout(), Some(Duration::from_secs(3600)));
+        assert_eq!(config.concurrency_limit, Some(4));
+        assert_eq!(config.min_free_disk_space, Some(1024 * 1024 * 1024));
+        assert_eq!(config.max_concurrent_proofs, NonZeroUsize::new(2));
+        assert_eq!(config.max_queued_proofs, 8);
+        assert_eq!(config.reserved_small_proofs, 1);
+        assert_eq!(config.small_proof_max_n_steps, 1 << 16);
+        assert_eq!(
+            config.http2_keepalive_interval(),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            config.http2_keepalive_timeout(),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(config.tcp_keepalive(), Some(Duration::from_secs(60)));
+        assert_eq!(config.job_history_size, 200);
+        assert!(config.require_prover_at_startup);
+        assert_eq!(config.workspace_ttl(), Some(Duration::from_secs(7200)));
+        assert_eq!(config.otlp_endpoint, None);
+        assert_eq!(config.prove_from_files_roots().unwrap(), None);
+        assert_eq!(
+            config.input_limits(),
+            InputLimits {
+                max_program_size: 16 * 1024 * 1024,
+                max_pie_size: 64 * 1024 * 1024,
+                max_pie_uncompressed_size: 512 * 1024 * 1024,
+                ..InputLimits::default()
+            }
+        );
+
+        let recursive_preset = config.parameter_presets().get("recursive").copied();
+        assert_eq!(
+            recursive_preset,
+            Some(ParameterPreset {
+                last_layer_degree_bound: 128,
+                log_n_cosets: 4,
+                n_queries: 24,
+                proof_of_work_bits: 20,
+            })
+        );
+        assert_eq!(
+            config.policy,
+            ProverPolicy {
+                allowed_layouts: Some(vec!["recursive".to_string(), "starknet".to_string()]),
+                max_n_steps: Some(1 << 24),
+                allow_split_proof: false,
+                allow_user_parameters: true,
+                allow_small_proof: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config = ServerConfig::from_toml("").unwrap();
+        let default_config = ServerConfig::default();
+
+        assert_eq!(config.bind_address, default_config.bind_address);
+        assert_eq!(config.proof_storage_dir, None);
+        assert!(config.proof_storage().is_none());
+    }
+
+    #[test]
+    fn test_disable_keepalive() {
+        let config = ServerConfig::default()
+            .apply_vars(vars(&[
+                ("MADARA_PROVER_HTTP2_KEEPALIVE_INTERVAL_SECS", "0"),
+                ("MADARA_PROVER_TCP_KEEPALIVE_SECS", "0"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.http2_keepalive_interval(), None);
+        assert_eq!(config.tcp_keepalive(), None);
+        assert_eq!(
+            config.http2_keepalive_timeout(),
+            Some(Duration::from_secs(DEFAULT_HTTP2_KEEPALIVE_TIMEOUT_SECS))
+        );
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let result = ServerConfig::from_toml("bind_adress = \"[::1]:8080\"");
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let config = ServerConfig::from_toml(EXAMPLE_CONFIG)
+            .unwrap()
+            .apply_vars(vars(&[
+                ("MADARA_PROVER_BIND_ADDRESS", "127.0.0.1:9000"),
+                ("MADARA_PROVER_CONCURRENCY_LIMIT", "1"),
+                ("MADARA_PROVER_UNIX_SOCKET", "/tmp/prover.sock"),
+                ("MADARA_PROVER_JOB_HISTORY_SIZE", "10"),
+                ("MADARA_PROVER_WORKSPACE_TTL_SECS", "0"),
+                ("MADARA_PROVER_MAX_PROGRAM_SIZE", "1024"),
+                (
+                    "MADARA_PROVER_POLICY_ALLOWED_LAYOUTS",
+                    "recursive, starknet_with_keccak",
+                ),
+                ("MADARA_PROVER_POLICY_ALLOW_SPLIT_PROOF", "true"),
+                ("MADARA_PROVER_PROVE_FROM_FILES", "true"),
+                (
+                    "MADARA_PROVER_PROVE_FROM_FILES_ROOTS",
+                    "/var/lib/cairo/artifacts:/tmp/artifacts",
+                ),
+                ("MADARA_PROVER_TLS_CERT", "/etc/madara-prover/server.crt"),
+                ("MADARA_PROVER_TLS_KEY", "/etc/madara-prover/server.key"),
+                (
+                    "MADARA_PROVER_TLS_CLIENT_CA",
+                    "/etc/madara-prover/clients.crt",
+                ),
+                ("MADARA_PROVER_PROVER_BIN_DIR", "/opt/stone/bin"),
+                (
+                    "MADARA_PROVER_PARAMETER_PRESETS",
+                    r#"{"starknet": {"last_layer_degree_bound": 64, "log_n_cosets": 2,
+                        "n_queries": 32, "proof_of_work_bits": 20}}"#,
+                ),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.bind_address, "127.0.0.1:9000".parse().unwrap());
+        assert_eq!(config.concurrency_limit, Some(1));
+        assert_eq!(config.job_history_size, 10);
+        assert_eq!(config.workspace_ttl(), None);
+        assert_eq!(config.input_limits().max_program_size, 1024);
+        assert_eq!(
+            config.policy.allowed_layouts,
+            Some(vec![
+                "recursive".to_string(),
+                "starknet_with_keccak".to_string()
+            ])
+        );
+        assert!(config.policy.allow_split_proof);
+        assert!(config.prove_from_files);
+        assert_eq!(
+            config.prove_from_files_roots,
+            vec![
+                PathBuf::from("/var/lib/cairo/artifacts"),
+                PathBuf::from("/tmp/artifacts")
+            ]
+        );
+        assert!(matches!(
+            config.bind_address(),
+            BindAddress::UnixSocket(path) if path == Path::new("/tmp/prover.sock")
+        ));
+        assert_eq!(
+            config.tls_cert,
+            Some(PathBuf::from("/etc/madara-prover/server.crt"))
+        );
+        assert_eq!(
+            config.tls_key,
+            Some(PathBuf::from("/etc/madara-prover/server.key"))
+        );
+        assert_eq!(
+            config.tls_client_ca,
+            Some(PathBuf::from("/etc/madara-prover/clients.crt"))
+        );
+        assert_eq!(config.prover_bin_dir, Some(PathBuf::from("/opt/stone/bin")));
+        // The presets of the file are replaced, the built-in ones still apply
+        assert!(!config.parameter_presets.contains_key("recursive"));
+        let parameter_presets = config.parameter_presets();
+        assert_eq!(
+            parameter_presets
+                .get("starknet")
+                .map(|preset| preset.n_queries),
+            Some(32)
+        );
+        assert_eq!(
+            parameter_presets.get("recursive"),
+            ParameterPresets::default().get("recursive")
+        );
+        // Not overridden
+        assert_eq!(config.request_timeout_secs, Some(3600));
+        assert_eq!(config.policy.max_n_steps, Some(1 << 24));
+    }
+
+    #[test]
+    fn test_env_bind_address_overrides_file_unix_socket() {
+        let config = ServerConfig::from_toml("unix_socket = \"/run/madara-prover.sock\"")
+            .unwrap()
+            .apply_vars(vars(&[("MADARA_PROVER_BIND_ADDRESS", "127.0.0.1:9000")]))
+            .unwrap();
+
+        assert!(matches!(
+            config.bind_address(),
+            BindAddress::Tcp(address) if address == "127.0.0.1:9000".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_no_concurrent_proofs() {
+        let result = ServerConfig::from_toml("max_concurrent_proofs = 0");
+        assert!(matches!(result, Err(ConfigError::Parse(_))));
+
+        let result = ServerConfig::default()
+            .apply_vars(vars(&[("MADARA_PROVER_MAX_CONCURRENT_PROOFS", "0")]));
+        assert!(matches!(result, Err(ConfigError::InvalidEnvVar { .. })));
+    }
+
+    #[test]
+    fn test_prove_from_files_relative_root() {
+        let config = ServerConfig::from_toml(
+            r#"
+            prove_from_files = true
+            prove_from_files_roots = ["artifacts"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            config.prove_from_files_roots(),
+            Err(ServerError::InvalidFileRoot { path, .. }) if path == Path::new("artifacts")
+        ));
+    }
+
+    #[test]
+    fn test_tls_config() {
+        assert!(ServerConfig::default().tls_config().unwrap().is_none());
+
+        let config = ServerConfig {
+            tls_cert: Some(PathBuf::from("/etc/madara-prover/server.crt")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.tls_config(),
+            Err(ServerError::InvalidTlsConfig(_))
+        ));
+
+        let config = ServerConfig {
+            tls_client_ca: Some(PathBuf::from("/etc/madara-prover/clients.crt")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.tls_config(),
+            Err(ServerError::InvalidTlsConfig(_))
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = ServerConfig {
+            tls_cert: Some(dir.path().join("server.crt")),
+            tls_key: Some(dir.path().join("server.key")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.tls_config(),
+            Err(ServerError::TlsFile { path, .. }) if path == dir.path().join("server.crt")
+        ));
+    }
+
+    #[test]
+    fn test_prover_search_path() {
+        let path = OsStr::new("/usr/local/bin:/usr/bin");
+        assert_eq!(
+            ServerConfig::default().prover_search_path(path).unwrap(),
+            path
+        );
+
+        let config = ServerConfig {
+            prover_bin_dir: Some(PathBuf::from("/opt/stone/bin")),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.prover_search_path(path).unwrap(),
+            OsStr::new("/opt/stone/bin:/usr/local/bin:/usr/bin")
+        );
+
+        let config = ServerConfig {
+            prover_bin_dir: Some(PathBuf::from("/opt/stone:bin")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.prover_search_path(path),
+            Err(ServerError::InvalidProverBinDir { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_env_var() {
+        let result =
+            ServerConfig::default().apply_vars(vars(&[("MADARA_PROVER_MAX_MESSAGE_SIZE", "big")]));
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnvVar { name, .. }) if name == "MADARA_PROVER_MAX_MESSAGE_SIZE"
+        ));
+    }
+}
@@ -1,9 +1,65 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum ServerError {
-    #[error("could not bind to Unix Domain Socket")]
+    #[error("could not bind to the server address")]
     Io(#[from] std::io::Error),
+    #[error(
+        "address {0} is already in use: stop the process listening on it or choose another \
+        address with --bind-address"
+    )]
+    AddressInUse(SocketAddr),
+    #[error("could not bind to Unix socket {}: {reason}", path.display())]
+    SocketPathUnavailable { path: PathBuf, reason: String },
+    #[error(
+        "permission denied binding to {target}: ports below 1024 require elevated privileges \
+        and Unix sockets require write access to their directory"
+    )]
+    PermissionDenied { target: String },
     #[error("could not start server")]
     Transport(#[from] tonic::transport::Error),
+    #[error(
+        "{binary} is not available: {reason}. Build the workspace or install the Stone prover \
+        and add its binaries to PATH or prover_bin_dir"
+    )]
+    ProverUnavailable { binary: String, reason: String },
+    #[error("invalid TLS configuration: {0}")]
+    InvalidTlsConfig(&'static str),
+    #[error("could not read TLS file {}: {source}", path.display())]
+    TlsFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid prover_bin_dir {}: {reason}", path.display())]
+    InvalidProverBinDir { path: PathBuf, reason: String },
+    #[error("invalid prove_from_files_roots entry {}: {reason}", path.display())]
+    InvalidFileRoot { path: PathBuf, reason: String },
+    #[error("could not initialize the trace exporter: {0}")]
+    Telemetry(String),
+    #[error("could not load the bootloader program")]
+    Bootloader(#[from] cairo_vm::types::errors::program_errors::ProgramError),
+    #[error(
+        "not enough disk space in {}: {available} bytes available, {required} bytes required",
+        dir.display()
+    )]
+    InsufficientDiskSpace {
+        dir: PathBuf,
+        available: u64,
+        required: u64,
+    },
+}
+
+impl ServerError {
+    /// Process exit code for this error, so that scripts can tell the bind failures apart.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ServerError::AddressInUse(_) => 3,
+            ServerError::SocketPathUnavailable { .. } => 4,
+            ServerError::PermissionDenied { .. } => 5,
+            _ => 1,
+        }
+    }
 }
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use stone_prover_sdk::models::ProverParameters;
+
+use crate::parameters::trace_degree_log;
+
+/// Size of a field element or a hash in the proof, in bytes.
+const ELEMENT_SIZE: u64 = 32;
+/// Number of commitments to trace-sized columns: the execution trace, the interaction trace
+/// and the composition polynomial.
+const TRACE_COMMITMENTS: u64 = 3;
+/// Rough number of trace values decommitted for each query.
+const TRACE_VALUES_PER_QUERY: u64 = 16;
+/// The proof is hex-encoded in the JSON output, along with its annotations.
+const JSON_ENCODING_FACTOR: u64 = 4;
+
+/// Number of recent proofs kept per layout to calibrate the duration model.
+const MAX_MEASUREMENTS: usize = 32;
+
+/// Rough proving time of the fibonacci test case (2^15 steps, 16 cosets), used until
+/// the server records its own proofs.
+const DEFAULT_MEASUREMENT: (u32, u32, Duration) = (1 << 15, 4, Duration::from_secs(10));
+
+/// Estimated cost of a proof.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProofEstimate {
+    /// Size of the JSON proof.
+    pub proof_size_bytes: u64,
+    pub est_duration: Duration,
+}
+
+/// Log2 of the size of the evaluation domain of the trace.
+fn evaluation_domain_log(nb_steps: u32, log_n_cosets: u32) -> u64 {
+    u64::from(trace_degree_log(nb_steps)) + u64::from(log_n_cosets)
+}
+
+/// Estimates the size of the proof from the number of decommitted values and
+/// authentication paths of each query.
+fn estimate_proof_size(nb_steps: u32, parameters: &ProverParameters) -> u64 {
+    let fri = &parameters.stark.fri;
+    let mut layer_log = evaluation_domain_log(nb_steps, parameters.stark.log_n_cosets);
+
+    let mut hashes = TRACE_COMMITMENTS * layer_log;
+    let mut values = TRACE_VALUES_PER_QUERY;
+    for (i, step) in fri.fri_step_list.iter().enumerate() {
+        values += 1 << step;
+        layer_log = layer_log.saturating_sub(u64::from(*step));
+        // The last layer is sent in the clear
+        if i + 1 < fri.fri_step_list.len() {
+            hashes += layer_log;
+        }
+    }
+
+    let elements =
+        u64::from(fri.n_queries) * (hashes + values) + u64::from(fri.last_layer_degree_bound);
+    elements * ELEMENT_SIZE * JSON_ENCODING_FACTOR
+}
+
+/// Proving work of an execution, in arbitrary units. The prover is dominated by the FFTs
+/// over the evaluation domain.
+fn work_units(nb_steps: u32, log_n_cosets: u32) -> f64 {
+    let domain_log = evaluation_domain_log(nb_steps, log_n_cosets);
+    2f64.powi(domain_log as i32) * domain_log as f64
+}
+
+fn seconds_per_work_unit(nb_steps: u32, log_n_cosets: u32, duration: Duration) -> f64 {
+    duration.as_secs_f64() / work_units(nb_steps, log_n_cosets)
+}
+
+/// Estimates the size and duration of proofs.
+///
+/// The duration model is calibrated from the proofs recorded by the server, per layout,
+/// and falls back to a default measurement until a proof completes.
+#[derive(Debug, Default)]
+pub struct ProofEstimator {
+    /// Proving time per work unit of the recent proofs, per layout.
+    measurements: Mutex<HashMap<String, VecDeque<f64>>>,
+}
+
+impl ProofEstimator {
+    /// Records the duration of a completed proof.
+    pub fn record(
+        &self,
+        layout: &str,
+        nb_steps: u32,
+        parameters: &ProverParameters,
+        duration: Duration,
+    ) {
+        let mut measurements = self.measurements.lock().unwrap();
+        let layout_measurements = measurements.entry(layout.to_string()).or_default();
+        if layout_measurements.len() >= MAX_MEASUREMENTS {
+            layout_measurements.pop_front();
+        }
+        layout_measurements.push_back(seconds_per_work_unit(
+            nb_steps,
+            parameters.stark.log_n_cosets,
+            duration,
+        ));
+    }
+
+    /// Average proving time per work unit for `layout`, or for all layouts if no proof
+    /// was recorded for it.
+    fn average_seconds_per_work_unit(&self, layout: &str) -> f64 {
+        let measurements = self.measurements.lock().unwrap();
+        let samples: Vec<f64> = match measurements.get(layout) {
+            Some(layout_measurements) => layout_measurements.iter().copied().collect(),
+            None => measurements.values().flatten().copied().collect(),
+        };
+
+        if samples.is_empty() {
+            let (nb_steps, log_n_cosets, duration) = DEFAULT_MEASUREMENT;
+            return seconds_per_work_unit(nb_steps, log_n_cosets, duration);
+        }
+        samples.iter().sum::<f64>() / samples.len() as f64
+    }
+
+    pub fn estimate(
+        &self,
+        nb_steps: u32,
+        layout: &str,
+        parameters: &ProverParameters,
+    ) -> ProofEstimate {
+        let work_units = work_units(nb_steps, parameters.stark.log_n_cosets);
+        ProofEstimate {
+            proof_size_bytes: estimate_proof_size(nb_steps, parameters),
+            est_duration: Duration::from_secs_f64(
+                work_units * self.average_seconds_per_work_unit(layout),
+            ),
+        }
+    }
+}
+
+/// Estimates the size and duration of a proof, without calibration.
+pub fn estimate_proof(nb_steps: u32, layout: &str, parameters: &ProverParameters) -> ProofEstimate {
+    ProofEstimator::default().estimate(nb_steps, layout, parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use stone_prover_sdk::json::read_json_from_file;
+    use test_cases::get_test_case_file_path;
+
+    use crate::parameters::{ParameterOverrides, ParameterPresets};
+
+    use super::*;
+
+    const LAYOUT: &str = "starknet_with_keccak";
+
+    fn fibonacci_parameters() -> ProverParameters {
+        read_json_from_file(get_test_case_file_path("fibonacci/cpu_air_params.json")).unwrap()
+    }
+
+    #[test]
+    fn test_estimate_grows_with_steps() {
+        let presets = ParameterPresets::default();
+        let estimates: Vec<_> = (10..24)
+            .map(|log_steps| {
+                let parameters = presets
+                    .prover_parameters(LAYOUT, 1 << log_steps, ParameterOverrides::default())
+                    .unwrap();
+                estimate_proof(1 << log_steps, LAYOUT, &parameters)
+            })
+            .collect();
+
+        for pair in estimates.windows(2) {
+            assert!(pair[1].proof_size_bytes > pair[0].proof_size_bytes);
+            assert!(pair[1].est_duration > pair[0].est_duration);
+        }
+    }
+
+    #[test]
+    fn test_fibonacci_proof_size() {
+        let proof_file = get_test_case_file_path("fibonacci/fibonacci_proof.json");
+        let proof_size = std::fs::metadata(proof_file).unwrap().len();
+
+        let estimate = estimate_proof(1 << 15, LAYOUT, &fibonacci_parameters());
+
+        assert!(
+            estimate.proof_size_bytes > proof_size / 10
+                && estimate.proof_size_bytes < proof_size * 10,
+            "estimated {} bytes, the proof has {} bytes",
+            estimate.proof_size_bytes,
+            proof_size
+        );
+    }
+
+    #[test]
+    fn test_default_duration() {
+        let (nb_steps, _, duration) = DEFAULT_MEASUREMENT;
+        let estimate = estimate_proof(nb_steps, LAYOUT, &fibonacci_parameters());
+
+        assert!((estimate.est_duration.as_secs_f64() - duration.as_secs_f64()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_calibration() {
+        let estimator = ProofEstimator::default();
+        let parameters = fibonacci_parameters();
+
+        estimator.record(LAYOUT, 1 << 15, &parameters, Duration::from_secs(2));
+        estimator.record(LAYOUT, 1 << 15, &parameters, Duration::from_secs(4));
+
+        let estimate = estimator.estimate(1 << 15, LAYOUT, &parameters);
+        assert!((estimate.est_duration.as_secs_f64() - 3.0).abs() < 1e-6);
+
+        // Layouts without measurements use the measurements of the other layouts
+        let estimate = estimator.estimate(1 << 15, "recursive", &parameters);
+        assert!((estimate.est_duration.as_secs_f64() - 3.0).abs() < 1e-6);
+    }
+}
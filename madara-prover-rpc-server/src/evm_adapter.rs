@@ -1,3 +1,4 @@
+use std::fmt::{Display, Formatter};
 use std::path::Path;
 
 use stark_evm_adapter::{
@@ -5,65 +6,281 @@ use stark_evm_adapter::{
     annotation_parser::{split_fri_merkle_statements, SplitProofs},
 };
 use std::io::BufRead;
-use stone_prover_sdk::json::read_json_from_file;
 use thiserror::Error;
 
+/// The inputs of `split_proof`, used to report which one could not be processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitProofInput {
+    Proof,
+    Annotations,
+    ExtraAnnotations,
+}
+
+impl Display for SplitProofInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SplitProofInput::Proof => "proof",
+            SplitProofInput::Annotations => "annotations",
+            SplitProofInput::ExtraAnnotations => "extra annotations",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum SplitProverError {
-    #[error("I/O Error")]
-    Io(#[from] std::io::Error),
-    #[error("Error involving split proof")]
+    #[error("Could not read {input} file: {source}")]
+    Io {
+        input: SplitProofInput,
+        source: std::io::Error,
+    },
+    #[error("Could not parse {input} file: {source}")]
+    InputParse {
+        input: SplitProofInput,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Could not parse the proof with its annotations: {0}")]
+    AnnotatedProofParse(#[from] serde_json::Error),
+    #[error("Error involving split proof: {0}")]
     ProofParseError(#[from] stark_evm_adapter::errors::ParseError),
+    #[error(
+        "Proof too small to split: its FRI step list ({0:?}) has no inner layer. Lower \
+        last_layer_degree_bound to add FRI steps"
+    )]
+    NoFriInnerLayers(Vec<u32>),
+    #[error(
+        "Split proof has {actual} FRI statements, expected one per FRI inner layer ({expected})"
+    )]
+    FriStatementCount { expected: usize, actual: usize },
+    #[error("The annotations do not match the proof: {0}")]
+    AnnotationMismatch(String),
+}
+
+/// Returns the number of FRI inner layers of a proof, each of which is registered on L1 as
+/// a FRI statement of the split proof.
+///
+/// The first step of `fri_step_list` folds the trace, whose decommitment is part of the
+/// trace statements: proofs with a single FRI step have no FRI statement. These proofs are
+/// rejected rather than split, as the main proof would be sent to L1 without any FRI
+/// statement to check its layers against. `ParameterPresets` always generates at least two
+/// steps, they can only come from user-provided parameters.
+pub fn fri_inner_layer_count(fri_step_list: &[u32]) -> Result<usize, SplitProverError> {
+    match fri_step_list.len() {
+        0 | 1 => Err(SplitProverError::NoFriInnerLayers(fri_step_list.to_vec())),
+        n_steps => Ok(n_steps - 1),
+    }
 }
 
 /// Uses stark-evm-adapter to split the proof.
+///
+/// If the proof already contains its annotations, as written by
+/// `cpu_air_prover --generate-annotations`, the annotation files are not read.
 pub fn split_proof(
     proof_file: &Path,
     annotations_file: &Path,
     extra_annotations_file: &Path,
 ) -> Result<SplitProofs, SplitProverError> {
-    // 'proof_file' is not expected to have an annotations or an extra_annotations field.
-    // but this will cause an error if we try to parse it as an AnnotatedProof without these
-    // fields.
-    //
-    // since these values are given as separate files, we will with the proof as a JSON object
-    // and add the 'annotations' and 'extra_annotations' fields manually, as the `stark-evm-adapter`
-    // binary does.
-    let mut proof_json: serde_json::Value = read_json_from_file(proof_file)?;
-    proof_json["annotations"] = load_annotations_file(annotations_file)?.into();
-    proof_json["extra_annotations"] = load_annotations_file(extra_annotations_file)?.into();
-
-    let annotated_proof: AnnotatedProof = serde_json::from_value(proof_json).unwrap(); // TODO
+    let proof_bytes = std::fs::read(proof_file).map_err(io_error(SplitProofInput::Proof))?;
+    let proof_json: serde_json::Value =
+        serde_json::from_slice(&proof_bytes).map_err(|e| SplitProverError::InputParse {
+            input: SplitProofInput::Proof,
+            source: e.into(),
+        })?;
+    if has_embedded_annotations(&proof_json) {
+        return split_annotated_proof(proof_json);
+    }
+
+    let annotations =
+        load_annotations_file(annotations_file).map_err(io_error(SplitProofInput::Annotations))?;
+    let extra_annotations = load_annotations_file(extra_annotations_file)
+        .map_err(io_error(SplitProofInput::ExtraAnnotations))?;
+
+    split_proof_from_parts(&proof_json, &annotations, &extra_annotations)
+}
+
+/// Uses stark-evm-adapter to split a proof already loaded in memory.
+pub fn split_proof_from_parts(
+    proof_json: &serde_json::Value,
+    annotations: &[String],
+    extra_annotations: &[String],
+) -> Result<SplitProofs, SplitProverError> {
+    split_annotated_proof(build_annotated_proof(
+        proof_json,
+        annotations,
+        extra_annotations,
+    ))
+}
+
+/// Uses stark-evm-adapter to split a proof built with `build_annotated_proof`.
+pub fn split_annotated_proof(
+    annotated_proof_json: serde_json::Value,
+) -> Result<SplitProofs, SplitProverError> {
+    let fri_step_list: Vec<u32> = serde_json::from_value(
+        annotated_proof_json["proof_parameters"]["stark"]["fri"]["fri_step_list"].clone(),
+    )?;
+    let expected_fri_statements = fri_inner_layer_count(&fri_step_list)?;
+    let annotated_proof: AnnotatedProof = serde_json::from_value(annotated_proof_json)?;
 
     let split_proofs: SplitProofs = split_fri_merkle_statements(annotated_proof)?;
 
+    // Missing statements would not be registered on L1, and the main proof rejected
+    let fri_statements = split_proofs.fri_merkle_statements.len();
+    if fri_statements != expected_fri_statements {
+        return Err(SplitProverError::FriStatementCount {
+            expected: expected_fri_statements,
+            actual: fri_statements,
+        });
+    }
+
     Ok(split_proofs)
 }
 
+/// Builds the annotated proof JSON object expected by stark-evm-adapter and other external
+/// tools: the proof with additional 'annotations' and 'extra_annotations' fields.
+///
+/// The proof produced by the prover does not have these fields, as the annotations are
+/// generated separately by the verifier. We add them manually, as the `stark-evm-adapter`
+/// binary does.
+pub fn build_annotated_proof(
+    proof_json: &serde_json::Value,
+    annotations: &[String],
+    extra_annotations: &[String],
+) -> serde_json::Value {
+    let mut annotated_proof_json = proof_json.clone();
+    annotated_proof_json["annotations"] = annotations.into();
+    annotated_proof_json["extra_annotations"] = extra_annotations.into();
+    annotated_proof_json
+}
+
+/// Returns whether the proof JSON has 'annotations' and 'extra_annotations' fields.
+pub fn has_embedded_annotations(proof_json: &serde_json::Value) -> bool {
+    ["annotations", "extra_annotations"].iter().all(|field| {
+        proof_json
+            .get(field)
+            .is_some_and(serde_json::Value::is_array)
+    })
+}
+
+/// Returns the byte range and the value of a prover-to-verifier annotation, ex:
+/// `P->V[0:32]: /cpu air/STARK/Original/Commit on Trace: Commitment: Hash(0x3c85...)`.
+/// The lines of the annotation files are quoted, the annotations of the proof are not.
+fn parse_prover_message(annotation: &str) -> Option<(usize, usize, &str)> {
+    let (_, message) = annotation.split_once("P->V[")?;
+    let (range, message) = message.split_once(']')?;
+    let (start, end) = range.split_once(':')?;
+    let value = message
+        .rsplit_once("(0x")
+        .and_then(|(_, value)| value.split_once(')'))
+        .map(|(value, _)| value)
+        .unwrap_or_default();
+    Some((start.parse().ok()?, end.parse().ok()?, value))
+}
+
+/// Checks that annotations were generated for `proof_json`, without running the verifier.
+///
+/// This is a consistency check, not a verification: the first prover message, the trace
+/// commitment, must be the first bytes of the proof, and the last prover message must end
+/// with the proof.
+pub fn check_annotations_match_proof(
+    proof_json: &serde_json::Value,
+    annotations: &[String],
+) -> Result<(), SplitProverError> {
+    let mismatch = |reason: &str| SplitProverError::AnnotationMismatch(reason.to_string());
+
+    let proof_hex = proof_json["proof_hex"]
+        .as_str()
+        .ok_or_else(|| mismatch("the proof has no proof_hex field"))?;
+    let proof_hex = proof_hex.strip_prefix("0x").unwrap_or(proof_hex);
+
+    let mut prover_messages = annotations
+        .iter()
+        .filter_map(|annotation| parse_prover_message(annotation));
+    let (start, end, commitment) = prover_messages
+        .next()
+        .ok_or_else(|| mismatch("no prover message in the annotations"))?;
+    let (_, last_end, _) = prover_messages.last().unwrap_or((start, end, commitment));
+
+    if last_end * 2 != proof_hex.len() {
+        return Err(SplitProverError::AnnotationMismatch(format!(
+            "the prover messages end at byte {last_end}, the proof has {} bytes",
+            proof_hex.len() / 2
+        )));
+    }
+    let proof_commitment = proof_hex.get(start * 2..end * 2).unwrap_or_default();
+    if !proof_commitment.eq_ignore_ascii_case(commitment) {
+        return Err(mismatch("the trace commitment differs from the proof"));
+    }
+
+    Ok(())
+}
+
+/// Invalid data, like annotation files that are not UTF-8, is a parse error rather than an
+/// I/O error.
+fn io_error(input: SplitProofInput) -> impl FnOnce(std::io::Error) -> SplitProverError {
+    move |source| match source.kind() {
+        std::io::ErrorKind::InvalidData => SplitProverError::InputParse {
+            input,
+            source: source.into(),
+        },
+        _ => SplitProverError::Io { input, source },
+    }
+}
+
 /// Reads an annotations file, parsing it into a vec of strings suitable for stark-evm-adapter's
 /// AnnotatedProof struct.
 /// May be called for both "annotations" and "extra-annotations".
 pub fn load_annotations_file(file: &Path) -> std::io::Result<Vec<String>> {
     let file = std::fs::File::open(file)?;
-    let lines: Vec<String> = std::io::BufReader::new(file)
-        .lines()
-        .map(|line| line.unwrap())
-        .collect();
-    Ok(lines)
+    std::io::BufReader::new(file).lines().collect()
 }
 
+#[cfg(test)]
 mod tests {
+    use std::io::Write;
+    use std::path::Path;
+
+    use tempfile::NamedTempFile;
+    use tonic::Code;
+
+    use stark_evm_adapter::annotated_proof::AnnotatedProof;
+
+    use crate::evm_adapter::{
+        build_annotated_proof, check_annotations_match_proof, fri_inner_layer_count,
+        has_embedded_annotations, load_annotations_file, split_annotated_proof, split_proof,
+        split_proof_from_parts, SplitProofInput, SplitProverError,
+    };
+    use crate::services::common::format_split_proof_error;
+    use stone_prover_sdk::json::read_json_from_file;
+
+    fn test_case_file(filename: &str) -> std::path::PathBuf {
+        test_cases::get_test_case_file_path(&format!(
+            "bootloader/empty_bootloader_proof/{filename}"
+        ))
+    }
+
+    fn write_temp_file(content: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        file
+    }
+
+    /// Writes the test proof without its embedded annotations, so that `split_proof`
+    /// reads the annotation files.
+    fn proof_without_annotations_file() -> NamedTempFile {
+        let mut proof_json: serde_json::Value =
+            read_json_from_file(test_case_file("annotated_proof.json")).unwrap();
+        let proof = proof_json.as_object_mut().unwrap();
+        proof.remove("annotations");
+        proof.remove("extra_annotations");
+
+        write_temp_file(serde_json::to_string(&proof_json).unwrap().as_bytes())
+    }
+
     #[test]
     fn split_proof_works_with_empty_bootloader_proof() {
-        let annotated_proof_file = test_cases::get_test_case_file_path(
-            "bootloader/empty_bootloader_proof/annotated_proof.json",
-        );
-        let annotations_file = test_cases::get_test_case_file_path(
-            "bootloader/empty_bootloader_proof/annotations.txt",
-        );
-        let extra_annotations_file = test_cases::get_test_case_file_path(
-            "bootloader/empty_bootloader_proof/extra_annotations.txt",
-        );
+        let annotated_proof_file = test_case_file("annotated_proof.json");
+        let annotations_file = test_case_file("annotations.txt");
+        let extra_annotations_file = test_case_file("extra_annotations.txt");
         let split_proofs = crate::evm_adapter::split_proof(
             &annotated_proof_file,
             &annotations_file,
@@ -71,8 +288,236 @@ mod tests {
         )
         .unwrap();
 
+        assert!(split_proofs.merkle_statements.len() > 0);
+        // fri_step_list: [0, 3, 3, 3, 3, 3, 3]
+        assert_eq!(split_proofs.fri_merkle_statements.len(), 6);
+        assert!(split_proofs.main_proof.proof.len() > 0);
+    }
+
+    #[test]
+    fn fri_inner_layers() {
+        assert_eq!(fri_inner_layer_count(&[0, 3, 3]).unwrap(), 2);
+        assert_eq!(fri_inner_layer_count(&[4, 1]).unwrap(), 1);
+        assert!(matches!(
+            fri_inner_layer_count(&[4]),
+            Err(SplitProverError::NoFriInnerLayers(steps)) if steps == vec![4]
+        ));
+    }
+
+    #[test]
+    fn split_proof_without_fri_inner_layers() {
+        let mut proof_json: serde_json::Value =
+            read_json_from_file(test_case_file("annotated_proof.json")).unwrap();
+        // Small program with the whole FRI degree in the last layer
+        proof_json["proof_parameters"]["stark"]["fri"]["fri_step_list"] = serde_json::json!([0]);
+
+        let error = split_annotated_proof(proof_json).unwrap_err();
+
+        assert!(matches!(error, SplitProverError::NoFriInnerLayers(_)));
+        assert!(error.to_string().contains("Lower last_layer_degree_bound"));
+    }
+
+    #[test]
+    fn split_proof_uses_embedded_annotations() {
+        let proof_file = test_case_file("annotated_proof.json");
+        let proof_json: serde_json::Value = read_json_from_file(&proof_file).unwrap();
+        assert!(has_embedded_annotations(&proof_json));
+
+        // The annotation files are not read
+        let missing_file = std::path::Path::new("/does/not/exist");
+        let split_proofs = split_proof(&proof_file, missing_file, missing_file).unwrap();
+        let expected_split_proofs = split_annotated_proof(proof_json).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&split_proofs).unwrap(),
+            serde_json::to_value(&expected_split_proofs).unwrap()
+        );
+    }
+
+    #[test]
+    fn embedded_annotations_round_trip() {
+        let proof_json: serde_json::Value =
+            read_json_from_file(test_case_file("annotated_proof.json")).unwrap();
+        let annotations: Vec<String> =
+            serde_json::from_value(proof_json["annotations"].clone()).unwrap();
+        let extra_annotations: Vec<String> =
+            serde_json::from_value(proof_json["extra_annotations"].clone()).unwrap();
+
+        let rebuilt_proof = build_annotated_proof(&proof_json, &annotations, &extra_annotations);
+        assert_eq!(rebuilt_proof, proof_json);
+
+        let annotated_proof: AnnotatedProof = serde_json::from_value(proof_json).unwrap();
+        assert_eq!(annotated_proof.annotations, annotations);
+        assert_eq!(annotated_proof.extra_annotations, extra_annotations);
+    }
+
+    #[test]
+    fn annotations_match_their_proof() {
+        let proof_json: serde_json::Value =
+            read_json_from_file(test_case_file("annotated_proof.json")).unwrap();
+        let annotations = load_annotations_file(&test_case_file("annotations.txt")).unwrap();
+        let embedded_annotations: Vec<String> =
+            serde_json::from_value(proof_json["annotations"].clone()).unwrap();
+
+        check_annotations_match_proof(&proof_json, &annotations).unwrap();
+        check_annotations_match_proof(&proof_json, &embedded_annotations).unwrap();
+    }
+
+    #[test]
+    fn stale_annotations_are_detected() {
+        let mut proof_json: serde_json::Value =
+            read_json_from_file(test_case_file("annotated_proof.json")).unwrap();
+        let annotations: Vec<String> =
+            serde_json::from_value(proof_json["annotations"].clone()).unwrap();
+        let proof_hex = proof_json["proof_hex"].as_str().unwrap().to_string();
+
+        // Another proof of the same size, with a different trace commitment
+        proof_json["proof_hex"] = format!("0x00{}", &proof_hex[4..]).into();
+        let error = check_annotations_match_proof(&proof_json, &annotations).unwrap_err();
+        assert!(error.to_string().contains("trace commitment"), "{}", error);
+
+        // Another proof with the same commitment, but a different size
+        proof_json["proof_hex"] = format!("{proof_hex}00").into();
+        let error = check_annotations_match_proof(&proof_json, &annotations).unwrap_err();
+        assert!(error.to_string().contains("end at byte"), "{}", error);
+
+        assert!(check_annotations_match_proof(&proof_json, &[]).is_err());
+    }
+
+    #[test]
+    fn split_proof_from_parts_matches_split_proof() {
+        let proof_file = proof_without_annotations_file();
+        let proof_file = proof_file.path();
+        let annotations_file = test_case_file("annotations.txt");
+        let extra_annotations_file = test_case_file("extra_annotations.txt");
+
+        let proof_json: serde_json::Value = read_json_from_file(&proof_file).unwrap();
+        let annotations = load_annotations_file(&annotations_file).unwrap();
+        let extra_annotations = load_annotations_file(&extra_annotations_file).unwrap();
+
+        let split_proofs =
+            split_proof_from_parts(&proof_json, &annotations, &extra_annotations).unwrap();
+        let expected_split_proofs =
+            split_proof(&proof_file, &annotations_file, &extra_annotations_file).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&split_proofs).unwrap(),
+            serde_json::to_value(&expected_split_proofs).unwrap()
+        );
         assert!(split_proofs.merkle_statements.len() > 0);
         assert!(split_proofs.fri_merkle_statements.len() > 0);
         assert!(split_proofs.main_proof.proof.len() > 0);
     }
+
+    #[test]
+    fn annotated_proof_can_be_read_by_stark_evm_adapter() {
+        let proof_json: serde_json::Value =
+            read_json_from_file(test_case_file("annotated_proof.json")).unwrap();
+        let annotations = load_annotations_file(&test_case_file("annotations.txt")).unwrap();
+        let extra_annotations =
+            load_annotations_file(&test_case_file("extra_annotations.txt")).unwrap();
+
+        let annotated_proof = build_annotated_proof(&proof_json, &annotations, &extra_annotations);
+        let annotated_proof_file =
+            write_temp_file(serde_json::to_string(&annotated_proof).unwrap().as_bytes());
+
+        let annotated_proof_str = std::fs::read_to_string(annotated_proof_file.path()).unwrap();
+        let annotated_proof: AnnotatedProof = serde_json::from_str(&annotated_proof_str).unwrap();
+
+        assert_eq!(annotated_proof.annotations, annotations);
+        assert_eq!(annotated_proof.extra_annotations, extra_annotations);
+    }
+
+    #[test]
+    fn split_proof_fails_with_truncated_proof() {
+        let proof = std::fs::read(test_case_file("annotated_proof.json")).unwrap();
+        let truncated_proof_file = write_temp_file(&proof[..proof.len() / 2]);
+
+        let result = split_proof(
+            truncated_proof_file.path(),
+            &test_case_file("annotations.txt"),
+            &test_case_file("extra_annotations.txt"),
+        );
+
+        assert!(matches!(
+            result,
+            Err(SplitProverError::InputParse {
+                input: SplitProofInput::Proof,
+                ..
+            })
+        ));
+        let status = format_split_proof_error(result.unwrap_err());
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn split_proof_fails_with_missing_proof() {
+        let result = split_proof(
+            Path::new("/does/not/exist/proof.json"),
+            &test_case_file("annotations.txt"),
+            &test_case_file("extra_annotations.txt"),
+        );
+
+        assert!(matches!(
+            result,
+            Err(SplitProverError::Io {
+                input: SplitProofInput::Proof,
+                ..
+            })
+        ));
+        let status = format_split_proof_error(result.unwrap_err());
+        assert_eq!(status.code(), Code::Internal);
+    }
+
+    #[test]
+    fn split_proof_fails_with_incomplete_proof() {
+        let incomplete_proof_file = write_temp_file(b"{}");
+
+        let result = split_proof(
+            incomplete_proof_file.path(),
+            &test_case_file("annotations.txt"),
+            &test_case_file("extra_annotations.txt"),
+        );
+
+        assert!(matches!(
+            result,
+            Err(SplitProverError::AnnotatedProofParse(_))
+        ));
+    }
+
+    #[test]
+    fn split_proof_fails_with_invalid_annotations_file() {
+        let invalid_annotations_file = write_temp_file(&[0xff, 0xfe, b'\n']);
+
+        let result = split_proof(
+            proof_without_annotations_file().path(),
+            &test_case_file("annotations.txt"),
+            invalid_annotations_file.path(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(SplitProverError::InputParse {
+                input: SplitProofInput::ExtraAnnotations,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn split_proof_fails_with_truncated_annotations() {
+        let annotations = std::fs::read_to_string(test_case_file("annotations.txt")).unwrap();
+        let lines: Vec<&str> = annotations.lines().collect();
+        let truncated_annotations_file =
+            write_temp_file(lines[..lines.len() / 2].join("\n").as_bytes());
+
+        let result = split_proof(
+            proof_without_annotations_file().path(),
+            truncated_annotations_file.path(),
+            &test_case_file("extra_annotations.txt"),
+        );
+
+        let status = format_split_proof_error(result.unwrap_err());
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
 }
@@ -0,0 +1,655 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use cairo_vm::program_hash::{compute_program_hash_chain, ProgramHashError};
+use cairo_vm::types::errors::program_errors::ProgramError;
+use cairo_vm::types::program::Program;
+use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use cairo_vm::Felt252;
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+use stone_prover_sdk::models::PublicInput;
+use thiserror::Error;
+
+use crate::public_input::{validate_public_memory_pages, PublicInputError};
+
+/// A GPS fact, as registered in the L1 fact registry.
+pub type FactHash = [u8; 32];
+
+/// Number of values written by the bootloader at the start of its output: the simple bootloader
+/// program hash, the hash of the supported Cairo verifier program hashes and the number of tasks.
+pub const BOOTLOADER_OUTPUT_HEADER_SIZE: usize = 3;
+
+/// Number of values written by the bootloader before the output of each task: the size of the
+/// task output (header included) and the program hash.
+pub const TASK_OUTPUT_HEADER_SIZE: usize = 2;
+
+/// Size of an entry of the binary memory: an 8-byte address and a 32-byte value,
+/// both little-endian.
+const MEMORY_ENTRY_SIZE: usize = 40;
+const MEMORY_ADDRESS_SIZE: usize = 8;
+
+#[derive(Debug, Error)]
+pub enum FactError {
+    #[error("Failed to strip program: {0}")]
+    StripProgram(#[from] ProgramError),
+    #[error("Failed to compute program hash: {0}")]
+    ProgramHash(#[from] ProgramHashError),
+    #[error(transparent)]
+    PublicInput(#[from] PublicInputError),
+    #[error("The public input has no output segment")]
+    MissingOutputSegment,
+    #[error("Invalid value in the public memory at address {0}")]
+    InvalidMemoryValue(u32),
+    #[error("The memory size ({0} bytes) is not a multiple of the entry size")]
+    InvalidMemorySize(usize),
+    #[error("The memory has no value at address {0}")]
+    MissingMemoryValue(u64),
+    #[error("The bootloader output is truncated")]
+    TruncatedOutput,
+    #[error("Expected {expected} fact topologies, got {actual}")]
+    TopologyCountMismatch { expected: usize, actual: usize },
+    #[error("Invalid fact topology: {0}")]
+    InvalidTopology(&'static str),
+    #[error("Could not read the fact topologies file: {0}")]
+    ReadTopologies(#[from] std::io::Error),
+    #[error("Invalid fact topologies file: {0}")]
+    InvalidTopologiesFile(#[from] serde_json::Error),
+    #[error(
+        "The fact topology of task {task_index} covers {topology_size} output values, \
+         the task wrote {output_size}"
+    )]
+    TaskTopologyMismatch {
+        task_index: usize,
+        output_size: usize,
+        topology_size: usize,
+    },
+}
+
+/// Describes how the output of a task is split in pages and how these pages are combined
+/// to compute the fact of the task.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FactTopology {
+    /// Pairs of (number of pages to add, number of nodes to merge).
+    pub tree_structure: Vec<usize>,
+    /// Size of each page of the task output.
+    pub page_sizes: Vec<usize>,
+}
+
+impl FactTopology {
+    /// Topology of a task that does not split its output: a single page.
+    pub fn single_page(output_size: usize) -> Self {
+        Self {
+            tree_structure: vec![1, 0],
+            page_sizes: vec![output_size],
+        }
+    }
+}
+
+/// Version of the bootloader, prepended to the program data when hashing a task program.
+pub const BOOTLOADER_VERSION: usize = 0;
+
+/// Computes the Pedersen hash of the program, as the bootloader does.
+pub fn compute_program_hash(
+    program: &Program,
+    bootloader_version: usize,
+) -> Result<Felt252, FactError> {
+    let stripped_program = program.get_stripped_program()?;
+    Ok(compute_program_hash_chain(
+        &stripped_program,
+        bootloader_version,
+    )?)
+}
+
+/// Computes the Pedersen hash of the program embedded in a PIE.
+pub fn compute_pie_program_hash(
+    pie: &CairoPie,
+    bootloader_version: usize,
+) -> Result<Felt252, FactError> {
+    Ok(compute_program_hash_chain(
+        &pie.metadata.program,
+        bootloader_version,
+    )?)
+}
+
+/// Returns the values written to the output segment, in order.
+pub fn output_segment_values(public_input: &PublicInput) -> Result<Vec<Felt252>, FactError> {
+    let output_segment = public_input
+        .memory_segments
+        .get("output")
+        .ok_or(FactError::MissingOutputSegment)?;
+
+    let mut output_entries: Vec<_> = public_input
+        .public_memory
+        .iter()
+        .filter(|entry| {
+            entry.address >= output_segment.begin_addr && entry.address < output_segment.stop_ptr
+        })
+        .collect();
+    output_entries.sort_by_key(|entry| entry.address);
+
+    output_entries
+        .into_iter()
+        .map(|entry| {
+            Felt252::from_hex(&entry.value)
+                .map_err(|_| FactError::InvalidMemoryValue(entry.address))
+        })
+        .collect()
+}
+
+/// Returns the values of the output segment, read from the binary memory of the execution.
+///
+/// Unlike `output_segment_values`, this does not rely on the output being part of the
+/// public memory.
+pub fn output_segment_values_from_memory(
+    public_input: &PublicInput,
+    memory: &[u8],
+) -> Result<Vec<Felt252>, FactError> {
+    let output_segment = public_input
+        .memory_segments
+        .get("output")
+        .ok_or(FactError::MissingOutputSegment)?;
+    if memory.len() % MEMORY_ENTRY_SIZE != 0 {
+        return Err(FactError::InvalidMemorySize(memory.len()));
+    }
+
+    let output_addresses = u64::from(output_segment.begin_addr)..u64::from(output_segment.stop_ptr);
+    let output_values: HashMap<u64, Felt252> = memory
+        .chunks_exact(MEMORY_ENTRY_SIZE)
+        .filter_map(|entry| {
+            let (address_bytes, value_bytes) = entry.split_at(MEMORY_ADDRESS_SIZE);
+            let address = u64::from_le_bytes(address_bytes.try_into().ok()?);
+            output_addresses
+                .contains(&address)
+                .then(|| (address, Felt252::from_bytes_le_slice(value_bytes)))
+        })
+        .collect();
+
+    output_addresses
+        .map(|address| {
+            output_values
+                .get(&address)
+                .copied()
+                .ok_or(FactError::MissingMemoryValue(address))
+        })
+        .collect()
+}
+
+/// Output of a single bootloader task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskOutput {
+    pub program_hash: Felt252,
+    pub output: Vec<Felt252>,
+}
+
+/// Splits the bootloader output into the output of each task.
+pub fn parse_bootloader_output(
+    bootloader_output: &[Felt252],
+) -> Result<Vec<TaskOutput>, FactError> {
+    let n_tasks = bootloader_output
+        .get(BOOTLOADER_OUTPUT_HEADER_SIZE - 1)
+        .and_then(felt_to_usize)
+        .ok_or(FactError::TruncatedOutput)?;
+
+    let mut offset = BOOTLOADER_OUTPUT_HEADER_SIZE;
+    let mut task_outputs = Vec::with_capacity(n_tasks);
+    for _ in 0..n_tasks {
+        let task_output_size = bootloader_output
+            .get(offset)
+            .and_then(felt_to_usize)
+            .filter(|size| *size >= TASK_OUTPUT_HEADER_SIZE)
+            .ok_or(FactError::TruncatedOutput)?;
+        let task_output = bootloader_output
+            .get(offset..offset + task_output_size)
+            .ok_or(FactError::TruncatedOutput)?;
+
+        task_outputs.push(TaskOutput {
+            program_hash: task_output[1],
+            output: task_output[TASK_OUTPUT_HEADER_SIZE..].to_vec(),
+        });
+        offset += task_output_size;
+    }
+
+    Ok(task_outputs)
+}
+
+/// Returns the output of each task of a bootloader run, read from its memory.
+pub fn extract_bootloader_output(
+    public_input: &PublicInput,
+    memory: &[u8],
+) -> Result<Vec<TaskOutput>, FactError> {
+    let bootloader_output = output_segment_values_from_memory(public_input, memory)?;
+    parse_bootloader_output(&bootloader_output)
+}
+
+fn felt_to_usize(felt: &Felt252) -> Option<usize> {
+    let bytes = felt.to_bytes_be();
+    let (high, low) = bytes.split_at(bytes.len() - std::mem::size_of::<u64>());
+    if high.iter().any(|byte| *byte != 0) {
+        return None;
+    }
+    usize::try_from(u64::from_be_bytes(low.try_into().ok()?)).ok()
+}
+
+fn keccak_felts<'a>(values: impl IntoIterator<Item = &'a [u8; 32]>) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for value in values {
+        hasher.update(value);
+    }
+    hasher.finalize().into()
+}
+
+/// Adds one to a 256-bit big-endian integer, wrapping around on overflow.
+fn add_one(mut value: [u8; 32]) -> [u8; 32] {
+    for byte in value.iter_mut().rev() {
+        let (sum, overflow) = byte.overflowing_add(1);
+        *byte = sum;
+        if !overflow {
+            break;
+        }
+    }
+    value
+}
+
+fn usize_to_word(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+/// Computes the root of the output Merkle tree described by the fact topology,
+/// as done by the GPS verifier contracts.
+fn compute_output_root(
+    output: &[Felt252],
+    fact_topology: &FactTopology,
+) -> Result<[u8; 32], FactError> {
+    if fact_topology.tree_structure.len() % 2 != 0 {
+        return Err(FactError::InvalidTopology(
+            "tree structure must have an even length",
+        ));
+    }
+    if fact_topology.page_sizes.iter().sum::<usize>() != output.len() {
+        return Err(FactError::InvalidTopology(
+            "page sizes do not match the output size",
+        ));
+    }
+
+    let output_words: Vec<[u8; 32]> = output.iter().map(Felt252::to_bytes_be).collect();
+    let mut page_sizes = fact_topology.page_sizes.iter();
+    let mut offset = 0;
+    // Each node is represented by its hash and the end offset of its last page.
+    let mut node_stack: Vec<([u8; 32], usize)> = vec![];
+
+    for pair in fact_topology.tree_structure.chunks(2) {
+        let (n_pages, n_nodes) = (pair[0], pair[1]);
+
+        for _ in 0..n_pages {
+            let page_size = *page_sizes.next().ok_or(FactError::InvalidTopology(
+                "tree structure references more pages than available",
+            ))?;
+            let page = &output_words[offset..offset + page_size];
+            offset += page_size;
+            node_stack.push((keccak_felts(page), offset));
+        }
+
+        if n_nodes > 0 {
+            if n_nodes > node_stack.len() {
+                return Err(FactError::InvalidTopology(
+                    "tree structure merges more nodes than available",
+                ));
+            }
+            let child_nodes = node_stack.split_off(node_stack.len() - n_nodes);
+            let end_offset = child_nodes[child_nodes.len() - 1].1;
+            let node_data: Vec<[u8; 32]> = child_nodes
+                .into_iter()
+                .flat_map(|(node_hash, end_offset)| [node_hash, usize_to_word(end_offset)])
+                .collect();
+            node_stack.push((add_one(keccak_felts(&node_data)), end_offset));
+        }
+    }
+
+    if page_sizes.next().is_some() {
+        return Err(FactError::InvalidTopology(
+            "some pages are not part of the tree structure",
+        ));
+    }
+    match node_stack.as_slice() {
+        [(root, _)] => Ok(*root),
+        _ => Err(FactError::InvalidTopology(
+            "tree structure does not reduce to a single root",
+        )),
+    }
+}
+
+/// Computes the fact of a task: keccak(program_hash, output_root).
+pub fn compute_fact_hash(
+    task_output: &TaskOutput,
+    fact_topology: &FactTopology,
+) -> Result<FactHash, FactError> {
+    let output_root = compute_output_root(&task_output.output, fact_topology)?;
+    Ok(keccak_felts([
+        &task_output.program_hash.to_bytes_be(),
+        &output_root,
+    ]))
+}
+
+/// Checks that there is one fact topology per task, and that the pages of each topology cover
+/// exactly the output of its task.
+pub fn validate_fact_topologies(
+    task_outputs: &[TaskOutput],
+    fact_topologies: &[FactTopology],
+) -> Result<(), FactError> {
+    if task_outputs.len() != fact_topologies.len() {
+        return Err(FactError::TopologyCountMismatch {
+            expected: task_outputs.len(),
+            actual: fact_topologies.len(),
+        });
+    }
+
+    for (task_index, (task_output, fact_topology)) in
+        task_outputs.iter().zip(fact_topologies).enumerate()
+    {
+        let topology_size: usize = fact_topology.page_sizes.iter().sum();
+        if topology_size != task_output.output.len() {
+            return Err(FactError::TaskTopologyMismatch {
+                task_index,
+                output_size: task_output.output.len(),
+                topology_size,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the fact of each task proven by the bootloader.
+///
+/// `fact_topologies` must contain one topology per task.
+pub fn compute_fact_hashes(
+    public_input: &PublicInput,
+    fact_topologies: &[FactTopology],
+) -> Result<Vec<FactHash>, FactError> {
+    validate_public_memory_pages(public_input)?;
+    let bootloader_output = output_segment_values(public_input)?;
+    let task_outputs = parse_bootloader_output(&bootloader_output)?;
+
+    validate_fact_topologies(&task_outputs, fact_topologies)?;
+
+    task_outputs
+        .iter()
+        .zip(fact_topologies)
+        .map(|(task_output, fact_topology)| compute_fact_hash(task_output, fact_topology))
+        .collect()
+}
+
+/// Fact topologies file written by the bootloader when `fact_topologies_path` is set.
+#[derive(Deserialize)]
+struct FactTopologiesFile {
+    fact_topologies: Vec<FactTopology>,
+}
+
+/// Reads the fact topology of each task from the file written by the bootloader.
+///
+/// The bootloader derives the topology of a task from the pages and the `gps_fact_topology`
+/// attribute that the task adds to the output builtin, and only falls back to a single page
+/// for the tasks that add no pages.
+pub fn read_fact_topologies(path: &Path) -> Result<Vec<FactTopology>, FactError> {
+    let file_contents = std::fs::read(path)?;
+    let fact_topologies_file: FactTopologiesFile = serde_json::from_slice(&file_contents)?;
+    Ok(fact_topologies_file.fact_topologies)
+}
+
+#[cfg(test)]
+mod tests {
+    use stone_prover_sdk::json::read_json_from_file;
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    fn fibonacci_public_input() -> PublicInput {
+        read_json_from_file(get_test_case_file_path(
+            "bootloader/programs/fibonacci/output/air_public_input.json",
+        ))
+        .unwrap()
+    }
+
+    fn felt(hex: &str) -> Felt252 {
+        Felt252::from_hex(hex).unwrap()
+    }
+
+    /// Parses a 32-byte hash. Not through Felt252, as hashes can exceed the field prime.
+    fn fact(hex: &str) -> FactHash {
+        let hex = hex.trim_start_matches("0x");
+        let mut fact = [0u8; 32];
+        for (i, byte) in fact.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        fact
+    }
+
+    const FIBONACCI_PROGRAM_HASH: &str =
+        "0x43b17e9592f33142246af4c06cd2b574b460dd1f718d76b51341175a62b220f";
+
+    #[test]
+    fn test_compute_program_hash() {
+        let program = Program::from_file(
+            get_test_case_file_path("bootloader/programs/fibonacci/program.json").as_path(),
+            Some("main"),
+        )
+        .unwrap();
+
+        let program_hash = compute_program_hash(&program, BOOTLOADER_VERSION).unwrap();
+
+        assert_eq!(program_hash, felt(FIBONACCI_PROGRAM_HASH));
+    }
+
+    #[test]
+    fn test_compute_pie_program_hash() {
+        let pie_bytes = std::fs::read(get_test_case_file_path(
+            "bootloader/pies/fibonacci/cairo_pie.zip",
+        ))
+        .unwrap();
+        let pie = CairoPie::from_bytes(&pie_bytes).unwrap();
+        let public_input: PublicInput = read_json_from_file(get_test_case_file_path(
+            "bootloader/pies/fibonacci/output/air_public_input.json",
+        ))
+        .unwrap();
+
+        let program_hash = compute_pie_program_hash(&pie, BOOTLOADER_VERSION).unwrap();
+
+        // Same hash as the one written by the bootloader
+        let bootloader_output = output_segment_values(&public_input).unwrap();
+        let task_outputs = parse_bootloader_output(&bootloader_output).unwrap();
+        assert_eq!(program_hash, task_outputs[0].program_hash);
+    }
+
+    #[test]
+    fn test_parse_bootloader_output() {
+        let bootloader_output = output_segment_values(&fibonacci_public_input()).unwrap();
+        let task_outputs = parse_bootloader_output(&bootloader_output).unwrap();
+
+        assert_eq!(
+            task_outputs,
+            vec![TaskOutput {
+                program_hash: felt(FIBONACCI_PROGRAM_HASH),
+                output: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_extract_bootloader_output() {
+        let output_dir = get_test_case_file_path("bootloader/pies/fibonacci/output");
+        let public_input: PublicInput =
+            read_json_from_file(output_dir.join("air_public_input.json")).unwrap();
+        let memory = std::fs::read(output_dir.join("memory.bin")).unwrap();
+
+        let task_outputs = extract_bootloader_output(&public_input, &memory).unwrap();
+
+        assert_eq!(
+            task_outputs,
+            vec![TaskOutput {
+                program_hash: felt(
+                    "0x1858bc7a7962f9487a7209dd6b4c3cbd4c5f6139c0098021be611b3b91adefb"
+                ),
+                output: vec![Felt252::from(10u64), Felt252::from(144u64)],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_extract_bootloader_output_invalid_memory() {
+        let public_input = fibonacci_public_input();
+
+        assert!(matches!(
+            extract_bootloader_output(&public_input, &[0u8; 41]),
+            Err(FactError::InvalidMemorySize(41))
+        ));
+        assert!(matches!(
+            extract_bootloader_output(&public_input, &[]),
+            Err(FactError::MissingMemoryValue(_))
+        ));
+    }
+
+    #[test]
+    fn test_compute_fact_hashes_fibonacci() {
+        let public_input = fibonacci_public_input();
+        // The fibonacci program writes no output
+        let fact_topologies = [FactTopology::single_page(0)];
+
+        let fact_hashes = compute_fact_hashes(&public_input, &fact_topologies).unwrap();
+
+        assert_eq!(
+            fact_hashes,
+            vec![fact(
+                "0x61fedf74dd5aea0e5db4b64b5a53f8b3dd2df4ece2e38a5f94cf49596894d288"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_compute_fact_hash_with_multiple_pages() {
+        let task_output = TaskOutput {
+            program_hash: Felt252::from(0x1234u64),
+            output: (1u64..=4).map(Felt252::from).collect(),
+        };
+
+        let single_page_fact =
+            compute_fact_hash(&task_output, &FactTopology::single_page(4)).unwrap();
+        assert_eq!(
+            single_page_fact,
+            fact("0x533bea27ef6c4b73de6e41b66957475afd6bab0afe256c682c4df5cbca901d7f")
+        );
+
+        let two_pages_topology = FactTopology {
+            tree_structure: vec![2, 2],
+            page_sizes: vec![2, 2],
+        };
+        let two_pages_fact = compute_fact_hash(&task_output, &two_pages_topology).unwrap();
+        assert_eq!(
+            two_pages_fact,
+            fact("0x315387f3746daf5c041c3989438647264f86cd533f6265f3948bebf7db633222")
+        );
+    }
+
+    #[test]
+    fn test_validate_fact_topologies() {
+        let task_outputs = [
+            TaskOutput {
+                program_hash: Felt252::from(0x1234u64),
+                output: vec![],
+            },
+            TaskOutput {
+                program_hash: Felt252::from(0x5678u64),
+                output: (1u64..=4).map(Felt252::from).collect(),
+            },
+        ];
+
+        let fact_topologies = [FactTopology::single_page(0), FactTopology::single_page(4)];
+        assert!(validate_fact_topologies(&task_outputs, &fact_topologies).is_ok());
+
+        let error = validate_fact_topologies(&task_outputs, &fact_topologies[..1]).unwrap_err();
+        assert!(matches!(
+            error,
+            FactError::TopologyCountMismatch {
+                expected: 2,
+                actual: 1
+            }
+        ));
+
+        // The topologies of the tasks are swapped
+        let swapped_topologies = [FactTopology::single_page(4), FactTopology::single_page(0)];
+        let error = validate_fact_topologies(&task_outputs, &swapped_topologies).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The fact topology of task 0 covers 4 output values, the task wrote 0"
+        );
+    }
+
+    #[test]
+    fn test_invalid_topologies() {
+        let task_output = TaskOutput {
+            program_hash: Felt252::from(0x1234u64),
+            output: (1u64..=4).map(Felt252::from).collect(),
+        };
+        let invalid_topologies = [
+            FactTopology::single_page(3),
+            FactTopology {
+                tree_structure: vec![1],
+                page_sizes: vec![4],
+            },
+            FactTopology {
+                tree_structure: vec![2, 0],
+                page_sizes: vec![2, 2],
+            },
+            FactTopology {
+                tree_structure: vec![1, 2],
+                page_sizes: vec![4],
+            },
+        ];
+
+        for fact_topology in invalid_topologies {
+            assert!(matches!(
+                compute_fact_hash(&task_output, &fact_topology),
+                Err(FactError::InvalidTopology(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_read_fact_topologies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fact_topologies.json");
+        std::fs::write(
+            &path,
+            r#"{"fact_topologies": [
+                {"tree_structure": [1, 0], "page_sizes": [0]},
+                {"tree_structure": [2, 2], "page_sizes": [2, 2]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let fact_topologies = read_fact_topologies(&path).unwrap();
+
+        assert_eq!(
+            fact_topologies,
+            vec![
+                FactTopology::single_page(0),
+                FactTopology {
+                    tree_structure: vec![2, 2],
+                    page_sizes: vec![2, 2],
+                },
+            ]
+        );
+
+        std::fs::write(&path, r#"{"fact_topologies": [{"page_sizes": [0]}]}"#).unwrap();
+        assert!(matches!(
+            read_fact_topologies(&path),
+            Err(FactError::InvalidTopologiesFile(_))
+        ));
+        assert!(matches!(
+            read_fact_topologies(&dir.path().join("missing.json")),
+            Err(FactError::ReadTopologies(_))
+        ));
+    }
+}
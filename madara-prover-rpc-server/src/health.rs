@@ -0,0 +1,359 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tonic::Status;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+
+use crate::error::ServerError;
+use crate::services::prover::prover_proto::prover_server::ProverServer;
+use crate::services::prover::ProverService;
+use crate::services::starknet_prover::starknet_prover_proto::starknet_prover_server::StarknetProverServer;
+use crate::services::starknet_prover::StarknetProverService;
+
+/// Interval between two disk space checks of the health monitor.
+pub const DISK_SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Space required on top of the memory and trace files: public/private inputs, proof,
+/// verifier annotations and the files the prover writes alongside them.
+pub const PROVER_DISK_SPACE_MARGIN: u64 = 64 * 1024 * 1024;
+
+/// Directory in which the prover creates its working directories.
+pub fn prover_working_dir_root() -> PathBuf {
+    std::env::temp_dir()
+}
+
+/// Returns the space available to the current user on the filesystem of `dir`, in bytes.
+pub fn available_space(dir: &Path) -> std::io::Result<u64> {
+    fs2::available_space(dir)
+}
+
+/// Estimates the disk space needed to prove an execution from the size of its memory
+/// and trace.
+pub fn estimate_required_space(memory_size: usize, trace_size: usize) -> u64 {
+    (memory_size as u64)
+        .saturating_add(trace_size as u64)
+        .saturating_add(PROVER_DISK_SPACE_MARGIN)
+}
+
+/// Checks that at least `required` bytes are available in `dir`, using `available_space`
+/// to query the filesystem.
+pub fn check_disk_space_with<F>(
+    dir: &Path,
+    required: u64,
+    available_space: F,
+) -> Result<(), ServerError>
+where
+    F: Fn(&Path) -> std::io::Result<u64>,
+{
+    let available = available_space(dir)?;
+    if available < required {
+        return Err(ServerError::InsufficientDiskSpace {
+            dir: dir.to_path_buf(),
+            available,
+            required,
+        });
+    }
+    Ok(())
+}
+
+pub fn check_disk_space(dir: &Path, required: u64) -> Result<(), ServerError> {
+    check_disk_space_with(dir, required, available_space)
+}
+
+/// Checks that the prover has enough disk space to prove an execution, before starting it.
+/// The prover otherwise fails midway with an unrelated error.
+pub fn check_disk_space_for_proof(memory_size: usize, trace_size: usize) -> Result<(), Status> {
+    let required = estimate_required_space(memory_size, trace_size);
+    check_disk_space(&prover_working_dir_root(), required).map_err(|e| match e {
+        ServerError::InsufficientDiskSpace { .. } => Status::resource_exhausted(e.to_string()),
+        e => Status::internal(format!("Could not check the available disk space: {e}")),
+    })
+}
+
+pub async fn set_serving_status(reporter: &mut HealthReporter, status: ServingStatus) {
+    reporter.set_service_status("", status).await;
+    reporter
+        .set_service_status(
+            <ProverServer<ProverService> as tonic::server::NamedService>::NAME,
+            status,
+        )
+        .await;
+    reporter
+        .set_service_status(
+            <StarknetProverServer<StarknetProverService> as tonic::server::NamedService>::NAME,
+            status,
+        )
+        .await;
+}
+
+/// Periodically checks that at least `min_free_space` bytes are available in `dir` and
+/// reports the services as not serving otherwise.
+pub async fn monitor_disk_space(
+    mut reporter: HealthReporter,
+    dir: PathBuf,
+    min_free_space: u64,
+    interval: Duration,
+) {
+    let mut interval = tokio::time::interval(interval);
+    let mut serving = None;
+
+    loop {
+        interval.tick().await;
+
+        let result = check_disk_space(&dir, min_free_space);
+        let is_serving = result.is_ok();
+        if serving == Some(is_serving) {
+            continue;
+        }
+
+        let status = match result {
+            Ok(()) => ServingStatus::Serving,
+            Err(e) => {
+                tracing::warn!("Disk space check failed: {e}");
+                ServingStatus::NotServing
+            }
+        };
+        set_serving_status(&mut reporter, status).await;
+        serving = Some(is_serving);
+    }
+}
+
+/// Binaries run by the SDK to prove and verify, looked up in `PATH`.
+pub const PROVER_BINARIES: [&str; 2] = ["cpu_air_prover", "cpu_air_verifier"];
+
+/// Resolves `binary` in the directories of `path`, a `PATH`-like list of directories.
+pub fn find_binary(binary: &str, path: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(path)
+        .map(|dir| dir.join(binary))
+        .find(|binary_path| binary_path.is_file())
+}
+
+/// A prover binary found in `PATH`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProverBinary {
+    pub name: &'static str,
+    pub path: PathBuf,
+    /// First line of the output of `--version`.
+    pub version: String,
+}
+
+/// Runs `binary_path --version` and returns the first line of its output.
+fn binary_version(binary_path: &Path) -> Result<String, String> {
+    let output = std::process::Command::new(binary_path)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("{} cannot be run: {e}", binary_path.display()))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} --version failed ({}): {}",
+            binary_path.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // Some builds print the version on stderr
+    let version = [&output.stdout, &output.stderr]
+        .into_iter()
+        .map(|output| String::from_utf8_lossy(output))
+        .find_map(|output| {
+            output
+                .lines()
+                .map(str::trim)
+                .find(|line| !line.is_empty())
+                .map(str::to_string)
+        })
+        .unwrap_or_default();
+    Ok(version)
+}
+
+/// Checks that the prover binaries are in `path` and run. Returns their paths and versions.
+pub fn check_prover_binaries(path: &OsStr) -> Result<Vec<ProverBinary>, ServerError> {
+    PROVER_BINARIES
+        .iter()
+        .map(|&binary| {
+            let unavailable = |reason: String| ServerError::ProverUnavailable {
+                binary: binary.to_string(),
+                reason,
+            };
+            let binary_path =
+                find_binary(binary, path).ok_or_else(|| unavailable("not found".to_string()))?;
+            // Fails if the binary is not executable, built for another architecture or
+            // missing shared libraries
+            let version = binary_version(&binary_path).map_err(unavailable)?;
+            Ok(ProverBinary {
+                name: binary,
+                path: binary_path,
+                version,
+            })
+        })
+        .collect()
+}
+
+/// Checks the prover binaries at startup. Returns the binaries if the server can prove,
+/// `None` if they are missing, or an error if `require_prover` is set and they are missing.
+pub fn check_prover_at_startup(
+    path: &OsStr,
+    require_prover: bool,
+) -> Result<Option<Vec<ProverBinary>>, ServerError> {
+    match check_prover_binaries(path) {
+        Ok(binaries) => Ok(Some(binaries)),
+        Err(e) if require_prover => Err(e),
+        Err(e) => {
+            tracing::warn!(
+                "Proving requests will fail, reporting the services as not serving: {e}"
+            );
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    /// Creates fake prover binaries running `script` in a temporary directory, executable
+    /// or not.
+    fn fake_prover_binaries_with(script: &str, executable: bool) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let mode = if executable { 0o755 } else { 0o644 };
+        for binary in PROVER_BINARIES {
+            let binary_path = dir.path().join(binary);
+            std::fs::write(&binary_path, format!("#!/bin/sh\n{script}\n")).unwrap();
+            std::fs::set_permissions(&binary_path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        dir
+    }
+
+    fn fake_prover_binaries(executable: bool) -> tempfile::TempDir {
+        fake_prover_binaries_with("echo \"$(basename $0) v1.0\"", executable)
+    }
+
+    #[test]
+    fn test_check_prover_binaries() {
+        let dir = fake_prover_binaries(true);
+
+        let binaries = check_prover_binaries(dir.path().as_os_str()).unwrap();
+
+        assert_eq!(
+            binaries,
+            vec![
+                ProverBinary {
+                    name: "cpu_air_prover",
+                    path: dir.path().join("cpu_air_prover"),
+                    version: "cpu_air_prover v1.0".to_string(),
+                },
+                ProverBinary {
+                    name: "cpu_air_verifier",
+                    path: dir.path().join("cpu_air_verifier"),
+                    version: "cpu_air_verifier v1.0".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_prover_binaries_version_fails() {
+        // Ex: a binary missing shared libraries
+        let dir = fake_prover_binaries_with("echo 'missing libgflags' >&2; exit 127", true);
+
+        let result = check_prover_binaries(dir.path().as_os_str());
+
+        assert!(matches!(
+            result,
+            Err(ServerError::ProverUnavailable { binary, reason })
+                if binary == "cpu_air_prover" && reason.ends_with("missing libgflags")
+        ));
+    }
+
+    #[test]
+    fn test_check_prover_binaries_not_executable() {
+        let dir = fake_prover_binaries(false);
+
+        let result = check_prover_binaries(dir.path().as_os_str());
+
+        assert!(matches!(
+            result,
+            Err(ServerError::ProverUnavailable { binary, .. }) if binary == "cpu_air_prover"
+        ));
+    }
+
+    #[test]
+    fn test_prover_required_at_startup() {
+        let error = check_prover_at_startup(OsStr::new(""), true).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("cpu_air_prover is not available: not found"));
+    }
+
+    #[test]
+    fn test_degraded_startup_without_prover() {
+        assert_eq!(
+            check_prover_at_startup(OsStr::new(""), false).unwrap(),
+            None
+        );
+
+        let dir = fake_prover_binaries(true);
+        assert!(check_prover_at_startup(dir.path().as_os_str(), false)
+            .unwrap()
+            .is_some());
+    }
+
+    /// Simulates a filesystem with a quota of `quota` bytes.
+    fn quota(quota: u64) -> impl Fn(&Path) -> std::io::Result<u64> {
+        move |_| Ok(quota)
+    }
+
+    #[test]
+    fn test_estimate_required_space() {
+        assert_eq!(
+            estimate_required_space(1000, 2000),
+            3000 + PROVER_DISK_SPACE_MARGIN
+        );
+    }
+
+    #[test]
+    fn test_check_disk_space_enough_space() {
+        let dir = tempfile::tempdir().unwrap();
+        check_disk_space_with(dir.path(), 1024, quota(1024)).unwrap();
+    }
+
+    #[test]
+    fn test_check_disk_space_tiny_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = check_disk_space_with(dir.path(), 1024, quota(512));
+
+        assert!(matches!(
+            result,
+            Err(ServerError::InsufficientDiskSpace {
+                available: 512,
+                required: 1024,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_check_disk_space_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = check_disk_space(&dir.path().join("missing"), 0);
+
+        assert!(matches!(result, Err(ServerError::Io(_))));
+    }
+
+    #[test]
+    fn test_check_disk_space_temp_dir() {
+        check_disk_space(&prover_working_dir_root(), 0).unwrap();
+    }
+
+    #[test]
+    fn test_check_disk_space_for_proof_too_large() {
+        // No filesystem has 16 EiB available
+        let status = check_disk_space_for_proof(usize::MAX / 2, usize::MAX / 2).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+    }
+}
@@ -0,0 +1,356 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::panic::AssertUnwindSafe;
+use std::sync::{OnceLock, RwLock};
+
+use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::BuiltinHintProcessor;
+use cairo_vm::hint_processor::hint_processor_definition::HintProcessorLogic;
+use cairo_vm::serde::deserialize_program::ApTracking;
+use cairo_vm::types::exec_scope::ExecutionScopes;
+use cairo_vm::vm::errors::hint_errors::HintError;
+use cairo_vm::vm::vm_core::VirtualMachine;
+use thiserror::Error;
+
+/// Builds a fresh hint processor for a single program run.
+pub type HintProcessorFactory = fn() -> BuiltinHintProcessor;
+
+/// Hint profile used when the request does not specify one.
+pub const DEFAULT_HINT_PROFILE: &str = "default";
+
+/// Hint profile for bootloader runs. The builtin hint processor of our cairo-vm fork already
+/// implements the bootloader hints, so this is the same processor as the default profile.
+pub const BOOTLOADER_HINT_PROFILE: &str = "bootloader";
+
+#[derive(Debug, Error)]
+pub enum HintProfileError {
+    #[error("Unknown hint profile: {0}")]
+    UnknownProfile(String),
+}
+
+fn hint_profiles() -> &'static RwLock<HashMap<String, HintProcessorFactory>> {
+    static HINT_PROFILES: OnceLock<RwLock<HashMap<String, HintProcessorFactory>>> = OnceLock::new();
+
+    HINT_PROFILES.get_or_init(|| {
+        let default_factory: HintProcessorFactory = BuiltinHintProcessor::new_empty;
+        RwLock::new(HashMap::from([
+            (DEFAULT_HINT_PROFILE.to_string(), default_factory),
+            (BOOTLOADER_HINT_PROFILE.to_string(), default_factory),
+        ]))
+    })
+}
+
+/// Registers a hint profile that execution requests can select by name.
+///
+/// Embedders should call this before `run_grpc_server`. Registering an existing name replaces
+/// the previous factory.
+pub fn register_hint_profile(name: &str, factory: HintProcessorFactory) {
+    hint_profiles()
+        .write()
+        .expect("hint profile registry is poisoned")
+        .insert(name.to_string(), factory);
+}
+
+/// Builds the hint processor for the specified profile, or for the default profile if `None`.
+///
+/// The code of the extra hints registered by the factory is normalized with
+/// `normalize_hint_code`, so that it matches the normalized program hints.
+pub fn hint_processor(profile: Option<&str>) -> Result<BuiltinHintProcessor, HintProfileError> {
+    let profile = profile.unwrap_or(DEFAULT_HINT_PROFILE);
+    let profiles = hint_profiles()
+        .read()
+        .expect("hint profile registry is poisoned");
+
+    let mut hint_processor = profiles
+        .get(profile)
+        .map(|factory| factory())
+        .ok_or_else(|| HintProfileError::UnknownProfile(profile.to_string()))?;
+
+    hint_processor.extra_hints = hint_processor
+        .extra_hints
+        .drain()
+        .map(|(code, hint_func)| (normalize_hint_code(&code), hint_func))
+        .collect();
+
+    Ok(hint_processor)
+}
+
+/// Canonicalizes the whitespace of a hint: strips trailing whitespace, leading/trailing empty
+/// lines and the indentation common to all lines.
+///
+/// This is the form in which cairo-lang stores hints in compiled programs. Hint implementations
+/// are looked up by their exact code, so this lets programs re-serialized by other tools still
+/// match. Relative indentation is preserved, as it is meaningful for Python hints.
+pub fn normalize_hint_code(code: &str) -> String {
+    let lines: Vec<&str> = code.lines().map(str::trim_end).collect();
+
+    let start = lines
+        .iter()
+        .position(|line| !line.is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(start, |index| index + 1);
+    let lines = &lines[start..end];
+
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns whether the hint processor implements the hint.
+///
+/// The builtin hint processor does not expose the list of hints it implements, so the hint is
+/// executed on an empty VM: only unimplemented hints fail with `HintError::UnknownHint`, the
+/// other ones fail on the missing variables or memory.
+fn is_hint_implemented(hint_processor: &mut BuiltinHintProcessor, code: &str) -> bool {
+    let Ok(hint_data) =
+        hint_processor.compile_hint(code, &ApTracking::default(), &HashMap::new(), &[])
+    else {
+        return false;
+    };
+
+    let mut vm = VirtualMachine::new(false);
+    let mut exec_scopes = ExecutionScopes::new();
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+        hint_processor.execute_hint(&mut vm, &mut exec_scopes, &hint_data, &HashMap::new())
+    }));
+
+    !matches!(result, Ok(Err(HintError::UnknownHint(_))))
+}
+
+/// Lists the hints of a compiled program that the hint processor does not implement.
+pub fn unimplemented_hints(
+    program: &serde_json::Value,
+    hint_processor: &mut BuiltinHintProcessor,
+) -> Vec<String> {
+    let hint_codes: BTreeSet<&str> = program
+        .get("hints")
+        .and_then(|hints| hints.as_object())
+        .into_iter()
+        .flat_map(|hints| hints.values())
+        .filter_map(|hints| hints.as_array())
+        .flatten()
+        .filter_map(|hint| hint.get("code").and_then(|code| code.as_str()))
+        .collect();
+
+    hint_codes
+        .into_iter()
+        .filter(|code| !is_hint_implemented(hint_processor, code))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Normalizes the code of all the hints of a compiled program with `normalize_hint_code`.
+///
+/// Hints that `hint_processor` does not implement are logged before the run starts, as the VM
+/// only reports them when it reaches them. Returns the program unchanged if all hints are already
+/// canonical, or if the program cannot be parsed as JSON (in which case loading the program will
+/// report the error).
+pub fn normalize_program_hints<'a>(
+    program: &'a [u8],
+    hint_processor: &mut BuiltinHintProcessor,
+) -> Cow<'a, [u8]> {
+    let Ok(mut program_json) = serde_json::from_slice::<serde_json::Value>(program) else {
+        return Cow::Borrowed(program);
+    };
+
+    let mut modified = false;
+    if let Some(hints) = program_json
+        .get_mut("hints")
+        .and_then(|hints| hints.as_object_mut())
+    {
+        let hints_at_pc = hints.values_mut().filter_map(|hints| hints.as_array_mut());
+        for hint in hints_at_pc.flatten() {
+            let Some(code) = hint.get_mut("code") else {
+                continue;
+            };
+            let Some(normalized_code) = code.as_str().map(normalize_hint_code) else {
+                continue;
+            };
+            if code.as_str() != Some(normalized_code.as_str()) {
+                *code = normalized_code.into();
+                modified = true;
+            }
+        }
+    }
+
+    let unimplemented_hints = unimplemented_hints(&program_json, hint_processor);
+    if !unimplemented_hints.is_empty() {
+        tracing::warn!(
+            "The program has {} hint(s) without implementation, the run will fail if it reaches \
+             them:\n{}",
+            unimplemented_hints.len(),
+            unimplemented_hints.join("\n---\n")
+        );
+    }
+
+    if !modified {
+        return Cow::Borrowed(program);
+    }
+
+    serde_json::to_vec(&program_json)
+        .map(Cow::Owned)
+        .unwrap_or(Cow::Borrowed(program))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use cairo_vm::hint_processor::builtin_hint_processor::builtin_hint_processor_definition::HintFunc;
+    use cairo_vm::hint_processor::hint_processor_definition::HintReference;
+    use cairo_vm::Felt252;
+    use serde_json::Value;
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    fn hint_codes(program: &Value) -> Vec<String> {
+        let mut codes: Vec<String> = program["hints"]
+            .as_object()
+            .unwrap()
+            .values()
+            .flat_map(|hints| hints.as_array().unwrap())
+            .map(|hint| hint["code"].as_str().unwrap().to_string())
+            .collect();
+        codes.sort();
+        codes
+    }
+
+    #[test]
+    fn test_normalize_hint_code() {
+        assert_eq!(normalize_hint_code("  x = 1  "), "x = 1");
+        assert_eq!(
+            normalize_hint_code("\n    if x:\n        y = 1\n    z = 2\n\n"),
+            "if x:\n    y = 1\nz = 2"
+        );
+        assert_eq!(normalize_hint_code("a\n\n  b"), "a\n\n  b");
+    }
+
+    #[test]
+    fn test_canonical_program_is_unchanged() {
+        let bootloader =
+            std::fs::read(get_test_case_file_path("bootloader/bootloader.json")).unwrap();
+        assert!(matches!(
+            normalize_program_hints(&bootloader, &mut BuiltinHintProcessor::new_empty()),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_normalize_reindented_bootloader() {
+        let bootloader =
+            std::fs::read(get_test_case_file_path("bootloader/bootloader.json")).unwrap();
+        let bootloader_json: Value = serde_json::from_slice(&bootloader).unwrap();
+
+        // Re-indent every hint, as some tools do when re-serializing programs
+        let mut reindented_json = bootloader_json.clone();
+        for hint in reindented_json["hints"]
+            .as_object_mut()
+            .unwrap()
+            .values_mut()
+            .flat_map(|hints| hints.as_array_mut().unwrap())
+        {
+            let code = hint["code"].as_str().unwrap();
+            let reindented_code: Vec<String> =
+                code.lines().map(|line| format!("    {line}")).collect();
+            hint["code"] = format!("\n{}\n", reindented_code.join("\n")).into();
+        }
+        assert_ne!(hint_codes(&reindented_json), hint_codes(&bootloader_json));
+
+        let reindented = serde_json::to_vec(&reindented_json).unwrap();
+        let normalized =
+            normalize_program_hints(&reindented, &mut BuiltinHintProcessor::new_empty());
+        let normalized_json: Value = serde_json::from_slice(&normalized).unwrap();
+
+        assert_eq!(hint_codes(&normalized_json), hint_codes(&bootloader_json));
+    }
+
+    fn bootloader_json() -> Value {
+        let bootloader =
+            std::fs::read(get_test_case_file_path("bootloader/bootloader.json")).unwrap();
+        serde_json::from_slice(&bootloader).unwrap()
+    }
+
+    fn toy_hint(
+        _vm: &mut VirtualMachine,
+        _exec_scopes: &mut ExecutionScopes,
+        _ids_data: &HashMap<String, HintReference>,
+        _ap_tracking: &ApTracking,
+        _constants: &HashMap<String, Felt252>,
+    ) -> Result<(), HintError> {
+        Ok(())
+    }
+
+    #[test]
+    fn test_bootloader_hints_are_implemented() {
+        let unimplemented =
+            unimplemented_hints(&bootloader_json(), &mut BuiltinHintProcessor::new_empty());
+        assert_eq!(unimplemented, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_unimplemented_hints() {
+        let mut program = bootloader_json();
+        let (_, hints_at_pc) = program["hints"]
+            .as_object_mut()
+            .unwrap()
+            .iter_mut()
+            .next()
+            .unwrap();
+        hints_at_pc.as_array_mut().unwrap()[0]["code"] = "toy_hint()".into();
+
+        let unimplemented = unimplemented_hints(&program, &mut BuiltinHintProcessor::new_empty());
+
+        assert_eq!(unimplemented, vec!["toy_hint()".to_string()]);
+    }
+
+    #[test]
+    fn test_registered_hint_code_is_normalized() {
+        fn toy_hint_processor() -> BuiltinHintProcessor {
+            let mut hint_processor = BuiltinHintProcessor::new_empty();
+            hint_processor.add_hint(
+                "\n    toy_hint()  \n".to_string(),
+                Rc::new(HintFunc(Box::new(toy_hint))),
+            );
+            hint_processor
+        }
+        register_hint_profile("test-toy-profile", toy_hint_processor);
+
+        let mut hint_processor = hint_processor(Some("test-toy-profile")).unwrap();
+
+        assert!(hint_processor.extra_hints.contains_key("toy_hint()"));
+        assert!(is_hint_implemented(&mut hint_processor, "toy_hint()"));
+    }
+
+    #[test]
+    fn test_default_profiles_are_registered() {
+        assert!(hint_processor(None).is_ok());
+        assert!(hint_processor(Some(DEFAULT_HINT_PROFILE)).is_ok());
+        assert!(hint_processor(Some(BOOTLOADER_HINT_PROFILE)).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_profile() {
+        let result = hint_processor(Some("does-not-exist"));
+        assert!(
+            matches!(result, Err(HintProfileError::UnknownProfile(ref name)) if name == "does-not-exist")
+        );
+    }
+
+    #[test]
+    fn test_register_profile() {
+        register_hint_profile("test-profile", BuiltinHintProcessor::new_empty);
+        assert!(hint_processor(Some("test-profile")).is_ok());
+    }
+}
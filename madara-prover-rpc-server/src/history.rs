@@ -0,0 +1,244 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use tonic::{Code, Status};
+
+use crate::limiter::{ProofClass, ProofPermit};
+
+/// Number of completed jobs kept by default.
+pub const DEFAULT_JOB_HISTORY_SIZE: usize = 100;
+
+/// Summary of a completed proving request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobSummary {
+    pub request_id: u64,
+    /// RPC of the request, ex: `prover.Prover/ExecuteAndProve`.
+    pub rpc: &'static str,
+    /// Layout and number of steps of the execution. Empty if the request failed before the
+    /// execution completed.
+    pub layout: String,
+    pub n_steps: u32,
+    pub started_at: SystemTime,
+    pub duration: Duration,
+    /// Class of the proof and time spent waiting for a proof slot. `None` if the number of
+    /// concurrent proofs is not limited, or if the request failed before getting a slot.
+    pub proof_class: Option<ProofClass>,
+    pub queue_duration: Duration,
+    /// Duration of the prover run, `None` if the prover did not run.
+    pub prove_duration: Option<Duration>,
+    pub status_code: Code,
+    pub status_message: String,
+    /// Size of the serialized proof, 0 if the request failed.
+    pub proof_size: u64,
+}
+
+/// A proving request in progress. The handlers fill in the details of the execution as
+/// they become available.
+#[derive(Debug)]
+pub struct Job {
+    request_id: u64,
+    rpc: &'static str,
+    started_at: SystemTime,
+    timer: Instant,
+    pub layout: String,
+    pub n_steps: u32,
+    pub proof_class: Option<ProofClass>,
+    pub queue_duration: Duration,
+    pub prove_duration: Option<Duration>,
+}
+
+impl Job {
+    pub fn request_id(&self) -> u64 {
+        self.request_id
+    }
+
+    /// Records the scheduling of the proof, if the number of concurrent proofs is limited.
+    pub fn record_permit(&mut self, permit: Option<&ProofPermit<'_>>) {
+        if let Some(permit) = permit {
+            self.proof_class = Some(permit.class());
+            self.queue_duration = permit.queue_duration();
+        }
+    }
+
+    /// Completes the job with the result of the request.
+    pub fn finish<T>(
+        self,
+        result: &Result<T, Status>,
+        proof_size: impl FnOnce(&T) -> u64,
+    ) -> JobSummary {
+        let (status_code, status_message, proof_size) = match result {
+            Ok(response) => (Code::Ok, String::new(), proof_size(response)),
+            Err(status) => (status.code(), status.message().to_string(), 0),
+        };
+
+        JobSummary {
+            request_id: self.request_id,
+            rpc: self.rpc,
+            layout: self.layout,
+            n_steps: self.n_steps,
+            started_at: self.started_at,
+            duration: self.timer.elapsed(),
+            proof_class: self.proof_class,
+            queue_duration: self.queue_duration,
+            prove_duration: self.prove_duration,
+            status_code,
+            status_message,
+            proof_size,
+        }
+    }
+}
+
+/// Keeps the summaries of the most recent proving requests, in memory.
+#[derive(Debug)]
+pub struct JobHistory {
+    /// Completed jobs, from oldest to newest.
+    jobs: Mutex<VecDeque<JobSummary>>,
+    capacity: usize,
+    next_request_id: AtomicU64,
+}
+
+impl Default for JobHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_JOB_HISTORY_SIZE)
+    }
+}
+
+impl JobHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            jobs: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            next_request_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Starts tracking a request to `rpc`.
+    pub fn start(&self, rpc: &'static str) -> Job {
+        Job {
+            request_id: self.next_request_id.fetch_add(1, Ordering::Relaxed),
+            rpc,
+            started_at: SystemTime::now(),
+            timer: Instant::now(),
+            layout: String::new(),
+            n_steps: 0,
+            proof_class: None,
+            queue_duration: Duration::ZERO,
+            prove_duration: None,
+        }
+    }
+
+    /// Records a completed job, evicting the oldest one if the history is full.
+    pub fn record(&self, job: JobSummary) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut jobs = self.jobs.lock().unwrap();
+        if jobs.len() >= self.capacity {
+            jobs.pop_front();
+        }
+        jobs.push_back(job);
+    }
+
+    /// Returns up to `limit` jobs, most recent first. All the jobs are returned if `limit`
+    /// is 0.
+    pub fn recent(&self, limit: usize) -> Vec<JobSummary> {
+        let jobs = self.jobs.lock().unwrap();
+        let limit = if limit == 0 { jobs.len() } else { limit };
+        jobs.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use crate::limiter::ProofLimiter;
+
+    use super::*;
+
+    fn record_job(history: &JobHistory, n_steps: u32) {
+        let mut job = history.start("prover.Prover/Prove");
+        job.n_steps = n_steps;
+        history.record(job.finish(&Ok::<_, Status>(()), |_| 1));
+    }
+
+    #[test]
+    fn test_recent_jobs_order() {
+        let history = JobHistory::default();
+        for n_steps in [1, 2, 3] {
+            record_job(&history, n_steps);
+        }
+
+        let jobs = history.recent(0);
+        assert_eq!(
+            jobs.iter().map(|job| job.n_steps).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+        assert_eq!(
+            jobs.iter().map(|job| job.request_id).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+
+        let jobs = history.recent(2);
+        assert_eq!(
+            jobs.iter().map(|job| job.n_steps).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+    }
+
+    #[test]
+    fn test_capacity() {
+        let history = JobHistory::new(2);
+        for n_steps in [1, 2, 3] {
+            record_job(&history, n_steps);
+        }
+
+        let jobs = history.recent(0);
+        assert_eq!(
+            jobs.iter().map(|job| job.n_steps).collect::<Vec<_>>(),
+            vec![3, 2]
+        );
+    }
+
+    #[test]
+    fn test_disabled_history() {
+        let history = JobHistory::new(0);
+        record_job(&history, 1);
+        assert!(history.recent(0).is_empty());
+    }
+
+    #[test]
+    fn test_failed_job() {
+        let history = JobHistory::default();
+        let job = history.start("prover.Prover/ExecuteAndProve");
+
+        let summary = job.finish(
+            &Err::<(), _>(Status::invalid_argument("bad program")),
+            |_| 1,
+        );
+
+        assert_eq!(summary.status_code, Code::InvalidArgument);
+        assert_eq!(summary.status_message, "bad program");
+        assert_eq!(summary.proof_size, 0);
+        assert_eq!(summary.prove_duration, None);
+        assert_eq!(summary.proof_class, None);
+    }
+
+    #[tokio::test]
+    async fn test_job_scheduling() {
+        let history = JobHistory::default();
+        let limiter =
+            ProofLimiter::new(NonZeroUsize::new(2).unwrap(), 0).with_reserved_slots(1, 1000);
+        let mut job = history.start("prover.Prover/Prove");
+
+        let permit = limiter.acquire(ProofClass::Small).await.unwrap();
+        job.record_permit(Some(&permit));
+        let summary = job.finish(&Ok::<_, Status>(()), |_| 1);
+
+        assert_eq!(summary.proof_class, Some(ProofClass::Small));
+        assert_eq!(summary.queue_duration, Duration::ZERO);
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+use tonic::{Code, Request, Status};
+
+/// Request metadata key holding the idempotency key of a prove request.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Duration for which the result of a request is kept.
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(3600);
+
+/// Maximum number of results kept in the cache.
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 64;
+
+/// Returns the idempotency key of a request, if any.
+pub fn idempotency_key<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|key| key.to_string())
+}
+
+/// SHA-256 hash of the protobuf encoding of a request, to detect an idempotency key reused
+/// for a different request.
+pub type RequestHash = [u8; 32];
+
+pub fn request_hash<M: prost::Message>(request: &M) -> RequestHash {
+    Sha256::digest(request.encode_to_vec()).into()
+}
+
+/// Status of a failed request, rebuilt for each of the requests waiting for it.
+#[derive(Debug, Clone)]
+struct CachedStatus {
+    code: Code,
+    message: String,
+    details: Vec<u8>,
+}
+
+impl From<Status> for CachedStatus {
+    fn from(status: Status) -> Self {
+        Self {
+            code: status.code(),
+            message: status.message().to_string(),
+            details: status.details().to_vec(),
+        }
+    }
+}
+
+impl From<CachedStatus> for Status {
+    fn from(status: CachedStatus) -> Self {
+        Status::with_details(status.code, status.message, status.details.into())
+    }
+}
+
+type CachedResult<T> = Arc<OnceCell<Result<T, CachedStatus>>>;
+
+#[derive(Debug)]
+struct CacheEntry<T> {
+    key: String,
+    request_hash: RequestHash,
+    created_at: Instant,
+    result: CachedResult<T>,
+}
+
+/// Deduplicates requests sharing the same idempotency key.
+///
+/// Replays of a request that is still running wait for its result instead of starting
+/// a new prover process, and replays of a completed request get the cached result.
+/// Failed requests are not cached so that they can be retried.
+#[derive(Debug)]
+pub struct IdempotencyCache<T> {
+    /// Entries, from oldest to newest.
+    entries: Mutex<VecDeque<CacheEntry<T>>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl<T> Default for IdempotencyCache<T> {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_TTL, DEFAULT_IDEMPOTENCY_CAPACITY)
+    }
+}
+
+impl<T> IdempotencyCache<T> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            ttl,
+            capacity,
+        }
+    }
+
+    /// Returns the entry of `key`, creating it if needed. Fails if the entry was created
+    /// by a different request.
+    fn entry(&self, key: &str, request_hash: RequestHash) -> Result<CachedResult<T>, Status> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|entry| entry.created_at.elapsed() < self.ttl);
+
+        if let Some(entry) = entries.iter().find(|entry| entry.key == key) {
+            if entry.request_hash != request_hash {
+                return Err(Status::invalid_argument(format!(
+                    "Idempotency key {key:?} was already used for a different request"
+                )));
+            }
+            return Ok(entry.result.clone());
+        }
+
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+
+        let result = CachedResult::default();
+        entries.push_back(CacheEntry {
+            key: key.to_string(),
+            request_hash,
+            created_at: Instant::now(),
+            result: result.clone(),
+        });
+        Ok(result)
+    }
+
+    fn remove(&self, key: &str, result: &CachedResult<T>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|entry| entry.key != key || !Arc::ptr_eq(&entry.result, result));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Clone> IdempotencyCache<T> {
+    /// Runs `f`, or returns the result of the previous request with the same key.
+    /// Requests without a key always run. Reusing a key for a request with a different
+    /// `request_hash` is rejected.
+    pub async fn run<F, Fut>(
+        &self,
+        key: Option<String>,
+        request_hash: RequestHash,
+        f: F,
+    ) -> Result<T, Status>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, Status>>,
+    {
+        let Some(key) = key else {
+            return f().await;
+        };
+
+        let cached_result = self.entry(&key, request_hash)?;
+        let result = cached_result
+            .get_or_init(|| async move { f().await.map_err(CachedStatus::from) })
+            .await
+            .clone();
+
+        if result.is_err() {
+            self.remove(&key, &cached_result);
+        }
+
+        result.map_err(Status::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const REQUEST: RequestHash = [0; 32];
+
+    /// Stands in for the prover, counting its invocations.
+    async fn counting_prover(
+        invocations: &AtomicUsize,
+        result: Result<u32, Status>,
+    ) -> Result<u32, Status> {
+        invocations.fetch_add(1, Ordering::SeqCst);
+        tokio::task::yield_now().await;
+        result
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let mut request = Request::new(());
+        assert_eq!(idempotency_key(&request), None);
+
+        request
+            .metadata_mut()
+            .insert(IDEMPOTENCY_KEY_HEADER, "some-key".parse().unwrap());
+        assert_eq!(idempotency_key(&request), Some("some-key".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_replays_run_once() {
+        let cache = IdempotencyCache::default();
+        let invocations = AtomicUsize::new(0);
+        let key = Some("key".to_string());
+
+        let (first, second) = tokio::join!(
+            cache.run(key.clone(), REQUEST, || counting_prover(
+                &invocations,
+                Ok(1)
+            )),
+            cache.run(key.clone(), REQUEST, || counting_prover(
+                &invocations,
+                Ok(2)
+            )),
+        );
+
+        assert_eq!(first.unwrap(), 1);
+        assert_eq!(second.unwrap(), 1);
+        assert_eq!(invocations.load(Ordering::SeqCst), 1);
+
+        // Completed requests are cached as well
+        let third = cache.run(key, REQUEST, || counting_prover(&invocations, Ok(3)));
+        assert_eq!(third.await.unwrap(), 1);
+        assert_eq!(invocations.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_key_always_run() {
+        let cache = IdempotencyCache::default();
+        let invocations = AtomicUsize::new(0);
+
+        cache
+            .run(None, REQUEST, || counting_prover(&invocations, Ok(1)))
+            .await
+            .unwrap();
+        cache
+            .run(None, REQUEST, || counting_prover(&invocations, Ok(1)))
+            .await
+            .unwrap();
+
+        assert_eq!(invocations.load(Ordering::SeqCst), 2);
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_not_cached() {
+        let cache = IdempotencyCache::default();
+        let invocations = AtomicUsize::new(0);
+        let key = Some("key".to_string());
+
+        let status = cache
+            .run(key.clone(), REQUEST, || {
+                counting_prover(&invocations, Err(Status::invalid_argument("bad program")))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "bad program");
+
+        let result = cache.run(key, REQUEST, || counting_prover(&invocations, Ok(1)));
+        assert_eq!(result.await.unwrap(), 1);
+        assert_eq!(invocations.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_expired_results_are_evicted() {
+        let cache = IdempotencyCache::new(Duration::ZERO, DEFAULT_IDEMPOTENCY_CAPACITY);
+        let invocations = AtomicUsize::new(0);
+        let key = Some("key".to_string());
+
+        for _ in 0..2 {
+            cache
+                .run(key.clone(), REQUEST, || {
+                    counting_prover(&invocations, Ok(1))
+                })
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(invocations.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_capacity() {
+        let cache = IdempotencyCache::new(DEFAULT_IDEMPOTENCY_TTL, 2);
+        let invocations = AtomicUsize::new(0);
+
+        for key in ["a", "b", "c"] {
+            cache
+                .run(Some(key.to_string()), REQUEST, || {
+                    counting_prover(&invocations, Ok(1))
+                })
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(cache.len(), 2);
+        // "a" was evicted
+        cache
+            .run(Some("a".to_string()), REQUEST, || {
+                counting_prover(&invocations, Ok(1))
+            })
+            .await
+            .unwrap();
+        assert_eq!(invocations.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_key_reused_for_another_request() {
+        let cache = IdempotencyCache::default();
+        let invocations = AtomicUsize::new(0);
+        let key = Some("key".to_string());
+
+        cache
+            .run(key.clone(), REQUEST, || {
+                counting_prover(&invocations, Ok(1))
+            })
+            .await
+            .unwrap();
+        let status = cache
+            .run(key, [1; 32], || counting_prover(&invocations, Ok(2)))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(invocations.load(Ordering::SeqCst), 1);
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct ProgramRequest {
+        #[prost(string, tag = "1")]
+        program: String,
+    }
+
+    #[test]
+    fn test_request_hash() {
+        let program = |program: &str| ProgramRequest {
+            program: program.to_string(),
+        };
+        assert_eq!(request_hash(&program("a")), request_hash(&program("a")));
+        assert_ne!(request_hash(&program("a")), request_hash(&program("b")));
+    }
+}
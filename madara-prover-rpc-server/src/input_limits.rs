@@ -0,0 +1,179 @@
+use std::io::Cursor;
+
+use tonic::Status;
+
+/// Maximum size of a compiled program, by default.
+pub const DEFAULT_MAX_PROGRAM_SIZE: u64 = 64 * 1024 * 1024;
+/// Maximum size of a zipped Cairo PIE, by default.
+pub const DEFAULT_MAX_PIE_SIZE: u64 = 256 * 1024 * 1024;
+/// Maximum total size of the files of a Cairo PIE once extracted, by default.
+pub const DEFAULT_MAX_PIE_UNCOMPRESSED_SIZE: u64 = 1024 * 1024 * 1024;
+/// Maximum size of the memory and trace of a `Prove` request, by default.
+pub const DEFAULT_MAX_MEMORY_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+pub const DEFAULT_MAX_TRACE_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Maximum sizes of the inputs of the requests, in bytes.
+///
+/// The inputs are checked before being parsed: parsing a program or extracting a PIE can
+/// take several times the size of the input in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLimits {
+    pub max_program_size: u64,
+    pub max_pie_size: u64,
+    pub max_pie_uncompressed_size: u64,
+    pub max_memory_size: u64,
+    pub max_trace_size: u64,
+}
+
+impl Default for InputLimits {
+    fn default() -> Self {
+        Self {
+            max_program_size: DEFAULT_MAX_PROGRAM_SIZE,
+            max_pie_size: DEFAULT_MAX_PIE_SIZE,
+            max_pie_uncompressed_size: DEFAULT_MAX_PIE_UNCOMPRESSED_SIZE,
+            max_memory_size: DEFAULT_MAX_MEMORY_SIZE,
+            max_trace_size: DEFAULT_MAX_TRACE_SIZE,
+        }
+    }
+}
+
+fn check_size(field: &str, size: u64, limit: u64) -> Result<(), Status> {
+    if size > limit {
+        return Err(Status::invalid_argument(format!(
+            "{field} is too large: {size} bytes, the limit is {limit} bytes"
+        )));
+    }
+    Ok(())
+}
+
+impl InputLimits {
+    pub fn check_program(&self, program: &[u8]) -> Result<(), Status> {
+        check_size("Program", program.len() as u64, self.max_program_size)
+    }
+
+    pub fn check_memory(&self, memory: &[u8]) -> Result<(), Status> {
+        self.check_memory_size(memory.len() as u64)
+    }
+
+    pub fn check_trace(&self, trace: &[u8]) -> Result<(), Status> {
+        self.check_trace_size(trace.len() as u64)
+    }
+
+    /// Same as `check_memory`, from the size of a memory file before reading it.
+    pub fn check_memory_size(&self, size: u64) -> Result<(), Status> {
+        check_size("Memory", size, self.max_memory_size)
+    }
+
+    pub fn check_trace_size(&self, size: u64) -> Result<(), Status> {
+        check_size("Trace", size, self.max_trace_size)
+    }
+
+    /// Checks the size of a zipped PIE, then the total size of its files as declared in the
+    /// central directory of the archive, without extracting it.
+    pub fn check_pie(&self, pie: &[u8]) -> Result<(), Status> {
+        check_size("PIE", pie.len() as u64, self.max_pie_size)?;
+
+        let uncompressed_size = pie_uncompressed_size(pie)
+            .map_err(|e| Status::invalid_argument(format!("Invalid PIE archive: {e}")))?;
+        check_size(
+            "Uncompressed PIE",
+            uncompressed_size,
+            self.max_pie_uncompressed_size,
+        )
+    }
+}
+
+/// Returns the total uncompressed size of the files of a zip archive, as declared in its
+/// central directory.
+pub fn pie_uncompressed_size(pie: &[u8]) -> zip::result::ZipResult<u64> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(pie))?;
+    let mut total_size: u64 = 0;
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        // Saturating, an overflow is as good as a size over the limit
+        total_size = total_size.saturating_add(file.size());
+    }
+    Ok(total_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use test_cases::get_test_case_file_path;
+    use tonic::Code;
+
+    use super::*;
+
+    fn fibonacci_pie() -> Vec<u8> {
+        std::fs::read(get_test_case_file_path(
+            "bootloader/pies/fibonacci/cairo_pie.zip",
+        ))
+        .unwrap()
+    }
+
+    /// Creates a small archive that expands to `uncompressed_size` bytes.
+    fn zip_bomb(uncompressed_size: usize) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        writer.start_file("memory.bin", options).unwrap();
+        writer.write_all(&vec![0u8; uncompressed_size]).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_program_too_large() {
+        let limits = InputLimits {
+            max_program_size: 10,
+            ..Default::default()
+        };
+
+        assert!(limits.check_program(&[0u8; 10]).is_ok());
+        let status = limits.check_program(&[0u8; 11]).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "Program is too large: 11 bytes, the limit is 10 bytes"
+        );
+    }
+
+    #[test]
+    fn test_valid_pie() {
+        let pie = fibonacci_pie();
+
+        assert!(InputLimits::default().check_pie(&pie).is_ok());
+        assert!(pie_uncompressed_size(&pie).unwrap() > pie.len() as u64);
+    }
+
+    #[test]
+    fn test_zip_bomb() {
+        let pie = zip_bomb(1024 * 1024);
+        let limits = InputLimits {
+            max_pie_uncompressed_size: 1024 * 1024 - 1,
+            ..Default::default()
+        };
+        assert!(pie.len() < 16 * 1024, "{}", pie.len());
+
+        let status = limits.check_pie(&pie).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status
+                .message()
+                .starts_with("Uncompressed PIE is too large"),
+            "{}",
+            status.message()
+        );
+    }
+
+    #[test]
+    fn test_invalid_pie_archive() {
+        let pie = fibonacci_pie();
+        // Cuts the central directory, at the end of the archive
+        let truncated_pie = &pie[..pie.len() - 32];
+
+        let status = InputLimits::default().check_pie(truncated_pie).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().starts_with("Invalid PIE archive"));
+    }
+}
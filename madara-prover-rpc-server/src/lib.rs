@@ -1,37 +1,184 @@
 use std::path::Path;
+use std::sync::Arc;
 
-use tokio::net::UnixListener;
 use tokio_stream::wrappers::UnixListenerStream;
+use tonic::transport::server::TcpIncoming;
 use tonic::transport::Server;
+use tonic_health::ServingStatus;
 
+pub use crate::config::ServerConfig;
 use crate::error::ServerError;
+use crate::health::{
+    check_prover_at_startup, monitor_disk_space, prover_working_dir_root, set_serving_status,
+    DISK_SPACE_CHECK_INTERVAL,
+};
+use crate::listener::{bind_tcp, bind_unix_socket, ServerListener};
+use crate::panic::CatchPanicLayer;
+use crate::services::common::{ProverBackend, StoneProverBackend};
 use crate::services::prover::prover_proto::prover_server::ProverServer;
 use crate::services::prover::ProverService;
 use crate::services::starknet_prover::starknet_prover_proto::starknet_prover_server::StarknetProverServer;
 use crate::services::starknet_prover::StarknetProverService;
+use crate::workspace::{monitor_workspaces, WORKSPACE_SWEEP_INTERVAL};
 
+pub mod artifacts;
+pub mod builtin_usage;
 pub mod cairo;
+pub mod checksum;
+pub mod config;
 pub mod error;
+pub mod estimate;
 pub mod evm_adapter;
+pub mod facts;
+pub mod health;
+pub mod hints;
+pub mod history;
+pub mod idempotency;
+pub mod input_limits;
+pub mod limiter;
+pub mod listener;
+pub mod local_files;
+pub mod logging;
+pub mod panic;
+pub mod parameters;
+pub mod policy;
+pub mod proto_ext;
+pub mod public_input;
 pub mod services;
+pub mod storage;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod workspace;
 
 pub enum BindAddress<'a> {
     Tcp(std::net::SocketAddr),
     UnixSocket(&'a Path),
 }
 
-pub async fn run_grpc_server(bind_address: BindAddress<'_>) -> Result<(), ServerError> {
-    let prover_service = ProverService::default();
-    let starknet_prover_service = StarknetProverService::default();
+pub async fn run_grpc_server(config: ServerConfig) -> Result<(), ServerError> {
+    let listener = match config.bind_address() {
+        BindAddress::Tcp(address) => ServerListener::Tcp(bind_tcp(address).await?),
+        BindAddress::UnixSocket(socket_path) => {
+            ServerListener::UnixSocket(bind_unix_socket(socket_path)?)
+        }
+    };
+    run_grpc_server_with_listener(config, listener).await
+}
 
-    let builder = Server::builder()
-        .add_service(ProverServer::new(prover_service))
-        .add_service(StarknetProverServer::new(starknet_prover_service));
+/// Same as `run_grpc_server`, on a socket bound by the caller. The `bind_address` and
+/// `unix_socket` settings are ignored.
+pub async fn run_grpc_server_with_listener(
+    config: ServerConfig,
+    listener: ServerListener,
+) -> Result<(), ServerError> {
+    let prover_search_path =
+        config.prover_search_path(&std::env::var_os("PATH").unwrap_or_default())?;
+    let prover_binaries =
+        check_prover_at_startup(&prover_search_path, config.require_prover_at_startup)?;
+    if config.prover_bin_dir.is_some() {
+        // The SDK runs the binaries found in PATH
+        std::env::set_var("PATH", &prover_search_path);
+    }
+    let prover_available = prover_binaries.is_some();
 
-    match bind_address {
-        BindAddress::Tcp(address) => builder.serve(address).await?,
-        BindAddress::UnixSocket(socket_path) => {
-            let uds = UnixListener::bind(socket_path)?;
+    let parameter_presets = config.parameter_presets();
+    let prover_backend: Arc<dyn ProverBackend> = Arc::new(StoneProverBackend);
+    let job_history = config.job_history();
+    let workspace_tracker = config.workspace_tracker();
+    let input_limits = config.input_limits();
+    let mut prover_service = ProverService::default()
+        .with_prover_backend(prover_backend.clone())
+        .with_parameter_presets(parameter_presets.clone())
+        .with_job_history(job_history.clone())
+        .with_workspace_tracker(workspace_tracker.clone())
+        .with_input_limits(input_limits)
+        .with_policy(config.policy.clone())
+        .with_prover_binaries(prover_binaries.unwrap_or_default());
+    if let Some(file_roots) = config.prove_from_files_roots()? {
+        prover_service = prover_service.with_prove_from_files(file_roots);
+    }
+    let mut starknet_prover_service = StarknetProverService::new()?
+        .with_prover_backend(prover_backend)
+        .with_parameter_presets(parameter_presets)
+        .with_job_history(job_history)
+        .with_workspace_tracker(workspace_tracker.clone())
+        .with_input_limits(input_limits)
+        .with_policy(config.policy.clone());
+    if let Some(proof_limiter) = config.proof_limiter() {
+        prover_service = prover_service.with_proof_limiter(proof_limiter.clone());
+        starknet_prover_service = starknet_prover_service.with_proof_limiter(proof_limiter);
+    }
+    if let Some(proof_storage) = config.proof_storage() {
+        prover_service = prover_service.with_proof_storage(proof_storage.clone());
+        starknet_prover_service = starknet_prover_service.with_proof_storage(proof_storage);
+    }
+
+    if config.workspace_ttl().is_some() {
+        tokio::spawn(monitor_workspaces(
+            workspace_tracker,
+            prover_working_dir_root(),
+            WORKSPACE_SWEEP_INTERVAL,
+        ));
+    }
+
+    let mut prover_server = ProverServer::new(prover_service);
+    let mut starknet_prover_server = StarknetProverServer::new(starknet_prover_service);
+    if let Some(max_message_size) = config.max_message_size {
+        prover_server = prover_server
+            .max_decoding_message_size(max_message_size)
+            .max_encoding_message_size(max_message_size);
+        starknet_prover_server = starknet_prover_server
+            .max_decoding_message_size(max_message_size)
+            .max_encoding_message_size(max_message_size);
+    }
+
+    let mut server = Server::builder()
+        .http2_keepalive_interval(config.http2_keepalive_interval())
+        .http2_keepalive_timeout(config.http2_keepalive_timeout());
+    if let Some(tls_config) = config.tls_config()? {
+        server = server.tls_config(tls_config)?;
+    }
+    if let Some(timeout) = config.request_timeout() {
+        server = server.timeout(timeout);
+    }
+    if let Some(concurrency_limit) = config.concurrency_limit {
+        server = server.concurrency_limit_per_connection(concurrency_limit);
+    }
+    #[cfg(feature = "otel")]
+    let server = server.layer(telemetry::TraceContextLayer);
+
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_serving::<ProverServer<ProverService>>()
+        .await;
+    health_reporter
+        .set_serving::<StarknetProverServer<StarknetProverService>>()
+        .await;
+    if !prover_available {
+        // Not monitoring the disk space, it would report the services as serving again
+        set_serving_status(&mut health_reporter, ServingStatus::NotServing).await;
+    } else if let Some(min_free_disk_space) = config.min_free_disk_space {
+        tokio::spawn(monitor_disk_space(
+            health_reporter,
+            prover_working_dir_root(),
+            min_free_disk_space,
+            DISK_SPACE_CHECK_INTERVAL,
+        ));
+    }
+
+    let builder = server
+        .layer(CatchPanicLayer)
+        .add_service(health_service)
+        .add_service(prover_server)
+        .add_service(starknet_prover_server);
+
+    match listener {
+        ServerListener::Tcp(listener) => {
+            let incoming = TcpIncoming::from_listener(listener, false, config.tcp_keepalive())
+                .map_err(|e| ServerError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+            builder.serve_with_incoming(incoming).await?
+        }
+        ServerListener::UnixSocket(uds) => {
             let uds_stream = UnixListenerStream::new(uds);
             builder.serve_with_incoming(uds_stream).await?
         }
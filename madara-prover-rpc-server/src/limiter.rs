@@ -0,0 +1,456 @@
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+
+/// Metadata key of the position the request would have in the queue.
+pub const QUEUE_POSITION_HEADER: &str = "x-queue-position";
+/// Metadata key of the estimated time before the request could start.
+pub const ESTIMATED_WAIT_HEADER: &str = "x-estimated-wait-seconds";
+/// Metadata key of the class of the rejected proof, `small` or `large`.
+pub const PROOF_CLASS_HEADER: &str = "x-proof-class";
+
+/// Maximum number of steps of a small proof, by default.
+pub const DEFAULT_SMALL_PROOF_MAX_N_STEPS: u32 = 1 << 16;
+
+/// Duration of a proof used for the estimations until a proof completes.
+pub const DEFAULT_PROOF_DURATION: Duration = Duration::from_secs(60);
+
+/// Weight of the latest proof in the moving average of the proof durations.
+const MOVING_AVERAGE_WEIGHT: f64 = 0.2;
+
+/// Priority class of a proof, from its estimated work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofClass {
+    Small,
+    Large,
+}
+
+impl ProofClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProofClass::Small => "small",
+            ProofClass::Large => "large",
+        }
+    }
+}
+
+/// Limits the number of proofs running at the same time.
+///
+/// Proofs over the limit wait in a bounded queue. Once the queue is full, requests are
+/// rejected with `RESOURCE_EXHAUSTED` along with their queue position and the estimated
+/// wait, so that clients (or a load balancer) can retry elsewhere.
+///
+/// Some of the slots can be reserved to small proofs, so that they do not wait behind
+/// large ones. Small proofs take a reserved slot first, then any free slot. Running proofs
+/// are never preempted.
+#[derive(Debug)]
+pub struct ProofLimiter {
+    /// Slots shared by all the proofs.
+    permits: Semaphore,
+    /// Slots reserved to small proofs.
+    reserved_permits: Semaphore,
+    small_proof_max_n_steps: u32,
+    max_concurrent_proofs: usize,
+    reserved_slots: usize,
+    max_queued_proofs: usize,
+    queued_proofs: AtomicUsize,
+    /// Exponential moving average of the proof durations, in seconds.
+    average_duration: Mutex<Option<f64>>,
+}
+
+/// Allows a proof to run. The slot is released when the permit is dropped.
+#[derive(Debug)]
+pub struct ProofPermit<'a> {
+    limiter: &'a ProofLimiter,
+    class: ProofClass,
+    queue_duration: Duration,
+    started_at: Instant,
+    _permit: SemaphorePermit<'a>,
+}
+
+impl ProofPermit<'_> {
+    pub fn class(&self) -> ProofClass {
+        self.class
+    }
+
+    /// Time spent waiting for the slot.
+    pub fn queue_duration(&self) -> Duration {
+        self.queue_duration
+    }
+
+    /// Checks that a proof admitted as small does not exceed the number of steps of small
+    /// proofs, once its execution is known.
+    pub fn check_n_steps(&self, n_steps: u32) -> Result<(), Status> {
+        self.limiter.check_n_steps(self.class, n_steps)
+    }
+
+    /// Records the duration of the proof in the estimations of the limiter. Only successful
+    /// proofs are recorded: a proof failing early would make the estimated waits too short.
+    pub fn record_success(&self) {
+        self.limiter.record_duration(self.started_at.elapsed());
+    }
+}
+
+/// Removes a request from the queue, even if it is cancelled while waiting.
+struct QueueGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueueGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ProofLimiter {
+    pub fn new(max_concurrent_proofs: NonZeroUsize, max_queued_proofs: usize) -> Self {
+        let max_concurrent_proofs = max_concurrent_proofs.get();
+        Self {
+            permits: Semaphore::new(max_concurrent_proofs),
+            reserved_permits: Semaphore::new(0),
+            small_proof_max_n_steps: DEFAULT_SMALL_PROOF_MAX_N_STEPS,
+            max_concurrent_proofs,
+            reserved_slots: 0,
+            max_queued_proofs,
+            queued_proofs: AtomicUsize::new(0),
+            average_duration: Mutex::new(None),
+        }
+    }
+
+    /// Reserves `reserved_slots` of the slots to small proofs, the proofs of at most
+    /// `small_proof_max_n_steps` steps. At least one slot is left to large proofs.
+    pub fn with_reserved_slots(self, reserved_slots: usize, small_proof_max_n_steps: u32) -> Self {
+        let reserved_slots = reserved_slots.min(self.max_concurrent_proofs.saturating_sub(1));
+        Self {
+            permits: Semaphore::new(self.max_concurrent_proofs - reserved_slots),
+            reserved_permits: Semaphore::new(reserved_slots),
+            small_proof_max_n_steps,
+            reserved_slots,
+            ..self
+        }
+    }
+
+    pub fn max_concurrent_proofs(&self) -> usize {
+        self.max_concurrent_proofs
+    }
+
+    pub fn max_queued_proofs(&self) -> usize {
+        self.max_queued_proofs
+    }
+
+    /// Number of slots reserved to small proofs, after leaving one slot to large proofs.
+    pub fn reserved_slots(&self) -> usize {
+        self.reserved_slots
+    }
+
+    pub fn small_proof_max_n_steps(&self) -> u32 {
+        self.small_proof_max_n_steps
+    }
+
+    /// Returns the class of a proof of `n_steps` steps.
+    pub fn classify(&self, n_steps: u32) -> ProofClass {
+        if n_steps <= self.small_proof_max_n_steps {
+            ProofClass::Small
+        } else {
+            ProofClass::Large
+        }
+    }
+
+    /// Checks that a proof of the `class` class does not exceed the number of steps of
+    /// small proofs if it is small.
+    pub fn check_n_steps(&self, class: ProofClass, n_steps: u32) -> Result<(), Status> {
+        let max_n_steps = self.small_proof_max_n_steps;
+        if class == ProofClass::Small && n_steps > max_n_steps {
+            return Err(Status::invalid_argument(format!(
+                "The execution has {n_steps} steps, over the limit of small proofs \
+                (small_proof_max_n_steps: {max_n_steps})"
+            )));
+        }
+        Ok(())
+    }
+
+    fn try_acquire(&self, class: ProofClass) -> Option<SemaphorePermit<'_>> {
+        match class {
+            ProofClass::Small => self
+                .reserved_permits
+                .try_acquire()
+                .or_else(|_| self.permits.try_acquire())
+                .ok(),
+            ProofClass::Large => self.permits.try_acquire().ok(),
+        }
+    }
+
+    /// Waits for a proof slot, or fails immediately if the queue is full.
+    pub async fn acquire(&self, class: ProofClass) -> Result<ProofPermit<'_>, Status> {
+        if let Some(permit) = self.try_acquire(class) {
+            return Ok(self.permit(permit, class, Duration::ZERO));
+        }
+
+        let queued_proofs = self.queued_proofs.fetch_add(1, Ordering::SeqCst);
+        let _queue_guard = QueueGuard(&self.queued_proofs);
+        if queued_proofs >= self.max_queued_proofs {
+            return Err(self.queue_full_status(queued_proofs + 1, class));
+        }
+
+        let queued_at = Instant::now();
+        let permit = match class {
+            ProofClass::Small => tokio::select! {
+                biased;
+                permit = self.reserved_permits.acquire() => permit,
+                permit = self.permits.acquire() => permit,
+            },
+            ProofClass::Large => self.permits.acquire().await,
+        }
+        .map_err(|_| Status::unavailable("The server is shutting down"))?;
+        Ok(self.permit(permit, class, queued_at.elapsed()))
+    }
+
+    fn permit<'a>(
+        &'a self,
+        permit: SemaphorePermit<'a>,
+        class: ProofClass,
+        queue_duration: Duration,
+    ) -> ProofPermit<'a> {
+        ProofPermit {
+            limiter: self,
+            class,
+            queue_duration,
+            started_at: Instant::now(),
+            _permit: permit,
+        }
+    }
+
+    fn record_duration(&self, duration: Duration) {
+        let mut average_duration = self.average_duration.lock().unwrap();
+        let duration = duration.as_secs_f64();
+        *average_duration = Some(match *average_duration {
+            Some(average) => average + MOVING_AVERAGE_WEIGHT * (duration - average),
+            None => duration,
+        });
+    }
+
+    /// Moving average of the recent proof durations.
+    pub fn average_proof_duration(&self) -> Duration {
+        self.average_duration
+            .lock()
+            .unwrap()
+            .map(Duration::from_secs_f64)
+            .unwrap_or(DEFAULT_PROOF_DURATION)
+    }
+
+    /// Estimates the time before a request at `queue_position` can start.
+    pub fn estimated_wait(&self, queue_position: usize) -> Duration {
+        let rounds = queue_position.div_ceil(self.max_concurrent_proofs);
+        self.average_proof_duration() * rounds as u32
+    }
+
+    fn queue_full_status(&self, queue_position: usize, class: ProofClass) -> Status {
+        let estimated_wait = self.estimated_wait(queue_position);
+        let message = format!(
+            "Too many proofs in progress (queue position: {queue_position}, estimated wait: {}s)",
+            estimated_wait.as_secs()
+        );
+
+        let mut status = Status::with_error_details(
+            Code::ResourceExhausted,
+            message,
+            ErrorDetails::with_retry_info(Some(estimated_wait)),
+        );
+        let metadata = status.metadata_mut();
+        metadata.insert(
+            QUEUE_POSITION_HEADER,
+            MetadataValue::from(queue_position as u64),
+        );
+        metadata.insert(
+            ESTIMATED_WAIT_HEADER,
+            MetadataValue::from(estimated_wait.as_secs()),
+        );
+        metadata.insert(
+            PROOF_CLASS_HEADER,
+            MetadataValue::from_static(class.as_str()),
+        );
+        status
+    }
+}
+
+/// Waits for a proof slot if the number of concurrent proofs is limited.
+pub async fn acquire_proof_permit(
+    limiter: Option<&ProofLimiter>,
+    class: ProofClass,
+) -> Result<Option<ProofPermit<'_>>, Status> {
+    match limiter {
+        Some(limiter) => limiter.acquire(class).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Returns the class of a proof of `n_steps` steps, `Large` if the number of concurrent
+/// proofs is not limited.
+pub fn classify_proof(limiter: Option<&ProofLimiter>, n_steps: u32) -> ProofClass {
+    limiter.map_or(ProofClass::Large, |limiter| limiter.classify(n_steps))
+}
+
+/// Checks the number of steps of a proof of the `class` class, before acquiring its slot.
+/// Always succeeds if the number of concurrent proofs is not limited.
+pub fn check_proof_n_steps(
+    limiter: Option<&ProofLimiter>,
+    class: ProofClass,
+    n_steps: u32,
+) -> Result<(), Status> {
+    limiter.map_or(Ok(()), |limiter| limiter.check_n_steps(class, n_steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slots(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    fn metadata_value(status: &Status, key: &str) -> u64 {
+        status
+            .metadata()
+            .get(key)
+            .expect("missing metadata")
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reject_over_limit() {
+        let limiter = ProofLimiter::new(slots(1), 0);
+
+        let _permit = limiter.acquire(ProofClass::Large).await.unwrap();
+        let status = limiter.acquire(ProofClass::Large).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(metadata_value(&status, QUEUE_POSITION_HEADER), 1);
+        assert_eq!(
+            metadata_value(&status, ESTIMATED_WAIT_HEADER),
+            DEFAULT_PROOF_DURATION.as_secs()
+        );
+        let details = status.get_error_details();
+        let retry_info = details.retry_info().unwrap();
+        assert_eq!(retry_info.retry_delay, Some(DEFAULT_PROOF_DURATION));
+    }
+
+    #[tokio::test]
+    async fn test_slot_released() {
+        let limiter = ProofLimiter::new(slots(1), 0);
+
+        let permit = limiter.acquire(ProofClass::Large).await.unwrap();
+        drop(permit);
+
+        limiter.acquire(ProofClass::Large).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queue() {
+        let limiter = &ProofLimiter::new(slots(1), 1);
+
+        let permit = limiter.acquire(ProofClass::Large).await.unwrap();
+        let (queued, rejected) = tokio::join!(limiter.acquire(ProofClass::Large), async move {
+            let status = limiter.acquire(ProofClass::Large).await.unwrap_err();
+            drop(permit);
+            status
+        });
+
+        assert!(queued.is_ok());
+        assert_eq!(metadata_value(&rejected, QUEUE_POSITION_HEADER), 2);
+        assert_eq!(limiter.queued_proofs.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reserved_slot_for_small_proofs() {
+        let limiter = &ProofLimiter::new(slots(2), 1).with_reserved_slots(1, 1000);
+        assert_eq!(limiter.classify(1000), ProofClass::Small);
+        assert_eq!(limiter.classify(1001), ProofClass::Large);
+
+        let large_permit = limiter.acquire(ProofClass::Large).await.unwrap();
+        // The other slot is reserved: a second large proof waits, a small one runs
+        let small_permit = limiter.acquire(ProofClass::Small).await.unwrap();
+        assert_eq!(small_permit.class(), ProofClass::Small);
+        assert_eq!(small_permit.queue_duration(), Duration::ZERO);
+
+        let (queued, rejected) = tokio::join!(limiter.acquire(ProofClass::Large), async move {
+            let status = limiter.acquire(ProofClass::Small).await.unwrap_err();
+            drop(large_permit);
+            status
+        });
+        assert_eq!(queued.unwrap().class(), ProofClass::Large);
+        assert_eq!(
+            rejected.metadata().get(PROOF_CLASS_HEADER).unwrap(),
+            "small"
+        );
+        drop(small_permit);
+    }
+
+    #[tokio::test]
+    async fn test_small_proof_takes_any_slot() {
+        let limiter = ProofLimiter::new(slots(2), 0).with_reserved_slots(1, 1000);
+
+        let _reserved_permit = limiter.acquire(ProofClass::Small).await.unwrap();
+        let _shared_permit = limiter.acquire(ProofClass::Small).await.unwrap();
+        let status = limiter.acquire(ProofClass::Large).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert_eq!(status.metadata().get(PROOF_CLASS_HEADER).unwrap(), "large");
+    }
+
+    #[tokio::test]
+    async fn test_large_proofs_keep_a_slot() {
+        // All the slots cannot be reserved
+        let limiter = ProofLimiter::new(slots(1), 0).with_reserved_slots(1, 1000);
+        assert_eq!(limiter.reserved_slots(), 0);
+
+        let permit = limiter.acquire(ProofClass::Large).await.unwrap();
+
+        assert!(permit.check_n_steps(1_000_000).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_small_proof_over_the_limit() {
+        let limiter = ProofLimiter::new(slots(2), 0).with_reserved_slots(1, 1000);
+
+        let permit = limiter.acquire(ProofClass::Small).await.unwrap();
+
+        assert!(permit.check_n_steps(1000).is_ok());
+        let status = permit.check_n_steps(1001).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("small_proof_max_n_steps: 1000"));
+    }
+
+    #[tokio::test]
+    async fn test_only_successful_proofs_are_recorded() {
+        let limiter = ProofLimiter::new(slots(1), 0);
+
+        let failed_permit = limiter.acquire(ProofClass::Large).await.unwrap();
+        drop(failed_permit);
+        assert_eq!(limiter.average_proof_duration(), DEFAULT_PROOF_DURATION);
+
+        let permit = limiter.acquire(ProofClass::Large).await.unwrap();
+        permit.record_success();
+        assert!(limiter.average_proof_duration() < DEFAULT_PROOF_DURATION);
+    }
+
+    #[test]
+    fn test_estimated_wait() {
+        let limiter = ProofLimiter::new(slots(2), 0);
+        assert_eq!(limiter.estimated_wait(1), DEFAULT_PROOF_DURATION);
+
+        limiter.record_duration(Duration::from_secs(10));
+        assert_eq!(limiter.average_proof_duration(), Duration::from_secs(10));
+        limiter.record_duration(Duration::from_secs(20));
+        assert_eq!(limiter.average_proof_duration(), Duration::from_secs(12));
+
+        assert_eq!(limiter.estimated_wait(2), Duration::from_secs(12));
+        assert_eq!(limiter.estimated_wait(3), Duration::from_secs(24));
+    }
+}
@@ -0,0 +1,116 @@
+//! Binding of the server sockets. The bind errors are converted to specific `ServerError`
+//! variants, since the I/O error alone does not tell the operator how to fix them.
+
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::Path;
+
+use tokio::net::{TcpListener, UnixListener};
+
+use crate::error::ServerError;
+
+/// A bound socket, on which the server accepts connections.
+pub enum ServerListener {
+    Tcp(TcpListener),
+    UnixSocket(UnixListener),
+}
+
+impl ServerListener {
+    /// Wraps a TCP listener bound by the caller, ex: on port 0 to get a free port from the OS.
+    pub fn from_std_tcp(listener: std::net::TcpListener) -> Result<Self, ServerError> {
+        listener.set_nonblocking(true)?;
+        Ok(Self::Tcp(TcpListener::from_std(listener)?))
+    }
+}
+
+/// Listens on a TCP address.
+pub async fn bind_tcp(address: SocketAddr) -> Result<TcpListener, ServerError> {
+    TcpListener::bind(address)
+        .await
+        .map_err(|error| match error.kind() {
+            ErrorKind::AddrInUse => ServerError::AddressInUse(address),
+            ErrorKind::PermissionDenied => ServerError::PermissionDenied {
+                target: address.to_string(),
+            },
+            _ => ServerError::Io(error),
+        })
+}
+
+/// Listens on a Unix socket.
+pub fn bind_unix_socket(path: &Path) -> Result<UnixListener, ServerError> {
+    UnixListener::bind(path).map_err(|error| {
+        let reason = match error.kind() {
+            ErrorKind::PermissionDenied => {
+                return ServerError::PermissionDenied {
+                    target: path.display().to_string(),
+                }
+            }
+            ErrorKind::AddrInUse => {
+                "the file already exists, remove it if no other server is using it".to_string()
+            }
+            ErrorKind::NotFound => "the parent directory does not exist".to_string(),
+            _ => error.to_string(),
+        };
+        ServerError::SocketPathUnavailable {
+            path: path.to_path_buf(),
+            reason,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_from_std_tcp() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let listener = ServerListener::from_std_tcp(listener).unwrap();
+
+        assert!(matches!(listener, ServerListener::Tcp(l) if l.local_addr().unwrap() == address));
+    }
+
+    #[tokio::test]
+    async fn test_tcp_address_in_use() {
+        let listener = bind_tcp("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let error = bind_tcp(address).await.unwrap_err();
+
+        assert!(matches!(error, ServerError::AddressInUse(addr) if addr == address));
+        assert_eq!(error.exit_code(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("prover.sock");
+        let _listener = bind_unix_socket(&socket_path).unwrap();
+
+        let error = bind_unix_socket(&socket_path).unwrap_err();
+
+        assert!(matches!(
+            error,
+            ServerError::SocketPathUnavailable { ref path, .. } if *path == socket_path
+        ));
+        assert_eq!(error.exit_code(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket_missing_parent_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("missing").join("prover.sock");
+
+        let error = bind_unix_socket(&socket_path).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "could not bind to Unix socket {}: the parent directory does not exist",
+                socket_path.display()
+            )
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Access to execution artifacts already on the filesystem of the server, for the
+//! `ProveFromFiles` RPC of co-located deployments.
+//!
+//! Requests can only read files within the roots allowed by the configuration. Paths
+//! outside of them are rejected with `PERMISSION_DENIED` before touching the filesystem,
+//! missing files with `NOT_FOUND`.
+
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use tonic::Status;
+
+use crate::error::ServerError;
+
+/// Directories from which `ProveFromFiles` requests can read artifacts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileRoots {
+    roots: Vec<PathBuf>,
+    /// The roots with their symlinks resolved, to check the files once resolved.
+    canonical_roots: Vec<PathBuf>,
+}
+
+fn outside_roots(field: &str, path: &Path) -> Status {
+    Status::permission_denied(format!(
+        "{field} {} is outside of the allowed roots of the server (prove_from_files_roots)",
+        path.display()
+    ))
+}
+
+impl FileRoots {
+    /// Checks that the roots are absolute paths of existing directories, at startup: a
+    /// misconfigured root would otherwise reject all the requests.
+    pub fn new(roots: Vec<PathBuf>) -> Result<Self, ServerError> {
+        let invalid_root = |root: &Path, reason: String| ServerError::InvalidFileRoot {
+            path: root.to_path_buf(),
+            reason,
+        };
+        let canonical_roots = roots
+            .iter()
+            .map(|root| {
+                if !root.is_absolute() {
+                    return Err(invalid_root(root, "not an absolute path".to_string()));
+                }
+                let canonical_root = root
+                    .canonicalize()
+                    .map_err(|e| invalid_root(root, e.to_string()))?;
+                if !canonical_root.is_dir() {
+                    return Err(invalid_root(root, "not a directory".to_string()));
+                }
+                Ok(canonical_root)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            roots,
+            canonical_roots,
+        })
+    }
+
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Resolves the path of a file of a request, `field` naming it in the errors.
+    ///
+    /// The path must be absolute and within one of the roots, both as written and once its
+    /// symlinks are resolved. Returns the canonical path of the file.
+    pub fn resolve(&self, field: &str, path: &str) -> Result<PathBuf, Status> {
+        let path = Path::new(path);
+        if !path.is_absolute() {
+            return Err(Status::invalid_argument(format!(
+                "{field} must be an absolute path, got '{}'",
+                path.display()
+            )));
+        }
+        if path
+            .components()
+            .any(|component| component == Component::ParentDir)
+        {
+            return Err(Status::invalid_argument(format!(
+                "{field} must not contain '..', got {}",
+                path.display()
+            )));
+        }
+        if !self.roots.iter().any(|root| path.starts_with(root)) {
+            return Err(outside_roots(field, path));
+        }
+
+        let canonical_path = path.canonicalize().map_err(|e| match e.kind() {
+            ErrorKind::NotFound => {
+                Status::not_found(format!("{field} {} does not exist", path.display()))
+            }
+            _ => Status::failed_precondition(format!(
+                "Could not access {field} {}: {e}",
+                path.display()
+            )),
+        })?;
+        // A symlink within a root can point outside of it
+        let within_roots = self
+            .canonical_roots
+            .iter()
+            .any(|root| canonical_path.starts_with(root));
+        if !within_roots {
+            return Err(outside_roots(field, path));
+        }
+        if !canonical_path.is_file() {
+            return Err(Status::invalid_argument(format!(
+                "{field} {} is not a file",
+                path.display()
+            )));
+        }
+
+        Ok(canonical_path)
+    }
+}
+
+/// Returns the size of a file resolved by `FileRoots::resolve`, in bytes.
+pub fn file_size(field: &str, path: &Path) -> Result<u64, Status> {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(|e| Status::failed_precondition(format!("Could not access {field}: {e}")))
+}
+
+/// Reads a file resolved by `FileRoots::resolve`. Only for the small files of a request:
+/// the prover reads the memory and trace in place.
+pub async fn read_file(field: &str, path: &Path) -> Result<Vec<u8>, Status> {
+    tokio::fs::read(path)
+        .await
+        .map_err(|e| Status::failed_precondition(format!("Could not read {field}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use test_cases::get_test_case_file_path;
+    use tonic::Code;
+
+    use super::*;
+
+    fn test_case_roots() -> FileRoots {
+        FileRoots::new(vec![get_test_case_file_path("fibonacci")]).unwrap()
+    }
+
+    fn path_str(path: &Path) -> &str {
+        path.to_str().unwrap()
+    }
+
+    #[test]
+    fn test_resolve_file_within_roots() {
+        let trace_file = get_test_case_file_path("fibonacci/fibonacci_trace.bin");
+
+        let path = test_case_roots()
+            .resolve("trace_path", path_str(&trace_file))
+            .unwrap();
+
+        assert_eq!(path, trace_file.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn test_file_outside_roots() {
+        let proof_file = get_test_case_file_path("bootloader/programs/fibonacci/program.json");
+
+        let status = test_case_roots()
+            .resolve("trace_path", path_str(&proof_file))
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains("prove_from_files_roots"));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let missing_file = get_test_case_file_path("fibonacci/missing_trace.bin");
+
+        let status = test_case_roots()
+            .resolve("trace_path", path_str(&missing_file))
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(
+            status.message(),
+            format!("trace_path {} does not exist", missing_file.display())
+        );
+    }
+
+    #[test]
+    fn test_missing_file_outside_roots() {
+        // Paths outside of the roots are rejected without revealing whether they exist
+        let status = test_case_roots()
+            .resolve("trace_path", "/nonexistent/trace.bin")
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        let roots = test_case_roots();
+        let escaping_path = get_test_case_file_path("fibonacci/../bootloader/bootloader.json");
+
+        for path in ["fibonacci_trace.bin", path_str(&escaping_path)] {
+            let status = roots.resolve("trace_path", path).unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_symlink_outside_roots() {
+        let root = tempfile::tempdir().unwrap();
+        let link = root.path().join("trace.bin");
+        std::os::unix::fs::symlink(
+            get_test_case_file_path("fibonacci/fibonacci_trace.bin"),
+            &link,
+        )
+        .unwrap();
+
+        let status = FileRoots::new(vec![root.path().to_path_buf()])
+            .unwrap()
+            .resolve("trace_path", path_str(&link))
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+
+    #[test]
+    fn test_invalid_roots() {
+        let missing_root = get_test_case_file_path("missing");
+        let file_root = get_test_case_file_path("fibonacci/fibonacci_trace.bin");
+
+        for root in [PathBuf::from("artifacts"), missing_root, file_root] {
+            let result = FileRoots::new(vec![root.clone()]);
+            assert!(
+                matches!(&result, Err(ServerError::InvalidFileRoot { path, .. }) if *path == root),
+                "{}",
+                root.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_symlinked_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("artifacts");
+        std::os::unix::fs::symlink(get_test_case_file_path("fibonacci"), &root).unwrap();
+        let trace_file = root.join("fibonacci_trace.bin");
+
+        let path = FileRoots::new(vec![root])
+            .unwrap()
+            .resolve("trace_path", path_str(&trace_file))
+            .unwrap();
+
+        assert_eq!(path, trace_file.canonicalize().unwrap());
+    }
+}
@@ -0,0 +1,27 @@
+//! Log events of the server, printed on stderr. Built with the `otel` feature, they are also
+//! exported to the collector along with the request traces, see `telemetry`.
+
+use tracing::Subscriber;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// Prints the events of level INFO and above on stderr.
+pub fn stderr_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(LevelFilter::INFO)
+}
+
+/// Installs `stderr_layer` as the only consumer of the events, when the traces are not
+/// exported. Does nothing if a subscriber is already installed.
+pub fn init_logging() {
+    let _ = tracing_subscriber::registry()
+        .with(stderr_layer())
+        .try_init();
+}
@@ -1,10 +1,443 @@
-use madara_prover_rpc_server::{run_grpc_server, BindAddress};
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+use madara_prover_rpc_server::config::parse_parameter_presets;
+use madara_prover_rpc_server::parameters::ParameterPreset;
+#[cfg(feature = "otel")]
+use madara_prover_rpc_server::telemetry;
+use madara_prover_rpc_server::{logging, run_grpc_server, ServerConfig};
+
+/// Prover RPC server.
+///
+/// Command-line flags take precedence over the `MADARA_PROVER_*` environment variables,
+/// which take precedence over the configuration file.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// TOML configuration file, see `config.example.toml`.
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// TCP address to listen on.
+    #[arg(long)]
+    bind_address: Option<SocketAddr>,
+
+    /// Listen on this Unix socket instead of TCP.
+    #[arg(long)]
+    unix_socket: Option<PathBuf>,
+
+    /// PEM certificate chain of the server. Serves TLS with --tls-key.
+    #[arg(long)]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the server.
+    #[arg(long)]
+    tls_key: Option<PathBuf>,
+
+    /// PEM certificates of the CAs signing the client certificates. Requires clients
+    /// to authenticate with a certificate.
+    #[arg(long)]
+    tls_client_ca: Option<PathBuf>,
+
+    /// Directory of the prover binaries, searched before PATH.
+    #[arg(long)]
+    prover_bin_dir: Option<PathBuf>,
+
+    /// Directory where the generated proofs are archived.
+    #[arg(long)]
+    proof_storage_dir: Option<PathBuf>,
+
+    /// Maximum size of a request or response message, in bytes.
+    #[arg(long)]
+    max_message_size: Option<usize>,
+
+    /// Maximum duration of a request, in seconds.
+    #[arg(long)]
+    request_timeout_secs: Option<u64>,
+
+    /// Maximum number of concurrent requests on each connection.
+    #[arg(long)]
+    concurrency_limit: Option<usize>,
+
+    /// Minimum free space in the temporary directory, in bytes.
+    #[arg(long)]
+    min_free_disk_space: Option<u64>,
+
+    /// Maximum number of proofs running at the same time.
+    #[arg(long)]
+    max_concurrent_proofs: Option<NonZeroUsize>,
+
+    /// Maximum number of proofs waiting for a slot.
+    #[arg(long)]
+    max_queued_proofs: Option<usize>,
+
+    /// Number of proof slots reserved to small proofs.
+    #[arg(long)]
+    reserved_small_proofs: Option<usize>,
+
+    /// Maximum number of steps of a small proof.
+    #[arg(long)]
+    small_proof_max_n_steps: Option<u32>,
+
+    /// Interval between HTTP/2 keepalive pings, in seconds. 0 disables the pings.
+    #[arg(long)]
+    http2_keepalive_interval_secs: Option<u64>,
+
+    /// Timeout of the HTTP/2 keepalive pings, in seconds.
+    #[arg(long)]
+    http2_keepalive_timeout_secs: Option<u64>,
+
+    /// Interval between TCP keepalive probes, in seconds. 0 disables TCP keepalive.
+    #[arg(long)]
+    tcp_keepalive_secs: Option<u64>,
+
+    /// Number of completed jobs kept in memory. 0 disables the history.
+    #[arg(long)]
+    job_history_size: Option<usize>,
+
+    /// Refuse to start if the prover binaries are not in PATH.
+    #[arg(long)]
+    require_prover_at_startup: bool,
+
+    /// Age after which orphaned prover working directories are removed, in seconds.
+    /// 0 disables the removal.
+    #[arg(long)]
+    workspace_ttl_secs: Option<u64>,
+
+    /// Maximum size of a program, in bytes.
+    #[arg(long)]
+    max_program_size: Option<u64>,
+
+    /// Maximum size of a zipped PIE, in bytes.
+    #[arg(long)]
+    max_pie_size: Option<u64>,
+
+    /// Maximum size of the files of a PIE once extracted, in bytes.
+    #[arg(long)]
+    max_pie_uncompressed_size: Option<u64>,
+
+    /// Maximum size of the memory of a Prove request, in bytes.
+    #[arg(long)]
+    max_memory_size: Option<u64>,
+
+    /// Maximum size of the trace of a Prove request, in bytes.
+    #[arg(long)]
+    max_trace_size: Option<u64>,
+
+    /// OTLP endpoint receiving the request traces. Requires the `otel` feature.
+    #[arg(long)]
+    otlp_endpoint: Option<String>,
+
+    /// Prover parameter presets, as a JSON object keyed by layout.
+    #[arg(long, value_parser = parse_parameter_presets)]
+    parameter_presets: Option<HashMap<String, ParameterPreset>>,
+
+    /// Enable the ProveFromFiles RPC.
+    #[arg(long)]
+    prove_from_files: bool,
+
+    /// Directories from which ProveFromFiles can read, separated by ':'.
+    #[arg(long, value_delimiter = ':')]
+    prove_from_files_roots: Option<Vec<PathBuf>>,
+
+    /// Layouts that can be proven, separated by commas.
+    #[arg(long, value_delimiter = ',')]
+    policy_allowed_layouts: Option<Vec<String>>,
+
+    /// Maximum number of steps of a proven execution.
+    #[arg(long)]
+    policy_max_n_steps: Option<u32>,
+
+    /// Allow Starknet proofs split for L1 verification (true or false).
+    #[arg(long)]
+    policy_allow_split_proof: Option<bool>,
+
+    /// Allow requests to provide their own prover parameters (true or false).
+    #[arg(long)]
+    policy_allow_user_parameters: Option<bool>,
+
+    /// Allow requests to run in the slots reserved to small proofs (true or false).
+    #[arg(long)]
+    policy_allow_small_proof: Option<bool>,
+}
+
+impl Args {
+    fn apply(self, mut config: ServerConfig) -> ServerConfig {
+        if let Some(bind_address) = self.bind_address {
+            config.listen_on_tcp(bind_address);
+        }
+        config.unix_socket = self.unix_socket.or(config.unix_socket);
+        config.tls_cert = self.tls_cert.or(config.tls_cert);
+        config.tls_key = self.tls_key.or(config.tls_key);
+        config.tls_client_ca = self.tls_client_ca.or(config.tls_client_ca);
+        config.prover_bin_dir = self.prover_bin_dir.or(config.prover_bin_dir);
+        config.proof_storage_dir = self.proof_storage_dir.or(config.proof_storage_dir);
+        config.max_message_size = self.max_message_size.or(config.max_message_size);
+        config.request_timeout_secs = self.request_timeout_secs.or(config.request_timeout_secs);
+        config.concurrency_limit = self.concurrency_limit.or(config.concurrency_limit);
+        config.min_free_disk_space = self.min_free_disk_space.or(config.min_free_disk_space);
+        config.max_concurrent_proofs = self.max_concurrent_proofs.or(config.max_concurrent_proofs);
+        if let Some(max_queued_proofs) = self.max_queued_proofs {
+            config.max_queued_proofs = max_queued_proofs;
+        }
+        if let Some(reserved_small_proofs) = self.reserved_small_proofs {
+            config.reserved_small_proofs = reserved_small_proofs;
+        }
+        if let Some(small_proof_max_n_steps) = self.small_proof_max_n_steps {
+            config.small_proof_max_n_steps = small_proof_max_n_steps;
+        }
+        if let Some(interval) = self.http2_keepalive_interval_secs {
+            config.http2_keepalive_interval_secs = interval;
+        }
+        if let Some(timeout) = self.http2_keepalive_timeout_secs {
+            config.http2_keepalive_timeout_secs = timeout;
+        }
+        if let Some(interval) = self.tcp_keepalive_secs {
+            config.tcp_keepalive_secs = interval;
+        }
+        if let Some(job_history_size) = self.job_history_size {
+            config.job_history_size = job_history_size;
+        }
+        if self.require_prover_at_startup {
+            config.require_prover_at_startup = true;
+        }
+        if let Some(workspace_ttl_secs) = self.workspace_ttl_secs {
+            config.workspace_ttl_secs = workspace_ttl_secs;
+        }
+        if let Some(max_program_size) = self.max_program_size {
+            config.max_program_size = max_program_size;
+        }
+        if let Some(max_pie_size) = self.max_pie_size {
+            config.max_pie_size = max_pie_size;
+        }
+        if let Some(max_pie_uncompressed_size) = self.max_pie_uncompressed_size {
+            config.max_pie_uncompressed_size = max_pie_uncompressed_size;
+        }
+        if let Some(max_memory_size) = self.max_memory_size {
+            config.max_memory_size = max_memory_size;
+        }
+        if let Some(max_trace_size) = self.max_trace_size {
+            config.max_trace_size = max_trace_size;
+        }
+        config.otlp_endpoint = self.otlp_endpoint.or(config.otlp_endpoint);
+        if let Some(parameter_presets) = self.parameter_presets {
+            config.parameter_presets = parameter_presets;
+        }
+        if self.prove_from_files {
+            config.prove_from_files = true;
+        }
+        if let Some(prove_from_files_roots) = self.prove_from_files_roots {
+            config.prove_from_files_roots = prove_from_files_roots;
+        }
+        if let Some(allowed_layouts) = self.policy_allowed_layouts {
+            config.policy.allowed_layouts = Some(allowed_layouts);
+        }
+        config.policy.max_n_steps = self.policy_max_n_steps.or(config.policy.max_n_steps);
+        if let Some(allow_split_proof) = self.policy_allow_split_proof {
+            config.policy.allow_split_proof = allow_split_proof;
+        }
+        if let Some(allow_user_parameters) = self.policy_allow_user_parameters {
+            config.policy.allow_user_parameters = allow_user_parameters;
+        }
+        if let Some(allow_small_proof) = self.policy_allow_small_proof {
+            config.policy.allow_small_proof = allow_small_proof;
+        }
+        config
+    }
+}
+
+fn print_error(error: &dyn std::error::Error) {
+    eprintln!("Error: {error}");
+    let mut source = error.source();
+    while let Some(cause) = source {
+        eprintln!("  Caused by: {cause}");
+        source = cause.source();
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let socket_addr: SocketAddr = "[::1]:8080".parse().unwrap();
-    run_grpc_server(BindAddress::Tcp(socket_addr)).await?;
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let config = match &args.config {
+        Some(config_file) => ServerConfig::from_file(config_file),
+        None => Ok(ServerConfig::default()),
+    }
+    .and_then(ServerConfig::apply_env);
+    let config = match config {
+        Ok(config) => args.apply(config),
+        Err(e) => {
+            print_error(&e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    #[cfg(feature = "otel")]
+    match telemetry::init_tracing(config.otlp_endpoint.as_deref()) {
+        Ok(true) => {}
+        Ok(false) => logging::init_logging(),
+        Err(e) => {
+            print_error(&e);
+            return ExitCode::FAILURE;
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        logging::init_logging();
+        if config.otlp_endpoint.is_some() {
+            tracing::warn!("Ignoring otlp_endpoint: the server is built without the otel feature");
+        }
+    }
+
+    // Bind failures have their own exit code, see `ServerError::exit_code`
+    let exit_code = match run_grpc_server(config).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            print_error(&e);
+            ExitCode::from(e.exit_code())
+        }
+    };
+
+    #[cfg(feature = "otel")]
+    telemetry::shutdown_tracing();
+
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use madara_prover_rpc_server::BindAddress;
+
+    use super::*;
+
+    const EXAMPLE_CONFIG: &str = include_str!("../config.example.toml");
+
+    fn apply_args(args: &[&str], config: ServerConfig) -> ServerConfig {
+        let args = std::iter::once("madara-prover-rpc-server").chain(args.iter().copied());
+        Args::try_parse_from(args).unwrap().apply(config)
+    }
+
+    #[test]
+    fn test_cli_bind_address_overrides_unix_socket() {
+        let config = ServerConfig {
+            unix_socket: Some(PathBuf::from("/run/madara-prover.sock")),
+            ..Default::default()
+        };
+
+        let config = apply_args(&["--bind-address", "127.0.0.1:9000"], config);
+
+        assert!(matches!(
+            config.bind_address(),
+            BindAddress::Tcp(address) if address == "127.0.0.1:9000".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_cli_unix_socket_overrides_bind_address() {
+        let config = apply_args(
+            &[
+                "--bind-address",
+                "127.0.0.1:9000",
+                "--unix-socket",
+                "/tmp/prover.sock",
+            ],
+            ServerConfig::default(),
+        );
+
+        assert!(matches!(
+            config.bind_address(),
+            BindAddress::UnixSocket(path) if path == Path::new("/tmp/prover.sock")
+        ));
+    }
+
+    #[test]
+    fn test_cli_overrides_env_and_file() {
+        let env_vars = HashMap::from([
+            ("MADARA_PROVER_TLS_CERT", "/etc/madara-prover/env.crt"),
+            ("MADARA_PROVER_TLS_KEY", "/etc/madara-prover/env.key"),
+            ("MADARA_PROVER_PROVER_BIN_DIR", "/opt/stone/env"),
+            ("MADARA_PROVER_POLICY_ALLOW_SPLIT_PROOF", "true"),
+            ("MADARA_PROVER_PROVE_FROM_FILES_ROOTS", "/var/lib/env"),
+        ]);
+        let config = ServerConfig::from_toml(EXAMPLE_CONFIG)
+            .unwrap()
+            .apply_vars(|name| env_vars.get(name).map(|value| value.to_string()))
+            .unwrap();
+
+        let config = apply_args(
+            &[
+                "--tls-cert",
+                "/etc/madara-prover/cli.crt",
+                "--tls-client-ca",
+                "/etc/madara-prover/clients.crt",
+                "--prover-bin-dir",
+                "/opt/stone/cli",
+                "--policy-allow-split-proof",
+                "false",
+                "--policy-allowed-layouts",
+                "recursive,small",
+                "--policy-max-n-steps",
+                "1024",
+                "--prove-from-files",
+                "--prove-from-files-roots",
+                "/var/lib/cli:/tmp/cli",
+                "--parameter-presets",
+                r#"{"small": {"last_layer_degree_bound": 32, "log_n_cosets": 2,
+                    "n_queries": 10, "proof_of_work_bits": 0}}"#,
+            ],
+            config,
+        );
+
+        assert_eq!(
+            config.tls_cert,
+            Some(PathBuf::from("/etc/madara-prover/cli.crt"))
+        );
+        assert_eq!(
+            config.tls_client_ca,
+            Some(PathBuf::from("/etc/madara-prover/clients.crt"))
+        );
+        assert_eq!(config.prover_bin_dir, Some(PathBuf::from("/opt/stone/cli")));
+        assert!(!config.policy.allow_split_proof);
+        assert_eq!(
+            config.policy.allowed_layouts,
+            Some(vec!["recursive".to_string(), "small".to_string()])
+        );
+        assert_eq!(config.policy.max_n_steps, Some(1024));
+        assert!(config.prove_from_files);
+        assert_eq!(
+            config.prove_from_files_roots,
+            vec![PathBuf::from("/var/lib/cli"), PathBuf::from("/tmp/cli")]
+        );
+        assert_eq!(
+            config
+                .parameter_presets
+                .get("small")
+                .map(|preset| preset.n_queries),
+            Some(10)
+        );
+        assert!(!config.parameter_presets.contains_key("recursive"));
+        // Not overridden on the command line
+        assert_eq!(
+            config.tls_key,
+            Some(PathBuf::from("/etc/madara-prover/env.key"))
+        );
+        assert!(config.policy.allow_user_parameters);
+        assert_eq!(config.concurrency_limit, Some(4));
+    }
 
-    Ok(())
+    #[test]
+    fn test_cli_invalid_parameter_presets() {
+        let args = [
+            "madara-prover-rpc-server",
+            "--parameter-presets",
+            r#"{"small": {"n_queries": 10}}"#,
+        ];
+        assert!(Args::try_parse_from(args).is_err());
+    }
 }
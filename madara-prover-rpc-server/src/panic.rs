@@ -0,0 +1,134 @@
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+use futures::FutureExt;
+use tonic::body::BoxBody;
+use tonic::codegen::http;
+use tonic::Status;
+use tower::{Layer, Service};
+
+/// Maximum length of the panic message returned to the client.
+const MAX_PANIC_MESSAGE_LENGTH: usize = 200;
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Extracts the message of a panic, if it is a string. Only the first line is kept and
+/// long messages are truncated.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    let first_line = message.lines().next().unwrap_or_default();
+    first_line.chars().take(MAX_PANIC_MESSAGE_LENGTH).collect()
+}
+
+/// Status returned to the client when the handler of a request panics.
+pub fn panic_status(panic: &(dyn Any + Send), request_id: u64) -> Status {
+    Status::internal(format!(
+        "Internal error while processing request {request_id}: {}",
+        panic_message(panic)
+    ))
+}
+
+/// Converts panics in request handlers into `INTERNAL` statuses.
+///
+/// Without it, a panic tears down the connection and the client only gets a transport
+/// error. The panic itself is logged by the panic hook, along with the backtrace if
+/// `RUST_BACKTRACE` is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CatchPanicLayer;
+
+impl<S> Layer<S> for CatchPanicLayer {
+    type Service = CatchPanic<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CatchPanic { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CatchPanic<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for CatchPanic<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<BoxBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let request_id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        let path = request.uri().path().to_string();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            match AssertUnwindSafe(future).catch_unwind().await {
+                Ok(result) => result,
+                Err(panic) => {
+                    tracing::error!(request_id, "Request {path} panicked");
+                    Ok(panic_status(panic.as_ref(), request_id).to_http())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let panic: Box<dyn Any + Send> = Box::new("index out of bounds\nmore details");
+        assert_eq!(panic_message(panic.as_ref()), "index out of bounds");
+
+        let panic: Box<dyn Any + Send> = Box::new("x".repeat(1000));
+        assert_eq!(
+            panic_message(panic.as_ref()).len(),
+            MAX_PANIC_MESSAGE_LENGTH
+        );
+
+        let panic: Box<dyn Any + Send> = Box::new(42);
+        assert_eq!(panic_message(panic.as_ref()), "unknown panic");
+    }
+
+    #[tokio::test]
+    async fn test_catch_panic() {
+        let service = tower::service_fn(|request: http::Request<()>| async move {
+            if request.uri().path() == "/panic" {
+                panic!("handler failed");
+            }
+            Ok::<_, Infallible>(http::Response::new(tonic::body::empty_body()))
+        });
+        let service = CatchPanicLayer.layer(service);
+
+        let request = http::Request::builder().uri("/panic").body(()).unwrap();
+        let response = service.clone().oneshot(request).await.unwrap();
+        let status = Status::from_header_map(response.headers()).unwrap();
+        assert_eq!(status.code(), tonic::Code::Internal);
+        assert!(status.message().contains("handler failed"));
+
+        // The service keeps working
+        let request = http::Request::builder().uri("/ok").body(()).unwrap();
+        let response = service.oneshot(request).await.unwrap();
+        assert!(Status::from_header_map(response.headers()).is_none());
+    }
+}
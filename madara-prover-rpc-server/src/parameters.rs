@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use stone_prover_sdk::fri::generate_prover_parameters;
+use stone_prover_sdk::models::ProverParameters;
+
+/// Last layer degree bound of medium-size executions, see `select_last_layer_degree_bound`.
+pub const DEFAULT_LAST_LAYER_DEGREE_BOUND: u32 = 64;
+pub const MIN_LAST_LAYER_DEGREE_BOUND: u32 = 32;
+pub const MAX_LAST_LAYER_DEGREE_BOUND: u32 = 128;
+
+/// Executions up to this number of steps use the smallest last layer.
+const SMALL_EXECUTION_MAX_STEPS: u32 = 512;
+/// Executions from this number of steps use the largest last layer.
+const LARGE_EXECUTION_MIN_STEPS: u32 = 1 << 22;
+
+/// Largest FRI step supported by Stone.
+pub const MAX_FRI_STEP: u32 = 4;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ParameterError {
+    #[error("max FRI step must be between 1 and {MAX_FRI_STEP}, got {0}")]
+    InvalidMaxFriStep(u32),
+    #[error("last layer degree bound must be a power of 2 smaller than the trace degree ({max}), got {bound}")]
+    InvalidLastLayerDegreeBound { bound: u32, max: u64 },
+}
+
+/// Log2 of the degree of the trace, which the FRI steps and the last layer add up to.
+pub fn trace_degree_log(nb_steps: u32) -> u32 {
+    nb_steps.max(1).ilog2() + 4
+}
+
+/// Selects the last layer degree bound from the number of steps of an execution.
+///
+/// Tiny executions use a smaller last layer, otherwise most of their degree would be in it,
+/// and very large executions a larger one to limit the number of FRI layers. The bound always
+/// leaves at least one FRI step.
+pub fn select_last_layer_degree_bound(nb_steps: u32) -> u32 {
+    let bound = if nb_steps <= SMALL_EXECUTION_MAX_STEPS {
+        MIN_LAST_LAYER_DEGREE_BOUND
+    } else if nb_steps >= LARGE_EXECUTION_MIN_STEPS {
+        MAX_LAST_LAYER_DEGREE_BOUND
+    } else {
+        DEFAULT_LAST_LAYER_DEGREE_BOUND
+    };
+    bound.min(1 << (trace_degree_log(nb_steps) - 1))
+}
+
+fn validate_last_layer_degree_bound(bound: u32, nb_steps: u32) -> Result<(), ParameterError> {
+    let max = 1u64 << trace_degree_log(nb_steps);
+    if !bound.is_power_of_two() || u64::from(bound) >= max {
+        return Err(ParameterError::InvalidLastLayerDegreeBound { bound, max });
+    }
+    Ok(())
+}
+
+/// Options of a request that modify the generated parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParameterOverrides {
+    /// Largest step of the FRI step list, between 1 and `MAX_FRI_STEP`. Smaller steps make
+    /// proofs cheaper to verify on-chain, at the cost of more FRI layers.
+    pub max_fri_step: Option<u32>,
+    /// Degree bound of the last FRI layer, a power of 2. Takes precedence over the preset
+    /// of the layout.
+    pub last_layer_degree_bound: Option<u32>,
+}
+
+/// Splits `fri_degree`, the sum of the FRI steps, in steps of at most `max_fri_step`,
+/// with the remainder last.
+pub fn compute_fri_steps(fri_degree: u32, max_fri_step: u32) -> Result<Vec<u32>, ParameterError> {
+    if !(1..=MAX_FRI_STEP).contains(&max_fri_step) {
+        return Err(ParameterError::InvalidMaxFriStep(max_fri_step));
+    }
+
+    let mut fri_steps = vec![max_fri_step; (fri_degree / max_fri_step) as usize];
+    let remainder = fri_degree % max_fri_step;
+    if remainder > 0 {
+        fri_steps.push(remainder);
+    }
+    Ok(fri_steps)
+}
+
+/// Default STARK parameters for a layout.
+///
+/// The FRI step list is still computed from the number of steps of each execution.
+/// The security of a proof is roughly `n_queries * log_n_cosets + proof_of_work_bits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParameterPreset {
+    pub last_layer_degree_bound: u32,
+    pub log_n_cosets: u32,
+    pub n_queries: u32,
+    pub proof_of_work_bits: u32,
+}
+
+/// Proofs verified on Starknet / L1 through SHARP-style pipelines.
+const STARKNET_PRESET: ParameterPreset = ParameterPreset {
+    last_layer_degree_bound: 64,
+    log_n_cosets: 4,
+    n_queries: 18,
+    proof_of_work_bits: 24,
+};
+
+/// Proofs verified by the Cairo verifier in a recursive proof: a smaller blowup makes
+/// proving cheaper, the additional queries are cheap to verify in Cairo.
+const RECURSIVE_PRESET: ParameterPreset = ParameterPreset {
+    last_layer_degree_bound: 128,
+    log_n_cosets: 2,
+    n_queries: 40,
+    proof_of_work_bits: 16,
+};
+
+/// Preset table, keyed by layout name.
+#[derive(Debug, Clone)]
+pub struct ParameterPresets {
+    presets: HashMap<String, ParameterPreset>,
+}
+
+impl Default for ParameterPresets {
+    fn default() -> Self {
+        let presets = HashMap::from([
+            ("recursive".to_string(), RECURSIVE_PRESET),
+            ("starknet".to_string(), STARKNET_PRESET),
+            ("starknet_with_keccak".to_string(), STARKNET_PRESET),
+        ]);
+        Self { presets }
+    }
+}
+
+impl ParameterPresets {
+    /// Adds presets to the table, replacing the existing ones for the same layouts.
+    pub fn with_overrides(mut self, overrides: HashMap<String, ParameterPreset>) -> Self {
+        self.presets.extend(overrides);
+        self
+    }
+
+    pub fn get(&self, layout: &str) -> Option<&ParameterPreset> {
+        self.presets.get(layout)
+    }
+
+    /// Generates the prover parameters for an execution of `nb_steps` steps with the
+    /// preset of `layout`. Layouts without a preset use the parameters generated by the SDK,
+    /// with a last layer degree bound selected from the number of steps.
+    pub fn prover_parameters(
+        &self,
+        layout: &str,
+        nb_steps: u32,
+        overrides: ParameterOverrides,
+    ) -> Result<ProverParameters, ParameterError> {
+        let preset = self.get(layout);
+        let last_layer_degree_bound = overrides
+            .last_layer_degree_bound
+            .or(preset.map(|preset| preset.last_layer_degree_bound))
+            .unwrap_or_else(|| select_last_layer_degree_bound(nb_steps));
+        validate_last_layer_degree_bound(last_layer_degree_bound, nb_steps)?;
+
+        let mut parameters = generate_prover_parameters(nb_steps, last_layer_degree_bound);
+        if let Some(preset) = preset {
+            parameters.stark.log_n_cosets = preset.log_n_cosets;
+            parameters.stark.fri.n_queries = preset.n_queries;
+            parameters.stark.fri.proof_of_work_bits = preset.proof_of_work_bits;
+        }
+
+        if let Some(max_fri_step) = overrides.max_fri_step {
+            let fri_degree = parameters.stark.fri.fri_step_list.iter().sum();
+            parameters.stark.fri.fri_step_list = compute_fri_steps(fri_degree, max_fri_step)?;
+        }
+
+        Ok(parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn fri_degree(parameters: &ProverParameters) -> u32 {
+        parameters.stark.fri.fri_step_list.iter().sum::<u32>()
+            + parameters.stark.fri.last_layer_degree_bound.ilog2()
+    }
+
+    #[rstest]
+    #[case("recursive", RECURSIVE_PRESET)]
+    #[case("starknet", STARKNET_PRESET)]
+    #[case("starknet_with_keccak", STARKNET_PRESET)]
+    fn test_preset(#[case] layout: &str, #[case] expected: ParameterPreset) {
+        let presets = ParameterPresets::default();
+        assert_eq!(presets.get(layout), Some(&expected));
+
+        let parameters = presets
+            .prover_parameters(layout, 1 << 14, ParameterOverrides::default())
+            .unwrap();
+        assert_eq!(
+            parameters.stark.fri.last_layer_degree_bound,
+            expected.last_layer_degree_bound
+        );
+        assert_eq!(parameters.stark.log_n_cosets, expected.log_n_cosets);
+        assert_eq!(parameters.stark.fri.n_queries, expected.n_queries);
+        assert_eq!(
+            parameters.stark.fri.proof_of_work_bits,
+            expected.proof_of_work_bits
+        );
+
+        // The FRI steps still add up to the degree of the trace
+        let default_parameters =
+            generate_prover_parameters(1 << 14, DEFAULT_LAST_LAYER_DEGREE_BOUND);
+        assert_eq!(fri_degree(&parameters), fri_degree(&default_parameters));
+    }
+
+    #[test]
+    fn test_layout_without_preset() {
+        let parameters = ParameterPresets::default()
+            .prover_parameters("small", 1 << 10, ParameterOverrides::default())
+            .unwrap();
+        let expected = generate_prover_parameters(1 << 10, select_last_layer_degree_bound(1 << 10));
+
+        assert_eq!(
+            serde_json::to_value(parameters).unwrap(),
+            serde_json::to_value(expected).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case(13, 4, vec![4, 4, 4, 1])]
+    #[case(12, 4, vec![4, 4, 4])]
+    #[case(13, 3, vec![3, 3, 3, 3, 1])]
+    #[case(14, 3, vec![3, 3, 3, 3, 2])]
+    #[case(13, 2, vec![2, 2, 2, 2, 2, 2, 1])]
+    #[case(4, 1, vec![1, 1, 1, 1])]
+    fn test_compute_fri_steps(
+        #[case] fri_degree: u32,
+        #[case] max_fri_step: u32,
+        #[case] expected: Vec<u32>,
+    ) {
+        let fri_steps = compute_fri_steps(fri_degree, max_fri_step).unwrap();
+        assert_eq!(fri_steps, expected);
+        assert_eq!(fri_steps.iter().sum::<u32>(), fri_degree);
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(5)]
+    fn test_compute_fri_steps_invalid_max_step(#[case] max_fri_step: u32) {
+        assert_eq!(
+            compute_fri_steps(13, max_fri_step),
+            Err(ParameterError::InvalidMaxFriStep(max_fri_step))
+        );
+    }
+
+    #[rstest]
+    #[case::tiny(16, 32)]
+    #[case::small(512, 32)]
+    #[case::medium(1 << 15, 64)]
+    #[case::large(1 << 21, 64)]
+    #[case::very_large(1 << 24, 128)]
+    fn test_select_last_layer_degree_bound(#[case] nb_steps: u32, #[case] expected: u32) {
+        let bound = select_last_layer_degree_bound(nb_steps);
+        assert_eq!(bound, expected);
+
+        // The generated parameters satisfy the Σ-steps equation
+        let parameters = ParameterPresets::default()
+            .prover_parameters("small", nb_steps, ParameterOverrides::default())
+            .unwrap();
+        assert_eq!(parameters.stark.fri.last_layer_degree_bound, bound);
+        assert_eq!(fri_degree(&parameters), trace_degree_log(nb_steps));
+        assert!(!parameters.stark.fri.fri_step_list.is_empty());
+    }
+
+    #[test]
+    fn test_select_last_layer_degree_bound_leaves_a_fri_step() {
+        // 2 steps: the trace degree is 2^5, the last layer cannot be 32
+        assert_eq!(select_last_layer_degree_bound(2), 16);
+    }
+
+    #[test]
+    fn test_override_last_layer_degree_bound() {
+        let presets = ParameterPresets::default();
+        let overrides = ParameterOverrides {
+            last_layer_degree_bound: Some(256),
+            ..Default::default()
+        };
+
+        let parameters = presets
+            .prover_parameters("starknet", 1 << 15, overrides)
+            .unwrap();
+
+        assert_eq!(parameters.stark.fri.last_layer_degree_bound, 256);
+        assert_eq!(fri_degree(&parameters), trace_degree_log(1 << 15));
+    }
+
+    #[rstest]
+    #[case(100)]
+    #[case(0)]
+    #[case(1 << 19)]
+    fn test_invalid_last_layer_degree_bound(#[case] bound: u32) {
+        let overrides = ParameterOverrides {
+            last_layer_degree_bound: Some(bound),
+            ..Default::default()
+        };
+
+        let result = ParameterPresets::default().prover_parameters("starknet", 1 << 15, overrides);
+
+        assert!(matches!(
+            result,
+            Err(ParameterError::InvalidLastLayerDegreeBound { .. })
+        ));
+    }
+
+    #[rstest]
+    #[case(2)]
+    #[case(3)]
+    fn test_max_fri_step(#[case] max_fri_step: u32) {
+        let presets = ParameterPresets::default();
+        let overrides = ParameterOverrides {
+            max_fri_step: Some(max_fri_step),
+            ..Default::default()
+        };
+
+        let parameters = presets
+            .prover_parameters("starknet", 1 << 14, overrides)
+            .unwrap();
+        let default_parameters = presets
+            .prover_parameters("starknet", 1 << 14, ParameterOverrides::default())
+            .unwrap();
+
+        let fri_step_list = &parameters.stark.fri.fri_step_list;
+        assert!(fri_step_list.iter().all(|step| *step <= max_fri_step));
+        // The Σ-steps equation still holds
+        assert_eq!(fri_degree(&parameters), fri_degree(&default_parameters));
+    }
+
+    #[test]
+    fn test_override_preset() {
+        let preset = ParameterPreset {
+            last_layer_degree_bound: 32,
+            log_n_cosets: 3,
+            n_queries: 30,
+            proof_of_work_bits: 20,
+        };
+        let presets = ParameterPresets::default()
+            .with_overrides(HashMap::from([("starknet".to_string(), preset)]));
+
+        assert_eq!(presets.get("starknet"), Some(&preset));
+        assert_eq!(presets.get("starknet_with_keccak"), Some(&STARKNET_PRESET));
+    }
+}
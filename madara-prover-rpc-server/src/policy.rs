@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use tonic::Status;
+
+/// Restrictions of a deployment on the proofs it generates. By default, everything is allowed.
+///
+/// Layouts and features that are not allowed are rejected with `PERMISSION_DENIED`, inputs
+/// over the limits with `INVALID_ARGUMENT`. Both name the policy setting.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProverPolicy {
+    /// Layouts that can be proven, all layouts if not set.
+    pub allowed_layouts: Option<Vec<String>>,
+    /// Maximum number of steps of a proven execution.
+    pub max_n_steps: Option<u32>,
+    /// Allow Starknet proofs split for L1 verification, which run the verifier.
+    pub allow_split_proof: bool,
+    /// Allow requests to provide their own prover parameters instead of the presets of the
+    /// server.
+    pub allow_user_parameters: bool,
+    /// Allow `ExecuteAndProve` requests to run in the slots reserved to small proofs with
+    /// `small_proof`. Their number of steps is checked once executed.
+    pub allow_small_proof: bool,
+}
+
+impl Default for ProverPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_layouts: None,
+            max_n_steps: None,
+            allow_split_proof: true,
+            allow_user_parameters: true,
+            allow_small_proof: true,
+        }
+    }
+}
+
+impl ProverPolicy {
+    pub fn check_layout(&self, layout: &str) -> Result<(), Status> {
+        match &self.allowed_layouts {
+            Some(allowed_layouts) if !allowed_layouts.iter().any(|allowed| allowed == layout) => {
+                Err(Status::permission_denied(format!(
+                    "Layout {layout} is not allowed by the policy of the server \
+                    (allowed_layouts: {})",
+                    allowed_layouts.join(", ")
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_n_steps(&self, n_steps: u32) -> Result<(), Status> {
+        match self.max_n_steps {
+            Some(max_n_steps) if n_steps > max_n_steps => Err(Status::invalid_argument(format!(
+                "The execution has {n_steps} steps, over the limit of the policy of the server \
+                (max_n_steps: {max_n_steps})"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_split_proof(&self, split_proof: bool) -> Result<(), Status> {
+        if split_proof && !self.allow_split_proof {
+            return Err(Status::permission_denied(
+                "Split proofs are not allowed by the policy of the server (allow_split_proof)",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_small_proof(&self, small_proof: bool) -> Result<(), Status> {
+        if small_proof && !self.allow_small_proof {
+            return Err(Status::permission_denied(
+                "Small proof requests are not allowed by the policy of the server \
+                (allow_small_proof)",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks whether the request can provide its own prover parameters, if it does.
+    pub fn check_user_parameters(&self, has_user_parameters: bool) -> Result<(), Status> {
+        if has_user_parameters && !self.allow_user_parameters {
+            return Err(Status::permission_denied(
+                "User-provided prover parameters are not allowed by the policy of the server \
+                (allow_user_parameters)",
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+
+    fn restrictive_policy() -> ProverPolicy {
+        ProverPolicy {
+            allowed_layouts: Some(vec!["recursive".to_string(), "small".to_string()]),
+            max_n_steps: Some(1024),
+            allow_split_proof: false,
+            allow_user_parameters: false,
+            allow_small_proof: false,
+        }
+    }
+
+    #[test]
+    fn test_default_policy_allows_everything() {
+        let policy = ProverPolicy::default();
+
+        assert!(policy.check_layout("starknet_with_keccak").is_ok());
+        assert!(policy.check_n_steps(u32::MAX).is_ok());
+        assert!(policy.check_split_proof(true).is_ok());
+        assert!(policy.check_user_parameters(true).is_ok());
+        assert!(policy.check_small_proof(true).is_ok());
+    }
+
+    #[test]
+    fn test_restrictive_policy() {
+        let policy = restrictive_policy();
+
+        assert!(policy.check_layout("recursive").is_ok());
+        let status = policy.check_layout("starknet_with_keccak").unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(
+            status.message(),
+            "Layout starknet_with_keccak is not allowed by the policy of the server \
+            (allowed_layouts: recursive, small)"
+        );
+
+        assert!(policy.check_n_steps(1024).is_ok());
+        let status = policy.check_n_steps(2048).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(status.message().contains("max_n_steps: 1024"));
+
+        assert!(policy.check_split_proof(false).is_ok());
+        let status = policy.check_split_proof(true).unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains("allow_split_proof"));
+
+        assert!(policy.check_user_parameters(false).is_ok());
+        let status = policy.check_user_parameters(true).unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains("allow_user_parameters"));
+
+        assert!(policy.check_small_proof(false).is_ok());
+        let status = policy.check_small_proof(true).unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(status.message().contains("allow_small_proof"));
+    }
+}
@@ -0,0 +1,23 @@
+pub use madara_prover_common::proto_ext::{base64_bytes, base64_bytes_list, MessageExt};
+
+#[cfg(test)]
+mod tests {
+    use crate::services::prover::prover_proto::ExecutionRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_execution_request_round_trip() {
+        let request = ExecutionRequest {
+            program: b"{\"data\": []}".to_vec(),
+            prover_parameters: Some("{}".to_string()),
+            hint_profile: Some("default".to_string()),
+            max_fri_step: Some(3),
+            ..Default::default()
+        };
+
+        let json = request.to_json().unwrap();
+        let deserialized: ExecutionRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, request);
+    }
+}
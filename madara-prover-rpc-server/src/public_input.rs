@@ -0,0 +1,388 @@
+use std::collections::{BTreeMap, HashSet};
+
+use cairo_vm::Felt252;
+use sha3::{Digest, Keccak256};
+use stone_prover_sdk::models::{PublicInput, PublicMemoryEntry};
+use thiserror::Error;
+
+pub const PROGRAM_SEGMENT: &str = "program";
+pub const EXECUTION_SEGMENT: &str = "execution";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PublicInputError {
+    #[error("Invalid range check bounds: rc_min ({rc_min}) > rc_max ({rc_max})")]
+    InvalidRangeCheckBounds { rc_min: u32, rc_max: u32 },
+    #[error("The number of steps must be a power of two, got {0}")]
+    InvalidStepCount(u32),
+    #[error("Unknown layout: {0}")]
+    UnknownLayout(String),
+    #[error("Missing {0} segment")]
+    MissingSegment(&'static str),
+    #[error("Segment {segment} is not a builtin of the {layout} layout")]
+    UnsupportedSegment { segment: String, layout: String },
+    #[error("Segment {0} ends before it begins")]
+    InvalidSegmentBounds(String),
+    #[error("Public memory address {0} is not part of any segment")]
+    AddressOutOfSegments(u32),
+    #[error("Public memory pages are not contiguous: page {0} is missing")]
+    MissingPage(u32),
+    #[error("Invalid value in the public memory at address {0}")]
+    InvalidMemoryValue(u32),
+}
+
+/// Returns the builtins of a layout, or `None` if the layout is unknown.
+pub fn layout_builtins(layout: &str) -> Option<&'static [&'static str]> {
+    let builtins: &[&str] = match layout {
+        "plain" => &[],
+        "small" | "dex" => &["output", "pedersen", "range_check", "ecdsa"],
+        "recursive" => &["output", "pedersen", "range_check", "bitwise"],
+        "recursive_large_output" => &["output", "pedersen", "range_check", "bitwise", "poseidon"],
+        "starknet" => &[
+            "output",
+            "pedersen",
+            "range_check",
+            "ecdsa",
+            "bitwise",
+            "ec_op",
+            "poseidon",
+        ],
+        "starknet_with_keccak" | "all_cairo" => &[
+            "output",
+            "pedersen",
+            "range_check",
+            "ecdsa",
+            "bitwise",
+            "ec_op",
+            "keccak",
+            "poseidon",
+        ],
+        "all_solidity" => &[
+            "output",
+            "pedersen",
+            "range_check",
+            "ecdsa",
+            "bitwise",
+            "ec_op",
+        ],
+        _ => return None,
+    };
+    Some(builtins)
+}
+
+/// Checks that the public input is consistent before sending it to the prover, which
+/// otherwise fails with hard-to-understand errors.
+pub fn validate_public_input(public_input: &PublicInput) -> Result<(), PublicInputError> {
+    if public_input.rc_min > public_input.rc_max {
+        return Err(PublicInputError::InvalidRangeCheckBounds {
+            rc_min: public_input.rc_min,
+            rc_max: public_input.rc_max,
+        });
+    }
+
+    if !public_input.n_steps.is_power_of_two() {
+        return Err(PublicInputError::InvalidStepCount(public_input.n_steps));
+    }
+
+    let builtins = layout_builtins(&public_input.layout)
+        .ok_or_else(|| PublicInputError::UnknownLayout(public_input.layout.clone()))?;
+
+    let segments = &public_input.memory_segments;
+    for (name, segment) in segments {
+        if name != PROGRAM_SEGMENT
+            && name != EXECUTION_SEGMENT
+            && !builtins.contains(&name.as_str())
+        {
+            return Err(PublicInputError::UnsupportedSegment {
+                segment: name.clone(),
+                layout: public_input.layout.clone(),
+            });
+        }
+        if segment.begin_addr > segment.stop_ptr {
+            return Err(PublicInputError::InvalidSegmentBounds(name.clone()));
+        }
+    }
+
+    let program_segment = segments
+        .get(PROGRAM_SEGMENT)
+        .ok_or(PublicInputError::MissingSegment(PROGRAM_SEGMENT))?;
+    let execution_segment = segments
+        .get(EXECUTION_SEGMENT)
+        .ok_or(PublicInputError::MissingSegment(EXECUTION_SEGMENT))?;
+
+    validate_public_memory_pages(public_input)?;
+
+    // The stop pointer of the program segment is the final pc, the program data itself
+    // spans up to the execution segment.
+    let program_data = program_segment.begin_addr..execution_segment.begin_addr;
+    for entry in &public_input.public_memory {
+        let address = entry.address;
+        let in_segment = program_data.contains(&address)
+            || segments
+                .values()
+                .any(|segment| (segment.begin_addr..segment.stop_ptr).contains(&address));
+        if !in_segment {
+            return Err(PublicInputError::AddressOutOfSegments(address));
+        }
+    }
+
+    Ok(())
+}
+
+/// Groups the public memory entries by page.
+pub fn public_memory_pages(public_input: &PublicInput) -> BTreeMap<u32, Vec<&PublicMemoryEntry>> {
+    let mut pages: BTreeMap<u32, Vec<&PublicMemoryEntry>> = BTreeMap::new();
+    for entry in &public_input.public_memory {
+        pages.entry(entry.page).or_default().push(entry);
+    }
+    pages
+}
+
+/// Returns the number of public memory entries in each page.
+pub fn public_memory_page_sizes(public_input: &PublicInput) -> BTreeMap<u32, usize> {
+    public_memory_pages(public_input)
+        .into_iter()
+        .map(|(page, entries)| (page, entries.len()))
+        .collect()
+}
+
+/// Checks that the public memory pages are numbered contiguously from 0.
+pub fn validate_public_memory_pages(public_input: &PublicInput) -> Result<(), PublicInputError> {
+    let pages = public_memory_pages(public_input);
+    for (expected_page, page) in (0u32..).zip(pages.keys()) {
+        if *page != expected_page {
+            return Err(PublicInputError::MissingPage(expected_page));
+        }
+    }
+    Ok(())
+}
+
+/// Computes the hash of the main public memory page (page 0), as registered by the L1 verifier:
+/// the keccak of the (address, value) pairs of the page, encoded as 32-byte words.
+pub fn main_page_hash(public_input: &PublicInput) -> Result<[u8; 32], PublicInputError> {
+    let mut hasher = Keccak256::new();
+    for entry in public_input
+        .public_memory
+        .iter()
+        .filter(|entry| entry.page == 0)
+    {
+        let value = Felt252::from_hex(&entry.value)
+            .map_err(|_| PublicInputError::InvalidMemoryValue(entry.address))?;
+        hasher.update(Felt252::from(entry.address).to_bytes_be());
+        hasher.update(value.to_bytes_be());
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Public memory as a set of (address, page, value), with values parsed to ignore
+/// differences in the hex representation.
+fn normalized_public_memory(public_input: &PublicInput) -> Option<HashSet<(u32, u32, Felt252)>> {
+    public_input
+        .public_memory
+        .iter()
+        .map(|entry| {
+            Felt252::from_hex(&entry.value)
+                .ok()
+                .map(|value| (entry.address, entry.page, value))
+        })
+        .collect()
+}
+
+/// Compares two public inputs, ignoring differences that do not matter to the prover such as
+/// the order of the public memory and the formatting of its values.
+pub fn public_inputs_semantically_equal(lhs: &PublicInput, rhs: &PublicInput) -> bool {
+    let same_segments = lhs.memory_segments.len() == rhs.memory_segments.len()
+        && lhs.memory_segments.iter().all(|(name, segment)| {
+            rhs.memory_segments.get(name).is_some_and(|other| {
+                segment.begin_addr == other.begin_addr && segment.stop_ptr == other.stop_ptr
+            })
+        });
+
+    let same_public_memory = match (normalized_public_memory(lhs), normalized_public_memory(rhs)) {
+        (Some(lhs_memory), Some(rhs_memory)) => lhs_memory == rhs_memory,
+        _ => false,
+    };
+
+    lhs.layout == rhs.layout
+        && lhs.rc_min == rhs.rc_min
+        && lhs.rc_max == rhs.rc_max
+        && lhs.n_steps == rhs.n_steps
+        && lhs.dynamic_params == rhs.dynamic_params
+        && same_segments
+        && same_public_memory
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::{fixture, rstest};
+    use stone_prover_sdk::json::read_json_from_file;
+    use stone_prover_sdk::models::{MemorySegmentAddresses, PublicMemoryEntry};
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    #[fixture]
+    fn public_input() -> PublicInput {
+        read_json_from_file(get_test_case_file_path(
+            "fibonacci/fibonacci_public_input.json",
+        ))
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case::fibonacci("fibonacci/fibonacci_public_input.json")]
+    #[case::bootloader("bootloader/programs/fibonacci/output/air_public_input.json")]
+    #[case::pie("bootloader/pies/fibonacci/output/air_public_input.json")]
+    fn test_valid_public_inputs(#[case] public_input_file: &str) {
+        let public_input: PublicInput =
+            read_json_from_file(get_test_case_file_path(public_input_file)).unwrap();
+        assert_eq!(validate_public_input(&public_input), Ok(()));
+    }
+
+    #[rstest]
+    fn test_invalid_rc_bounds(mut public_input: PublicInput) {
+        public_input.rc_min = public_input.rc_max + 1;
+        assert!(matches!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::InvalidRangeCheckBounds { .. })
+        ));
+    }
+
+    #[rstest]
+    fn test_invalid_step_count(mut public_input: PublicInput) {
+        public_input.n_steps = 1000;
+        assert_eq!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::InvalidStepCount(1000))
+        );
+    }
+
+    #[rstest]
+    fn test_unknown_layout(mut public_input: PublicInput) {
+        public_input.layout = "does_not_exist".to_string();
+        assert_eq!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::UnknownLayout(
+                "does_not_exist".to_string()
+            ))
+        );
+    }
+
+    #[rstest]
+    fn test_segment_not_in_layout(mut public_input: PublicInput) {
+        public_input.layout = "small".to_string();
+        assert!(matches!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::UnsupportedSegment { .. })
+        ));
+    }
+
+    #[rstest]
+    fn test_missing_execution_segment(mut public_input: PublicInput) {
+        public_input.memory_segments.remove(EXECUTION_SEGMENT);
+        assert_eq!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::MissingSegment(EXECUTION_SEGMENT))
+        );
+    }
+
+    #[rstest]
+    fn test_invalid_segment_bounds(mut public_input: PublicInput) {
+        public_input.memory_segments.insert(
+            "output".to_string(),
+            MemorySegmentAddresses {
+                begin_addr: 10,
+                stop_ptr: 5,
+            },
+        );
+        assert_eq!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::InvalidSegmentBounds("output".to_string()))
+        );
+    }
+
+    #[rstest]
+    fn test_address_out_of_segments(mut public_input: PublicInput) {
+        public_input.public_memory.push(PublicMemoryEntry {
+            address: u32::MAX,
+            value: "0x1".to_string(),
+            page: 0,
+        });
+        assert_eq!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::AddressOutOfSegments(u32::MAX))
+        );
+    }
+
+    #[rstest]
+    fn test_non_contiguous_pages(mut public_input: PublicInput) {
+        let last_entry = public_input.public_memory.last_mut().unwrap();
+        last_entry.page = 2;
+        assert_eq!(
+            validate_public_memory_pages(&public_input),
+            Err(PublicInputError::MissingPage(1))
+        );
+        assert_eq!(
+            validate_public_input(&public_input),
+            Err(PublicInputError::MissingPage(1))
+        );
+    }
+
+    #[rstest]
+    fn test_public_memory_pages() {
+        let public_input: PublicInput = read_json_from_file(get_test_case_file_path(
+            "bootloader/pies/fibonacci/output/air_public_input.json",
+        ))
+        .unwrap();
+
+        let pages = public_memory_pages(&public_input);
+        assert_eq!(pages.keys().copied().collect::<Vec<_>>(), vec![0, 1]);
+        let page_1_addresses: Vec<u32> = pages[&1].iter().map(|entry| entry.address).collect();
+        assert_eq!(page_1_addresses, vec![1958, 1959]);
+
+        let page_sizes = public_memory_page_sizes(&public_input);
+        assert_eq!(page_sizes[&1], 2);
+        assert_eq!(
+            page_sizes.values().sum::<usize>(),
+            public_input.public_memory.len()
+        );
+    }
+
+    #[rstest]
+    #[case::fibonacci(
+        "fibonacci/fibonacci_public_input.json",
+        "0x328f9a45f9e11cec6e243266240766ed2b52c3d3b137fb2015707f4df72e4991"
+    )]
+    #[case::bootloader(
+        "bootloader/programs/fibonacci/output/air_public_input.json",
+        "0x997e7114501b912c426092062c60e3351a9648672a764eb70495cec882f1e687"
+    )]
+    fn test_main_page_hash(#[case] public_input_file: &str, #[case] expected_hash: &str) {
+        let public_input: PublicInput =
+            read_json_from_file(get_test_case_file_path(public_input_file)).unwrap();
+
+        let page_hash = main_page_hash(&public_input).unwrap();
+
+        let page_hash_hex: String = page_hash.iter().map(|byte| format!("{byte:02x}")).collect();
+        assert_eq!(format!("0x{page_hash_hex}"), expected_hash);
+    }
+
+    #[rstest]
+    fn test_semantic_equality(public_input: PublicInput) {
+        let mut other = public_input.clone();
+        other.public_memory.reverse();
+        for entry in other.public_memory.iter_mut() {
+            entry.value = format!("0x{:0>64}", entry.value.trim_start_matches("0x"));
+        }
+        assert!(public_inputs_semantically_equal(&public_input, &other));
+
+        other.rc_max += 1;
+        assert!(!public_inputs_semantically_equal(&public_input, &other));
+    }
+
+    #[rstest]
+    fn test_semantic_equality_detects_memory_changes(public_input: PublicInput) {
+        let mut other = public_input.clone();
+        other.public_memory[0].value = "0x1234".to_string();
+        assert!(!public_inputs_semantically_equal(&public_input, &other));
+    }
+}
@@ -1,13 +1,21 @@
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use cairo_vm::air_private_input::AirPrivateInput;
+use serde::Serialize;
 use stone_prover_sdk::error::{ProverError, VerifierError};
-use stone_prover_sdk::fri::generate_prover_parameters;
+use stone_prover_sdk::json::read_json_from_file;
 use stone_prover_sdk::models::{
-    Proof, ProofAnnotations, ProverConfig, ProverParameters, ProverWorkingDirectory,
+    Proof, ProofAnnotations, ProverConfig, ProverParameters, ProverWorkingDirectory, PublicInput,
 };
-use stone_prover_sdk::prover::run_prover_async;
+use stone_prover_sdk::prover::{run_prover_async, run_prover_from_command_line_async};
 use stone_prover_sdk::verifier::run_verifier_with_annotations_async;
 use tonic::Status;
 
 use crate::evm_adapter;
+use crate::evm_adapter::SplitProverError;
+use crate::parameters::{ParameterOverrides, ParameterPresets};
 use stone_prover_sdk::cairo_vm::ExecutionArtifacts;
 
 pub async fn call_prover(
@@ -26,9 +34,235 @@ pub async fn call_prover(
     .await
 }
 
+/// Execution artifacts already on the filesystem of the server. The memory and trace can be
+/// larger than the RAM of the server: they are passed to the prover by path.
+#[derive(Debug)]
+pub struct ExecutionFiles {
+    pub public_input: PublicInput,
+    pub private_input: AirPrivateInput,
+    pub memory_file: PathBuf,
+    pub trace_file: PathBuf,
+}
+
+impl ExecutionFiles {
+    /// Loads the memory and trace in memory, for the backends that only prove artifacts.
+    pub fn read(&self) -> std::io::Result<ExecutionArtifacts> {
+        Ok(ExecutionArtifacts {
+            public_input: self.public_input.clone(),
+            private_input: self.private_input.clone(),
+            memory: std::fs::read(&self.memory_file)?,
+            trace: std::fs::read(&self.trace_file)?,
+        })
+    }
+}
+
+fn write_json_file<T: Serialize>(path: &Path, value: &T) -> Result<(), ProverError> {
+    let json = serde_json::to_vec(value).map_err(|e| ProverError::IoError(e.into()))?;
+    std::fs::write(path, json).map_err(ProverError::IoError)
+}
+
+/// Same as `call_prover`, with the memory and trace read by the prover from their files.
+///
+/// The working directory links to the memory and trace files instead of holding copies.
+/// The files must not change until the prover exits.
+pub async fn call_prover_with_files(
+    execution_files: &ExecutionFiles,
+    prover_config: &ProverConfig,
+    prover_parameters: &ProverParameters,
+) -> Result<(Proof, ProverWorkingDirectory), ProverError> {
+    let dir = tempfile::tempdir().map_err(ProverError::IoError)?;
+    let file = |name: &str| dir.path().join(name);
+    let working_dir = ProverWorkingDirectory {
+        public_input_file: file("public_input.json"),
+        private_input_file: file("private_input.json"),
+        memory_file: file("memory.bin"),
+        prover_config_file: file("prover_config.json"),
+        prover_parameter_file: file("prover_parameters.json"),
+        trace_file: file("trace.bin"),
+        proof_file: file("proof.json"),
+        annotations_file: None,
+        extra_annotations_file: None,
+        dir,
+    };
+
+    std::os::unix::fs::symlink(&execution_files.memory_file, &working_dir.memory_file)
+        .map_err(ProverError::IoError)?;
+    std::os::unix::fs::symlink(&execution_files.trace_file, &working_dir.trace_file)
+        .map_err(ProverError::IoError)?;
+    let private_input = execution_files.private_input.to_serializable(
+        working_dir.trace_file.to_string_lossy().into_owned(),
+        working_dir.memory_file.to_string_lossy().into_owned(),
+    );
+    write_json_file(
+        &working_dir.public_input_file,
+        &execution_files.public_input,
+    )?;
+    write_json_file(&working_dir.private_input_file, &private_input)?;
+    write_json_file(&working_dir.prover_config_file, prover_config)?;
+    write_json_file(&working_dir.prover_parameter_file, prover_parameters)?;
+
+    run_prover_from_command_line_async(
+        &working_dir.public_input_file,
+        &working_dir.private_input_file,
+        &working_dir.prover_config_file,
+        &working_dir.prover_parameter_file,
+        &working_dir.proof_file,
+    )
+    .await?;
+
+    let proof = std::fs::read(&working_dir.proof_file).map_err(ProverError::IoError)?;
+    let proof = serde_json::from_slice(&proof).map_err(|e| ProverError::IoError(e.into()))?;
+    Ok((proof, working_dir))
+}
+
+/// Annotations produced by the verifier, loaded in memory so that they outlive the working
+/// directory of the prover.
+#[derive(Debug)]
+pub struct VerifierAnnotations {
+    pub annotations: Vec<String>,
+    pub extra_annotations: Vec<String>,
+    /// Files the annotations were loaded from. They are deleted along with the working directory.
+    pub files: ProofAnnotations,
+}
+
+impl VerifierAnnotations {
+    pub fn load(files: ProofAnnotations) -> std::io::Result<Self> {
+        let annotations = evm_adapter::load_annotations_file(&files.annotation_file)?;
+        let extra_annotations = evm_adapter::load_annotations_file(&files.extra_output_file)?;
+
+        Ok(Self {
+            annotations,
+            extra_annotations,
+            files,
+        })
+    }
+}
+
+/// Proof generated by a prover backend.
+pub struct ProverOutput {
+    pub proof: Proof,
+    /// Working directory of the prover, holding the proof file. `None` if the backend does
+    /// not write the proof to a file.
+    pub working_dir: Option<ProverWorkingDirectory>,
+}
+
+impl ProverOutput {
+    /// Returns the proof as written by the prover.
+    fn proof_json(&self) -> Result<serde_json::Value, Status> {
+        match &self.working_dir {
+            Some(working_dir) => read_json_from_file(&working_dir.proof_file)
+                .map_err(|e| Status::internal(format!("Could not read proof file: {}", e))),
+            None => serde_json::to_value(&self.proof)
+                .map_err(|e| Status::internal(format!("Could not serialize proof: {}", e))),
+        }
+    }
+
+    /// Returns the proof file as written by the prover, so that it can be passed as-is to
+    /// `cpu_air_verifier`. `Proof` only has a subset of the fields of the file.
+    ///
+    /// The split proofs built by `verify_and_annotate_proof` are added to the file.
+    pub fn serialized_proof(&self) -> Result<String, Status> {
+        let serialize_error =
+            |_| Status::internal("Could not parse the proof returned by the prover");
+        match (&self.working_dir, &self.proof.split_proofs) {
+            (Some(working_dir), None) => std::fs::read_to_string(&working_dir.proof_file)
+                .map_err(|e| Status::internal(format!("Could not read proof file: {}", e))),
+            (Some(_), Some(split_proofs)) => {
+                let mut proof_json = self.proof_json()?;
+                proof_json["split_proofs"] =
+                    serde_json::to_value(split_proofs).map_err(serialize_error)?;
+                serde_json::to_string(&proof_json).map_err(serialize_error)
+            }
+            (None, _) => serde_json::to_string(&self.proof).map_err(serialize_error),
+        }
+    }
+}
+
+/// Generates and verifies the proofs of the services.
+///
+/// The server uses `StoneProverBackend`. Tests can use `MockProverBackend` instead, to run
+/// the handlers without the Stone binaries.
+#[tonic::async_trait]
+pub trait ProverBackend: Debug + Send + Sync {
+    async fn prove(
+        &self,
+        execution_artifacts: &ExecutionArtifacts,
+        prover_config: &ProverConfig,
+        prover_parameters: &ProverParameters,
+    ) -> Result<ProverOutput, ProverError>;
+
+    /// Same as `prove`, for artifacts on the filesystem. By default, the memory and trace
+    /// are read and passed to `prove`.
+    async fn prove_files(
+        &self,
+        execution_files: &ExecutionFiles,
+        prover_config: &ProverConfig,
+        prover_parameters: &ProverParameters,
+    ) -> Result<ProverOutput, ProverError> {
+        let execution_artifacts = execution_files.read().map_err(ProverError::IoError)?;
+        self.prove(&execution_artifacts, prover_config, prover_parameters)
+            .await
+    }
+
+    /// Verifies a proof returned by `prove` and returns the verifier annotations.
+    async fn verify_with_annotations(
+        &self,
+        prover_output: &mut ProverOutput,
+    ) -> Result<VerifierAnnotations, VerifierError>;
+}
+
+/// Runs `cpu_air_prover` and `cpu_air_verifier`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StoneProverBackend;
+
+#[tonic::async_trait]
+impl ProverBackend for StoneProverBackend {
+    async fn prove(
+        &self,
+        execution_artifacts: &ExecutionArtifacts,
+        prover_config: &ProverConfig,
+        prover_parameters: &ProverParameters,
+    ) -> Result<ProverOutput, ProverError> {
+        let (proof, working_dir) =
+            call_prover(execution_artifacts, prover_config, prover_parameters).await?;
+        Ok(ProverOutput {
+            proof,
+            working_dir: Some(working_dir),
+        })
+    }
+
+    /// Runs the prover on the memory and trace files, without loading them in memory.
+    async fn prove_files(
+        &self,
+        execution_files: &ExecutionFiles,
+        prover_config: &ProverConfig,
+        prover_parameters: &ProverParameters,
+    ) -> Result<ProverOutput, ProverError> {
+        let (proof, working_dir) =
+            call_prover_with_files(execution_files, prover_config, prover_parameters).await?;
+        Ok(ProverOutput {
+            proof,
+            working_dir: Some(working_dir),
+        })
+    }
+
+    async fn verify_with_annotations(
+        &self,
+        prover_output: &mut ProverOutput,
+    ) -> Result<VerifierAnnotations, VerifierError> {
+        match prover_output.working_dir.as_mut() {
+            Some(working_dir) => call_verifier(working_dir).await,
+            None => Err(VerifierError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "The proof was not written to a file",
+            ))),
+        }
+    }
+}
+
 pub async fn call_verifier(
     working_dir: &mut ProverWorkingDirectory,
-) -> Result<ProofAnnotations, VerifierError> {
+) -> Result<VerifierAnnotations, VerifierError> {
     let annotations_file = working_dir.dir.path().join("annotations_file.txt");
     let extra_annotations_file = working_dir.dir.path().join("extra_annotations_file.txt");
 
@@ -42,10 +276,18 @@ pub async fn call_verifier(
     )
     .await?;
 
-    Ok(ProofAnnotations {
+    let annotations = VerifierAnnotations::load(ProofAnnotations {
         annotation_file: annotations_file,
         extra_output_file: extra_annotations_file,
-    })
+    })?;
+
+    Ok(annotations)
+}
+
+/// Returns the JSON error wrapped in an IO error, if any. The SDK reports the files it fails
+/// to parse, ex: a malformed proof, as IO errors.
+fn json_error(io_error: &std::io::Error) -> Option<&serde_json::Error> {
+    io_error.get_ref()?.downcast_ref::<serde_json::Error>()
 }
 
 pub fn format_prover_error(e: ProverError) -> Status {
@@ -55,9 +297,12 @@ pub fn format_prover_error(e: ProverError) -> Status {
             prover_output.status,
             String::from_utf8_lossy(&prover_output.stderr),
         )),
-        ProverError::IoError(io_error) => {
-            Status::internal(format!("Could not run the prover: {}", io_error))
-        }
+        ProverError::IoError(io_error) => match json_error(&io_error) {
+            Some(json_error) => {
+                Status::internal(format!("Could not parse the prover output: {}", json_error))
+            }
+            None => Status::internal(format!("Could not run the prover: {}", io_error)),
+        },
         ProverError::SerdeError(serde_error) => Status::invalid_argument(format!(
             "Could not parse one or more arguments: {}",
             serde_error
@@ -65,65 +310,580 @@ pub fn format_prover_error(e: ProverError) -> Status {
     }
 }
 
+/// Log line of `cpu_air_verifier` when it rejects a proof. The reason is logged before it.
+const PROOF_REJECTED_MARKER: &str = "Invalid proof";
+
+/// Reason for which `cpu_air_verifier` failed, from its exit status and error output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifierFailure {
+    /// The proof is well-formed, but invalid.
+    ProofRejected { reason: String },
+    /// The proof file could not be parsed.
+    MalformedProof { reason: String },
+    /// The verifier crashed or failed for a reason unrelated to the proof.
+    Crashed { reason: String },
+}
+
+/// Returns the messages of the error and fatal log lines of a glog output, ex:
+/// `E0101 12:00:00.000000 1 verifier_main_helper.cc:42] <message>`. Uncaught exceptions
+/// are reported by the C++ runtime as `what(): <message>`.
+fn glog_error_messages(stderr: &str) -> Vec<&str> {
+    stderr
+        .lines()
+        .filter_map(|line| {
+            if let Some((_, message)) = line.split_once("what():") {
+                return Some(message.trim());
+            }
+            if !(line.starts_with('E') || line.starts_with('F')) {
+                return None;
+            }
+            line.split_once("] ").map(|(_, message)| message.trim())
+        })
+        .filter(|message| !message.is_empty())
+        .collect()
+}
+
+/// Classifies a failed run of `cpu_air_verifier`.
+pub fn classify_verifier_failure(verifier_output: &Output) -> VerifierFailure {
+    let stderr = String::from_utf8_lossy(&verifier_output.stderr);
+    let messages = glog_error_messages(&stderr);
+    let reason = |messages: &[&str]| match messages {
+        [] => stderr.trim().to_string(),
+        messages => messages.join("; "),
+    };
+
+    // Killed by a signal: the verifier crashed, whatever it logged before
+    if verifier_output.status.code().is_none() && !is_json_error(&stderr) {
+        return VerifierFailure::Crashed {
+            reason: format!("{}: {}", verifier_output.status, reason(&messages)),
+        };
+    }
+
+    if stderr.contains(PROOF_REJECTED_MARKER) {
+        let details: Vec<_> = messages
+            .iter()
+            .copied()
+            .filter(|message| !message.starts_with(PROOF_REJECTED_MARKER))
+            .collect();
+        let reason = match details.as_slice() {
+            [] => PROOF_REJECTED_MARKER.to_string(),
+            details => reason(details),
+        };
+        return VerifierFailure::ProofRejected { reason };
+    }
+    if is_json_error(&stderr) {
+        return VerifierFailure::MalformedProof {
+            reason: reason(&messages),
+        };
+    }
+
+    VerifierFailure::Crashed {
+        reason: format!("{}: {}", verifier_output.status, reason(&messages)),
+    }
+}
+
+/// Whether the verifier failed to parse its input. JSON errors are raised from
+/// `json_value.cc` in Stone.
+fn is_json_error(stderr: &str) -> bool {
+    stderr.contains("json_value") || stderr.to_lowercase().contains("parse")
+}
+
 pub fn format_verifier_error(e: VerifierError) -> Status {
     match e {
-        VerifierError::CommandError(verifier_output) => Status::invalid_argument(format!(
-            "Verifier run failed ({}): {}",
-            verifier_output.status,
-            String::from_utf8_lossy(&verifier_output.stderr),
-        )),
-        VerifierError::IoError(io_error) => {
-            Status::internal(format!("Could not run the verifier: {}", io_error))
+        VerifierError::CommandError(verifier_output) => {
+            match classify_verifier_failure(&verifier_output) {
+                VerifierFailure::ProofRejected { reason } => {
+                    Status::failed_precondition(format!("Proof rejected by the verifier: {reason}"))
+                }
+                VerifierFailure::MalformedProof { reason } => {
+                    Status::invalid_argument(format!("Malformed proof: {reason}"))
+                }
+                VerifierFailure::Crashed { reason } => {
+                    Status::internal(format!("Verifier run failed ({reason})"))
+                }
+            }
+        }
+        VerifierError::IoError(io_error) => match json_error(&io_error) {
+            Some(json_error) => Status::internal(format!(
+                "Could not parse the verifier output: {}",
+                json_error
+            )),
+            None => Status::internal(format!("Could not run the verifier: {}", io_error)),
+        },
+    }
+}
+
+pub fn format_split_proof_error(e: SplitProverError) -> Status {
+    match e {
+        SplitProverError::Io { .. } => {
+            Status::internal(format!("Unable to generate split proof: {}", e))
+        }
+        SplitProverError::InputParse { .. }
+        | SplitProverError::AnnotatedProofParse(_)
+        | SplitProverError::ProofParseError(_)
+        | SplitProverError::NoFriInnerLayers(_)
+        | SplitProverError::AnnotationMismatch(_) => {
+            Status::invalid_argument(format!("Unable to generate split proof: {}", e))
+        }
+        SplitProverError::FriStatementCount { .. } => {
+            Status::internal(format!("Unable to generate split proof: {}", e))
         }
     }
 }
 
+pub fn get_prover_config(user_provided_config: Option<String>) -> Result<ProverConfig, Status> {
+    if let Some(config_str) = user_provided_config {
+        return serde_json::from_str(&config_str)
+            .map_err(|_| Status::invalid_argument("Could not read prover config"));
+    }
+
+    Ok(ProverConfig::default())
+}
+
+/// Returns the parameters provided by the user if any, or the parameters generated from
+/// the preset of the layout.
 pub fn get_prover_parameters(
     user_provided_parameters: Option<String>,
     nb_steps: u32,
+    layout: &str,
+    parameter_presets: &ParameterPresets,
+    overrides: ParameterOverrides,
 ) -> Result<ProverParameters, Status> {
     if let Some(params_str) = user_provided_parameters {
         return serde_json::from_str(&params_str)
             .map_err(|_| Status::invalid_argument("Could not read prover parameters"));
     }
 
-    let last_layer_degree_bound = 64;
-    Ok(generate_prover_parameters(
-        nb_steps,
-        last_layer_degree_bound,
-    ))
+    parameter_presets
+        .prover_parameters(layout, nb_steps, overrides)
+        .map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// Returns the proof if it embeds annotations generated for it, ex: by
+/// `cpu_air_prover --generate-annotations`. Stale annotations are ignored.
+fn reusable_annotated_proof(proof_json: &serde_json::Value) -> Option<serde_json::Value> {
+    if !evm_adapter::has_embedded_annotations(proof_json) {
+        return None;
+    }
+    let annotations: Vec<String> =
+        serde_json::from_value(proof_json["annotations"].clone()).ok()?;
+
+    match evm_adapter::check_annotations_match_proof(proof_json, &annotations) {
+        Ok(()) => Some(proof_json.clone()),
+        Err(e) => {
+            tracing::warn!("Not reusing the annotations of the proof: {e}");
+            None
+        }
+    }
 }
 
-/// Calls `cpu_air_verifier` to verify the proof and produce annotations, then uses
-/// `stark-evm-adapter` to split the proof. The given Proof will then be modified to contain
-/// this additional split-proof.
+/// Verifies the proof to produce annotations, then uses `stark-evm-adapter` to split
+/// the proof. The proof of `prover_output` will then be modified to contain this additional
+/// split-proof.
+///
+/// With `reuse_annotations`, the annotations embedded in the proof are used instead of
+/// running the verifier, if they match the proof.
+///
+/// Returns the annotated proof, which is also written to `annotated_proof.json` in the working
+/// directory.
 pub async fn verify_and_annotate_proof(
-    proof: &mut Proof,
-    working_dir: &mut ProverWorkingDirectory,
-) -> Result<(), Status> {
-    let _ = // TODO: return type seems worthless here
-        call_verifier(working_dir)
+    prover_backend: &dyn ProverBackend,
+    prover_output: &mut ProverOutput,
+    reuse_annotations: bool,
+) -> Result<serde_json::Value, Status> {
+    let proof_json = prover_output.proof_json()?;
+
+    let reused_annotated_proof = if reuse_annotations {
+        reusable_annotated_proof(&proof_json)
+    } else {
+        None
+    };
+    let annotated_proof = match reused_annotated_proof {
+        Some(annotated_proof) => annotated_proof,
+        None => {
+            let verifier_annotations = prover_backend
+                .verify_with_annotations(prover_output)
+                .await
+                .map_err(format_verifier_error)?;
+            evm_adapter::build_annotated_proof(
+                &proof_json,
+                &verifier_annotations.annotations,
+                &verifier_annotations.extra_annotations,
+            )
+        }
+    };
+    if let Some(working_dir) = &prover_output.working_dir {
+        std::fs::write(
+            working_dir.dir.path().join("annotated_proof.json"),
+            annotated_proof.to_string(),
+        )
+        .map_err(|e| Status::internal(format!("Could not write annotated proof file: {}", e)))?;
+    }
+
+    let split_proof = evm_adapter::split_annotated_proof(annotated_proof.clone())
+        .map_err(format_split_proof_error)?;
+
+    prover_output.proof.split_proofs = Some(split_proof);
+
+    Ok(annotated_proof)
+}
+
+/// Prover backend returning canned proofs from `test-cases`.
+#[cfg(test)]
+pub mod mock {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::Mutex;
+
+    use test_cases::{get_test_case_file_path, load_test_case_file};
+
+    use super::*;
+
+    #[derive(Debug)]
+    pub struct MockProverBackend {
+        /// The proof as JSON, or the error output of the prover.
+        result: Result<String, String>,
+        /// Parameters of each call to `prove`.
+        calls: Mutex<Vec<serde_json::Value>>,
+        /// Whether to write the proof to a working directory, like `cpu_air_prover`.
+        with_working_dir: bool,
+    }
+
+    impl MockProverBackend {
+        /// Returns the proof of the specified test case file.
+        pub fn new(proof_file: &str) -> Self {
+            Self {
+                result: Ok(load_test_case_file(proof_file)),
+                calls: Mutex::new(vec![]),
+                with_working_dir: false,
+            }
+        }
+
+        /// Fails like `cpu_air_prover` exiting with `stderr`.
+        pub fn failing(stderr: &str) -> Self {
+            Self {
+                result: Err(stderr.to_string()),
+                calls: Mutex::new(vec![]),
+                with_working_dir: false,
+            }
+        }
+
+        /// Writes the proof to a working directory, like `cpu_air_prover`.
+        pub fn with_working_dir(mut self) -> Self {
+            self.with_working_dir = true;
+            self
+        }
+
+        /// Returns the canned proof, written to a working directory if configured.
+        pub fn output(&self) -> Result<ProverOutput, ProverError> {
+            let proof_str = match &self.result {
+                Ok(proof) => proof,
+                Err(stderr) => {
+                    return Err(ProverError::CommandError(Output {
+                        status: ExitStatus::from_raw(1 << 8),
+                        stdout: vec![],
+                        stderr: stderr.as_bytes().to_vec(),
+                    }))
+                }
+            };
+            let proof = serde_json::from_str(proof_str).expect("invalid canned proof");
+            if !self.with_working_dir {
+                return Ok(ProverOutput {
+                    proof,
+                    working_dir: None,
+                });
+            }
+
+            let dir = tempfile::tempdir().map_err(ProverError::IoError)?;
+            let file = |name: &str| dir.path().join(name);
+            let working_dir = ProverWorkingDirectory {
+                public_input_file: file("public_input.json"),
+                private_input_file: file("private_input.json"),
+                memory_file: file("memory.bin"),
+                prover_config_file: file("prover_config.json"),
+                prover_parameter_file: file("prover_parameters.json"),
+                trace_file: file("trace.bin"),
+                proof_file: file("proof.json"),
+                annotations_file: None,
+                extra_annotations_file: None,
+                dir,
+            };
+            std::fs::write(&working_dir.proof_file, proof_str).map_err(ProverError::IoError)?;
+            Ok(ProverOutput {
+                proof,
+                working_dir: Some(working_dir),
+            })
+        }
+
+        /// Prover parameters of each call to `prove`.
+        pub fn calls(&self) -> Vec<serde_json::Value> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    #[tonic::async_trait]
+    impl ProverBackend for MockProverBackend {
+        async fn prove(
+            &self,
+            _execution_artifacts: &ExecutionArtifacts,
+            _prover_config: &ProverConfig,
+            prover_parameters: &ProverParameters,
+        ) -> Result<ProverOutput, ProverError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(serde_json::to_value(prover_parameters).unwrap());
+
+            self.output()
+        }
+
+        async fn verify_with_annotations(
+            &self,
+            _prover_output: &mut ProverOutput,
+        ) -> Result<VerifierAnnotations, VerifierError> {
+            VerifierAnnotations::load(ProofAnnotations {
+                annotation_file: get_test_case_file_path(
+                    "bootloader/empty_bootloader_proof/annotations.txt",
+                ),
+                extra_output_file: get_test_case_file_path(
+                    "bootloader/empty_bootloader_proof/extra_annotations.txt",
+                ),
+            })
+            .map_err(VerifierError::IoError)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_cases::load_test_case_file;
+    use tonic::Code;
+
+    use super::*;
+
+    #[test]
+    fn test_format_prover_io_error() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::NotFound, "cpu_air_prover");
+
+        let status = format_prover_error(ProverError::IoError(io_error));
+
+        assert_eq!(status.code(), Code::Internal);
+        assert!(
+            status.message().starts_with("Could not run the prover"),
+            "{}",
+            status.message()
+        );
+    }
+
+    #[test]
+    fn test_format_prover_json_error() {
+        // `read_json_from_file` converts JSON errors into IO errors
+        let json_error = serde_json::from_str::<Proof>("{\"proof_hex\": ").unwrap_err();
+        let io_error = std::io::Error::from(json_error);
+
+        let status = format_prover_error(ProverError::IoError(io_error));
+
+        assert_eq!(status.code(), Code::Internal);
+        assert!(
+            status
+                .message()
+                .starts_with("Could not parse the prover output: EOF while parsing"),
+            "{}",
+            status.message()
+        );
+    }
+
+    #[test]
+    fn test_format_verifier_json_error() {
+        let json_error = serde_json::from_str::<serde_json::Value>("not json").unwrap_err();
+        let io_error = std::io::Error::from(json_error);
+
+        let status = format_verifier_error(VerifierError::IoError(io_error));
+
+        assert_eq!(status.code(), Code::Internal);
+        assert!(
+            status
+                .message()
+                .starts_with("Could not parse the verifier output"),
+            "{}",
+            status.message()
+        );
+        assert!(status.message().contains("line 1 column 1"));
+    }
+
+    fn verifier_output(status: i32, stderr: &str) -> Output {
+        use std::os::unix::process::ExitStatusExt;
+
+        Output {
+            status: std::process::ExitStatus::from_raw(status),
+            stdout: vec![],
+            stderr: stderr.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_format_verifier_proof_rejected() {
+        let output = verifier_output(
+            1 << 8,
+            "I0101 12:00:00.000000 1 cpu_air_verifier_main.cc:40] Verifying proof\n\
+             E0101 12:00:01.000000 1 verifier_main_helper.cc:63] Fri layer 2: Wrong decommitment.\n\
+             E0101 12:00:01.000000 1 cpu_air_verifier_main.cc:88] Invalid proof.\n",
+        );
+        assert_eq!(
+            classify_verifier_failure(&output),
+            VerifierFailure::ProofRejected {
+                reason: "Fri layer 2: Wrong decommitment.".to_string()
+            }
+        );
+
+        let status = format_verifier_error(VerifierError::CommandError(output));
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert!(status.message().contains("Wrong decommitment"));
+    }
+
+    #[test]
+    fn test_format_verifier_malformed_proof() {
+        let output = verifier_output(
+            6,
+            "terminate called after throwing an instance of 'starkware::StarkwareException'\n\
+             \x20 what():  src/starkware/utils/json_value.cc:30: Failed to parse JSON\n",
+        );
+
+        let status = format_verifier_error(VerifierError::CommandError(output));
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status.message().contains("Failed to parse JSON"),
+            "{}",
+            status.message()
+        );
+    }
+
+    #[test]
+    fn test_format_verifier_crash() {
+        // SIGSEGV, without any log
+        let status = format_verifier_error(VerifierError::CommandError(verifier_output(11, "")));
+        assert_eq!(status.code(), Code::Internal);
+        assert!(status.message().starts_with("Verifier run failed"));
+
+        let output = verifier_output(
+            1 << 8,
+            "F0101 12:00:00.000000 1 main.cc:10] Out of memory\n",
+        );
+        assert_eq!(
+            classify_verifier_failure(&output),
+            VerifierFailure::Crashed {
+                reason: "exit status: 1: Out of memory".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn user_provided_parameters_override_presets() {
+        let params_str = load_test_case_file("fibonacci/cpu_air_params.json");
+        let presets = ParameterPresets::default();
+
+        let overrides = ParameterOverrides {
+            max_fri_step: Some(2),
+            last_layer_degree_bound: Some(32),
+        };
+
+        let parameters = get_prover_parameters(
+            Some(params_str.clone()),
+            1 << 14,
+            "recursive",
+            &presets,
+            overrides,
+        )
+        .unwrap();
+
+        let expected: serde_json::Value = serde_json::from_str(&params_str).unwrap();
+        assert_eq!(serde_json::to_value(parameters).unwrap(), expected);
+    }
+
+    #[test]
+    fn verifier_annotations_outlive_working_dir() {
+        let working_dir = tempfile::tempdir().unwrap();
+        let annotation_file = working_dir.path().join("annotations_file.txt");
+        let extra_output_file = working_dir.path().join("extra_annotations_file.txt");
+        std::fs::write(&annotation_file, "first\nsecond\n").unwrap();
+        std::fs::write(&extra_output_file, "extra\n").unwrap();
+
+        let verifier_annotations = VerifierAnnotations::load(ProofAnnotations {
+            annotation_file: annotation_file.clone(),
+            extra_output_file,
+        })
+        .unwrap();
+        drop(working_dir);
+
+        assert!(!annotation_file.exists());
+        assert_eq!(verifier_annotations.annotations, vec!["first", "second"]);
+        assert_eq!(verifier_annotations.extra_annotations, vec!["extra"]);
+    }
+
+    #[test]
+    fn verifier_annotations_missing_file() {
+        let working_dir = tempfile::tempdir().unwrap();
+
+        let result = VerifierAnnotations::load(ProofAnnotations {
+            annotation_file: working_dir.path().join("annotations_file.txt"),
+            extra_output_file: working_dir.path().join("extra_annotations_file.txt"),
+        });
+
+        assert!(result.is_err());
+    }
+
+    fn annotated_proof() -> serde_json::Value {
+        serde_json::from_str(&load_test_case_file(
+            "bootloader/empty_bootloader_proof/annotated_proof.json",
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn serialized_proof_includes_split_proofs() {
+        let prover_backend =
+            mock::MockProverBackend::new("bootloader/empty_bootloader_proof/annotated_proof.json")
+                .with_working_dir();
+        let mut prover_output = prover_backend.output().unwrap();
+
+        verify_and_annotate_proof(&prover_backend, &mut prover_output, false)
             .await
-            .map_err(format_verifier_error)?;
-
-    let proof_file_path = working_dir.proof_file.as_path();
-    let annotations_file_path = working_dir
-        .annotations_file
-        .clone()
-        .ok_or(Status::internal("Expected annotations_file_path"))?;
-    let extra_annotations_file_path = working_dir
-        .extra_annotations_file
-        .clone()
-        .ok_or(Status::internal("Expected extra_annotations_file_path"))?;
-
-    let split_proof = evm_adapter::split_proof(
-        proof_file_path,
-        annotations_file_path.as_path(),
-        extra_annotations_file_path.as_path(),
-    )
-    .map_err(|_| Status::internal("Unable to generate split proof"))?;
+            .unwrap();
+
+        let proof: serde_json::Value =
+            serde_json::from_str(&prover_output.serialized_proof().unwrap()).unwrap();
+        assert!(
+            proof["split_proofs"].is_object(),
+            "{}",
+            proof["split_proofs"]
+        );
+        // The other fields of the proof file are kept
+        assert_eq!(proof["proof_hex"], annotated_proof()["proof_hex"]);
+        assert_eq!(proof["annotations"], annotated_proof()["annotations"]);
+    }
+
+    #[test]
+    fn embedded_annotations_are_reused() {
+        let proof_json = annotated_proof();
+
+        assert_eq!(reusable_annotated_proof(&proof_json), Some(proof_json));
+    }
+
+    #[test]
+    fn stale_embedded_annotations_are_not_reused() {
+        let mut proof_json = annotated_proof();
+        // Annotations of a proof of a different size
+        let proof_hex = proof_json["proof_hex"].as_str().unwrap();
+        proof_json["proof_hex"] = format!("{proof_hex}00").into();
 
-    proof.split_proofs = Some(split_proof);
+        assert_eq!(reusable_annotated_proof(&proof_json), None);
+    }
 
-    Ok(())
+    #[test]
+    fn proof_without_annotations_is_not_reused() {
+        let mut proof_json = annotated_proof();
+        proof_json.as_object_mut().unwrap().remove("annotations");
+
+        assert_eq!(reusable_annotated_proof(&proof_json), None);
+    }
 }
@@ -1,3 +1,5 @@
 mod common;
 pub mod prover;
 pub mod starknet_prover;
+
+pub use common::{classify_verifier_failure, format_verifier_error, VerifierFailure};
@@ -1,148 +1,1891 @@
 use cairo_vm::air_private_input::{AirPrivateInput, AirPrivateInputSerializable};
+use cairo_vm::cairo_run::{cairo_run, CairoRunConfig};
+use prost::bytes::Bytes;
+use sha3::{Digest, Keccak256};
+use std::io::Read;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio_stream::Stream;
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
 
+use crate::builtin_usage::{suggest_layout, BuiltinUsageReport};
 use crate::cairo::execution_error_to_status;
+use crate::checksum::{sha256_hex, verify_sha256};
+use crate::estimate::ProofEstimator;
+use crate::health::{check_disk_space_for_proof, ProverBinary};
+use crate::hints::{hint_processor, normalize_program_hints};
+use crate::history::{Job, JobHistory, JobSummary};
+use crate::idempotency::{idempotency_key, request_hash, IdempotencyCache};
+use crate::input_limits::InputLimits;
+use crate::limiter::{
+    acquire_proof_permit, check_proof_n_steps, classify_proof, ProofClass, ProofLimiter,
+};
+use crate::local_files::{file_size, read_file, FileRoots};
+use crate::parameters::{ParameterOverrides, ParameterPresets};
+use crate::policy::ProverPolicy;
+use crate::public_input::validate_public_input;
 use crate::services::common;
-use crate::services::common::format_prover_error;
+use crate::services::common::{
+    format_prover_error, ExecutionFiles, ProverBackend, ProverOutput, StoneProverBackend,
+};
+use crate::services::prover::prover_proto::execution_stream_response::Message;
 use crate::services::prover::prover_proto::prover_server::Prover;
 use crate::services::prover::prover_proto::{
-    ExecutionRequest, ExecutionResponse, ProverRequest, ProverResponse,
-};
-use stone_prover_sdk::cairo_vm::{
-    extract_execution_artifacts, run_in_proof_mode, ExecutionArtifacts, ExecutionError,
+    Capabilities, EstimateProofRequest, EstimateProofResponse, ExecutionChunk, ExecutionChunkKind,
+    ExecutionHeader, ExecutionRequest, ExecutionResponse, ExecutionStreamResponse,
+    GetCapabilitiesRequest, ListRecentJobsRequest, ListRecentJobsResponse, ProveFromFilesRequest,
+    ProverRequest, ProverResponse,
 };
+use crate::storage::{archive_proof, ProofStorage, StoredProof};
+use crate::workspace::{WorkingDirectoryExt, WorkspaceTracker, WorkspaceUsage};
+use stone_prover_sdk::cairo_vm::{extract_execution_artifacts, ExecutionArtifacts, ExecutionError};
 use stone_prover_sdk::error::ProverError;
-use stone_prover_sdk::models::{Layout, Proof, ProverConfig, ProverWorkingDirectory};
+use stone_prover_sdk::models::{ProverConfig, ProverParameters, PublicInput};
 
 pub mod prover_proto {
     tonic::include_proto!("prover");
 }
 
-fn run_cairo_program_in_proof_mode(
+/// Runs a Cairo program in proof mode with the hints of the specified profile.
+pub fn run_cairo_program_in_proof_mode(
     program: &[u8],
-    layout: Layout,
-) -> Result<ExecutionArtifacts, ExecutionError> {
-    let allow_missing_builtins = Some(false);
-    let (cairo_runner, vm) = run_in_proof_mode(program, layout, allow_missing_builtins)?;
-    extract_execution_artifacts(cairo_runner, vm)
+    layout: &str,
+    hint_profile: Option<&str>,
+) -> Result<ExecutionArtifacts, Status> {
+    let mut hint_processor =
+        hint_processor(hint_profile).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+    let cairo_run_config = CairoRunConfig {
+        entrypoint: "main",
+        trace_enabled: true,
+        relocate_mem: true,
+        layout,
+        proof_mode: true,
+        secure_run: None,
+        disable_trace_padding: false,
+        allow_missing_builtins: Some(false),
+    };
+
+    let program = normalize_program_hints(program, &mut hint_processor);
+
+    cairo_run(&program, &cairo_run_config, &mut hint_processor)
+        .map_err(ExecutionError::RunFailed)
+        .and_then(|(cairo_runner, vm)| extract_execution_artifacts(cairo_runner, vm))
+        .map_err(execution_error_to_status)
+}
+
+impl From<BuiltinUsageReport> for prover_proto::BuiltinUsageReport {
+    fn from(report: BuiltinUsageReport) -> Self {
+        let suggested_layout = suggest_layout(&report).unwrap_or_default().to_string();
+        Self {
+            builtins: report
+                .builtins
+                .into_iter()
+                .map(|builtin| prover_proto::BuiltinUsage {
+                    name: builtin.name,
+                    segment_size: builtin.segment_size,
+                    instances: builtin.instances,
+                })
+                .collect(),
+            n_steps: report.n_steps,
+            rc_min: report.rc_min,
+            rc_max: report.rc_max,
+            suggested_layout,
+        }
+    }
+}
+
+fn builtin_usage(public_input: &PublicInput) -> Option<prover_proto::BuiltinUsageReport> {
+    Some(BuiltinUsageReport::from_public_input(public_input).into())
+}
+
+/// SHA-256 digests of the memory and trace of an execution, as hex strings.
+struct ArtifactsSha256 {
+    memory: String,
+    trace: String,
+}
+
+/// Computes the SHA-256 digests of the memory and trace of an execution on a blocking thread.
+async fn artifacts_sha256(
+    artifacts: ExecutionArtifacts,
+) -> Result<(ExecutionArtifacts, ArtifactsSha256), Status> {
+    tokio::task::spawn_blocking(move || {
+        let sha256 = ArtifactsSha256 {
+            memory: sha256_hex(&artifacts.memory),
+            trace: sha256_hex(&artifacts.trace),
+        };
+        (artifacts, sha256)
+    })
+    .await
+    .map_err(|e| Status::internal(format!("Could not hash the execution artifacts: {e}")))
+}
+
+/// Verifies the SHA-256 digests of the memory and trace of a `Prove` request on a blocking
+/// thread.
+async fn verify_artifacts_sha256(
+    memory: Bytes,
+    trace: Bytes,
+    memory_sha256: Option<String>,
+    trace_sha256: Option<String>,
+) -> Result<(), Status> {
+    tokio::task::spawn_blocking(move || {
+        verify_sha256("Memory", &memory, memory_sha256.as_deref())?;
+        verify_sha256("Trace", &trace, trace_sha256.as_deref())
+    })
+    .await
+    .unwrap_or_else(|e| {
+        Err(Status::internal(format!(
+            "Could not hash the execution artifacts: {e}"
+        )))
+    })
 }
 
 fn format_execution_result(
-    execution_result: Result<ExecutionArtifacts, ExecutionError>,
+    artifacts: ExecutionArtifacts,
+    sha256: ArtifactsSha256,
 ) -> Result<ExecutionResponse, Status> {
-    match execution_result {
-        Ok(artifacts) => serde_json::to_string(&artifacts.public_input)
-            .map(|public_input_str| ExecutionResponse {
-                public_input: public_input_str,
-                memory: artifacts.memory,
-                trace: artifacts.trace,
-            })
-            .map_err(|_| Status::internal("Failed to serialize public input")),
-        Err(e) => Err(execution_error_to_status(e)),
+    serde_json::to_vec(&artifacts.public_input)
+        .map(|public_input| ExecutionResponse {
+            public_input,
+            memory_sha256: sha256.memory,
+            trace_sha256: sha256.trace,
+            memory: artifacts.memory.into(),
+            trace: artifacts.trace.into(),
+            builtin_usage: builtin_usage(&artifacts.public_input),
+        })
+        .map_err(|_| Status::internal("Failed to serialize public input"))
+}
+
+/// Size of the memory and trace chunks sent by `ExecuteStreamed`.
+pub const EXECUTION_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Splits `data` into chunks of at most `chunk_size` bytes, tagged with their offset.
+/// Chunks are only copied out of `data` when the iterator is consumed.
+fn data_chunks(
+    data: Vec<u8>,
+    kind: ExecutionChunkKind,
+    chunk_size: usize,
+) -> impl Iterator<Item = ExecutionStreamResponse> {
+    (0..data.len()).step_by(chunk_size).map(move |offset| {
+        let end = std::cmp::min(offset + chunk_size, data.len());
+        ExecutionStreamResponse {
+            message: Some(Message::Chunk(ExecutionChunk {
+                kind: kind.into(),
+                offset: offset as u64,
+                data: data[offset..end].to_vec(),
+            })),
+        }
+    })
+}
+
+/// Converts the execution artifacts into a header message followed by the memory chunks,
+/// then the trace chunks.
+fn format_execution_stream(
+    artifacts: ExecutionArtifacts,
+    sha256: ArtifactsSha256,
+    chunk_size: usize,
+) -> Result<impl Iterator<Item = ExecutionStreamResponse>, Status> {
+    let public_input = serde_json::to_vec(&artifacts.public_input)
+        .map_err(|_| Status::internal("Failed to serialize public input"))?;
+    let header = ExecutionStreamResponse {
+        message: Some(Message::Header(ExecutionHeader {
+            public_input,
+            memory_size: artifacts.memory.len() as u64,
+            trace_size: artifacts.trace.len() as u64,
+            builtin_usage: builtin_usage(&artifacts.public_input),
+            memory_sha256: sha256.memory,
+            trace_sha256: sha256.trace,
+        })),
+    };
+
+    Ok(std::iter::once(header)
+        .chain(data_chunks(
+            artifacts.memory,
+            ExecutionChunkKind::Memory,
+            chunk_size,
+        ))
+        .chain(data_chunks(
+            artifacts.trace,
+            ExecutionChunkKind::Trace,
+            chunk_size,
+        )))
+}
+
+/// Input of `input_digest`: bytes in memory, or the content of a file.
+enum DigestInput<'a> {
+    Bytes(&'a [u8]),
+    File(&'a Path),
+}
+
+/// Serializes the JSON inputs of the prover in the canonical form of serde_json, with
+/// sorted keys.
+fn canonical_json_inputs(
+    public_input: &PublicInput,
+    prover_config: &ProverConfig,
+    prover_parameters: &ProverParameters,
+) -> Result<[Vec<u8>; 3], Status> {
+    let to_json = |value: serde_json::Result<serde_json::Value>| {
+        value
+            .and_then(|value| serde_json::to_vec(&value))
+            .map_err(|e| Status::internal(format!("Could not serialize the prover inputs: {e}")))
+    };
+    Ok([
+        to_json(serde_json::to_value(public_input))?,
+        to_json(serde_json::to_value(prover_config))?,
+        to_json(serde_json::to_value(prover_parameters))?,
+    ])
+}
+
+fn digest_inputs(inputs: &[DigestInput]) -> std::io::Result<String> {
+    let mut hasher = Keccak256::new();
+    for input in inputs {
+        // Length-prefixed, so that moving bytes from one input to the next changes the digest
+        match input {
+            DigestInput::Bytes(bytes) => {
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(bytes);
+            }
+            DigestInput::File(path) => {
+                let mut file = std::fs::File::open(path)?;
+                hasher.update(file.metadata()?.len().to_be_bytes());
+                let mut buffer = vec![0; 1024 * 1024];
+                loop {
+                    let n = file.read(&mut buffer)?;
+                    if n == 0 {
+                        break;
+                    }
+                    hasher.update(&buffer[..n]);
+                }
+            }
+        }
     }
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok(format!("0x{digest}"))
+}
+
+/// Computes the digest of the inputs of the prover, to correlate a proof to its inputs.
+///
+/// The JSON inputs are hashed once parsed, in the canonical form of serde_json with sorted
+/// keys: inputs that only differ by their formatting have the same digest. The memory and
+/// trace are hashed on a blocking thread.
+pub async fn input_digest(
+    execution_artifacts: Arc<ExecutionArtifacts>,
+    prover_config: &ProverConfig,
+    prover_parameters: &ProverParameters,
+) -> Result<String, Status> {
+    let [public_input, prover_config, prover_parameters] = canonical_json_inputs(
+        &execution_artifacts.public_input,
+        prover_config,
+        prover_parameters,
+    )?;
+    tokio::task::spawn_blocking(move || {
+        digest_inputs(&[
+            DigestInput::Bytes(&public_input),
+            DigestInput::Bytes(&execution_artifacts.memory),
+            DigestInput::Bytes(&execution_artifacts.trace),
+            DigestInput::Bytes(&prover_config),
+            DigestInput::Bytes(&prover_parameters),
+        ])
+    })
+    .await
+    .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+    .map_err(|e| Status::internal(format!("Could not hash the prover inputs: {e}")))
+}
+
+/// Same as `input_digest`, reading the memory and trace from their files. The digest is
+/// the same as for the artifacts in memory.
+pub async fn input_digest_from_files(
+    execution_files: &ExecutionFiles,
+    prover_config: &ProverConfig,
+    prover_parameters: &ProverParameters,
+) -> Result<String, Status> {
+    let [public_input, prover_config, prover_parameters] = canonical_json_inputs(
+        &execution_files.public_input,
+        prover_config,
+        prover_parameters,
+    )?;
+    let memory_file = execution_files.memory_file.clone();
+    let trace_file = execution_files.trace_file.clone();
+    tokio::task::spawn_blocking(move || {
+        digest_inputs(&[
+            DigestInput::Bytes(&public_input),
+            DigestInput::File(&memory_file),
+            DigestInput::File(&trace_file),
+            DigestInput::Bytes(&prover_config),
+            DigestInput::Bytes(&prover_parameters),
+        ])
+    })
+    .await
+    .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+    .map_err(|e| Status::internal(format!("Could not hash the prover inputs: {e}")))
 }
 
 /// Formats the output of the prover subprocess into the server response.
 fn format_prover_result(
-    prover_result: Result<(Proof, ProverWorkingDirectory), ProverError>,
+    prover_result: Result<ProverOutput, ProverError>,
+    input_digest: String,
 ) -> Result<ProverResponse, Status> {
     match prover_result {
-        Ok((proof, _)) => serde_json::to_string(&proof)
-            .map(|proof_str| ProverResponse { proof: proof_str })
-            .map_err(|_| Status::internal("Could not parse the proof returned by the prover")),
+        Ok(prover_output) => prover_output
+            .serialized_proof()
+            .map(|proof_str| ProverResponse {
+                proof: proof_str,
+                input_digest,
+                storage_ref: None,
+            }),
         Err(e) => Err(format_prover_error(e)),
     }
 }
 
-fn get_prover_config(user_provided_config: Option<String>) -> Result<ProverConfig, Status> {
-    if let Some(config_str) = user_provided_config {
-        return serde_json::from_str(&config_str)
-            .map_err(|_| Status::invalid_argument("Could not read prover config"));
+impl From<JobSummary> for prover_proto::JobSummary {
+    fn from(job: JobSummary) -> Self {
+        let millis = |duration: std::time::Duration| duration.as_millis() as u64;
+        Self {
+            request_id: job.request_id,
+            rpc: job.rpc.to_string(),
+            layout: job.layout,
+            n_steps: job.n_steps,
+            started_at_ms: job
+                .started_at
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(millis)
+                .unwrap_or_default(),
+            duration_ms: millis(job.duration),
+            prove_duration_ms: job.prove_duration.map(millis),
+            status_code: job.status_code as i32,
+            status_message: job.status_message,
+            proof_size: job.proof_size,
+            proof_class: job
+                .proof_class
+                .map(|proof_class| proof_class.as_str().to_string())
+                .unwrap_or_default(),
+            queue_duration_ms: millis(job.queue_duration),
+        }
     }
+}
 
-    Ok(ProverConfig::default())
+impl From<ProverBinary> for prover_proto::ProverBinary {
+    fn from(binary: ProverBinary) -> Self {
+        Self {
+            name: binary.name.to_string(),
+            path: binary.path.to_string_lossy().into_owned(),
+            version: binary.version,
+        }
+    }
 }
 
-#[derive(Debug, Default)]
-pub struct ProverService {}
+impl From<WorkspaceUsage> for prover_proto::WorkspaceUsage {
+    fn from(usage: WorkspaceUsage) -> Self {
+        Self {
+            live_workspaces: usage.live_workspaces as u64,
+            bytes_used: usage.bytes_used,
+        }
+    }
+}
 
-#[tonic::async_trait]
-impl Prover for ProverService {
-    async fn execute(
+impl From<InputLimits> for prover_proto::InputLimits {
+    fn from(limits: InputLimits) -> Self {
+        Self {
+            max_program_size: limits.max_program_size,
+            max_pie_size: limits.max_pie_size,
+            max_pie_uncompressed_size: limits.max_pie_uncompressed_size,
+            max_memory_size: limits.max_memory_size,
+            max_trace_size: limits.max_trace_size,
+        }
+    }
+}
+
+impl From<&ProofLimiter> for prover_proto::ProofLimits {
+    fn from(limiter: &ProofLimiter) -> Self {
+        Self {
+            max_concurrent_proofs: limiter.max_concurrent_proofs() as u64,
+            max_queued_proofs: limiter.max_queued_proofs() as u64,
+            reserved_small_proofs: limiter.reserved_slots() as u64,
+            small_proof_max_n_steps: limiter.small_proof_max_n_steps(),
+        }
+    }
+}
+
+impl From<ProverPolicy> for prover_proto::ProverPolicy {
+    fn from(policy: ProverPolicy) -> Self {
+        Self {
+            allowed_layouts: policy
+                .allowed_layouts
+                .map(|layouts| prover_proto::LayoutList { layouts }),
+            max_n_steps: policy.max_n_steps,
+            allow_split_proof: policy.allow_split_proof,
+            allow_user_parameters: policy.allow_user_parameters,
+            allow_small_proof: policy.allow_small_proof,
+        }
+    }
+}
+
+/// Parses the private input and prover config of a `Prove` request.
+fn parse_prover_inputs(
+    private_input: &str,
+    prover_config: &str,
+) -> Result<(AirPrivateInput, ProverConfig), Status> {
+    let private_input: AirPrivateInputSerializable = serde_json::from_str(private_input)
+        .map_err(|_| Status::invalid_argument("Could not deserialize private input"))?;
+    let prover_config = serde_json::from_str(prover_config)
+        .map_err(|_| Status::invalid_argument("Could not deserialize prover config"))?;
+    Ok((AirPrivateInput::from(private_input), prover_config))
+}
+
+/// Inputs of a proof: artifacts in memory, or files for `ProveFromFiles`.
+#[derive(Clone, Copy)]
+enum ProverInputs<'a> {
+    Artifacts(&'a ExecutionArtifacts),
+    Files(&'a ExecutionFiles),
+}
+
+impl ProverInputs<'_> {
+    fn public_input(&self) -> &PublicInput {
+        match self {
+            ProverInputs::Artifacts(execution_artifacts) => &execution_artifacts.public_input,
+            ProverInputs::Files(execution_files) => &execution_files.public_input,
+        }
+    }
+}
+
+/// Layout used to run programs submitted to the Prover service.
+const PROVER_LAYOUT: &str = "starknet_with_keccak";
+
+#[derive(Debug)]
+pub struct ProverService {
+    prover_backend: Arc<dyn ProverBackend>,
+    idempotency_cache: IdempotencyCache<ProverResponse>,
+    proof_limiter: Option<Arc<ProofLimiter>>,
+    parameter_presets: ParameterPresets,
+    proof_estimator: ProofEstimator,
+    job_history: Arc<JobHistory>,
+    workspace_tracker: Arc<WorkspaceTracker>,
+    input_limits: InputLimits,
+    policy: ProverPolicy,
+    /// Roots of the files readable by `ProveFromFiles`, `None` if the RPC is disabled.
+    file_roots: Option<FileRoots>,
+    proof_storage: Option<Arc<dyn ProofStorage>>,
+    /// Prover binaries found at startup, reported by `GetCapabilities`.
+    prover_binaries: Vec<ProverBinary>,
+}
+
+impl Default for ProverService {
+    fn default() -> Self {
+        Self {
+            prover_backend: Arc::new(StoneProverBackend),
+            idempotency_cache: IdempotencyCache::default(),
+            proof_limiter: None,
+            parameter_presets: ParameterPresets::default(),
+            proof_estimator: ProofEstimator::default(),
+            job_history: Arc::new(JobHistory::default()),
+            workspace_tracker: Arc::new(WorkspaceTracker::default()),
+            input_limits: InputLimits::default(),
+            policy: ProverPolicy::default(),
+            file_roots: None,
+            proof_storage: None,
+            prover_binaries: vec![],
+        }
+    }
+}
+
+impl ProverService {
+    /// Replaces the backend generating the proofs.
+    pub fn with_prover_backend(mut self, prover_backend: Arc<dyn ProverBackend>) -> Self {
+        self.prover_backend = prover_backend;
+        self
+    }
+
+    /// Limits the number of concurrent proofs.
+    pub fn with_proof_limiter(mut self, proof_limiter: Arc<ProofLimiter>) -> Self {
+        self.proof_limiter = Some(proof_limiter);
+        self
+    }
+
+    /// Reports the prover binaries found at startup in `GetCapabilities`.
+    pub fn with_prover_binaries(mut self, prover_binaries: Vec<ProverBinary>) -> Self {
+        self.prover_binaries = prover_binaries;
+        self
+    }
+
+    /// Archives the generated proofs in the specified storage.
+    pub fn with_proof_storage(mut self, proof_storage: Arc<dyn ProofStorage>) -> Self {
+        self.proof_storage = Some(proof_storage);
+        self
+    }
+
+    /// Replaces the default prover parameter presets.
+    pub fn with_parameter_presets(mut self, parameter_presets: ParameterPresets) -> Self {
+        self.parameter_presets = parameter_presets;
+        self
+    }
+
+    /// Records the completed jobs in the specified history, to share it with other services.
+    pub fn with_job_history(mut self, job_history: Arc<JobHistory>) -> Self {
+        self.job_history = job_history;
+        self
+    }
+
+    /// Registers the working directories of the prover in the specified tracker, to share it
+    /// with other services.
+    pub fn with_workspace_tracker(mut self, workspace_tracker: Arc<WorkspaceTracker>) -> Self {
+        self.workspace_tracker = workspace_tracker;
+        self
+    }
+
+    /// Replaces the default size limits of the programs, memory and trace.
+    pub fn with_input_limits(mut self, input_limits: InputLimits) -> Self {
+        self.input_limits = input_limits;
+        self
+    }
+
+    /// Restricts the proofs of the service, see `ProverPolicy`.
+    pub fn with_policy(mut self, policy: ProverPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Enables `ProveFromFiles`, for the files within `file_roots`.
+    pub fn with_prove_from_files(mut self, file_roots: FileRoots) -> Self {
+        self.file_roots = Some(file_roots);
+        self
+    }
+
+    /// Runs the prover and records its duration to calibrate the proof estimates.
+    async fn call_prover(
         &self,
-        request: Request<ExecutionRequest>,
-    ) -> Result<Response<ExecutionResponse>, Status> {
-        let execution_request = request.into_inner();
+        inputs: ProverInputs<'_>,
+        prover_config: &ProverConfig,
+        prover_parameters: &ProverParameters,
+        job: &mut Job,
+    ) -> Result<ProverOutput, ProverError> {
+        let public_input = inputs.public_input();
+        job.layout = public_input.layout.clone();
+        job.n_steps = public_input.n_steps;
+
+        let prove_span = tracing::info_span!(
+            "prove",
+            layout = %public_input.layout,
+            n_steps = public_input.n_steps,
+        );
+        let started_at = Instant::now();
+        let prove = match inputs {
+            ProverInputs::Artifacts(execution_artifacts) => {
+                self.prover_backend
+                    .prove(execution_artifacts, prover_config, prover_parameters)
+            }
+            ProverInputs::Files(execution_files) => {
+                self.prover_backend
+                    .prove_files(execution_files, prover_config, prover_parameters)
+            }
+        };
+        let prover_result = prove.instrument(prove_span).await;
+        let prove_duration = started_at.elapsed();
+        job.prove_duration = Some(prove_duration);
+        if let Ok(ProverOutput {
+            working_dir: Some(working_dir),
+            ..
+        }) = &prover_result
+        {
+            self.workspace_tracker
+                .register(working_dir.path(), job.request_id());
+        }
+        if prover_result.is_ok() {
+            self.proof_estimator.record(
+                &public_input.layout,
+                public_input.n_steps,
+                prover_parameters,
+                prove_duration,
+            );
+        }
+        prover_result
+    }
 
-        let layout = Layout::StarknetWithKeccak;
-        let execution_result = run_cairo_program_in_proof_mode(&execution_request.program, layout);
-        let execution_result = format_execution_result(execution_result);
+    /// Archives the proof of a response if proof storage is configured. A storage failure
+    /// leaves the response without a storage reference instead of failing the request.
+    async fn store_proof(&self, response: &mut ProverResponse, public_input: &PublicInput) {
+        let Some(proof_storage) = &self.proof_storage else {
+            return;
+        };
+        let stored_proof = StoredProof {
+            proof: response.proof.clone(),
+            public_input: serde_json::to_string(public_input).ok(),
+            annotated_proof: None,
+        };
+        response.storage_ref = archive_proof(proof_storage.as_ref(), &stored_proof)
+            .await
+            .map(|storage_ref| storage_ref.0);
+    }
 
-        execution_result.map(Response::new)
+    /// Records a completed job in the history.
+    fn record_job(&self, job: Job, result: &Result<ProverResponse, Status>) {
+        self.job_history
+            .record(job.finish(result, |response| response.proof.len() as u64));
     }
 
-    async fn prove(
+    /// Parses the public input of a `Prove` request and checks it against the policy.
+    fn parse_public_input(&self, public_input_bytes: &[u8]) -> Result<PublicInput, Status> {
+        let public_input: PublicInput = serde_json::from_slice(public_input_bytes)
+            .map_err(|_| Status::invalid_argument("Could not deserialize public input"))?;
+        validate_public_input(&public_input)
+            .map_err(|e| Status::invalid_argument(format!("Invalid public input: {e}")))?;
+        self.policy.check_layout(&public_input.layout)?;
+        self.policy.check_n_steps(public_input.n_steps)?;
+        Ok(public_input)
+    }
+
+    async fn run_prove(
         &self,
-        request: Request<ProverRequest>,
-    ) -> Result<Response<ProverResponse>, Status> {
+        request: ProverRequest,
+        job: &mut Job,
+    ) -> Result<ProverResponse, Status> {
         let ProverRequest {
-            public_input: public_input_str,
+            public_input: public_input_bytes,
             private_input: private_input_str,
             memory,
             trace,
             prover_config: prover_config_str,
             prover_parameters: prover_parameters_str,
-        } = request.into_inner();
+            memory_sha256,
+            trace_sha256,
+        } = request;
 
-        let public_input = serde_json::from_str(&public_input_str)
-            .map_err(|_| Status::invalid_argument("Could not deserialize public input"))?;
-        let private_input: AirPrivateInputSerializable =
-            serde_json::from_str(&private_input_str)
+        self.policy
+            .check_user_parameters(prover_parameters_str.is_some())?;
+        self.input_limits.check_memory(&memory)?;
+        self.input_limits.check_trace(&trace)?;
+        verify_artifacts_sha256(memory.clone(), trace.clone(), memory_sha256, trace_sha256).await?;
+        let public_input = self.parse_public_input(&public_input_bytes)?;
+        let (private_input, prover_config) =
+            parse_prover_inputs(&private_input_str, &prover_config_str)?;
+        let prover_parameters = common::get_prover_parameters(
+            prover_parameters_str,
+            public_input.n_steps,
+            &public_input.layout,
+            &self.parameter_presets,
+            ParameterOverrides::default(),
+        )?;
+
+        let execution_artifacts = Arc::new(ExecutionArtifacts {
+            public_input,
+            private_input,
+            memory: memory.into(),
+            trace: trace.into(),
+        });
+
+        check_disk_space_for_proof(
+            execution_artifacts.memory.len(),
+            execution_artifacts.trace.len(),
+        )?;
+        let input_digest = input_digest(
+            execution_artifacts.clone(),
+            &prover_config,
+            &prover_parameters,
+        )
+        .await?;
+
+        let proof_limiter = self.proof_limiter.as_deref();
+        let proof_class = classify_proof(proof_limiter, execution_artifacts.public_input.n_steps);
+        let permit = acquire_proof_permit(proof_limiter, proof_class).await?;
+        job.record_permit(permit.as_ref());
+        let prover_result = self
+            .call_prover(
+                ProverInputs::Artifacts(&execution_artifacts),
+                &prover_config,
+                &prover_parameters,
+                job,
+            )
+            .await;
+        if let (Some(permit), Ok(_)) = (&permit, &prover_result) {
+            permit.record_success();
+        }
+
+        let mut response = format_prover_result(prover_result, input_digest)?;
+        self.store_proof(&mut response, &execution_artifacts.public_input)
+            .await;
+        Ok(response)
+    }
+
+    /// Proves the artifacts of a `ProveFromFiles` request like a `Prove` request. The paths
+    /// are all checked before reading any file. The memory and trace are not loaded in
+    /// memory: the prover reads them in place.
+    async fn run_prove_from_files(
+        &self,
+        request: ProveFromFilesRequest,
+        job: &mut Job,
+    ) -> Result<ProverResponse, Status> {
+        let file_roots = self.file_roots.as_ref().ok_or_else(|| {
+            Status::unimplemented("ProveFromFiles is not enabled on this server (prove_from_files)")
+        })?;
+        let ProveFromFilesRequest {
+            public_input_path,
+            private_input_path,
+            memory_path,
+            trace_path,
+            prover_config,
+            prover_parameters,
+        } = request;
+
+        self.policy
+            .check_user_parameters(prover_parameters.is_some())?;
+        let public_input_path = file_roots.resolve("public_input_path", &public_input_path)?;
+        let private_input_path = file_roots.resolve("private_input_path", &private_input_path)?;
+        let memory_path = file_roots.resolve("memory_path", &memory_path)?;
+        let trace_path = file_roots.resolve("trace_path", &trace_path)?;
+        self.input_limits
+            .check_memory_size(file_size("memory_path", &memory_path)?)?;
+        self.input_limits
+            .check_trace_size(file_size("trace_path", &trace_path)?)?;
+        let public_input =
+            self.parse_public_input(&read_file("public_input_path", &public_input_path).await?)?;
+        let private_input =
+            String::from_utf8(read_file("private_input_path", &private_input_path).await?)
                 .map_err(|_| Status::invalid_argument("Could not deserialize private input"))?;
-        let prover_config = serde_json::from_str(&prover_config_str)
-            .map_err(|_| Status::invalid_argument("Could not deserialize prover config"))?;
-        let prover_parameters = serde_json::from_str(&prover_parameters_str)
-            .map_err(|_| Status::invalid_argument("Could not deserialize prover parameters"))?;
+        let (private_input, prover_config) = parse_prover_inputs(&private_input, &prover_config)?;
+        let prover_parameters = common::get_prover_parameters(
+            prover_parameters,
+            public_input.n_steps,
+            &public_input.layout,
+            &self.parameter_presets,
+            ParameterOverrides::default(),
+        )?;
 
-        let execution_artifacts = ExecutionArtifacts {
+        let execution_files = ExecutionFiles {
             public_input,
-            private_input: AirPrivateInput::from(private_input),
-            memory,
-            trace,
+            private_input,
+            memory_file: memory_path,
+            trace_file: trace_path,
         };
+        // The working directory only links to the memory and trace
+        check_disk_space_for_proof(0, 0)?;
+        let input_digest =
+            input_digest_from_files(&execution_files, &prover_config, &prover_parameters).await?;
 
-        let prover_result =
-            common::call_prover(&execution_artifacts, &prover_config, &prover_parameters).await;
-        let formatted_result = format_prover_result(prover_result);
+        let proof_limiter = self.proof_limiter.as_deref();
+        let proof_class = classify_proof(proof_limiter, execution_files.public_input.n_steps);
+        let permit = acquire_proof_permit(proof_limiter, proof_class).await?;
+        job.record_permit(permit.as_ref());
+        let prover_result = self
+            .call_prover(
+                ProverInputs::Files(&execution_files),
+                &prover_config,
+                &prover_parameters,
+                job,
+            )
+            .await;
+        if let (Some(permit), Ok(_)) = (&permit, &prover_result) {
+            permit.record_success();
+        }
 
-        formatted_result.map(Response::new)
+        let mut response = format_prover_result(prover_result, input_digest)?;
+        self.store_proof(&mut response, &execution_files.public_input)
+            .await;
+        Ok(response)
     }
 
-    async fn execute_and_prove(
+    async fn run_execute_and_prove(
         &self,
-        request: Request<ExecutionRequest>,
-    ) -> Result<Response<ProverResponse>, Status> {
+        request: ExecutionRequest,
+        job: &mut Job,
+    ) -> Result<ProverResponse, Status> {
         let ExecutionRequest {
             program,
             prover_config: prover_config_str,
             prover_parameters: prover_parameters_str,
-        } = request.into_inner();
+            hint_profile,
+            max_fri_step,
+            last_layer_degree_bound,
+            deterministic,
+            small_proof,
+            program_sha256,
+        } = request;
 
-        let prover_config = get_prover_config(prover_config_str)?;
-        let layout = Layout::StarknetWithKeccak;
+        if deterministic && (prover_config_str.is_none() || prover_parameters_str.is_none()) {
+            return Err(Status::invalid_argument(
+                "Deterministic requests must set prover_config and prover_parameters",
+            ));
+        }
+        self.input_limits.check_program(&program)?;
+        verify_sha256("Program", &program, program_sha256.as_deref())?;
+        self.policy
+            .check_user_parameters(prover_parameters_str.is_some())?;
+        self.policy.check_layout(PROVER_LAYOUT)?;
+        self.policy.check_small_proof(small_proof)?;
+        let prover_config = common::get_prover_config(prover_config_str)?;
 
-        let execution_artifacts = run_cairo_program_in_proof_mode(&program, layout);
-        let execution_artifacts = execution_artifacts
-            .map_err(|e| Status::internal(format!("Failed to run program: {e}")))?;
+        let execution_artifacts = tracing::info_span!("execute").in_scope(|| {
+            run_cairo_program_in_proof_mode(&program, PROVER_LAYOUT, hint_profile.as_deref())
+        })?;
+        let execution_artifacts = Arc::new(execution_artifacts);
+        self.policy
+            .check_n_steps(execution_artifacts.public_input.n_steps)?;
+        let proof_limiter = self.proof_limiter.as_deref();
+        let proof_class = if small_proof {
+            ProofClass::Small
+        } else {
+            ProofClass::Large
+        };
+        check_proof_n_steps(
+            proof_limiter,
+            proof_class,
+            execution_artifacts.public_input.n_steps,
+        )?;
 
         let prover_parameters = common::get_prover_parameters(
             prover_parameters_str,
             execution_artifacts.public_input.n_steps,
+            &execution_artifacts.public_input.layout,
+            &self.parameter_presets,
+            ParameterOverrides {
+                max_fri_step,
+                last_layer_degree_bound,
+            },
+        )?;
+
+        check_disk_space_for_proof(
+            execution_artifacts.memory.len(),
+            execution_artifacts.trace.len(),
+        )?;
+        let input_digest = input_digest(
+            execution_artifacts.clone(),
+            &prover_config,
+            &prover_parameters,
+        )
+        .await?;
+
+        let permit = acquire_proof_permit(proof_limiter, proof_class).await?;
+        job.record_permit(permit.as_ref());
+        let prover_result = self
+            .call_prover(
+                ProverInputs::Artifacts(&execution_artifacts),
+                &prover_config,
+                &prover_parameters,
+                job,
+            )
+            .await;
+        if let (Some(permit), Ok(_)) = (&permit, &prover_result) {
+            permit.record_success();
+        }
+
+        let mut response = format_prover_result(prover_result, input_digest)?;
+        self.store_proof(&mut response, &execution_artifacts.public_input)
+            .await;
+        Ok(response)
+    }
+}
+
+#[tonic::async_trait]
+impl Prover for ProverService {
+    type ExecuteStreamedStream =
+        Pin<Box<dyn Stream<Item = Result<ExecutionStreamResponse, Status>> + Send>>;
+
+    async fn execute(
+        &self,
+        request: Request<ExecutionRequest>,
+    ) -> Result<Response<ExecutionResponse>, Status> {
+        let execution_request = request.into_inner();
+        self.input_limits
+            .check_program(&execution_request.program)?;
+        verify_sha256(
+            "Program",
+            &execution_request.program,
+            execution_request.program_sha256.as_deref(),
+        )?;
+
+        let artifacts = run_cairo_program_in_proof_mode(
+            &execution_request.program,
+            PROVER_LAYOUT,
+            execution_request.hint_profile.as_deref(),
         )?;
+        let (artifacts, sha256) = artifacts_sha256(artifacts).await?;
+
+        format_execution_result(artifacts, sha256).map(Response::new)
+    }
 
-        let prover_result =
-            common::call_prover(&execution_artifacts, &prover_config, &prover_parameters).await;
+    async fn execute_streamed(
+        &self,
+        request: Request<ExecutionRequest>,
+    ) -> Result<Response<Self::ExecuteStreamedStream>, Status> {
+        let execution_request = request.into_inner();
+        self.input_limits
+            .check_program(&execution_request.program)?;
+        verify_sha256(
+            "Program",
+            &execution_request.program,
+            execution_request.program_sha256.as_deref(),
+        )?;
+
+        let artifacts = run_cairo_program_in_proof_mode(
+            &execution_request.program,
+            PROVER_LAYOUT,
+            execution_request.hint_profile.as_deref(),
+        )?;
+        let (artifacts, sha256) = artifacts_sha256(artifacts).await?;
+        let messages = format_execution_stream(artifacts, sha256, EXECUTION_CHUNK_SIZE)?;
+
+        let stream = tokio_stream::iter(messages.map(Ok));
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn prove(
+        &self,
+        request: Request<ProverRequest>,
+    ) -> Result<Response<ProverResponse>, Status> {
+        let mut job = self.job_history.start("prover.Prover/Prove");
+        let result = self.run_prove(request.into_inner(), &mut job).await;
+        self.record_job(job, &result);
+
+        result.map(Response::new)
+    }
+
+    async fn prove_from_files(
+        &self,
+        request: Request<ProveFromFilesRequest>,
+    ) -> Result<Response<ProverResponse>, Status> {
+        let mut job = self.job_history.start("prover.Prover/ProveFromFiles");
+        let result = self
+            .run_prove_from_files(request.into_inner(), &mut job)
+            .await;
+        self.record_job(job, &result);
+
+        result.map(Response::new)
+    }
+
+    async fn execute_and_prove(
+        &self,
+        request: Request<ExecutionRequest>,
+    ) -> Result<Response<ProverResponse>, Status> {
+        let idempotency_key = idempotency_key(&request);
+        let request = request.into_inner();
+        let request_hash = request_hash(&request);
+
+        self.idempotency_cache
+            .run(idempotency_key, request_hash, || async move {
+                let mut job = self.job_history.start("prover.Prover/ExecuteAndProve");
+                let result = self.run_execute_and_prove(request, &mut job).await;
+                self.record_job(job, &result);
+                result
+            })
+            .await
+            .map(Response::new)
+    }
+
+    async fn estimate_proof(
+        &self,
+        request: Request<EstimateProofRequest>,
+    ) -> Result<Response<EstimateProofResponse>, Status> {
+        let EstimateProofRequest {
+            nb_steps,
+            layout,
+            prover_parameters,
+            max_fri_step,
+            last_layer_degree_bound,
+        } = request.into_inner();
+
+        if nb_steps == 0 {
+            return Err(Status::invalid_argument(
+                "The number of steps must be positive",
+            ));
+        }
+        let layout = if layout.is_empty() {
+            PROVER_LAYOUT.to_string()
+        } else {
+            layout
+        };
+
+        let prover_parameters = common::get_prover_parameters(
+            prover_parameters,
+            nb_steps,
+            &layout,
+            &self.parameter_presets,
+            ParameterOverrides {
+                max_fri_step,
+                last_layer_degree_bound,
+            },
+        )?;
+        let estimate = self
+            .proof_estimator
+            .estimate(nb_steps, &layout, &prover_parameters);
+
+        Ok(Response::new(EstimateProofResponse {
+            proof_size_bytes: estimate.proof_size_bytes,
+            estimated_duration_ms: estimate.est_duration.as_millis() as u64,
+        }))
+    }
+
+    async fn list_recent_jobs(
+        &self,
+        request: Request<ListRecentJobsRequest>,
+    ) -> Result<Response<ListRecentJobsResponse>, Status> {
+        let ListRecentJobsRequest { limit } = request.into_inner();
+
+        let jobs = self
+            .job_history
+            .recent(limit as usize)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(Response::new(ListRecentJobsResponse { jobs }))
+    }
+
+    async fn get_capabilities(
+        &self,
+        _request: Request<GetCapabilitiesRequest>,
+    ) -> Result<Response<Capabilities>, Status> {
+        Ok(Response::new(Capabilities {
+            prover_binaries: self
+                .prover_binaries
+                .iter()
+                .cloned()
+                .map(Into::into)
+                .collect(),
+            workspace_usage: Some(self.workspace_tracker.usage().into()),
+            input_limits: Some(self.input_limits.into()),
+            proof_limits: self.proof_limiter.as_deref().map(Into::into),
+            policy: Some(self.policy.clone().into()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use stone_prover_sdk::models::Proof;
+    use test_cases::{get_test_case_file_path, load_test_case_file};
+    use tonic::Code;
+
+    use crate::limiter::DEFAULT_SMALL_PROOF_MAX_N_STEPS;
+    use crate::services::common::mock::MockProverBackend;
+    use crate::storage::{FilesystemStorage, StorageRef};
+
+    use super::*;
+
+    const FIBONACCI_PROOF_FILE: &str = "fibonacci/fibonacci_proof.json";
+
+    fn fibonacci_program() -> Vec<u8> {
+        std::fs::read(get_test_case_file_path("fibonacci/fibonacci_compiled.json")).unwrap()
+    }
+
+    fn fibonacci_prover_request() -> ProverRequest {
+        let artifacts =
+            run_cairo_program_in_proof_mode(&fibonacci_program(), PROVER_LAYOUT, None).unwrap();
+        let private_input = artifacts
+            .private_input
+            .to_serializable(String::new(), String::new());
+
+        ProverRequest {
+            public_input: serde_json::to_vec(&artifacts.public_input).unwrap(),
+            private_input: serde_json::to_string(&private_input).unwrap(),
+            memory_sha256: Some(sha256_hex(&artifacts.memory)),
+            trace_sha256: Some(sha256_hex(&artifacts.trace)),
+            memory: artifacts.memory.into(),
+            trace: artifacts.trace.into(),
+            prover_config: load_test_case_file("fibonacci/cpu_air_prover_config.json"),
+            prover_parameters: Some(load_test_case_file("fibonacci/cpu_air_params.json")),
+        }
+    }
+
+    fn mock_service(prover_backend: &Arc<MockProverBackend>) -> ProverService {
+        ProverService::default().with_prover_backend(prover_backend.clone())
+    }
+
+    fn assert_canned_proof(response: ProverResponse) {
+        let proof: Proof = serde_json::from_str(&response.proof).unwrap();
+        let expected_proof: Proof =
+            serde_json::from_str(&load_test_case_file(FIBONACCI_PROOF_FILE)).unwrap();
+        assert_eq!(proof.proof_hex, expected_proof.proof_hex);
+    }
+
+    #[tokio::test]
+    async fn test_prove() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let request = fibonacci_prover_request();
+        let expected_parameters: serde_json::Value =
+            serde_json::from_str(request.prover_parameters.as_ref().unwrap()).unwrap();
+
+        let response = service.prove(Request::new(request)).await.unwrap();
+
+        assert_canned_proof(response.into_inner());
+        assert_eq!(prover_backend.calls(), vec![expected_parameters]);
+    }
+
+    #[tokio::test]
+    async fn test_prove_stores_proof() {
+        let storage_root = tempfile::tempdir().unwrap();
+        let proof_storage = Arc::new(FilesystemStorage::new(storage_root.path()));
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend).with_proof_storage(proof_storage.clone());
+
+        let response = service
+            .prove(Request::new(fibonacci_prover_request()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let storage_ref = StorageRef(response.storage_ref.clone().unwrap());
+        let stored_proof = proof_storage.fetch(&storage_ref).await.unwrap();
+        assert_eq!(stored_proof.proof, response.proof);
+        assert!(stored_proof.public_input.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_prove_storage_failure_returns_proof() {
+        // A file as storage root: the proof directory cannot be created
+        let storage_root = tempfile::NamedTempFile::new().unwrap();
+        let proof_storage = Arc::new(FilesystemStorage::new(storage_root.path()));
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend).with_proof_storage(proof_storage);
+
+        let response = service
+            .prove(Request::new(fibonacci_prover_request()))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.storage_ref, None);
+        assert_canned_proof(response);
+    }
+
+    #[tokio::test]
+    async fn test_prove_prover_error() {
+        let prover_backend = Arc::new(MockProverBackend::failing("Out of memory"));
+        let service = mock_service(&prover_backend);
+
+        let status = service
+            .prove(Request::new(fibonacci_prover_request()))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status.message().starts_with("Prover run failed"),
+            "{}",
+            status.message()
+        );
+        assert!(status.message().contains("Out of memory"));
+    }
+
+    #[tokio::test]
+    async fn test_prove_invalid_public_input() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let request = ProverRequest {
+            public_input: b"not a public input".to_vec(),
+            ..fibonacci_prover_request()
+        };
+
+        let status = service.prove(Request::new(request)).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "Could not deserialize public input");
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prove_corrupted_trace() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let request = fibonacci_prover_request();
+        let expected_sha256 = request.trace_sha256.clone().unwrap();
+        let mut trace = request.trace.to_vec();
+        trace[100] ^= 1;
+
+        let status = service
+            .prove(Request::new(ProverRequest {
+                trace: trace.into(),
+                ..request
+            }))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::DataLoss);
+        assert!(
+            status.message().starts_with(&format!(
+                "Trace checksum mismatch: expected SHA-256 {expected_sha256}"
+            )),
+            "{}",
+            status.message()
+        );
+        // Rejected before running the prover
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    fn fibonacci_prove_from_files_request() -> ProveFromFilesRequest {
+        let path = |file: &str| {
+            get_test_case_file_path(&format!("fibonacci/{file}"))
+                .to_string_lossy()
+                .into_owned()
+        };
+        ProveFromFilesRequest {
+            public_input_path: path("fibonacci_public_input.json"),
+            private_input_path: path("fibonacci_private_input.json"),
+            memory_path: path("fibonacci_memory.bin"),
+            trace_path: path("fibonacci_trace.bin"),
+            prover_config: load_test_case_file("fibonacci/cpu_air_prover_config.json"),
+            prover_parameters: Some(load_test_case_file("fibonacci/cpu_air_params.json")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prove_from_files() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend).with_prove_from_files(
+            FileRoots::new(vec![get_test_case_file_path("fibonacci")]).unwrap(),
+        );
+
+        let response = service
+            .prove_from_files(Request::new(fibonacci_prove_from_files_request()))
+            .await
+            .unwrap();
+
+        assert_canned_proof(response.into_inner());
+        assert_eq!(prover_backend.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_input_digest_from_files() {
+        let request = fibonacci_prove_from_files_request();
+        let (private_input, prover_config) = parse_prover_inputs(
+            &load_test_case_file("fibonacci/fibonacci_private_input.json"),
+            &request.prover_config,
+        )
+        .unwrap();
+        let prover_parameters =
+            serde_json::from_str(request.prover_parameters.as_ref().unwrap()).unwrap();
+        let execution_files = ExecutionFiles {
+            public_input: serde_json::from_str(&load_test_case_file(
+                "fibonacci/fibonacci_public_input.json",
+            ))
+            .unwrap(),
+            private_input,
+            memory_file: request.memory_path.into(),
+            trace_file: request.trace_path.into(),
+        };
+
+        let digest = input_digest_from_files(&execution_files, &prover_config, &prover_parameters)
+            .await
+            .unwrap();
+
+        // Same digest as for the artifacts in memory
+        let execution_artifacts = Arc::new(execution_files.read().unwrap());
+        assert_eq!(
+            digest,
+            input_digest(execution_artifacts, &prover_config, &prover_parameters)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prove_from_files_disabled() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+
+        let status = service
+            .prove_from_files(Request::new(fibonacci_prove_from_files_request()))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::Unimplemented);
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prove_from_files_outside_roots() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let root = tempfile::tempdir().unwrap();
+        let service = mock_service(&prover_backend)
+            .with_prove_from_files(FileRoots::new(vec![root.path().to_path_buf()]).unwrap());
+        let missing_trace = root.path().join("trace.bin");
+
+        let status = service
+            .prove_from_files(Request::new(fibonacci_prove_from_files_request()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(
+            status.message().starts_with("public_input_path"),
+            "{}",
+            status.message()
+        );
+
+        let request = ProveFromFilesRequest {
+            trace_path: missing_trace.to_string_lossy().into_owned(),
+            ..fibonacci_prove_from_files_request()
+        };
+        let service = service.with_prove_from_files(
+            FileRoots::new(vec![
+                root.path().to_path_buf(),
+                get_test_case_file_path("fibonacci"),
+            ])
+            .unwrap(),
+        );
+        let status = service
+            .prove_from_files(Request::new(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert!(status.message().starts_with("trace_path"));
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_corrupted_program() {
+        let service = ProverService::default();
+        let program = fibonacci_program();
+        let request = ExecutionRequest {
+            program_sha256: Some(sha256_hex(&program[1..])),
+            program,
+            prover_config: None,
+            prover_parameters: None,
+            hint_profile: None,
+            max_fri_step: None,
+            last_layer_degree_bound: None,
+            deterministic: false,
+            small_proof: false,
+        };
+
+        let status = service.execute(Request::new(request)).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::DataLoss);
+        assert!(status.message().starts_with("Program checksum mismatch"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_program_too_large() {
+        let service = ProverService::default().with_input_limits(InputLimits {
+            max_program_size: 1024,
+            ..InputLimits::default()
+        });
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            ..Default::default()
+        };
+
+        let status = service.execute(Request::new(request)).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status.message().starts_with("Program is too large"),
+            "{}",
+            status.message()
+        );
+        assert!(status.message().ends_with("the limit is 1024 bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_prove_layout_not_allowed() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend).with_policy(ProverPolicy {
+            allowed_layouts: Some(vec!["recursive".to_string()]),
+            ..ProverPolicy::default()
+        });
+
+        let status = service
+            .prove(Request::new(fibonacci_prover_request()))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(
+            status.message().contains("allowed_layouts: recursive"),
+            "{}",
+            status.message()
+        );
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prove_user_parameters_not_allowed() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend).with_policy(ProverPolicy {
+            allow_user_parameters: false,
+            ..ProverPolicy::default()
+        });
+
+        let status = service
+            .prove(Request::new(fibonacci_prover_request()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert!(prover_backend.calls().is_empty());
+
+        // Requests without parameters are proved with the parameters of the presets
+        let request = ProverRequest {
+            prover_parameters: None,
+            ..fibonacci_prover_request()
+        };
+        let public_input: PublicInput = serde_json::from_slice(&request.public_input).unwrap();
+        let expected_parameters = ParameterPresets::default()
+            .prover_parameters(
+                &public_input.layout,
+                public_input.n_steps,
+                ParameterOverrides::default(),
+            )
+            .unwrap();
+
+        let response = service.prove(Request::new(request)).await.unwrap();
+
+        assert_canned_proof(response.into_inner());
+        assert_eq!(
+            prover_backend.calls(),
+            vec![serde_json::to_value(expected_parameters).unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_too_many_steps() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend).with_policy(ProverPolicy {
+            max_n_steps: Some(16),
+            ..ProverPolicy::default()
+        });
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status.message().contains("max_n_steps: 16"),
+            "{}",
+            status.message()
+        );
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_small_proof_uses_reserved_slot() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let proof_limiter = Arc::new(
+            ProofLimiter::new(NonZeroUsize::new(2).unwrap(), 0)
+                .with_reserved_slots(1, DEFAULT_SMALL_PROOF_MAX_N_STEPS),
+        );
+        let service = mock_service(&prover_backend).with_proof_limiter(proof_limiter.clone());
+        // A large proof is running in the only shared slot
+        let _large_permit = proof_limiter.acquire(ProofClass::Large).await.unwrap();
+
+        let response = tokio::time::timeout(
+            Duration::from_secs(10),
+            service.prove(Request::new(fibonacci_prover_request())),
+        )
+        .await
+        .expect("the small proof waited for the large one");
+        assert_canned_proof(response.unwrap().into_inner());
+
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            ..Default::default()
+        };
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let jobs = service
+            .list_recent_jobs(Request::new(ListRecentJobsRequest { limit: 0 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .jobs;
+        assert_eq!(jobs.len(), 2);
+        // The rejected request did not get a slot
+        assert_eq!(jobs[0].proof_class, "");
+        assert_eq!(jobs[1].proof_class, "small");
+    }
+
+    #[tokio::test]
+    async fn test_small_proof_request_over_the_limit() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let proof_limiter = Arc::new(
+            ProofLimiter::new(NonZeroUsize::new(2).unwrap(), 0).with_reserved_slots(1, 16),
+        );
+        let service = mock_service(&prover_backend).with_proof_limiter(proof_limiter);
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            small_proof: true,
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status.message().contains("small_proof_max_n_steps: 16"),
+            "{}",
+            status.message()
+        );
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request_does_not_take_a_slot() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let proof_limiter = Arc::new(ProofLimiter::new(NonZeroUsize::new(1).unwrap(), 0));
+        let service = mock_service(&prover_backend).with_proof_limiter(proof_limiter.clone());
+        // The only slot is taken and the queue is empty: a valid request is rejected
+        let _permit = proof_limiter.acquire(ProofClass::Large).await.unwrap();
+        let request = ProverRequest {
+            prover_config: "not a config".to_string(),
+            ..fibonacci_prover_request()
+        };
+
+        let status = service.prove(Request::new(request)).await.unwrap_err();
+
+        // Rejected for its input, not for the lack of slots
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_builtin_usage() {
+        let service = ProverService::default();
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            ..Default::default()
+        };
+
+        let response = service.execute(Request::new(request)).await.unwrap();
+
+        let response = response.into_inner();
+        assert_eq!(response.memory_sha256, sha256_hex(&response.memory));
+        assert_eq!(response.trace_sha256, sha256_hex(&response.trace));
+        let builtin_usage = response.builtin_usage.unwrap();
+        assert!(builtin_usage.builtins.is_empty());
+        assert!(builtin_usage.n_steps > 0);
+        assert_eq!(builtin_usage.suggested_layout, "plain");
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            max_fri_step: Some(2),
+            ..Default::default()
+        };
+
+        let response = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap();
+
+        assert_canned_proof(response.into_inner());
+        // The parameters are generated from the preset of the layout
+        let calls = prover_backend.calls();
+        assert_eq!(calls.len(), 1);
+        let fri_step_list: Vec<u64> =
+            serde_json::from_value(calls[0]["stark"]["fri"]["fri_step_list"].clone()).unwrap();
+        assert!(fri_step_list.iter().all(|step| *step <= 2));
+    }
+
+    #[tokio::test]
+    async fn test_input_digest() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let prover_request = fibonacci_prover_request();
+        let execution_request = ExecutionRequest {
+            program: fibonacci_program(),
+            prover_config: Some(prover_request.prover_config.clone()),
+            prover_parameters: prover_request.prover_parameters.clone(),
+            deterministic: true,
+            ..Default::default()
+        };
+
+        let prove_response = service
+            .prove(Request::new(prover_request.clone()))
+            .await
+            .unwrap()
+            .into_inner();
+        let execute_and_prove_response = service
+            .execute_and_prove(Request::new(execution_request))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // Same execution, config and parameters, whatever the RPC
+        assert!(prove_response.input_digest.starts_with("0x"));
+        assert_eq!(prove_response.input_digest.len(), 66);
+        assert_eq!(
+            prove_response.input_digest,
+            execute_and_prove_response.input_digest
+        );
+
+        let mut prover_parameters: serde_json::Value =
+            serde_json::from_str(prover_request.prover_parameters.as_ref().unwrap()).unwrap();
+        prover_parameters["stark"]["log_n_cosets"] = 3.into();
+        let other_request = ProverRequest {
+            prover_parameters: Some(prover_parameters.to_string()),
+            ..prover_request
+        };
+        let other_response = service
+            .prove(Request::new(other_request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_ne!(other_response.input_digest, prove_response.input_digest);
+    }
+
+    #[tokio::test]
+    async fn test_deterministic_request_without_parameters() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            prover_config: Some(load_test_case_file("fibonacci/cpu_air_prover_config.json")),
+            deterministic: true,
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(
+            status.message().contains("prover_parameters"),
+            "{}",
+            status.message()
+        );
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_failing_program() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let request = ExecutionRequest {
+            program: std::fs::read(get_test_case_file_path(
+                "failing-assert/failing_assert_compiled.json",
+            ))
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_invalid_prover_config() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            prover_config: Some("not a config".to_string()),
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "Could not read prover config");
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_list_recent_jobs() {
+        let prover_backend = Arc::new(MockProverBackend::new(FIBONACCI_PROOF_FILE));
+        let service = mock_service(&prover_backend);
+
+        let prove_response = service
+            .prove(Request::new(fibonacci_prover_request()))
+            .await
+            .unwrap()
+            .into_inner();
+        let request = ExecutionRequest {
+            program: fibonacci_program(),
+            prover_config: Some("not a config".to_string()),
+            ..Default::default()
+        };
+        service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        let jobs = service
+            .list_recent_jobs(Request::new(ListRecentJobsRequest { limit: 0 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .jobs;
+
+        assert_eq!(jobs.len(), 2);
+        let (failed_job, proved_job) = (&jobs[0], &jobs[1]);
+
+        assert_eq!(failed_job.rpc, "prover.Prover/ExecuteAndProve");
+        assert_eq!(failed_job.status_code, Code::InvalidArgument as i32);
+        assert_eq!(failed_job.status_message, "Could not read prover config");
+        assert_eq!(failed_job.proof_size, 0);
+        assert_eq!(failed_job.prove_duration_ms, None);
+
+        assert_eq!(proved_job.rpc, "prover.Prover/Prove");
+        assert_eq!(proved_job.status_code, Code::Ok as i32);
+        assert_eq!(proved_job.layout, PROVER_LAYOUT);
+        assert!(proved_job.n_steps > 0);
+        assert!(proved_job.prove_duration_ms.is_some());
+        assert_eq!(proved_job.proof_size, prove_response.proof.len() as u64);
+        assert!(proved_job.request_id < failed_job.request_id);
+        assert!(proved_job.started_at_ms <= failed_job.started_at_ms);
+
+        let jobs = service
+            .list_recent_jobs(Request::new(ListRecentJobsRequest { limit: 1 }))
+            .await
+            .unwrap()
+            .into_inner()
+            .jobs;
+        assert_eq!(jobs, vec![failed_job.clone()]);
+    }
+
+    fn chunks(data: Vec<u8>, chunk_size: usize) -> Vec<ExecutionChunk> {
+        data_chunks(data, ExecutionChunkKind::Trace, chunk_size)
+            .map(|response| match response.message {
+                Some(Message::Chunk(chunk)) => chunk,
+                _ => panic!("expected a chunk"),
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_get_capabilities() {
+        let service = ProverService::default().with_prover_binaries(vec![ProverBinary {
+            name: "cpu_air_prover",
+            path: PathBuf::from("/usr/local/bin/cpu_air_prover"),
+            version: "cpu_air_prover v1.0".to_string(),
+        }]);
+
+        let capabilities = service
+            .get_capabilities(Request::new(GetCapabilitiesRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            capabilities.prover_binaries,
+            vec![prover_proto::ProverBinary {
+                name: "cpu_air_prover".to_string(),
+                path: "/usr/local/bin/cpu_air_prover".to_string(),
+                version: "cpu_air_prover v1.0".to_string(),
+            }]
+        );
+        assert_eq!(
+            capabilities.workspace_usage,
+            Some(prover_proto::WorkspaceUsage {
+                live_workspaces: 0,
+                bytes_used: 0,
+            })
+        );
+        assert_eq!(
+            capabilities.input_limits,
+            Some(InputLimits::default().into())
+        );
+        assert_eq!(capabilities.proof_limits, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_capabilities_policy() {
+        let service = ProverService::default().with_policy(ProverPolicy {
+            allowed_layouts: Some(vec!["recursive".to_string()]),
+            max_n_steps: Some(1 << 20),
+            allow_split_proof: false,
+            ..Default::default()
+        });
+
+        let capabilities = service
+            .get_capabilities(Request::new(GetCapabilitiesRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            capabilities.policy,
+            Some(prover_proto::ProverPolicy {
+                allowed_layouts: Some(prover_proto::LayoutList {
+                    layouts: vec!["recursive".to_string()]
+                }),
+                max_n_steps: Some(1 << 20),
+                allow_split_proof: false,
+                allow_user_parameters: true,
+                allow_small_proof: true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_capabilities_proof_limits() {
+        let proof_limiter =
+            ProofLimiter::new(NonZeroUsize::new(2).unwrap(), 4).with_reserved_slots(1, 1000);
+        let service = ProverService::default().with_proof_limiter(Arc::new(proof_limiter));
+
+        let capabilities = service
+            .get_capabilities(Request::new(GetCapabilitiesRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            capabilities.proof_limits,
+            Some(prover_proto::ProofLimits {
+                max_concurrent_proofs: 2,
+                max_queued_proofs: 4,
+                reserved_small_proofs: 1,
+                small_proof_max_n_steps: 1000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_data_chunks() {
+        let chunks = chunks((0..10).collect(), 4);
+
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|chunk| (chunk.offset, chunk.data.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, vec![0, 1, 2, 3]),
+                (4, vec![4, 5, 6, 7]),
+                (8, vec![8, 9])
+            ]
+        );
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.kind() == ExecutionChunkKind::Trace));
+    }
 
-        format_prover_result(prover_result).map(Response::new)
+    #[test]
+    fn test_data_chunks_empty() {
+        assert!(chunks(vec![], 4).is_empty());
     }
 }
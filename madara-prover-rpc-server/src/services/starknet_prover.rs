@@ -1,5 +1,9 @@
 use std::any::Any;
 use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
 
 use cairo_vm::cairo_run::CairoRunConfig;
 use cairo_vm::hint_processor::builtin_hint_processor::bootloader::types::{
@@ -17,18 +21,40 @@ use cairo_vm::vm::runners::cairo_runner::CairoRunner;
 use cairo_vm::vm::security::verify_secure_runner;
 use cairo_vm::vm::vm_core::VirtualMachine;
 use cairo_vm::{any_box, Felt252};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Deserialize;
 use tonic::{Request, Response, Status};
+use tracing::Instrument;
 
 use stone_prover_sdk::error::ProverError;
-use stone_prover_sdk::models::{Proof, ProverConfig, ProverWorkingDirectory};
+use stone_prover_sdk::models::PublicInput;
 
+use crate::evm_adapter::fri_inner_layer_count;
+use crate::facts::{
+    compute_fact_hashes, compute_pie_program_hash, compute_program_hash, extract_bootloader_output,
+    read_fact_topologies, FactError, FactHash, FactTopology, TaskOutput, BOOTLOADER_VERSION,
+};
+use crate::health::check_disk_space_for_proof;
+use crate::hints::normalize_program_hints;
+use crate::history::{Job, JobHistory};
+use crate::idempotency::{idempotency_key, request_hash, IdempotencyCache};
+use crate::input_limits::InputLimits;
+use crate::limiter::{acquire_proof_permit, check_proof_n_steps, ProofClass, ProofLimiter};
+use crate::parameters::{ParameterOverrides, ParameterPresets};
+use crate::policy::ProverPolicy;
+use crate::public_input::public_memory_page_sizes;
 use crate::services::common::{
-    call_prover, format_prover_error, get_prover_parameters, verify_and_annotate_proof,
+    format_prover_error, format_split_proof_error, get_prover_config, get_prover_parameters,
+    verify_and_annotate_proof, ProverBackend, ProverOutput, StoneProverBackend,
 };
 use crate::services::starknet_prover::starknet_prover_proto::starknet_prover_server::StarknetProver;
 use crate::services::starknet_prover::starknet_prover_proto::{
-    StarknetExecutionRequest, StarknetProverResponse,
+    self, GetStoredProofRequest, StarknetExecutionRequest, StarknetProverResponse,
+    StoredProofResponse,
 };
+use crate::storage::{archive_proof, ProofStorage, StorageRef, StoredProof};
+use crate::workspace::{WorkingDirectoryExt, WorkspaceTracker};
 use stone_prover_sdk::cairo_vm::{extract_execution_artifacts, ExecutionArtifacts, ExecutionError};
 
 pub mod starknet_prover_proto {
@@ -88,51 +114,225 @@ pub fn cairo_run(
     Ok((cairo_runner, vm))
 }
 
+/// Layout used to run the bootloader.
+const BOOTLOADER_LAYOUT: &str = "starknet_with_keccak";
+
+/// Builtins available in the bootloader layout. `segment_arena` is a virtual builtin that
+/// does not need layout support.
+const BOOTLOADER_LAYOUT_BUILTINS: &[&str] = &[
+    "output",
+    "pedersen",
+    "range_check",
+    "ecdsa",
+    "bitwise",
+    "ec_op",
+    "keccak",
+    "poseidon",
+    "segment_arena",
+];
+
+/// Versions of the Cairo PIE format that the bootloader can run.
+const SUPPORTED_PIE_VERSIONS: &[&str] = &["1.0", "1.1"];
+
+/// Error while preparing a bootloader task. The first field of each variant is the index of
+/// the task, programs first then PIEs, in the order they appear in the request.
 #[derive(thiserror::Error, Debug)]
 enum BootloaderTaskError {
-    #[error("Failed to read program: {0}")]
-    Program(#[from] ProgramError),
+    #[error("Task {0}: failed to read program: {1}")]
+    Program(usize, ProgramError),
+
+    #[error("Task {0}: program has no `main` entrypoint")]
+    MissingEntrypoint(usize),
+
+    #[error("Task {0}: failed to read PIE: {1}")]
+    Pie(usize, CairoPieError),
+
+    #[error(
+        "Task {0}: PIE uses builtins that are not available in the {BOOTLOADER_LAYOUT} layout: {1}"
+    )]
+    UnsupportedBuiltins(usize, String),
+
+    #[error("Task {0}: PIE builtins are not ordered as in the {BOOTLOADER_LAYOUT} layout: {1}")]
+    UnorderedBuiltins(usize, String),
+
+    #[error(
+        "Task {0}: unsupported PIE version {1}, supported versions: {}",
+        SUPPORTED_PIE_VERSIONS.join(", ")
+    )]
+    UnsupportedPieVersion(usize, String),
+
+    #[error("Task {0}: failed to compute the program hash: {1}")]
+    ProgramHash(usize, FactError),
+}
+
+fn make_program_task(
+    task_index: usize,
+    program_bytes: &[u8],
+) -> Result<TaskSpec, BootloaderTaskError> {
+    // Tasks run with the hint processor of the bootloader
+    let mut hint_processor = BuiltinHintProcessor::new_empty();
+    let program_bytes = normalize_program_hints(program_bytes, &mut hint_processor);
+    let program = Program::from_bytes(&program_bytes, Some("main")).map_err(|e| match e {
+        ProgramError::EntrypointNotFound(_) => BootloaderTaskError::MissingEntrypoint(task_index),
+        e => BootloaderTaskError::Program(task_index, e),
+    })?;
+
+    Ok(TaskSpec {
+        task: Task::Program(program),
+    })
+}
+
+/// Checks that the PIE can be run by the bootloader.
+fn validate_pie(task_index: usize, pie: &CairoPie) -> Result<(), BootloaderTaskError> {
+    let mut unsupported_builtins: Vec<&str> = pie
+        .metadata
+        .builtin_segments
+        .keys()
+        .map(|name| name.strip_suffix("_builtin").unwrap_or(name))
+        .filter(|name| !BOOTLOADER_LAYOUT_BUILTINS.contains(name))
+        .collect();
+
+    if !unsupported_builtins.is_empty() {
+        unsupported_builtins.sort();
+        return Err(BootloaderTaskError::UnsupportedBuiltins(
+            task_index,
+            unsupported_builtins.join(", "),
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct PieVersion {
+    cairo_pie: String,
+}
+
+#[derive(Deserialize)]
+struct PieProgram {
+    builtins: Vec<String>,
+}
 
-    #[error("Failed to read PIE: {0}")]
-    Pie(#[from] CairoPieError),
+#[derive(Deserialize)]
+struct PieMetadata {
+    program: PieProgram,
+}
+
+/// Reads a JSON file of a PIE archive. Returns `None` if the file is missing or invalid, in
+/// which case parsing the PIE reports the error.
+fn read_pie_json<T: for<'de> Deserialize<'de>>(pie_bytes: &[u8], name: &str) -> Option<T> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(pie_bytes)).ok()?;
+    let mut contents = Vec::new();
+    archive
+        .by_name(name)
+        .ok()?
+        .read_to_end(&mut contents)
+        .ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+/// Checks the PIE metadata that `CairoPie` does not expose: the version of the PIE format, and
+/// the order of the program builtins, which the bootloader expects to follow the layout.
+fn validate_pie_metadata(task_index: usize, pie_bytes: &[u8]) -> Result<(), BootloaderTaskError> {
+    if let Some(version) = read_pie_json::<PieVersion>(pie_bytes, "version.json") {
+        if !SUPPORTED_PIE_VERSIONS.contains(&version.cairo_pie.as_str()) {
+            return Err(BootloaderTaskError::UnsupportedPieVersion(
+                task_index,
+                version.cairo_pie,
+            ));
+        }
+    }
+
+    if let Some(metadata) = read_pie_json::<PieMetadata>(pie_bytes, "metadata.json") {
+        let builtins: Vec<&str> = metadata
+            .program
+            .builtins
+            .iter()
+            .map(|name| name.strip_suffix("_builtin").unwrap_or(name))
+            .collect();
+        // Position of each builtin in the layout, unsupported builtins are reported by
+        // `validate_pie`.
+        let layout_positions: Vec<usize> = builtins
+            .iter()
+            .filter_map(|name| {
+                BOOTLOADER_LAYOUT_BUILTINS
+                    .iter()
+                    .position(|builtin| builtin == name)
+            })
+            .collect();
+        if layout_positions.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(BootloaderTaskError::UnorderedBuiltins(
+                task_index,
+                builtins.join(", "),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// PIEs only contain the stripped program (bytecode, builtins and entrypoint): the bootloader
+/// replays their execution from the PIE memory and never runs their hints, so unlike programs
+/// there are no hints to normalize.
+fn make_pie_task(task_index: usize, pie_bytes: &[u8]) -> Result<TaskSpec, BootloaderTaskError> {
+    validate_pie_metadata(task_index, pie_bytes)?;
+    let pie =
+        CairoPie::from_bytes(pie_bytes).map_err(|e| BootloaderTaskError::Pie(task_index, e))?;
+    validate_pie(task_index, &pie)?;
+
+    Ok(TaskSpec {
+        task: Task::Pie(pie),
+    })
 }
 
 fn make_bootloader_tasks(
     programs: &[Vec<u8>],
     pies: &[Vec<u8>],
 ) -> Result<Vec<TaskSpec>, BootloaderTaskError> {
-    let program_tasks = programs.iter().map(|program_bytes| {
-        let program = Program::from_bytes(program_bytes, Some("main"));
-        program
-            .map(|program| TaskSpec {
-                task: Task::Program(program),
-            })
-            .map_err(BootloaderTaskError::Program)
-    });
+    let program_tasks = programs
+        .iter()
+        .enumerate()
+        .map(|(task_index, program_bytes)| make_program_task(task_index, program_bytes));
 
-    let cairo_pie_tasks = pies.iter().map(|pie_bytes| {
-        let pie = CairoPie::from_bytes(pie_bytes);
-        pie.map(|pie| TaskSpec {
-            task: Task::Pie(pie),
-        })
-        .map_err(BootloaderTaskError::Pie)
-    });
+    let cairo_pie_tasks = pies
+        .iter()
+        .enumerate()
+        .map(|(pie_index, pie_bytes)| make_pie_task(programs.len() + pie_index, pie_bytes));
 
     program_tasks.chain(cairo_pie_tasks).collect()
 }
 
+/// Computes the hash of the program of each task, as written by the bootloader in its output.
+fn task_program_hashes(tasks: &[TaskSpec]) -> Result<Vec<Felt252>, BootloaderTaskError> {
+    tasks
+        .iter()
+        .enumerate()
+        .map(|(task_index, task_spec)| {
+            match &task_spec.task {
+                Task::Program(program) => compute_program_hash(program, BOOTLOADER_VERSION),
+                Task::Pie(pie) => compute_pie_program_hash(pie, BOOTLOADER_VERSION),
+            }
+            .map_err(|e| BootloaderTaskError::ProgramHash(task_index, e))
+        })
+        .collect()
+}
+
+/// Runs the bootloader on the tasks in proof mode.
+///
+/// If `fact_topologies_path` is set, the bootloader writes the fact topology of each task
+/// to this file, see `read_fact_topologies`.
 pub fn run_bootloader_in_proof_mode(
     bootloader: &Program,
     tasks: Vec<TaskSpec>,
+    fact_topologies_path: Option<PathBuf>,
 ) -> Result<ExecutionArtifacts, ExecutionError> {
     let proof_mode = true;
-    let layout = "starknet_with_keccak";
 
     let cairo_run_config = CairoRunConfig {
         entrypoint: "main",
         trace_enabled: true,
         relocate_mem: true,
-        layout,
+        layout: BOOTLOADER_LAYOUT,
         proof_mode,
         secure_run: None,
         disable_trace_padding: false,
@@ -143,7 +343,7 @@ pub fn run_bootloader_in_proof_mode(
 
     let bootloader_input = BootloaderInput {
         simple_bootloader_input: SimpleBootloaderInput {
-            fact_topologies_path: None,
+            fact_topologies_path,
             single_page: false,
             tasks,
         },
@@ -173,58 +373,913 @@ pub fn run_bootloader_in_proof_mode(
     extract_execution_artifacts(cairo_runner, vm)
 }
 
+impl From<FactTopology> for starknet_prover_proto::FactTopology {
+    fn from(fact_topology: FactTopology) -> Self {
+        Self {
+            tree_structure: fact_topology
+                .tree_structure
+                .into_iter()
+                .map(|value| value as u64)
+                .collect(),
+            page_sizes: fact_topology
+                .page_sizes
+                .into_iter()
+                .map(|value| value as u64)
+                .collect(),
+        }
+    }
+}
+
+fn format_fact_hash(fact_hash: &FactHash) -> String {
+    let hex: String = fact_hash.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("0x{hex}")
+}
+
+fn format_felt(felt: &Felt252) -> String {
+    format_fact_hash(&felt.to_bytes_be())
+}
+
+impl From<TaskOutput> for starknet_prover_proto::TaskOutput {
+    fn from(task_output: TaskOutput) -> Self {
+        Self {
+            program_hash: format_felt(&task_output.program_hash),
+            output: task_output.output.iter().map(format_felt).collect(),
+        }
+    }
+}
+
+/// Facts of the tasks of a bootloader run, and the public memory pages they are computed from.
+struct TaskFacts {
+    program_hashes: Vec<Felt252>,
+    task_outputs: Vec<TaskOutput>,
+    fact_hashes: Vec<FactHash>,
+    fact_topologies: Vec<FactTopology>,
+    public_memory_page_sizes: Vec<u64>,
+}
+
+/// Runs the bootloader and reads the fact topologies it recorded for the tasks.
+fn execute_bootloader_tasks(
+    bootloader: &Program,
+    tasks: Vec<TaskSpec>,
+) -> Result<(ExecutionArtifacts, Vec<FactTopology>), Status> {
+    let fact_topologies_dir = tempfile::tempdir().map_err(|e| {
+        Status::internal(format!(
+            "Could not create a directory for the fact topologies: {e}"
+        ))
+    })?;
+    let fact_topologies_path = fact_topologies_dir.path().join("fact_topologies.json");
+
+    let execution_artifacts =
+        run_bootloader_in_proof_mode(bootloader, tasks, Some(fact_topologies_path.clone()))
+            .map_err(|e| Status::internal(format!("Failed to run bootloader: {e}")))?;
+    let fact_topologies = read_fact_topologies(&fact_topologies_path)
+        .map_err(|e| Status::internal(format!("Failed to read the fact topologies: {e}")))?;
+
+    Ok((execution_artifacts, fact_topologies))
+}
+
+/// Computes the fact of each task of the bootloader run, after checking that the bootloader
+/// ran the programs with the expected hashes.
+///
+/// `fact_topologies` are the topologies recorded by the bootloader, one per task.
+fn get_task_facts(
+    execution_artifacts: &ExecutionArtifacts,
+    program_hashes: Vec<Felt252>,
+    fact_topologies: Vec<FactTopology>,
+) -> Result<TaskFacts, Status> {
+    let public_input = &execution_artifacts.public_input;
+    let task_outputs = extract_bootloader_output(public_input, &execution_artifacts.memory)
+        .map_err(|e| Status::internal(format!("Failed to read the bootloader output: {e}")))?;
+    if !task_outputs
+        .iter()
+        .map(|task_output| task_output.program_hash)
+        .eq(program_hashes.iter().copied())
+    {
+        return Err(Status::internal(
+            "The program hashes in the bootloader output do not match the submitted tasks",
+        ));
+    }
+
+    let fact_hashes = compute_fact_hashes(public_input, &fact_topologies)
+        .map_err(|e| Status::internal(format!("Failed to compute fact hashes: {e}")))?;
+    // Pages are numbered contiguously from 0, as checked by compute_fact_hashes
+    let public_memory_page_sizes = public_memory_page_sizes(public_input)
+        .into_values()
+        .map(|page_size| page_size as u64)
+        .collect();
+
+    Ok(TaskFacts {
+        program_hashes,
+        task_outputs,
+        fact_hashes,
+        fact_topologies,
+        public_memory_page_sizes,
+    })
+}
+
 /// Formats the output of the prover subprocess into the server response.
 fn format_prover_result(
-    prover_result: Result<(Proof, ProverWorkingDirectory), ProverError>,
+    prover_result: Result<ProverOutput, ProverError>,
+    annotated_proof: Option<serde_json::Value>,
+    task_facts: TaskFacts,
+    public_input: &PublicInput,
 ) -> Result<StarknetProverResponse, Status> {
+    let public_input = serde_json::to_string(public_input)
+        .map_err(|_| Status::internal("Failed to serialize public input"))?;
+
     match prover_result {
-        Ok((proof, _)) => serde_json::to_string(&proof)
-            .map(|proof_str| StarknetProverResponse { proof: proof_str })
-            .map_err(|_| Status::internal("Could not parse the proof returned by the prover")),
+        Ok(prover_output) => {
+            prover_output
+                .serialized_proof()
+                .map(|proof_str| StarknetProverResponse {
+                    proof_size: proof_str.len() as u64,
+                    proof: proof_str,
+                    annotated_proof: annotated_proof
+                        .map(|annotated_proof| annotated_proof.to_string()),
+                    fact_hashes: task_facts
+                        .fact_hashes
+                        .iter()
+                        .map(format_fact_hash)
+                        .collect(),
+                    fact_topologies: task_facts
+                        .fact_topologies
+                        .into_iter()
+                        .map(Into::into)
+                        .collect(),
+                    public_memory_page_sizes: task_facts.public_memory_page_sizes,
+                    storage_ref: None,
+                    public_input,
+                    program_hashes: task_facts.program_hashes.iter().map(format_felt).collect(),
+                    task_outputs: task_facts
+                        .task_outputs
+                        .into_iter()
+                        .map(Into::into)
+                        .collect(),
+                    compressed_proof: vec![],
+                })
+        }
         Err(e) => Err(format_prover_error(e)),
     }
 }
 
-#[derive(Debug, Default)]
-pub struct StarknetProverService {}
+/// Compresses a serialized proof with gzip. The hex-encoded proofs of the Starknet OS
+/// exceed 100 MB and compress well.
+fn gzip_proof(proof: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(proof.as_bytes())?;
+    encoder.finish()
+}
 
-#[tonic::async_trait]
-impl StarknetProver for StarknetProverService {
-    async fn execute_and_prove(
+/// Parses a compiled bootloader program, normalizing its hints.
+fn parse_bootloader(bootloader_program: &[u8]) -> Result<Program, ProgramError> {
+    let mut hint_processor = BuiltinHintProcessor::new_empty();
+    let bootloader_program = normalize_program_hints(bootloader_program, &mut hint_processor);
+    Program::from_bytes(&bootloader_program, Some("main"))
+}
+
+#[derive(Debug)]
+pub struct StarknetProverService {
+    /// Parsed once when the service is created. Cloning a program is cheap as its data
+    /// is reference-counted.
+    bootloader_program: Program,
+    prover_backend: Arc<dyn ProverBackend>,
+    proof_storage: Option<Arc<dyn ProofStorage>>,
+    idempotency_cache: IdempotencyCache<StarknetProverResponse>,
+    proof_limiter: Option<Arc<ProofLimiter>>,
+    parameter_presets: ParameterPresets,
+    job_history: Arc<JobHistory>,
+    workspace_tracker: Arc<WorkspaceTracker>,
+    input_limits: InputLimits,
+    policy: ProverPolicy,
+}
+
+impl StarknetProverService {
+    /// Creates the service with the bootloader embedded in the server.
+    ///
+    /// The embedded bootloader is parsed once per process and shared by all the services.
+    pub fn new() -> Result<Self, ProgramError> {
+        static EMBEDDED_BOOTLOADER: OnceLock<Program> = OnceLock::new();
+
+        if let Some(bootloader_program) = EMBEDDED_BOOTLOADER.get() {
+            return Ok(Self::with_bootloader_program(bootloader_program.clone()));
+        }
+        let bootloader_program = parse_bootloader(BOOTLOADER_PROGRAM)?;
+        let bootloader_program = EMBEDDED_BOOTLOADER.get_or_init(|| bootloader_program);
+        Ok(Self::with_bootloader_program(bootloader_program.clone()))
+    }
+
+    /// Creates the service with the specified (compiled) bootloader program.
+    pub fn with_bootloader(bootloader_program: &[u8]) -> Result<Self, ProgramError> {
+        parse_bootloader(bootloader_program).map(Self::with_bootloader_program)
+    }
+
+    fn with_bootloader_program(bootloader_program: Program) -> Self {
+        Self {
+            bootloader_program,
+            prover_backend: Arc::new(StoneProverBackend),
+            proof_storage: None,
+            idempotency_cache: IdempotencyCache::default(),
+            proof_limiter: None,
+            parameter_presets: ParameterPresets::default(),
+            job_history: Arc::new(JobHistory::default()),
+            workspace_tracker: Arc::new(WorkspaceTracker::default()),
+            input_limits: InputLimits::default(),
+            policy: ProverPolicy::default(),
+        }
+    }
+
+    /// Replaces the backend generating the proofs.
+    pub fn with_prover_backend(mut self, prover_backend: Arc<dyn ProverBackend>) -> Self {
+        self.prover_backend = prover_backend;
+        self
+    }
+
+    /// Archives the generated proofs in the specified storage.
+    pub fn with_proof_storage(mut self, proof_storage: Arc<dyn ProofStorage>) -> Self {
+        self.proof_storage = Some(proof_storage);
+        self
+    }
+
+    /// Limits the number of concurrent proofs.
+    pub fn with_proof_limiter(mut self, proof_limiter: Arc<ProofLimiter>) -> Self {
+        self.proof_limiter = Some(proof_limiter);
+        self
+    }
+
+    /// Replaces the default prover parameter presets.
+    pub fn with_parameter_presets(mut self, parameter_presets: ParameterPresets) -> Self {
+        self.parameter_presets = parameter_presets;
+        self
+    }
+
+    /// Records the completed jobs in the specified history, to share it with other services.
+    pub fn with_job_history(mut self, job_history: Arc<JobHistory>) -> Self {
+        self.job_history = job_history;
+        self
+    }
+
+    /// Registers the working directories of the prover in the specified tracker, to share it
+    /// with other services.
+    pub fn with_workspace_tracker(mut self, workspace_tracker: Arc<WorkspaceTracker>) -> Self {
+        self.workspace_tracker = workspace_tracker;
+        self
+    }
+
+    /// Replaces the default size limits of the programs and PIEs.
+    pub fn with_input_limits(mut self, input_limits: InputLimits) -> Self {
+        self.input_limits = input_limits;
+        self
+    }
+
+    /// Restricts the proofs of the service, see `ProverPolicy`.
+    pub fn with_policy(mut self, policy: ProverPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Checks the size of the tasks before parsing them, see `InputLimits`.
+    fn check_task_sizes(&self, programs: &[Vec<u8>], pies: &[Vec<u8>]) -> Result<(), Status> {
+        let program_checks = programs
+            .iter()
+            .map(|program| self.input_limits.check_program(program));
+        let pie_checks = pies.iter().map(|pie| self.input_limits.check_pie(pie));
+
+        program_checks
+            .chain(pie_checks)
+            .enumerate()
+            .try_for_each(|(task_index, result)| {
+                result.map_err(|status| {
+                    Status::invalid_argument(format!("Task {task_index}: {}", status.message()))
+                })
+            })
+    }
+
+    async fn run_execute_and_prove(
         &self,
-        request: Request<StarknetExecutionRequest>,
-    ) -> Result<Response<StarknetProverResponse>, Status> {
+        request: StarknetExecutionRequest,
+        job: &mut Job,
+    ) -> Result<StarknetProverResponse, Status> {
         let StarknetExecutionRequest {
             programs,
             pies,
             split_proof,
-        } = request.into_inner();
+            prover_config: prover_config_str,
+            prover_parameters: prover_parameters_str,
+            compress_proof,
+            reuse_annotations,
+            small_proof,
+        } = request;
 
-        let bootloader_program = Program::from_bytes(BOOTLOADER_PROGRAM, Some("main"))
-            .map_err(|e| Status::internal(format!("Failed to load bootloader program: {}", e)))?;
-        let prover_config = ProverConfig::default();
+        self.check_task_sizes(&programs, &pies)?;
+        self.policy.check_split_proof(split_proof)?;
+        self.policy
+            .check_user_parameters(prover_parameters_str.is_some())?;
+        self.policy.check_layout(BOOTLOADER_LAYOUT)?;
+        self.policy.check_small_proof(small_proof)?;
+        let prover_config = get_prover_config(prover_config_str)?;
 
         let bootloader_tasks = make_bootloader_tasks(&programs, &pies).map_err(|e| {
             Status::invalid_argument(format!("Could not parse programs/PIEs: {}", e))
         })?;
+        let program_hashes = task_program_hashes(&bootloader_tasks)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
 
-        let execution_artifacts =
-            run_bootloader_in_proof_mode(&bootloader_program, bootloader_tasks)
-                .map_err(|e| Status::internal(format!("Failed to run bootloader: {e}")))?;
+        let (execution_artifacts, fact_topologies) = tracing::info_span!("execute")
+            .in_scope(|| execute_bootloader_tasks(&self.bootloader_program, bootloader_tasks))?;
+        self.policy
+            .check_n_steps(execution_artifacts.public_input.n_steps)?;
+        let proof_limiter = self.proof_limiter.as_deref();
+        let proof_class = if small_proof {
+            ProofClass::Small
+        } else {
+            ProofClass::Large
+        };
+        check_proof_n_steps(
+            proof_limiter,
+            proof_class,
+            execution_artifacts.public_input.n_steps,
+        )?;
+        let task_facts = get_task_facts(&execution_artifacts, program_hashes, fact_topologies)?;
 
-        let prover_parameters =
-            get_prover_parameters(None, execution_artifacts.public_input.n_steps)?;
+        let prover_parameters = get_prover_parameters(
+            prover_parameters_str,
+            execution_artifacts.public_input.n_steps,
+            &execution_artifacts.public_input.layout,
+            &self.parameter_presets,
+            ParameterOverrides::default(),
+        )?;
+        if split_proof {
+            // Fails before proving rather than after
+            fri_inner_layer_count(&prover_parameters.stark.fri.fri_step_list)
+                .map_err(format_split_proof_error)?;
+        }
 
-        let (mut proof, mut working_dir) =
-            call_prover(&execution_artifacts, &prover_config, &prover_parameters)
-                .await
-                .map_err(format_prover_error)?;
+        check_disk_space_for_proof(
+            execution_artifacts.memory.len(),
+            execution_artifacts.trace.len(),
+        )?;
+
+        let permit = acquire_proof_permit(proof_limiter, proof_class).await?;
+        job.record_permit(permit.as_ref());
+        job.layout = execution_artifacts.public_input.layout.clone();
+        job.n_steps = execution_artifacts.public_input.n_steps;
+        let prove_span = tracing::info_span!(
+            "prove",
+            layout = %job.layout,
+            n_steps = job.n_steps,
+        );
+        let started_at = Instant::now();
+        let prover_result = self
+            .prover_backend
+            .prove(&execution_artifacts, &prover_config, &prover_parameters)
+            .instrument(prove_span)
+            .await;
+        job.prove_duration = Some(started_at.elapsed());
+        let mut prover_output = prover_result.map_err(format_prover_error)?;
+        if let Some(working_dir) = &prover_output.working_dir {
+            self.workspace_tracker
+                .register(working_dir.path(), job.request_id());
+        }
 
         // If split proof was requested, build it
-        if split_proof {
-            verify_and_annotate_proof(&mut proof, &mut working_dir).await?;
+        let annotated_proof = if split_proof {
+            Some(
+                verify_and_annotate_proof(
+                    self.prover_backend.as_ref(),
+                    &mut prover_output,
+                    reuse_annotations,
+                )
+                .await?,
+            )
+        } else {
+            None
         };
 
-        format_prover_result(Ok((proof, working_dir))).map(Response::new)
+        let mut response = format_prover_result(
+            Ok(prover_output),
+            annotated_proof,
+            task_facts,
+            &execution_artifacts.public_input,
+        )?;
+
+        if let Some(proof_storage) = &self.proof_storage {
+            let stored_proof = StoredProof {
+                proof: response.proof.clone(),
+                public_input: Some(response.public_input.clone()),
+                annotated_proof: response.annotated_proof.clone(),
+            };
+            response.storage_ref = archive_proof(proof_storage.as_ref(), &stored_proof)
+                .await
+                .map(|storage_ref| storage_ref.0);
+        }
+
+        if compress_proof {
+            // Compressing a proof of the Starknet OS takes seconds, off the async workers
+            let proof = std::mem::take(&mut response.proof);
+            response.compressed_proof = tokio::task::spawn_blocking(move || gzip_proof(&proof))
+                .await
+                .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+                .map_err(|e| Status::internal(format!("Could not compress the proof: {e}")))?;
+        }
+
+        // Only complete proofs count in the wait estimates, see `record_success`
+        if let Some(permit) = &permit {
+            permit.record_success();
+        }
+        Ok(response)
+    }
+
+    fn proof_storage(&self) -> Result<&dyn ProofStorage, Status> {
+        self.proof_storage
+            .as_deref()
+            .ok_or_else(|| Status::unimplemented("Proof storage is not configured"))
+    }
+}
+
+#[tonic::async_trait]
+impl StarknetProver for StarknetProverService {
+    async fn execute_and_prove(
+        &self,
+        request: Request<StarknetExecutionRequest>,
+    ) -> Result<Response<StarknetProverResponse>, Status> {
+        let idempotency_key = idempotency_key(&request);
+        let request = request.into_inner();
+        let request_hash = request_hash(&request);
+
+        self.idempotency_cache
+            .run(idempotency_key, request_hash, || async move {
+                let mut job = self
+                    .job_history
+                    .start("starknet_prover.StarknetProver/ExecuteAndProve");
+                let result = self.run_execute_and_prove(request, &mut job).await;
+                self.job_history
+                    .record(job.finish(&result, |response| response.proof_size));
+                result
+            })
+            .await
+            .map(Response::new)
+    }
+
+    async fn get_stored_proof(
+        &self,
+        request: Request<GetStoredProofRequest>,
+    ) -> Result<Response<StoredProofResponse>, Status> {
+        let GetStoredProofRequest { storage_ref } = request.into_inner();
+
+        let stored_proof = self
+            .proof_storage()?
+            .fetch(&StorageRef(storage_ref))
+            .await?;
+
+        Ok(Response::new(StoredProofResponse {
+            proof: stored_proof.proof,
+            public_input: stored_proof.public_input,
+            annotated_proof: stored_proof.annotated_proof,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_cases::get_test_case_file_path;
+
+    use crate::facts::compute_fact_hash;
+    use crate::services::common::mock::MockProverBackend;
+
+    use super::*;
+
+    fn fibonacci_program() -> Vec<u8> {
+        std::fs::read(get_test_case_file_path(
+            "bootloader/programs/fibonacci/program.json",
+        ))
+        .unwrap()
+    }
+
+    fn fibonacci_pie() -> Vec<u8> {
+        std::fs::read(get_test_case_file_path(
+            "bootloader/pies/fibonacci/cairo_pie.zip",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_service_loads_embedded_bootloader() {
+        assert!(StarknetProverService::new().is_ok());
+    }
+
+    #[test]
+    fn test_service_invalid_bootloader() {
+        let result = StarknetProverService::with_bootloader(b"not a program");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_make_bootloader_tasks() {
+        let tasks = make_bootloader_tasks(&[fibonacci_program()], &[fibonacci_pie()]).unwrap();
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[test]
+    fn test_task_program_hashes() {
+        let tasks = make_bootloader_tasks(&[fibonacci_program()], &[fibonacci_pie()]).unwrap();
+
+        let program_hashes = task_program_hashes(&tasks).unwrap();
+
+        assert_eq!(program_hashes.len(), 2);
+        assert_eq!(
+            format_felt(&program_hashes[0]),
+            "0x043b17e9592f33142246af4c06cd2b574b460dd1f718d76b51341175a62b220f"
+        );
+    }
+
+    #[test]
+    fn test_program_without_main() {
+        let mut program: serde_json::Value = serde_json::from_slice(&fibonacci_program()).unwrap();
+        program["identifiers"]
+            .as_object_mut()
+            .unwrap()
+            .remove("__main__.main");
+        let program = serde_json::to_vec(&program).unwrap();
+
+        let error = make_bootloader_tasks(&[fibonacci_program(), program], &[]).unwrap_err();
+
+        assert!(matches!(error, BootloaderTaskError::MissingEntrypoint(1)));
+        assert_eq!(
+            error.to_string(),
+            "Task 1: program has no `main` entrypoint"
+        );
+    }
+
+    #[test]
+    fn test_pie_task_is_not_normalized() {
+        // Re-indenting the hints of the program task does not affect the PIE task
+        let mut program: serde_json::Value = serde_json::from_slice(&fibonacci_program()).unwrap();
+        for hint in program["hints"]
+            .as_object_mut()
+            .unwrap()
+            .values_mut()
+            .flat_map(|hints| hints.as_array_mut().unwrap())
+        {
+            let code = hint["code"].as_str().unwrap();
+            let reindented_code: Vec<String> =
+                code.lines().map(|line| format!("    {line}")).collect();
+            hint["code"] = format!("\n{}\n", reindented_code.join("\n")).into();
+        }
+        let program = serde_json::to_vec(&program).unwrap();
+
+        let tasks = make_bootloader_tasks(&[program], &[fibonacci_pie()]).unwrap();
+        let expected_tasks =
+            make_bootloader_tasks(&[fibonacci_program()], &[fibonacci_pie()]).unwrap();
+
+        assert_eq!(
+            task_program_hashes(&tasks).unwrap(),
+            task_program_hashes(&expected_tasks).unwrap()
+        );
+        assert!(matches!(tasks[1].task, Task::Pie(_)));
+    }
+
+    #[test]
+    fn test_pie_only_tasks() {
+        let tasks = make_bootloader_tasks(&[], &[fibonacci_pie(), fibonacci_pie()]).unwrap();
+
+        let program_hashes = task_program_hashes(&tasks).unwrap();
+
+        assert_eq!(program_hashes.len(), 2);
+        assert_eq!(program_hashes[0], program_hashes[1]);
+    }
+
+    #[test]
+    fn test_services_share_the_embedded_bootloader() {
+        let first = StarknetProverService::new().unwrap();
+        let second = StarknetProverService::new().unwrap();
+        assert_eq!(first.bootloader_program, second.bootloader_program);
+    }
+
+    #[test]
+    fn test_invalid_pie() {
+        let error = make_bootloader_tasks(&[fibonacci_program()], &[vec![0u8; 16]]).unwrap_err();
+        assert!(matches!(error, BootloaderTaskError::Pie(1, _)));
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_with_mock_backend() {
+        let proof_file = "bootloader/programs/fibonacci/output/proof.json";
+        let prover_backend = Arc::new(MockProverBackend::new(proof_file));
+        let service = StarknetProverService::new()
+            .unwrap()
+            .with_prover_backend(prover_backend.clone());
+        let request = StarknetExecutionRequest {
+            programs: vec![fibonacci_program()],
+            ..Default::default()
+        };
+
+        let response = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let proof: serde_json::Value = serde_json::from_str(&response.proof).unwrap();
+        let expected_proof: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(get_test_case_file_path(proof_file)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(proof["proof_hex"], expected_proof["proof_hex"]);
+        assert_eq!(response.fact_hashes.len(), 1);
+        assert_eq!(
+            response.program_hashes,
+            vec!["0x043b17e9592f33142246af4c06cd2b574b460dd1f718d76b51341175a62b220f"]
+        );
+        assert_eq!(prover_backend.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_records_jobs() {
+        let proof_file = "bootloader/programs/fibonacci/output/proof.json";
+        let job_history = Arc::new(JobHistory::default());
+        let service = StarknetProverService::new()
+            .unwrap()
+            .with_prover_backend(Arc::new(MockProverBackend::new(proof_file)))
+            .with_job_history(job_history.clone());
+
+        let mut proof_sizes = vec![];
+        for programs in [vec![fibonacci_program()], vec![b"not a program".to_vec()]] {
+            let request = StarknetExecutionRequest {
+                programs,
+                ..Default::default()
+            };
+            let result = service.execute_and_prove(Request::new(request)).await;
+            proof_sizes.push(result.map_or(0, |response| response.into_inner().proof_size));
+        }
+
+        let jobs = job_history.recent(0);
+        assert_eq!(jobs.len(), 2);
+        assert!(jobs
+            .iter()
+            .all(|job| job.rpc == "starknet_prover.StarknetProver/ExecuteAndProve"));
+        assert_eq!(jobs[0].status_code, tonic::Code::InvalidArgument);
+        assert_eq!(jobs[1].status_code, tonic::Code::Ok);
+        assert_eq!(jobs[1].layout, BOOTLOADER_LAYOUT);
+        assert_eq!(
+            jobs.iter().map(|job| job.proof_size).collect::<Vec<_>>(),
+            vec![0, proof_sizes[0]]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_invalid_program() {
+        let prover_backend = Arc::new(MockProverBackend::failing("unreachable"));
+        let service = StarknetProverService::new()
+            .unwrap()
+            .with_prover_backend(prover_backend.clone());
+        let request = StarknetExecutionRequest {
+            programs: vec![b"not a program".to_vec()],
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status
+            .message()
+            .starts_with("Could not parse programs/PIEs"));
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split_proof_without_fri_inner_layers() {
+        let prover_backend = Arc::new(MockProverBackend::failing("unreachable"));
+        let service = StarknetProverService::new()
+            .unwrap()
+            .with_prover_backend(prover_backend.clone());
+        // The whole FRI degree of the small fibonacci program in the last layer
+        let mut prover_parameters: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(get_test_case_file_path(
+                "bootloader/programs/fibonacci/cpu_air_params.json",
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        prover_parameters["stark"]["fri"]["fri_step_list"] = serde_json::json!([0]);
+        let request = StarknetExecutionRequest {
+            programs: vec![fibonacci_program()],
+            split_proof: true,
+            prover_parameters: Some(prover_parameters.to_string()),
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(
+            status.message().contains("Proof too small to split"),
+            "{}",
+            status.message()
+        );
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_pie_too_large() {
+        let prover_backend = Arc::new(MockProverBackend::failing("unreachable"));
+        let pie = fibonacci_pie();
+        let service = StarknetProverService::new()
+            .unwrap()
+            .with_prover_backend(prover_backend.clone())
+            .with_input_limits(InputLimits {
+                max_pie_uncompressed_size: pie.len() as u64,
+                ..InputLimits::default()
+            });
+        let request = StarknetExecutionRequest {
+            programs: vec![fibonacci_program()],
+            pies: vec![pie],
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(
+            status
+                .message()
+                .starts_with("Task 1: Uncompressed PIE is too large"),
+            "{}",
+            status.message()
+        );
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split_proof_not_allowed() {
+        let prover_backend = Arc::new(MockProverBackend::failing("unreachable"));
+        let service = StarknetProverService::new()
+            .unwrap()
+            .with_prover_backend(prover_backend.clone())
+            .with_policy(ProverPolicy {
+                allow_split_proof: false,
+                ..ProverPolicy::default()
+            });
+        let request = StarknetExecutionRequest {
+            programs: vec![fibonacci_program()],
+            split_proof: true,
+            ..Default::default()
+        };
+
+        let status = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        assert!(
+            status.message().contains("allow_split_proof"),
+            "{}",
+            status.message()
+        );
+        assert!(prover_backend.calls().is_empty());
+    }
+
+    #[test]
+    fn test_gzip_proof() {
+        let proof = std::fs::read_to_string(get_test_case_file_path(
+            "bootloader/programs/fibonacci/output/proof.json",
+        ))
+        .unwrap();
+
+        let compressed_proof = gzip_proof(&proof).unwrap();
+        assert!(compressed_proof.len() < proof.len());
+
+        let mut decompressed_proof = String::new();
+        flate2::read::GzDecoder::new(compressed_proof.as_slice())
+            .read_to_string(&mut decompressed_proof)
+            .unwrap();
+        assert_eq!(decompressed_proof, proof);
+    }
+
+    /// Replaces a file of the fibonacci PIE archive.
+    fn patch_fibonacci_pie(name: &str, contents: &[u8]) -> Vec<u8> {
+        let pie = fibonacci_pie();
+        let mut archive = zip::ZipArchive::new(Cursor::new(pie.as_slice())).unwrap();
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index).unwrap();
+            let file_name = file.name().to_string();
+            let mut file_contents = Vec::new();
+            file.read_to_end(&mut file_contents).unwrap();
+            if file_name == name {
+                file_contents = contents.to_vec();
+            }
+            writer.start_file(file_name, options).unwrap();
+            writer.write_all(&file_contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_execute_and_prove_multi_page_task() {
+        // The PIE splits its output, [10, 144], in two pages merged into a single node
+        let additional_data = serde_json::json!({
+            "output_builtin": {
+                "pages": {"1": [1, 1]},
+                "attributes": {"gps_fact_topology": [2, 2]},
+            }
+        });
+        let pie = patch_fibonacci_pie(
+            "additional_data.json",
+            &serde_json::to_vec(&additional_data).unwrap(),
+        );
+        let service = StarknetProverService::new()
+            .unwrap()
+            .with_prover_backend(Arc::new(MockProverBackend::new(
+                "bootloader/programs/fibonacci/output/proof.json",
+            )));
+        let request = StarknetExecutionRequest {
+            pies: vec![pie],
+            ..Default::default()
+        };
+
+        let response = service
+            .execute_and_prove(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let fact_topology = FactTopology {
+            tree_structure: vec![2, 2],
+            page_sizes: vec![1, 1],
+        };
+        assert_eq!(response.fact_topologies, vec![fact_topology.clone().into()]);
+        let task_output = TaskOutput {
+            program_hash: Felt252::from_hex(&response.program_hashes[0]).unwrap(),
+            output: vec![Felt252::from(10u64), Felt252::from(144u64)],
+        };
+        let fact_hash = compute_fact_hash(&task_output, &fact_topology).unwrap();
+        assert_eq!(response.fact_hashes, vec![format_fact_hash(&fact_hash)]);
+        let single_page_fact_hash =
+            compute_fact_hash(&task_output, &FactTopology::single_page(2)).unwrap();
+        assert_ne!(fact_hash, single_page_fact_hash);
+    }
+
+    #[test]
+    fn test_pie_with_unsupported_version() {
+        let pie = patch_fibonacci_pie("version.json", br#"{"cairo_pie": "0.9"}"#);
+
+        let error = make_bootloader_tasks(&[fibonacci_program()], &[pie]).unwrap_err();
+
+        assert!(matches!(
+            error,
+            BootloaderTaskError::UnsupportedPieVersion(1, _)
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Task 1: unsupported PIE version 0.9, supported versions: 1.0, 1.1"
+        );
+    }
+
+    #[test]
+    fn test_pie_built_for_another_layout() {
+        // Layouts such as `all_cairo` order the builtins differently
+        let mut metadata: serde_json::Value =
+            read_pie_json(&fibonacci_pie(), "metadata.json").unwrap();
+        metadata["program"]["builtins"] = serde_json::json!(["output", "poseidon", "pedersen"]);
+        let pie = patch_fibonacci_pie("metadata.json", &serde_json::to_vec(&metadata).unwrap());
+
+        let error = make_bootloader_tasks(&[], &[pie]).unwrap_err();
+
+        assert!(matches!(
+            error,
+            BootloaderTaskError::UnorderedBuiltins(0, _)
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Task 0: PIE builtins are not ordered as in the starknet_with_keccak layout: output, poseidon, pedersen"
+        );
+    }
+
+    #[test]
+    fn test_pie_with_unsupported_builtins() {
+        let mut pie = CairoPie::from_bytes(&fibonacci_pie()).unwrap();
+        let output_segment = pie.metadata.builtin_segments["output"].clone();
+        pie.metadata
+            .builtin_segments
+            .insert("range_check96".to_string(), output_segment);
+
+        let error = validate_pie(3, &pie).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Task 3: PIE uses builtins that are not available in the starknet_with_keccak layout: range_check96"
+        );
     }
 }
@@ -0,0 +1,433 @@
+//! Archival of the proofs generated by the server.
+//!
+//! When a storage backend is configured, proofs are stored after a successful proving request
+//! and can be retrieved later with the `GetStoredProof` RPC.
+
+use std::fmt::Debug;
+use std::path::PathBuf;
+
+use sha3::{Digest, Keccak256};
+use tonic::Status;
+
+const PROOF_FILE: &str = "proof.json";
+const PUBLIC_INPUT_FILE: &str = "public_input.json";
+const ANNOTATED_PROOF_FILE: &str = "annotated_proof.json";
+
+/// Reference to a stored proof, returned to clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageRef(pub String);
+
+/// A proof and the optional artifacts stored alongside it. All fields are JSON documents.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StoredProof {
+    pub proof: String,
+    pub public_input: Option<String>,
+    pub annotated_proof: Option<String>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum StorageError {
+    #[error("Invalid storage reference: {0}")]
+    InvalidRef(String),
+    #[error("No proof stored under {0}")]
+    NotFound(String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+impl From<StorageError> for Status {
+    fn from(e: StorageError) -> Self {
+        match e {
+            StorageError::InvalidRef(_) => Status::invalid_argument(e.to_string()),
+            StorageError::NotFound(_) => Status::not_found(e.to_string()),
+            StorageError::Io(_) | StorageError::Backend(_) => {
+                Status::internal(format!("Proof storage failed: {e}"))
+            }
+        }
+    }
+}
+
+/// A backend where proofs are archived.
+#[tonic::async_trait]
+pub trait ProofStorage: Debug + Send + Sync {
+    /// Stores a proof and its artifacts under the specified request ID.
+    async fn store(
+        &self,
+        request_id: &str,
+        stored_proof: &StoredProof,
+    ) -> Result<StorageRef, StorageError>;
+
+    /// Retrieves a proof previously stored with `store`.
+    async fn fetch(&self, storage_ref: &StorageRef) -> Result<StoredProof, StorageError>;
+}
+
+/// Derives a request ID from the content of the proof, so that storing the same proof twice
+/// does not create duplicates.
+pub fn proof_request_id(proof: &str) -> String {
+    let hash = Keccak256::digest(proof.as_bytes());
+    hash[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Stores the proof of a successful proving request, under the request ID derived from the
+/// proof. Storage failures are logged and do not fail the request: the proof is still returned
+/// to the client, without a storage reference.
+pub async fn archive_proof(
+    proof_storage: &dyn ProofStorage,
+    stored_proof: &StoredProof,
+) -> Option<StorageRef> {
+    let request_id = proof_request_id(&stored_proof.proof);
+    match proof_storage.store(&request_id, stored_proof).await {
+        Ok(storage_ref) => Some(storage_ref),
+        Err(e) => {
+            tracing::error!(request_id, "Could not store the proof: {e}");
+            None
+        }
+    }
+}
+
+/// Request IDs are used as path components and object keys, only allow safe characters.
+fn validate_request_id(request_id: &str) -> Result<(), StorageError> {
+    let is_valid = !request_id.is_empty()
+        && request_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !is_valid {
+        return Err(StorageError::InvalidRef(request_id.to_string()));
+    }
+    Ok(())
+}
+
+/// Stores each proof in its own directory: `<root>/<request_id>/proof.json`.
+#[derive(Debug, Clone)]
+pub struct FilesystemStorage {
+    root: PathBuf,
+}
+
+impl FilesystemStorage {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+async fn read_optional_file(path: PathBuf) -> Result<Option<String>, StorageError> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[tonic::async_trait]
+impl ProofStorage for FilesystemStorage {
+    async fn store(
+        &self,
+        request_id: &str,
+        stored_proof: &StoredProof,
+    ) -> Result<StorageRef, StorageError> {
+        validate_request_id(request_id)?;
+
+        let proof_dir = self.root.join(request_id);
+        tokio::fs::create_dir_all(&proof_dir).await?;
+
+        tokio::fs::write(proof_dir.join(PROOF_FILE), &stored_proof.proof).await?;
+        if let Some(public_input) = &stored_proof.public_input {
+            tokio::fs::write(proof_dir.join(PUBLIC_INPUT_FILE), public_input).await?;
+        }
+        if let Some(annotated_proof) = &stored_proof.annotated_proof {
+            tokio::fs::write(proof_dir.join(ANNOTATED_PROOF_FILE), annotated_proof).await?;
+        }
+
+        Ok(StorageRef(request_id.to_string()))
+    }
+
+    async fn fetch(&self, storage_ref: &StorageRef) -> Result<StoredProof, StorageError> {
+        validate_request_id(&storage_ref.0)?;
+
+        let proof_dir = self.root.join(&storage_ref.0);
+        let proof = read_optional_file(proof_dir.join(PROOF_FILE))
+            .await?
+            .ok_or_else(|| StorageError::NotFound(storage_ref.0.clone()))?;
+
+        Ok(StoredProof {
+            proof,
+            public_input: read_optional_file(proof_dir.join(PUBLIC_INPUT_FILE)).await?,
+            annotated_proof: read_optional_file(proof_dir.join(ANNOTATED_PROOF_FILE)).await?,
+        })
+    }
+}
+
+#[cfg(feature = "s3")]
+pub use s3::{ObjectStore, S3Storage};
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use super::*;
+
+    /// Minimal object store interface, implemented for the AWS S3 client. Any S3-compatible
+    /// service can be used by configuring the endpoint of the client.
+    #[tonic::async_trait]
+    pub trait ObjectStore: Debug + Send + Sync {
+        async fn put_object(&self, bucket: &str, key: &str, data: Vec<u8>) -> Result<(), String>;
+
+        /// Returns `Ok(None)` if the object does not exist.
+        async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, String>;
+    }
+
+    #[tonic::async_trait]
+    impl ObjectStore for aws_sdk_s3::Client {
+        async fn put_object(&self, bucket: &str, key: &str, data: Vec<u8>) -> Result<(), String> {
+            aws_sdk_s3::Client::put_object(self)
+                .bucket(bucket)
+                .key(key)
+                .body(data.into())
+                .send()
+                .await
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        }
+
+        async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, String> {
+            let response = aws_sdk_s3::Client::get_object(self)
+                .bucket(bucket)
+                .key(key)
+                .send()
+                .await;
+            match response {
+                Ok(output) => output
+                    .body
+                    .collect()
+                    .await
+                    .map(|data| Some(data.into_bytes().to_vec()))
+                    .map_err(|e| e.to_string()),
+                Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+    }
+
+    /// Stores each proof under `<prefix>/<request_id>/proof.json` in an S3 bucket.
+    #[derive(Debug)]
+    pub struct S3Storage<C: ObjectStore> {
+        client: C,
+        bucket: String,
+        prefix: String,
+    }
+
+    impl<C: ObjectStore> S3Storage<C> {
+        pub fn new(client: C, bucket: String, prefix: String) -> Self {
+            Self {
+                client,
+                bucket,
+                prefix,
+            }
+        }
+
+        fn key(&self, request_id: &str, file: &str) -> String {
+            if self.prefix.is_empty() {
+                format!("{request_id}/{file}")
+            } else {
+                format!("{}/{request_id}/{file}", self.prefix.trim_end_matches('/'))
+            }
+        }
+
+        async fn get_string(&self, key: String) -> Result<Option<String>, StorageError> {
+            let data = self
+                .client
+                .get_object(&self.bucket, &key)
+                .await
+                .map_err(StorageError::Backend)?;
+            data.map(|data| {
+                String::from_utf8(data)
+                    .map_err(|_| StorageError::Backend(format!("{key} is not valid UTF-8")))
+            })
+            .transpose()
+        }
+    }
+
+    #[tonic::async_trait]
+    impl<C: ObjectStore> ProofStorage for S3Storage<C> {
+        async fn store(
+            &self,
+            request_id: &str,
+            stored_proof: &StoredProof,
+        ) -> Result<StorageRef, StorageError> {
+            validate_request_id(request_id)?;
+
+            let files = [
+                (PROOF_FILE, Some(&stored_proof.proof)),
+                (PUBLIC_INPUT_FILE, stored_proof.public_input.as_ref()),
+                (ANNOTATED_PROOF_FILE, stored_proof.annotated_proof.as_ref()),
+            ];
+            for (file, content) in files {
+                if let Some(content) = content {
+                    self.client
+                        .put_object(
+                            &self.bucket,
+                            &self.key(request_id, file),
+                            content.clone().into_bytes(),
+                        )
+                        .await
+                        .map_err(StorageError::Backend)?;
+                }
+            }
+
+            Ok(StorageRef(request_id.to_string()))
+        }
+
+        async fn fetch(&self, storage_ref: &StorageRef) -> Result<StoredProof, StorageError> {
+            let request_id = &storage_ref.0;
+            validate_request_id(request_id)?;
+
+            let proof = self
+                .get_string(self.key(request_id, PROOF_FILE))
+                .await?
+                .ok_or_else(|| StorageError::NotFound(request_id.clone()))?;
+
+            Ok(StoredProof {
+                proof,
+                public_input: self
+                    .get_string(self.key(request_id, PUBLIC_INPUT_FILE))
+                    .await?,
+                annotated_proof: self
+                    .get_string(self.key(request_id, ANNOTATED_PROOF_FILE))
+                    .await?,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::collections::HashMap;
+        use std::sync::Mutex;
+
+        use super::*;
+
+        /// In-memory fake of an S3 bucket.
+        #[derive(Debug, Default)]
+        struct FakeObjectStore {
+            objects: Mutex<HashMap<(String, String), Vec<u8>>>,
+        }
+
+        #[tonic::async_trait]
+        impl ObjectStore for FakeObjectStore {
+            async fn put_object(
+                &self,
+                bucket: &str,
+                key: &str,
+                data: Vec<u8>,
+            ) -> Result<(), String> {
+                self.objects
+                    .lock()
+                    .unwrap()
+                    .insert((bucket.to_string(), key.to_string()), data);
+                Ok(())
+            }
+
+            async fn get_object(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>, String> {
+                Ok(self
+                    .objects
+                    .lock()
+                    .unwrap()
+                    .get(&(bucket.to_string(), key.to_string()))
+                    .cloned())
+            }
+        }
+
+        #[tokio::test]
+        async fn test_s3_storage_round_trip() {
+            let storage = S3Storage::new(
+                FakeObjectStore::default(),
+                "proofs".to_string(),
+                "madara/".to_string(),
+            );
+            let stored_proof = StoredProof {
+                proof: "{}".to_string(),
+                public_input: None,
+                annotated_proof: Some("[]".to_string()),
+            };
+
+            let storage_ref = storage.store("abc", &stored_proof).await.unwrap();
+
+            assert!(storage
+                .client
+                .objects
+                .lock()
+                .unwrap()
+                .contains_key(&("proofs".to_string(), "madara/abc/proof.json".to_string())));
+            assert_eq!(storage.fetch(&storage_ref).await.unwrap(), stored_proof);
+        }
+
+        #[tokio::test]
+        async fn test_s3_storage_not_found() {
+            let storage = S3Storage::new(
+                FakeObjectStore::default(),
+                "proofs".to_string(),
+                String::new(),
+            );
+
+            let result = storage.fetch(&StorageRef("abc".to_string())).await;
+
+            assert!(matches!(result, Err(StorageError::NotFound(_))));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_filesystem_storage_round_trip() {
+        let root = tempfile::tempdir().unwrap();
+        let storage = FilesystemStorage::new(root.path());
+        let stored_proof = StoredProof {
+            proof: r#"{"proof_hex": "0x1234"}"#.to_string(),
+            public_input: Some(r#"{"layout": "starknet_with_keccak"}"#.to_string()),
+            annotated_proof: None,
+        };
+
+        let request_id = proof_request_id(&stored_proof.proof);
+        let storage_ref = storage.store(&request_id, &stored_proof).await.unwrap();
+
+        assert!(root.path().join(&request_id).join(PROOF_FILE).exists());
+        assert_eq!(storage.fetch(&storage_ref).await.unwrap(), stored_proof);
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_storage_not_found() {
+        let root = tempfile::tempdir().unwrap();
+        let storage = FilesystemStorage::new(root.path());
+
+        let result = storage.fetch(&StorageRef("abc".to_string())).await;
+
+        assert!(matches!(result, Err(StorageError::NotFound(_))));
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::parent_dir("..")]
+    #[case::path("a/b")]
+    #[tokio::test]
+    async fn test_filesystem_storage_invalid_ref(#[case] storage_ref: &str) {
+        let root = tempfile::tempdir().unwrap();
+        let storage = FilesystemStorage::new(root.path());
+
+        let result = storage.fetch(&StorageRef(storage_ref.to_string())).await;
+
+        assert!(matches!(result, Err(StorageError::InvalidRef(_))));
+    }
+
+    #[test]
+    fn test_proof_request_id_is_deterministic() {
+        assert_eq!(proof_request_id("proof"), proof_request_id("proof"));
+        assert_ne!(proof_request_id("proof"), proof_request_id("other proof"));
+        assert_eq!(proof_request_id("proof").len(), 32);
+    }
+}
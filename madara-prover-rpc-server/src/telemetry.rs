@@ -0,0 +1,231 @@
+//! Export of the request traces to an OpenTelemetry collector, with the `otel` feature.
+//!
+//! Each RPC gets a span, a child of the `traceparent` of the request if any. The services
+//! open `execute` and `prove` spans inside it, the latter with the layout and number of
+//! steps of the proof.
+
+use std::task::{Context, Poll};
+
+use opentelemetry::propagation::Extractor;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::Resource;
+use tonic::codegen::http;
+use tower::{Layer, Service};
+use tracing::instrument::Instrumented;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::error::ServerError;
+use crate::logging::stderr_layer;
+
+/// Name of the server in the exported traces.
+pub const SERVICE_NAME: &str = "madara-prover";
+
+/// Standard variables configuring the OTLP endpoint. The exporter is only installed if one
+/// of them is set, or if the endpoint is set in the server configuration.
+const OTLP_ENDPOINT_ENV_VARS: [&str; 2] = [
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+];
+
+/// Installs the OTLP exporter and the `traceparent` propagator.
+///
+/// `otlp_endpoint` takes precedence over the `OTEL_EXPORTER_OTLP_*` variables. The log events
+/// are still printed on stderr. Returns `false` without installing anything if the endpoint is
+/// not configured.
+pub fn init_tracing(otlp_endpoint: Option<&str>) -> Result<bool, ServerError> {
+    let endpoint_in_env = OTLP_ENDPOINT_ENV_VARS
+        .iter()
+        .any(|name| std::env::var_os(name).is_some());
+    if otlp_endpoint.is_none() && !endpoint_in_env {
+        return Ok(false);
+    }
+
+    let mut exporter = opentelemetry_otlp::new_exporter().tonic().with_env();
+    if let Some(endpoint) = otlp_endpoint {
+        exporter = exporter.with_endpoint(endpoint);
+    }
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(
+            opentelemetry_sdk::trace::config()
+                .with_resource(Resource::new([KeyValue::new("service.name", SERVICE_NAME)])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .map_err(|e| ServerError::Telemetry(e.to_string()))?;
+
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(stderr_layer())
+        .try_init()
+        .map_err(|e| ServerError::Telemetry(e.to_string()))?;
+
+    Ok(true)
+}
+
+/// Exports the remaining spans before the server exits.
+pub fn shutdown_tracing() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+struct HeaderExtractor<'a>(&'a http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+/// Creates the span of an RPC, named after its path and continuing the trace of the
+/// `traceparent` metadata of the request.
+pub fn rpc_span<B>(request: &http::Request<B>) -> tracing::Span {
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(request.headers()))
+    });
+
+    let span = tracing::info_span!(
+        "rpc",
+        otel.name = request.uri().path(),
+        otel.kind = "server",
+        rpc.system = "grpc",
+    );
+    span.set_parent(parent);
+    span
+}
+
+/// Runs each request in its RPC span, see `rpc_span`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceContextLayer;
+
+impl<S> Layer<S> for TraceContextLayer {
+    type Service = TraceContext<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceContext { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceContext<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<http::Request<ReqBody>> for TraceContext<S>
+where
+    S: Service<http::Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Instrumented<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let span = rpc_span(&request);
+        self.inner.call(request).instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use opentelemetry::trace::{SpanId, TraceId, TracerProvider as _};
+    use opentelemetry_sdk::export::trace::SpanData;
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use test_cases::get_test_case_file_path;
+    use tonic::Request;
+
+    use crate::services::common::mock::MockProverBackend;
+    use crate::services::prover::prover_proto::prover_server::Prover;
+    use crate::services::prover::prover_proto::ExecutionRequest;
+    use crate::services::prover::ProverService;
+
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+    fn find_span<'a>(spans: &'a [SpanData], name: &str) -> &'a SpanData {
+        spans
+            .iter()
+            .find(|span| span.name == name)
+            .unwrap_or_else(|| panic!("No {name} span"))
+    }
+
+    fn attribute(span: &SpanData, key: &str) -> Option<String> {
+        span.attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .map(|attribute| attribute.value.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_span_hierarchy() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+        let service = ProverService::default().with_prover_backend(Arc::new(
+            MockProverBackend::new("fibonacci/fibonacci_proof.json"),
+        ));
+        let request = ExecutionRequest {
+            program: std::fs::read(get_test_case_file_path("fibonacci/fibonacci_compiled.json"))
+                .unwrap(),
+            ..Default::default()
+        };
+        let http_request = http::Request::builder()
+            .uri("/prover.Prover/ExecuteAndProve")
+            .header("traceparent", format!("00-{TRACE_ID}-{PARENT_SPAN_ID}-01"))
+            .body(())
+            .unwrap();
+
+        service
+            .execute_and_prove(Request::new(request))
+            .instrument(rpc_span(&http_request))
+            .await
+            .unwrap();
+        provider.force_flush();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let rpc = find_span(&spans, "/prover.Prover/ExecuteAndProve");
+        assert_eq!(
+            rpc.span_context.trace_id(),
+            TraceId::from_hex(TRACE_ID).unwrap()
+        );
+        assert_eq!(
+            rpc.parent_span_id,
+            SpanId::from_hex(PARENT_SPAN_ID).unwrap()
+        );
+        for name in ["execute", "prove"] {
+            let span = find_span(&spans, name);
+            assert_eq!(span.parent_span_id, rpc.span_context.span_id());
+            assert_eq!(span.span_context.trace_id(), rpc.span_context.trace_id());
+        }
+
+        let prove = find_span(&spans, "prove");
+        assert_eq!(
+            attribute(prove, "layout").as_deref(),
+            Some("starknet_with_keccak")
+        );
+        assert!(attribute(prove, "n_steps").is_some());
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use stone_prover_sdk::models::ProverWorkingDirectory;
+
+/// Time after which a working directory is considered orphaned, by default.
+/// Must exceed the longest proof: the directory of a running request would otherwise be
+/// removed under its feet.
+pub const DEFAULT_WORKSPACE_TTL_SECS: u64 = 6 * 3600;
+
+/// Interval between two sweeps of the orphaned working directories.
+pub const WORKSPACE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// File written in the working directories registered by the tracker. Identifies the
+/// directories of the server among the other directories of the temporary directory, when
+/// sweeping the orphans of a previous run.
+pub const WORKSPACE_MARKER_FILE: &str = ".madara-prover-workspace";
+
+/// Path and size of the working directory of the prover.
+pub trait WorkingDirectoryExt {
+    fn path(&self) -> &Path;
+
+    /// Total size of the files in the directory, in bytes.
+    fn size(&self) -> std::io::Result<u64>;
+}
+
+impl WorkingDirectoryExt for ProverWorkingDirectory {
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    fn size(&self) -> std::io::Result<u64> {
+        dir_size(self.path())
+    }
+}
+
+/// Returns the total size of the files in `dir` and its subdirectories, in bytes.
+pub fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Live working directories and the space they use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkspaceUsage {
+    pub live_workspaces: usize,
+    pub bytes_used: u64,
+}
+
+#[derive(Debug)]
+struct Workspace {
+    request_id: u64,
+    deadline: Instant,
+}
+
+/// Keeps track of the working directories of the prover, to remove the ones that outlive
+/// their request.
+///
+/// Working directories are deleted when the `ProverWorkingDirectory` guard is dropped, but
+/// a panic, an aborted task or a leaked guard leaves them in the temporary directory. The
+/// tracker removes the directories still present after their deadline.
+#[derive(Debug)]
+pub struct WorkspaceTracker {
+    workspaces: Mutex<HashMap<PathBuf, Workspace>>,
+    ttl: Duration,
+}
+
+impl Default for WorkspaceTracker {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_WORKSPACE_TTL_SECS))
+    }
+}
+
+impl WorkspaceTracker {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            workspaces: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Tracks the working directory of request `request_id`. It is removed by `sweep` if it
+    /// still exists after the TTL of the tracker, or by `sweep_orphans` after a restart.
+    pub fn register(&self, path: &Path, request_id: u64) {
+        if let Err(e) = std::fs::write(path.join(WORKSPACE_MARKER_FILE), b"") {
+            tracing::warn!(
+                request_id,
+                "Could not mark working directory {}: {e}",
+                path.display()
+            );
+        }
+        let workspace = Workspace {
+            request_id,
+            deadline: Instant::now() + self.ttl,
+        };
+        self.workspaces
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), workspace);
+    }
+
+    /// Forgets the working directories deleted since the last sweep and removes the ones
+    /// past their deadline. Returns the number of removed directories.
+    pub fn sweep(&self) -> usize {
+        self.sweep_at(Instant::now())
+    }
+
+    fn sweep_at(&self, now: Instant) -> usize {
+        let mut workspaces = self.workspaces.lock().unwrap();
+        let mut removed = 0;
+        workspaces.retain(|path, workspace| {
+            if !path.exists() {
+                return false;
+            }
+            if workspace.deadline > now {
+                return true;
+            }
+
+            match std::fs::remove_dir_all(path) {
+                Ok(()) => {
+                    tracing::info!(
+                        request_id = workspace.request_id,
+                        "Removed orphaned working directory {}",
+                        path.display()
+                    );
+                    removed += 1;
+                    false
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        request_id = workspace.request_id,
+                        "Could not remove orphaned working directory {}: {e}",
+                        path.display()
+                    );
+                    true
+                }
+            }
+        });
+        removed
+    }
+
+    /// Removes the working directories of `root` registered by a previous run of the server,
+    /// ex: before a crash, and older than the TTL. Directories without the marker file are
+    /// left alone. Returns the number of removed directories.
+    pub fn sweep_orphans(&self, root: &Path) -> usize {
+        self.sweep_orphans_at(root, SystemTime::now())
+    }
+
+    fn sweep_orphans_at(&self, root: &Path, now: SystemTime) -> usize {
+        let entries = match std::fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not list working directories in {}: {e}",
+                    root.display()
+                );
+                return 0;
+            }
+        };
+
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if self.workspaces.lock().unwrap().contains_key(&path) {
+                continue;
+            }
+            let Ok(marked_at) = std::fs::metadata(path.join(WORKSPACE_MARKER_FILE))
+                .and_then(|metadata| metadata.modified())
+            else {
+                continue;
+            };
+            let age = now.duration_since(marked_at).unwrap_or_default();
+            if age < self.ttl {
+                continue;
+            }
+
+            match std::fs::remove_dir_all(&path) {
+                Ok(()) => {
+                    tracing::info!(
+                        "Removed orphaned working directory {} of a previous run",
+                        path.display()
+                    );
+                    removed += 1;
+                }
+                Err(e) => tracing::warn!(
+                    "Could not remove orphaned working directory {}: {e}",
+                    path.display()
+                ),
+            }
+        }
+        removed
+    }
+
+    /// Returns the number of tracked working directories that still exist, and their size.
+    pub fn usage(&self) -> WorkspaceUsage {
+        let workspaces = self.workspaces.lock().unwrap();
+        workspaces
+            .keys()
+            .filter_map(|path| dir_size(path).ok())
+            .fold(WorkspaceUsage::default(), |usage, size| WorkspaceUsage {
+                live_workspaces: usage.live_workspaces + 1,
+                bytes_used: usage.bytes_used + size,
+            })
+    }
+}
+
+/// Periodically removes the orphaned working directories of `tracker`, and the ones left in
+/// `root` by a previous run of the server.
+pub async fn monitor_workspaces(tracker: Arc<WorkspaceTracker>, root: PathBuf, interval: Duration) {
+    let mut interval = tokio::time::interval(interval);
+
+    loop {
+        interval.tick().await;
+        tracker.sweep();
+        tracker.sweep_orphans(&root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a working directory and leaks it, as if its request had crashed.
+    fn leak_workspace(size: usize) -> PathBuf {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("proof.json"), vec![b'0'; size]).unwrap();
+        dir.into_path()
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("memory.bin"), [0u8; 100]).unwrap();
+        std::fs::create_dir(dir.path().join("subdir")).unwrap();
+        std::fs::write(dir.path().join("subdir/trace.bin"), [0u8; 50]).unwrap();
+
+        assert_eq!(dir_size(dir.path()).unwrap(), 150);
+    }
+
+    #[test]
+    fn test_sweep_removes_orphaned_workspaces() {
+        let tracker = WorkspaceTracker::new(Duration::from_secs(60));
+        let path = leak_workspace(10);
+        tracker.register(&path, 1);
+
+        assert_eq!(
+            tracker.usage(),
+            WorkspaceUsage {
+                live_workspaces: 1,
+                bytes_used: 10
+            }
+        );
+
+        // Before the deadline, the request may still be running
+        assert_eq!(tracker.sweep(), 0);
+        assert!(path.exists());
+
+        assert_eq!(
+            tracker.sweep_at(Instant::now() + Duration::from_secs(61)),
+            1
+        );
+        assert!(!path.exists());
+        assert_eq!(tracker.usage(), WorkspaceUsage::default());
+    }
+
+    #[test]
+    fn test_sweep_forgets_deleted_workspaces() {
+        let tracker = WorkspaceTracker::new(Duration::from_secs(60));
+        let dir = tempfile::tempdir().unwrap();
+        tracker.register(dir.path(), 1);
+        drop(dir);
+
+        assert_eq!(
+            tracker.sweep_at(Instant::now() + Duration::from_secs(61)),
+            0
+        );
+        assert!(tracker.workspaces.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sweep_orphans_of_previous_run() {
+        let root = tempfile::tempdir().unwrap();
+        let orphan = tempfile::tempdir_in(root.path()).unwrap().into_path();
+        let unrelated = tempfile::tempdir_in(root.path()).unwrap().into_path();
+        WorkspaceTracker::new(Duration::from_secs(60)).register(&orphan, 1);
+
+        // The server restarts and forgets its working directories
+        let tracker = WorkspaceTracker::new(Duration::from_secs(60));
+        assert_eq!(tracker.sweep_orphans(root.path()), 0);
+        assert!(orphan.exists());
+
+        let later = SystemTime::now() + Duration::from_secs(61);
+        assert_eq!(tracker.sweep_orphans_at(root.path(), later), 1);
+        assert!(!orphan.exists());
+        assert!(unrelated.exists());
+    }
+
+    #[test]
+    fn test_sweep_orphans_skips_tracked_workspaces() {
+        let root = tempfile::tempdir().unwrap();
+        let path = tempfile::tempdir_in(root.path()).unwrap().into_path();
+        let tracker = WorkspaceTracker::new(Duration::ZERO);
+        tracker.register(&path, 1);
+
+        assert_eq!(tracker.sweep_orphans(root.path()), 0);
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_monitor_workspaces() {
+        let tracker = Arc::new(WorkspaceTracker::new(Duration::ZERO));
+        let path = leak_workspace(10);
+        tracker.register(&path, 1);
+        let root = tempfile::tempdir().unwrap();
+
+        let monitor = tokio::spawn(monitor_workspaces(
+            tracker.clone(),
+            root.path().to_path_buf(),
+            Duration::from_millis(10),
+        ));
+        for _ in 0..100 {
+            if !path.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        monitor.abort();
+
+        assert!(!path.exists());
+        assert_eq!(tracker.usage(), WorkspaceUsage::default());
+    }
+}
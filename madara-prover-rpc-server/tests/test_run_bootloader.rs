@@ -6,14 +6,22 @@ mod tests {
     use cairo_vm::hint_processor::builtin_hint_processor::bootloader::types::{Task, TaskSpec};
     use cairo_vm::types::program::Program;
     use cairo_vm::vm::runners::cairo_pie::CairoPie;
+    use cairo_vm::Felt252;
     use rstest::{fixture, rstest};
     use stone_prover_sdk::json::read_json_from_file;
     use stone_prover_sdk::models::PublicInput;
 
+    use madara_prover_rpc_server::facts::{
+        compute_fact_hash, compute_fact_hashes, compute_program_hash, extract_bootloader_output,
+        read_fact_topologies, FactTopology, BOOTLOADER_VERSION,
+    };
     use madara_prover_rpc_server::services::starknet_prover::run_bootloader_in_proof_mode;
     use stone_prover_sdk::cairo_vm::ExecutionArtifacts;
     use test_cases::{get_test_case_file_path, load_test_case_file};
-    use test_fixtures::{assert_memory_eq, assert_private_input_eq};
+    use test_fixtures::{
+        assert_or_update_bytes, assert_or_update_json, assert_or_update_memory,
+        update_golden_enabled, DiffArtifacts,
+    };
 
     #[fixture]
     fn bootloader() -> Program {
@@ -48,6 +56,32 @@ mod tests {
         }
     }
 
+    /// Compares the artifacts to the expected output. With `UPDATE_GOLDEN=1`, the public input,
+    /// memory and trace files are updated instead.
+    fn assert_output_eq(
+        artifacts: ExecutionArtifacts,
+        expected_output: ExecutionArtifacts,
+        test_case_dir: &Path,
+    ) {
+        let diff = artifacts.diff(&expected_output);
+        if diff.is_empty() {
+            return;
+        }
+        if !update_golden_enabled() || !diff.private_input.is_empty() {
+            panic!("{diff}");
+        }
+
+        let output_dir = test_case_dir.join("output");
+        if !diff.public_input.is_empty() {
+            assert_or_update_json(
+                &artifacts.public_input,
+                &output_dir.join("air_public_input.json"),
+            );
+        }
+        assert_or_update_bytes(&artifacts.trace, &output_dir.join("trace.bin"));
+        assert_or_update_memory(&artifacts.memory, &output_dir.join("memory.bin"));
+    }
+
     #[rstest]
     #[case::fibonacci("fibonacci")]
     fn test_program(bootloader: Program, #[case] test_case: String) {
@@ -61,13 +95,9 @@ mod tests {
             task: Task::Program(program),
         }];
 
-        let artifacts = run_bootloader_in_proof_mode(&bootloader, tasks).unwrap();
+        let artifacts = run_bootloader_in_proof_mode(&bootloader, tasks, None).unwrap();
 
-        assert_eq!(artifacts.public_input, expected_output.public_input);
-        assert_eq!(artifacts.trace, expected_output.trace);
-
-        assert_private_input_eq(artifacts.private_input, expected_output.private_input);
-        assert_memory_eq(&artifacts.memory, &expected_output.memory);
+        assert_output_eq(artifacts, expected_output, &test_case_dir);
     }
 
     #[rstest]
@@ -86,13 +116,158 @@ mod tests {
             task: Task::Pie(cairo_pie),
         }];
 
-        let artifacts = run_bootloader_in_proof_mode(&bootloader, tasks).unwrap();
+        let artifacts = run_bootloader_in_proof_mode(&bootloader, tasks, None).unwrap();
 
-        assert_eq!(artifacts.public_input, expected_output.public_input);
-        assert_eq!(artifacts.trace, expected_output.trace);
+        assert_output_eq(artifacts, expected_output, &test_case_dir);
+    }
 
-        assert_private_input_eq(artifacts.private_input, expected_output.private_input);
-        assert_memory_eq(&artifacts.memory, &expected_output.memory);
+    /// Returns the values written to the output segment, in order.
+    fn output_segment(public_input: &PublicInput) -> Vec<String> {
+        let output_segment = &public_input.memory_segments["output"];
+        let mut output_entries: Vec<_> = public_input
+            .public_memory
+            .iter()
+            .filter(|entry| {
+                entry.address >= output_segment.begin_addr
+                    && entry.address < output_segment.stop_ptr
+            })
+            .collect();
+        output_entries.sort_by_key(|entry| entry.address);
+
+        output_entries
+            .into_iter()
+            .map(|entry| entry.value.clone())
+            .collect()
+    }
+
+    /// Number of values at the start of the bootloader output: the two bootloader config
+    /// values and the number of tasks.
+    const BOOTLOADER_OUTPUT_HEADER_SIZE: usize = 3;
+
+    /// Returns the part of the bootloader output written for each task
+    /// (size, program hash and task output).
+    fn task_outputs(bootloader_output: &[String]) -> &[String] {
+        &bootloader_output[BOOTLOADER_OUTPUT_HEADER_SIZE..]
+    }
+
+    #[rstest]
+    fn test_multiple_tasks(bootloader: Program) {
+        let program_test_case_dir = get_test_case_file_path("bootloader/programs/fibonacci");
+        let pie_test_case_dir = get_test_case_file_path("bootloader/pies/fibonacci");
+        let expected_program_output =
+            output_segment(&expected_output(&program_test_case_dir).public_input);
+        let expected_pie_output = output_segment(&expected_output(&pie_test_case_dir).public_input);
+
+        let program = Program::from_file(
+            program_test_case_dir.join("program.json").as_path(),
+            Some("main"),
+        )
+        .unwrap();
+        let cairo_pie =
+            CairoPie::from_file(pie_test_case_dir.join("cairo_pie.zip").as_path()).unwrap();
+        let tasks = vec![
+            TaskSpec {
+                task: Task::Program(program.clone()),
+            },
+            TaskSpec {
+                task: Task::Pie(cairo_pie),
+            },
+            TaskSpec {
+                task: Task::Program(program),
+            },
+        ];
+        let n_tasks = tasks.len();
+
+        let artifacts = run_bootloader_in_proof_mode(&bootloader, tasks, None).unwrap();
+        let output = output_segment(&artifacts.public_input);
+
+        // The bootloader config is the same regardless of the tasks
+        assert_eq!(
+            output[..BOOTLOADER_OUTPUT_HEADER_SIZE - 1],
+            expected_program_output[..BOOTLOADER_OUTPUT_HEADER_SIZE - 1]
+        );
+        assert_eq!(
+            output[BOOTLOADER_OUTPUT_HEADER_SIZE - 1],
+            format!("{:#x}", n_tasks)
+        );
+
+        // Each task output must appear in order, as if the task had been run on its own
+        let expected_task_outputs = [
+            task_outputs(&expected_program_output),
+            task_outputs(&expected_pie_output),
+            task_outputs(&expected_program_output),
+        ]
+        .concat();
+        assert_eq!(task_outputs(&output), expected_task_outputs);
+    }
+
+    /// Runs the programs of the `multiple-programs` test case and checks the output, fact
+    /// topology and fact of each task.
+    #[rstest]
+    fn test_two_programs(bootloader: Program) {
+        let test_case: serde_json::Value = serde_json::from_str(&load_test_case_file(
+            "bootloader/multiple-programs/tasks.json",
+        ))
+        .unwrap();
+        let programs: Vec<Program> = test_case["programs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|program_file| {
+                let program_file = get_test_case_file_path(program_file.as_str().unwrap());
+                Program::from_file(program_file.as_path(), Some("main")).unwrap()
+            })
+            .collect();
+        let expected_outputs: Vec<Vec<String>> =
+            serde_json::from_value(test_case["task_outputs"].clone()).unwrap();
+        assert_eq!(programs.len(), 2);
+
+        let tasks = programs
+            .iter()
+            .map(|program| TaskSpec {
+                task: Task::Program(program.clone()),
+            })
+            .collect();
+        let fact_topologies_dir = tempfile::tempdir().unwrap();
+        let fact_topologies_path = fact_topologies_dir.path().join("fact_topologies.json");
+        let artifacts =
+            run_bootloader_in_proof_mode(&bootloader, tasks, Some(fact_topologies_path.clone()))
+                .unwrap();
+
+        let task_outputs =
+            extract_bootloader_output(&artifacts.public_input, &artifacts.memory).unwrap();
+        assert_eq!(task_outputs.len(), programs.len());
+        for ((task_output, program), expected_output) in
+            task_outputs.iter().zip(&programs).zip(&expected_outputs)
+        {
+            assert_eq!(
+                task_output.program_hash,
+                compute_program_hash(program, BOOTLOADER_VERSION).unwrap()
+            );
+            let expected_output: Vec<Felt252> = expected_output
+                .iter()
+                .map(|value| Felt252::from_hex(value).unwrap())
+                .collect();
+            assert_eq!(task_output.output, expected_output);
+        }
+
+        // Each task gets its own topology and fact. The programs add no output pages, so the
+        // bootloader records a single page per task.
+        let fact_topologies = read_fact_topologies(&fact_topologies_path).unwrap();
+        assert_eq!(
+            fact_topologies,
+            expected_outputs
+                .iter()
+                .map(|output| FactTopology::single_page(output.len()))
+                .collect::<Vec<_>>()
+        );
+        let fact_hashes = compute_fact_hashes(&artifacts.public_input, &fact_topologies).unwrap();
+        for (task_index, fact_hash) in fact_hashes.iter().enumerate() {
+            let expected_fact_hash =
+                compute_fact_hash(&task_outputs[task_index], &fact_topologies[task_index]).unwrap();
+            assert_eq!(fact_hash, &expected_fact_hash);
+        }
+        assert_ne!(fact_hashes[0], fact_hashes[1]);
     }
 
     #[rstest]
@@ -107,12 +282,8 @@ mod tests {
             task: Task::Pie(os_pie),
         }];
 
-        let artifacts = run_bootloader_in_proof_mode(&bootloader, tasks).unwrap();
-
-        assert_eq!(artifacts.public_input, expected_output.public_input);
-        assert_eq!(artifacts.trace, expected_output.trace);
+        let artifacts = run_bootloader_in_proof_mode(&bootloader, tasks, None).unwrap();
 
-        assert_private_input_eq(artifacts.private_input, expected_output.private_input);
-        assert_memory_eq(&artifacts.memory, &expected_output.memory);
+        assert_output_eq(artifacts, expected_output, &test_case_dir);
     }
 }
@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use cairo_vm::air_private_input::{AirPrivateInput, AirPrivateInputSerializable};
+use cairo_vm::hint_processor::builtin_hint_processor::bootloader::types::{Task, TaskSpec};
+use cairo_vm::types::program::Program;
+use cairo_vm::vm::runners::builtin_runner::OUTPUT_BUILTIN_NAME;
+use cairo_vm::vm::runners::cairo_pie::CairoPie;
+use clap::Parser;
+use serde::Serialize;
+use stone_prover_sdk::cairo_vm::ExecutionArtifacts;
+use stone_prover_sdk::json::read_json_from_file;
+use stone_prover_sdk::models::{Proof, ProverConfig, ProverParameters, PublicInput};
+use stone_prover_sdk::prover::run_prover_async;
+
+use madara_prover_rpc_server::artifacts::relativize_private_input_paths;
+use madara_prover_rpc_server::public_input::public_inputs_semantically_equal;
+use madara_prover_rpc_server::services::prover::run_cairo_program_in_proof_mode;
+use madara_prover_rpc_server::services::starknet_prover::run_bootloader_in_proof_mode;
+use test_cases::get_test_case_file_path;
+
+/// Regenerates the execution artifacts (and optionally the proof) of a test case.
+///
+/// Files are only rewritten if their content changed semantically, ex: the memory files
+/// generated by the Python and Rust VMs list the same cells in a different order.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Test case directory, relative to `test-cases/cases` (ex: `bootloader/programs/fibonacci`).
+    test_case: String,
+
+    /// Also regenerate the proof. Requires `cpu_air_prover` in the PATH.
+    #[arg(long)]
+    with_proof: bool,
+
+    /// Only print the files that would change, without writing anything.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// Entry point of a test case: a program run by the bootloader, a PIE run by the bootloader
+/// or a program run on its own.
+enum TestCaseSource {
+    BootloaderProgram(PathBuf),
+    BootloaderPie(PathBuf),
+    Program(PathBuf),
+}
+
+/// Location of the files of a test case.
+struct TestCaseFiles {
+    dir: PathBuf,
+    source: TestCaseSource,
+    public_input: PathBuf,
+    private_input: PathBuf,
+    memory: PathBuf,
+    trace: PathBuf,
+    proof: PathBuf,
+    prover_config: PathBuf,
+    prover_parameters: PathBuf,
+}
+
+impl TestCaseFiles {
+    fn find(test_case: &str) -> Result<Self, Box<dyn Error>> {
+        let dir = get_test_case_file_path(test_case);
+        let prover_config = dir.join("cpu_air_prover_config.json");
+        let prover_parameters = dir.join("cpu_air_params.json");
+
+        if dir.join("program.json").exists() || dir.join("cairo_pie.zip").exists() {
+            let source = if dir.join("program.json").exists() {
+                TestCaseSource::BootloaderProgram(dir.join("program.json"))
+            } else {
+                TestCaseSource::BootloaderPie(dir.join("cairo_pie.zip"))
+            };
+            let output_dir = dir.join("output");
+
+            return Ok(Self {
+                source,
+                public_input: output_dir.join("air_public_input.json"),
+                private_input: output_dir.join("air_private_input.json"),
+                memory: output_dir.join("memory.bin"),
+                trace: output_dir.join("trace.bin"),
+                proof: output_dir.join("proof.json"),
+                prover_config,
+                prover_parameters,
+                dir,
+            });
+        }
+
+        // Test cases generated by `generate_test_case.py`: all files are prefixed with the
+        // program name.
+        let compiled_program = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .find(|path| path.to_string_lossy().ends_with("_compiled.json"))
+            .ok_or_else(|| format!("No program found in {}", dir.display()))?;
+        let file_name = compiled_program.file_name().unwrap().to_string_lossy();
+        let prefix = file_name.trim_end_matches("_compiled.json").to_string();
+
+        Ok(Self {
+            source: TestCaseSource::Program(compiled_program),
+            public_input: dir.join(format!("{prefix}_public_input.json")),
+            private_input: dir.join(format!("{prefix}_private_input.json")),
+            memory: dir.join(format!("{prefix}_memory.bin")),
+            trace: dir.join(format!("{prefix}_trace.bin")),
+            proof: dir.join(format!("{prefix}_proof.json")),
+            prover_config,
+            prover_parameters,
+            dir,
+        })
+    }
+
+    fn display<'a>(&self, path: &'a Path) -> std::path::Display<'a> {
+        path.strip_prefix(&self.dir).unwrap_or(path).display()
+    }
+}
+
+fn run_test_case(files: &TestCaseFiles) -> Result<ExecutionArtifacts, Box<dyn Error>> {
+    let bootloader_task = match &files.source {
+        TestCaseSource::BootloaderProgram(program_file) => {
+            let program = Program::from_file(program_file, Some("main"))?;
+            Task::Program(program)
+        }
+        TestCaseSource::BootloaderPie(pie_file) => Task::Pie(CairoPie::from_file(pie_file)?),
+        TestCaseSource::Program(program_file) => {
+            // Reuse the layout of the existing fixture
+            let public_input: PublicInput = read_json_from_file(&files.public_input)?;
+            let program = std::fs::read(program_file)?;
+            let artifacts = run_cairo_program_in_proof_mode(&program, &public_input.layout, None)
+                .map_err(|status| status.message().to_string())?;
+            return Ok(artifacts);
+        }
+    };
+
+    let bootloader = Program::from_file(
+        get_test_case_file_path("bootloader/bootloader.json").as_path(),
+        Some("main"),
+    )?;
+    let tasks = vec![TaskSpec {
+        task: bootloader_task,
+    }];
+
+    Ok(run_bootloader_in_proof_mode(&bootloader, tasks)?)
+}
+
+/// Serializes a value the same way as the Python tooling (4-space indentation).
+fn to_json_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+    let mut serializer = serde_json::Serializer::with_formatter(&mut bytes, formatter);
+    value.serialize(&mut serializer)?;
+    Ok(bytes)
+}
+
+/// Reads a memory file as a map of address -> value bytes.
+fn read_memory_cells(memory: &[u8]) -> HashMap<&[u8], &[u8]> {
+    memory
+        .chunks(40)
+        .map(|pair| pair.split_at(8.min(pair.len())))
+        .collect()
+}
+
+/// The private input fixtures do not include the output builtin, see `assert_private_input_eq`.
+fn without_output_builtin(private_input: AirPrivateInput) -> AirPrivateInput {
+    let mut map = private_input.0;
+    map.remove(OUTPUT_BUILTIN_NAME);
+    AirPrivateInput(map)
+}
+
+/// A file to regenerate, along with whether its content changed.
+struct RegeneratedFile {
+    path: PathBuf,
+    content: Vec<u8>,
+    changed: bool,
+}
+
+fn regenerate_artifacts(
+    files: &TestCaseFiles,
+    artifacts: ExecutionArtifacts,
+) -> Result<Vec<RegeneratedFile>, Box<dyn Error>> {
+    let mut regenerated_files = vec![];
+
+    let existing_public_input: Option<PublicInput> = read_json_from_file(&files.public_input).ok();
+    let public_input_changed = match existing_public_input {
+        Some(existing) => !public_inputs_semantically_equal(&artifacts.public_input, &existing),
+        None => true,
+    };
+    regenerated_files.push(RegeneratedFile {
+        path: files.public_input.clone(),
+        content: to_json_bytes(&artifacts.public_input)?,
+        changed: public_input_changed,
+    });
+
+    let existing_private_input: Option<AirPrivateInputSerializable> =
+        read_json_from_file(&files.private_input).ok();
+    let private_input = without_output_builtin(artifacts.private_input);
+    let private_input_changed = match existing_private_input {
+        Some(existing) => without_output_builtin(existing.into()).0 != private_input.0,
+        None => true,
+    };
+    // The trace and memory paths are relative to the private input, as written by
+    // `write_artifacts_to_dir`
+    let mut private_input_json = serde_json::to_value(private_input.to_serializable(
+        files.trace.to_string_lossy().into_owned(),
+        files.memory.to_string_lossy().into_owned(),
+    ))?;
+    if let Some(private_input_dir) = files.private_input.parent() {
+        relativize_private_input_paths(&mut private_input_json, private_input_dir);
+    }
+    regenerated_files.push(RegeneratedFile {
+        path: files.private_input.clone(),
+        content: to_json_bytes(&private_input_json)?,
+        changed: private_input_changed,
+    });
+
+    let existing_memory = std::fs::read(&files.memory).unwrap_or_default();
+    let memory_changed =
+        read_memory_cells(&artifacts.memory) != read_memory_cells(&existing_memory);
+    regenerated_files.push(RegeneratedFile {
+        path: files.memory.clone(),
+        content: artifacts.memory,
+        changed: memory_changed,
+    });
+
+    let existing_trace = std::fs::read(&files.trace).unwrap_or_default();
+    regenerated_files.push(RegeneratedFile {
+        path: files.trace.clone(),
+        changed: artifacts.trace != existing_trace,
+        content: artifacts.trace,
+    });
+
+    Ok(regenerated_files)
+}
+
+async fn regenerate_proof(files: &TestCaseFiles) -> Result<RegeneratedFile, Box<dyn Error>> {
+    // Prove the artifacts as they are written on disk
+    let public_input: PublicInput = read_json_from_file(&files.public_input)?;
+    let private_input: AirPrivateInputSerializable = read_json_from_file(&files.private_input)?;
+    let memory = std::fs::read(&files.memory)?;
+    let trace = std::fs::read(&files.trace)?;
+    let prover_config: ProverConfig = read_json_from_file(&files.prover_config)?;
+    let prover_parameters: ProverParameters = read_json_from_file(&files.prover_parameters)?;
+
+    let (proof, working_dir) = run_prover_async(
+        &public_input,
+        &private_input.into(),
+        &memory,
+        &trace,
+        &prover_config,
+        &prover_parameters,
+    )
+    .await?;
+
+    let existing_proof: Option<Proof> = read_json_from_file(&files.proof).ok();
+    let changed = match existing_proof {
+        Some(existing) => existing.proof_hex != proof.proof_hex,
+        None => true,
+    };
+
+    Ok(RegeneratedFile {
+        path: files.proof.clone(),
+        content: std::fs::read(&working_dir.proof_file)?,
+        changed,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+
+    let files = TestCaseFiles::find(&args.test_case)?;
+    println!("Regenerating {}...", args.test_case);
+
+    let artifacts = run_test_case(&files)?;
+    let mut regenerated_files = regenerate_artifacts(&files, artifacts)?;
+
+    for file in regenerated_files.iter().filter(|file| file.changed) {
+        if !args.dry_run {
+            std::fs::write(&file.path, &file.content)?;
+        }
+    }
+
+    if args.with_proof {
+        if args.dry_run {
+            println!("Skipping the proof in dry-run mode.");
+        } else {
+            let proof_file = regenerate_proof(&files).await?;
+            if proof_file.changed {
+                std::fs::write(&proof_file.path, &proof_file.content)?;
+            }
+            regenerated_files.push(proof_file);
+        }
+    }
+
+    for file in &regenerated_files {
+        let status = if file.changed { "changed" } else { "unchanged" };
+        println!("  {:<10} {}", status, files.display(&file.path));
+    }
+
+    let n_changed = regenerated_files.iter().filter(|file| file.changed).count();
+    match (n_changed, args.dry_run) {
+        (0, _) => println!("No files changed."),
+        (n, true) => println!("{n} file(s) would change (dry run)."),
+        (n, false) => println!("{n} file(s) changed."),
+    }
+
+    Ok(())
+}
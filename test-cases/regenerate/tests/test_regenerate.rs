@@ -0,0 +1,23 @@
+use std::process::Command;
+
+use rstest::rstest;
+
+/// Regenerating the fixtures with the current VM must not change them.
+#[rstest]
+#[case::bootloader_program("bootloader/programs/fibonacci")]
+#[case::bootloader_pie("bootloader/pies/fibonacci")]
+#[case::bootloader_pie_stone_e2e("bootloader/pies/fibonacci-stone-e2e")]
+fn test_regenerate_dry_run(#[case] test_case: &str) {
+    let output = Command::new(env!("CARGO_BIN_EXE_regenerate"))
+        .args([test_case, "--dry-run"])
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("No files changed."), "{}", stdout);
+}
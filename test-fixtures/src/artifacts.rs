@@ -0,0 +1,381 @@
+//! Comparison of execution artifacts, with a bounded report of the differences.
+//!
+//! `assert_eq!` on the artifacts would dump megabytes of memory and trace on failure, and
+//! would report differences that do not matter: the order of the memory and public memory
+//! cells differs between the Python and Rust VMs, and the Rust VM adds the output builtin to
+//! the private input.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use cairo_vm::air_private_input::AirPrivateInput;
+use cairo_vm::vm::runners::builtin_runner::OUTPUT_BUILTIN_NAME;
+use cairo_vm::Felt252;
+use serde_json::Value;
+use stone_prover_sdk::cairo_vm::ExecutionArtifacts;
+
+use crate::{assert_memory_eq, MemoryFileError, MAX_MEMORY_DIFF_ENTRIES};
+
+/// Maximum number of public input fields and trace steps reported by `ArtifactsDiff`.
+pub const MAX_DIFF_ENTRIES: usize = MAX_MEMORY_DIFF_ENTRIES;
+
+/// Size of a trace entry: the ap, fp and pc registers, as 64-bit integers.
+pub const TRACE_ENTRY_SIZE: usize = 24;
+
+/// Steps that differ between two traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceDiff {
+    /// The first differing steps.
+    pub steps: Vec<usize>,
+    /// Total number of differing steps, among the steps present in both traces.
+    pub total: usize,
+    pub actual_n_steps: usize,
+    pub expected_n_steps: usize,
+}
+
+/// Differences between two sets of execution artifacts. Empty if the artifacts are
+/// equivalent.
+#[derive(Debug, Default)]
+pub struct ArtifactsDiff {
+    /// The first differing public input fields, by path, ex: `n_steps: 32768 != 65536`.
+    pub public_input: Vec<String>,
+    /// Total number of differing public input fields.
+    pub public_input_total: usize,
+    /// Builtins whose private input differs.
+    pub private_input: Vec<String>,
+    /// Memory mismatch, or the error reading one of the memories.
+    pub memory: Option<MemoryFileError>,
+    pub trace: Option<TraceDiff>,
+}
+
+impl ArtifactsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.public_input.is_empty()
+            && self.private_input.is_empty()
+            && self.memory.is_none()
+            && self.trace.is_none()
+    }
+}
+
+impl fmt::Display for ArtifactsDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "Execution artifacts are equivalent");
+        }
+
+        write!(f, "Execution artifacts differ:")?;
+        if !self.public_input.is_empty() {
+            write!(
+                f,
+                "\n- Public input differs at {} field(s):",
+                self.public_input_total
+            )?;
+            for field in &self.public_input {
+                write!(f, "\n    {field}")?;
+            }
+            if self.public_input_total > self.public_input.len() {
+                write!(
+                    f,
+                    "\n    ... and {} more",
+                    self.public_input_total - self.public_input.len()
+                )?;
+            }
+        }
+        if !self.private_input.is_empty() {
+            write!(
+                f,
+                "\n- Private input differs for builtin(s): {}",
+                self.private_input.join(", ")
+            )?;
+        }
+        if let Some(error) = &self.memory {
+            write!(f, "\n- {}", error.to_string().replace('\n', "\n  "))?;
+        }
+        if let Some(trace) = &self.trace {
+            write!(
+                f,
+                "\n- Traces differ: {} actual step(s), {} expected step(s)",
+                trace.actual_n_steps, trace.expected_n_steps
+            )?;
+            if trace.total > 0 {
+                let steps: Vec<String> = trace.steps.iter().map(usize::to_string).collect();
+                write!(
+                    f,
+                    ", {} differing step(s): {}",
+                    trace.total,
+                    steps.join(", ")
+                )?;
+                if trace.total > trace.steps.len() {
+                    write!(f, ", ...")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Comparison of execution artifacts, see `ArtifactsDiff`.
+///
+/// `ExecutionArtifacts` is defined by the SDK, this trait adds the comparison to it.
+pub trait DiffArtifacts {
+    /// Lists the differences between `self`, the actual artifacts, and `expected`.
+    fn diff(&self, expected: &Self) -> ArtifactsDiff;
+}
+
+impl DiffArtifacts for ExecutionArtifacts {
+    fn diff(&self, expected: &Self) -> ArtifactsDiff {
+        let mut public_input = vec![];
+        let actual_public_input = serde_json::to_value(&self.public_input).unwrap();
+        let expected_public_input = serde_json::to_value(&expected.public_input).unwrap();
+        public_input_diff(
+            &actual_public_input,
+            &expected_public_input,
+            &mut public_input,
+        );
+        let public_input_total = public_input.len();
+        public_input.truncate(MAX_DIFF_ENTRIES);
+
+        ArtifactsDiff {
+            public_input,
+            public_input_total,
+            private_input: private_input_diff(&self.private_input, &expected.private_input),
+            memory: assert_memory_eq(&self.memory, &expected.memory).err(),
+            trace: trace_diff(&self.trace, &expected.trace),
+        }
+    }
+}
+
+/// Checks that two sets of execution artifacts are equivalent. On mismatch, panics with a
+/// summary of the differences.
+pub fn assert_artifacts_eq(actual: &ExecutionArtifacts, expected: &ExecutionArtifacts) {
+    let diff = actual.diff(expected);
+    if !diff.is_empty() {
+        panic!("{diff}");
+    }
+}
+
+/// Public memory as a set of (address, page, value). `None` if an entry is malformed.
+fn normalized_public_memory(public_memory: &Value) -> Option<HashSet<(u64, u64, Felt252)>> {
+    public_memory
+        .as_array()?
+        .iter()
+        .map(|entry| {
+            let address = entry["address"].as_u64()?;
+            let page = entry["page"].as_u64()?;
+            let value = Felt252::from_hex(entry["value"].as_str()?).ok()?;
+            Some((address, page, value))
+        })
+        .collect()
+}
+
+fn public_memory_diff(actual: &Value, expected: &Value, differences: &mut Vec<String>) {
+    let (Some(actual_memory), Some(expected_memory)) = (
+        normalized_public_memory(actual),
+        normalized_public_memory(expected),
+    ) else {
+        if actual != expected {
+            differences.push("public_memory: malformed entries".to_string());
+        }
+        return;
+    };
+
+    let format_entry = |(address, page, value): &(u64, u64, Felt252)| {
+        format!("address {address}, page {page}, value {value}")
+    };
+    let mut missing: Vec<_> = expected_memory.difference(&actual_memory).collect();
+    missing.sort_unstable_by_key(|(address, page, _)| (*address, *page));
+    let mut unexpected: Vec<_> = actual_memory.difference(&expected_memory).collect();
+    unexpected.sort_unstable_by_key(|(address, page, _)| (*address, *page));
+
+    differences.extend(
+        missing
+            .into_iter()
+            .map(|entry| format!("public_memory: missing ({})", format_entry(entry))),
+    );
+    differences.extend(
+        unexpected
+            .into_iter()
+            .map(|entry| format!("public_memory: unexpected ({})", format_entry(entry))),
+    );
+}
+
+fn public_input_diff(actual: &Value, expected: &Value, differences: &mut Vec<String>) {
+    let empty = serde_json::Map::new();
+    let actual_fields = actual.as_object().unwrap_or(&empty);
+    let expected_fields = expected.as_object().unwrap_or(&empty);
+    let fields: BTreeSet<&String> = actual_fields.keys().chain(expected_fields.keys()).collect();
+
+    for field in fields {
+        let actual_value = actual_fields.get(field).unwrap_or(&Value::Null);
+        let expected_value = expected_fields.get(field).unwrap_or(&Value::Null);
+        if field == "public_memory" {
+            public_memory_diff(actual_value, expected_value, differences);
+        } else {
+            json_diff(field, actual_value, expected_value, differences);
+        }
+    }
+}
+
+/// Lists the leaves that differ between two JSON values, by path.
+fn json_diff(path: &str, actual: &Value, expected: &Value, differences: &mut Vec<String>) {
+    match (actual, expected) {
+        (Value::Object(actual_fields), Value::Object(expected_fields)) => {
+            let fields: BTreeSet<&String> =
+                actual_fields.keys().chain(expected_fields.keys()).collect();
+            for field in fields {
+                json_diff(
+                    &format!("{path}.{field}"),
+                    actual_fields.get(field).unwrap_or(&Value::Null),
+                    expected_fields.get(field).unwrap_or(&Value::Null),
+                    differences,
+                );
+            }
+        }
+        (Value::Array(actual_values), Value::Array(expected_values))
+            if actual_values.len() == expected_values.len() =>
+        {
+            for (index, (actual, expected)) in actual_values.iter().zip(expected_values).enumerate()
+            {
+                json_diff(&format!("{path}[{index}]"), actual, expected, differences);
+            }
+        }
+        _ if actual != expected => differences.push(format!("{path}: {actual} != {expected}")),
+        _ => {}
+    }
+}
+
+/// Lists the builtins whose private input differs, ignoring the output builtin which is only
+/// listed by the Rust VM.
+pub(crate) fn private_input_diff(
+    actual: &AirPrivateInput,
+    expected: &AirPrivateInput,
+) -> Vec<String> {
+    let builtins: BTreeSet<&String> = actual
+        .0
+        .keys()
+        .chain(expected.0.keys())
+        .filter(|builtin| builtin.as_str() != OUTPUT_BUILTIN_NAME)
+        .collect();
+
+    builtins
+        .into_iter()
+        .filter(|builtin| actual.0.get(*builtin) != expected.0.get(*builtin))
+        .cloned()
+        .collect()
+}
+
+fn trace_diff(actual: &[u8], expected: &[u8]) -> Option<TraceDiff> {
+    if actual == expected {
+        return None;
+    }
+
+    let differing_steps: Vec<usize> = actual
+        .chunks(TRACE_ENTRY_SIZE)
+        .zip(expected.chunks(TRACE_ENTRY_SIZE))
+        .enumerate()
+        .filter(|(_, (actual_entry, expected_entry))| actual_entry != expected_entry)
+        .map(|(step, _)| step)
+        .collect();
+
+    Some(TraceDiff {
+        total: differing_steps.len(),
+        steps: differing_steps.into_iter().take(MAX_DIFF_ENTRIES).collect(),
+        actual_n_steps: actual.len().div_ceil(TRACE_ENTRY_SIZE),
+        expected_n_steps: expected.len().div_ceil(TRACE_ENTRY_SIZE),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cairo_vm::air_private_input::AirPrivateInputSerializable;
+    use stone_prover_sdk::json::read_json_from_file;
+    use test_cases::get_test_case_file_path;
+
+    use super::*;
+
+    fn fibonacci_artifacts() -> ExecutionArtifacts {
+        let file = |name: &str| get_test_case_file_path(&format!("fibonacci/{name}"));
+        let private_input: AirPrivateInputSerializable =
+            read_json_from_file(file("fibonacci_private_input.json")).unwrap();
+
+        ExecutionArtifacts {
+            public_input: read_json_from_file(file("fibonacci_public_input.json")).unwrap(),
+            private_input: private_input.into(),
+            memory: std::fs::read(file("fibonacci_memory.bin")).unwrap(),
+            trace: std::fs::read(file("fibonacci_trace.bin")).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_equivalent_artifacts() {
+        let expected = fibonacci_artifacts();
+        let mut actual = fibonacci_artifacts();
+        // The order of the public memory does not matter
+        actual.public_input.public_memory.reverse();
+
+        let diff = actual.diff(&expected);
+
+        assert!(diff.is_empty(), "{diff}");
+        assert_artifacts_eq(&actual, &expected);
+    }
+
+    #[test]
+    fn test_corrupted_trace() {
+        let expected = fibonacci_artifacts();
+        let mut actual = fibonacci_artifacts();
+        actual.trace[2 * TRACE_ENTRY_SIZE] ^= 1;
+        actual.trace[5 * TRACE_ENTRY_SIZE + 8] ^= 1;
+
+        let diff = actual.diff(&expected);
+
+        let n_steps = expected.trace.len() / TRACE_ENTRY_SIZE;
+        assert_eq!(
+            diff.trace,
+            Some(TraceDiff {
+                steps: vec![2, 5],
+                total: 2,
+                actual_n_steps: n_steps,
+                expected_n_steps: n_steps,
+            })
+        );
+        assert!(diff.public_input.is_empty());
+        assert!(diff.memory.is_none());
+        assert_eq!(
+            diff.to_string(),
+            format!(
+                "Execution artifacts differ:\n- Traces differ: {n_steps} actual step(s), \
+                {n_steps} expected step(s), 2 differing step(s): 2, 5"
+            )
+        );
+    }
+
+    #[test]
+    fn test_public_input_diff() {
+        let expected = fibonacci_artifacts();
+        let mut actual = fibonacci_artifacts();
+        actual.public_input.n_steps *= 2;
+        actual.public_input.public_memory.pop();
+
+        let diff = actual.diff(&expected);
+
+        assert_eq!(diff.public_input_total, 2);
+        assert!(diff.public_input[0].starts_with("n_steps: "));
+        assert!(diff.public_input[1].starts_with("public_memory: missing (address "));
+    }
+
+    #[test]
+    fn test_json_diff_paths() {
+        let mut differences = vec![];
+        json_diff(
+            "memory_segments",
+            &serde_json::json!({"output": {"begin_addr": 102, "stop_ptr": 104}}),
+            &serde_json::json!({"output": {"begin_addr": 102, "stop_ptr": 102}}),
+            &mut differences,
+        );
+
+        assert_eq!(
+            differences,
+            vec!["memory_segments.output.stop_ptr: 104 != 102"]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::error::Error;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use test_fixtures::{canonical_memory_hash, convert_memory, MemoryLayout};
+
+/// Converts a memory file generated by the Python or Rust VM to its canonical form: cells
+/// sorted by address, without duplicates.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Memory file to convert.
+    input: PathBuf,
+
+    /// Where to write the converted memory file. If not set, only the hash is printed.
+    output: Option<PathBuf>,
+
+    /// Size of the addresses in the memory file, in bytes.
+    #[arg(long, default_value_t = MemoryLayout::DEFAULT.addr_size)]
+    addr_size: usize,
+
+    /// Size of the values in the memory file, in bytes.
+    #[arg(long, default_value_t = MemoryLayout::DEFAULT.felt_size)]
+    felt_size: usize,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let layout = MemoryLayout {
+        addr_size: args.addr_size,
+        felt_size: args.felt_size,
+    };
+
+    let memory = std::fs::read(&args.input)?;
+    let converted_memory = convert_memory(&memory, layout)?;
+
+    if let Some(output) = &args.output {
+        std::fs::write(output, &converted_memory)?;
+    }
+
+    let hash = canonical_memory_hash(&converted_memory, layout)?;
+    let hash: String = hash.iter().map(|byte| format!("{byte:02x}")).collect();
+    println!("Canonical memory hash: 0x{hash}");
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+//! Assertions against golden files, the expected outputs stored under `test-cases/cases/`.
+//!
+//! With `UPDATE_GOLDEN=1`, a mismatch rewrites the golden file with the actual value instead
+//! of failing the test. This is meant for cairo-vm or Stone version bumps: review the diff of
+//! the test cases before committing it. Updates that look like a bug rather than a version
+//! bump, like an empty output, are refused.
+
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+use stone_prover_sdk::models::Proof;
+use test_cases::get_test_case_file_path;
+
+use crate::assert_memory_eq;
+
+/// Environment variable enabling the update of the golden files.
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// Returns whether the tests should update the golden files instead of failing.
+pub fn update_golden_enabled() -> bool {
+    std::env::var(UPDATE_GOLDEN_ENV).is_ok_and(|value| value == "1")
+}
+
+/// Writes a golden file, after checking that the update looks like a legitimate change of
+/// the expected output.
+fn update_golden_file(expected_file: &Path, content: &[u8], is_empty: bool) {
+    if !expected_file.starts_with(get_test_case_file_path("")) {
+        panic!(
+            "Refusing to update {}: golden files must be under test-cases/cases",
+            expected_file.display()
+        );
+    }
+    if is_empty {
+        panic!(
+            "Refusing to update {}: the actual output is empty",
+            expected_file.display()
+        );
+    }
+
+    std::fs::write(expected_file, content).unwrap_or_else(|e| {
+        panic!("Could not update {}: {e}", expected_file.display());
+    });
+    eprintln!("Updated golden file {}", expected_file.display());
+}
+
+fn read_golden_file(expected_file: &Path) -> Vec<u8> {
+    std::fs::read(expected_file)
+        .unwrap_or_else(|e| panic!("Could not read {}: {e}", expected_file.display()))
+}
+
+fn is_empty_json(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        Value::Array(values) => values.is_empty(),
+        Value::Object(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+/// Checks that `actual` is byte for byte identical to the content of `expected_file`.
+pub fn assert_or_update_bytes(actual: &[u8], expected_file: &Path) {
+    if actual == read_golden_file(expected_file) {
+        return;
+    }
+
+    if update_golden_enabled() {
+        update_golden_file(expected_file, actual, actual.is_empty());
+    } else {
+        panic!(
+            "{} differs from the actual output, run with {UPDATE_GOLDEN_ENV}=1 to update it",
+            expected_file.display()
+        );
+    }
+}
+
+/// Checks that `actual` and `expected_file` describe the same memory, see `assert_memory_eq`.
+pub fn assert_or_update_memory(actual: &[u8], expected_file: &Path) {
+    let error = match assert_memory_eq(actual, &read_golden_file(expected_file)) {
+        Ok(()) => return,
+        Err(e) => e,
+    };
+
+    if update_golden_enabled() {
+        update_golden_file(expected_file, actual, actual.is_empty());
+    } else {
+        panic!(
+            "{}: {error}\nRun with {UPDATE_GOLDEN_ENV}=1 to update it",
+            expected_file.display()
+        );
+    }
+}
+
+/// Checks that `actual` serializes to the JSON value of `expected_file`.
+pub fn assert_or_update_json<T: Serialize>(actual: &T, expected_file: &Path) {
+    let actual = serde_json::to_value(actual).expect("Could not serialize the actual value");
+    let expected: Value = serde_json::from_slice(&read_golden_file(expected_file))
+        .unwrap_or_else(|e| panic!("Could not parse {}: {e}", expected_file.display()));
+    if actual == expected {
+        return;
+    }
+
+    if update_golden_enabled() {
+        let content = serde_json::to_vec_pretty(&actual).unwrap();
+        update_golden_file(expected_file, &content, is_empty_json(&actual));
+    } else {
+        assert_eq!(
+            actual,
+            expected,
+            "{} differs from the actual output, run with {UPDATE_GOLDEN_ENV}=1 to update it",
+            expected_file.display()
+        );
+    }
+}
+
+/// Checks that `actual` has the same `proof_hex` as the proof file `expected_file`.
+///
+/// On update, the fields of `Proof` are replaced in the proof file, the other fields of the
+/// file are kept.
+pub fn assert_or_update_proof(actual: &Proof, expected_file: &Path) {
+    let mut expected: Value = serde_json::from_slice(&read_golden_file(expected_file))
+        .unwrap_or_else(|e| panic!("Could not parse {}: {e}", expected_file.display()));
+    if expected["proof_hex"].as_str() == Some(actual.proof_hex.as_str()) {
+        return;
+    }
+
+    if !update_golden_enabled() {
+        assert_eq!(
+            Some(actual.proof_hex.as_str()),
+            expected["proof_hex"].as_str(),
+            "{} differs from the actual proof, run with {UPDATE_GOLDEN_ENV}=1 to update it",
+            expected_file.display()
+        );
+    }
+
+    let actual_fields = serde_json::to_value(actual).expect("Could not serialize the proof");
+    if let (Value::Object(expected_fields), Value::Object(actual_fields)) =
+        (&mut expected, actual_fields)
+    {
+        expected_fields.extend(actual_fields);
+    }
+    let content = serde_json::to_vec_pretty(&expected).unwrap();
+    let is_empty = actual.proof_hex.trim_start_matches("0x").is_empty();
+    update_golden_file(expected_file, &content, is_empty);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_golden_file() {
+        let memory_file = get_test_case_file_path("fibonacci/fibonacci_memory.bin");
+        let memory = std::fs::read(&memory_file).unwrap();
+
+        assert_or_update_bytes(&memory, &memory_file);
+        assert_or_update_memory(&memory, &memory_file);
+    }
+
+    #[test]
+    #[should_panic(expected = "UPDATE_GOLDEN=1")]
+    fn test_mismatch_suggests_update() {
+        let proof_file = get_test_case_file_path("fibonacci/fibonacci_proof.json");
+        assert_or_update_bytes(b"not a proof", &proof_file);
+    }
+
+    #[test]
+    #[should_panic(expected = "the actual output is empty")]
+    fn test_refuse_empty_update() {
+        let proof_file = get_test_case_file_path("fibonacci/fibonacci_proof.json");
+        update_golden_file(&proof_file, b"", true);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be under test-cases/cases")]
+    fn test_refuse_update_outside_test_cases() {
+        let dir = tempfile::tempdir().unwrap();
+        update_golden_file(&dir.path().join("proof.json"), b"{}", false);
+    }
+
+    #[test]
+    fn test_is_empty_json() {
+        assert!(is_empty_json(&Value::Null));
+        assert!(is_empty_json(&serde_json::json!({})));
+        assert!(is_empty_json(&serde_json::json!([])));
+        assert!(!is_empty_json(&serde_json::json!({"proof_hex": "0x12"})));
+    }
+}
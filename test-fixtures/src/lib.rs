@@ -3,15 +3,24 @@ use std::io::Read;
 use std::path::{Path, PathBuf};
 
 use cairo_vm::air_private_input::{AirPrivateInput, AirPrivateInputSerializable};
-use cairo_vm::vm::runners::builtin_runner::OUTPUT_BUILTIN_NAME;
 use cairo_vm::Felt252;
 use rstest::fixture;
+use sha3::{Digest, Keccak256};
 use tempfile::NamedTempFile;
 
 use stone_prover_sdk::json::read_json_from_file;
 use stone_prover_sdk::models::{Proof, ProverConfig, ProverParameters, PublicInput};
 use test_cases::get_test_case_file_path;
 
+pub use crate::artifacts::{assert_artifacts_eq, ArtifactsDiff, DiffArtifacts};
+pub use crate::golden::{
+    assert_or_update_bytes, assert_or_update_json, assert_or_update_memory, assert_or_update_proof,
+    update_golden_enabled, UPDATE_GOLDEN_ENV,
+};
+
+pub mod artifacts;
+pub mod golden;
+
 /// Reads and deserializes a JSON proof file.
 pub fn read_proof_file<P: AsRef<Path>>(proof_file: P) -> Proof {
     let proof: Proof = read_json_from_file(proof_file).expect("Could not open proof file");
@@ -98,6 +107,8 @@ pub struct ParsedProverTestCase {
     pub prover_config: ProverConfig,
     pub prover_parameters: ProverParameters,
     pub proof: Proof,
+    /// Golden file of `proof`.
+    pub proof_file: PathBuf,
 }
 
 #[fixture]
@@ -123,77 +134,436 @@ pub fn parsed_prover_test_case(#[from(fibonacci)] files: ProverTestCase) -> Pars
         prover_config,
         prover_parameters,
         proof,
+        proof_file: files.proof_file,
+    }
+}
+
+/// Sizes of the fields of a binary memory file, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLayout {
+    pub addr_size: usize,
+    pub felt_size: usize,
+}
+
+impl MemoryLayout {
+    /// Layout used by both the Python and Rust VMs: 8-byte addresses, 32-byte felts.
+    pub const DEFAULT: Self = Self {
+        addr_size: 8,
+        felt_size: 32,
+    };
+
+    pub fn pair_size(&self) -> usize {
+        self.addr_size + self.felt_size
+    }
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self::DEFAULT
     }
 }
 
+/// Maximum number of differing addresses reported by `assert_memory_eq`.
+pub const MAX_MEMORY_DIFF_ENTRIES: usize = 20;
+
+/// A memory cell that differs between two memory files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryCellDiff {
+    pub address: u64,
+    pub actual: Option<Felt252>,
+    pub expected: Option<Felt252>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemoryFileError {
+    #[error("Could not read memory file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid memory layout: {0:?}")]
+    InvalidLayout(MemoryLayout),
+    #[error("Unexpected EOF at offset {offset}: expected {expected} bytes, got {actual}")]
+    UnexpectedEof {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Address at offset {0} does not fit in 64 bits")]
+    AddressOverflow(usize),
+    #[error("Duplicate address {address} with differing values: {first} and {second}")]
+    DuplicateAddress {
+        address: u64,
+        first: Felt252,
+        second: Felt252,
+    },
+    #[error("{}", format_memory_diff(.differences, *.total))]
+    Mismatch {
+        /// The first differing cells, by address.
+        differences: Vec<MemoryCellDiff>,
+        /// Total number of differing cells.
+        total: usize,
+    },
+}
+
+fn format_memory_diff(differences: &[MemoryCellDiff], total: usize) -> String {
+    let format_value = |value: &Option<Felt252>| match value {
+        Some(value) => value.to_string(),
+        None => "<missing>".to_string(),
+    };
+
+    let mut message = format!("Memories differ at {} address(es):", total);
+    for diff in differences {
+        message.push_str(&format!(
+            "\n  [{}] actual: {}, expected: {}",
+            diff.address,
+            format_value(&diff.actual),
+            format_value(&diff.expected)
+        ));
+    }
+    if total > differences.len() {
+        message.push_str(&format!("\n  ... and {} more", total - differences.len()));
+    }
+
+    message
+}
+
 /// Reads a memory file as (address, value) pairs.
 pub fn read_memory_pairs<R: Read>(
     mut reader: R,
-    addr_size: usize,
-    felt_size: usize,
-) -> Vec<(u64, Felt252)> {
-    let pair_size = addr_size + felt_size;
+    layout: MemoryLayout,
+) -> Result<Vec<(u64, Felt252)>, MemoryFileError> {
+    if layout.addr_size == 0 || layout.felt_size == 0 || layout.felt_size > 32 {
+        return Err(MemoryFileError::InvalidLayout(layout));
+    }
+
+    let pair_size = layout.pair_size();
     let mut memory = Vec::<(u64, Felt252)>::new();
+    let mut offset = 0;
 
     loop {
         let mut element = Vec::with_capacity(pair_size);
         let n = reader
             .by_ref()
             .take(pair_size as u64)
-            .read_to_end(&mut element)
-            .unwrap();
+            .read_to_end(&mut element)?;
         if n == 0 {
             break;
         }
-        assert_eq!(n, pair_size);
+        if n != pair_size {
+            return Err(MemoryFileError::UnexpectedEof {
+                offset,
+                expected: pair_size,
+                actual: n,
+            });
+        }
 
-        let (address_bytes, value_bytes) = element.split_at(addr_size);
-        let address = {
-            let mut value = 0;
-            for (index, byte) in address_bytes[..8].iter().enumerate() {
-                value += u64::from(*byte) << (index * 8);
-            }
-            value
-        };
+        let (address_bytes, value_bytes) = element.split_at(layout.addr_size);
+        let (low_bytes, high_bytes) = address_bytes.split_at(address_bytes.len().min(8));
+        if high_bytes.iter().any(|byte| *byte != 0) {
+            return Err(MemoryFileError::AddressOverflow(offset));
+        }
+        let address = low_bytes
+            .iter()
+            .enumerate()
+            .fold(0u64, |acc, (index, byte)| {
+                acc | (u64::from(*byte) << (index * 8))
+            });
         let value = Felt252::from_bytes_le_slice(value_bytes);
         memory.push((address, value));
+        offset += pair_size;
     }
 
-    memory
+    Ok(memory)
 }
 
-/// Converts a vector of (address, value) pairs to a hashmap. Panics if a key appears more than once.
-fn memory_pairs_to_hashmap(pairs: Vec<(u64, Felt252)>) -> HashMap<u64, Felt252> {
+/// Converts a vector of (address, value) pairs to a hashmap. Fails if an address appears more
+/// than once with different values.
+fn memory_pairs_to_hashmap(
+    pairs: Vec<(u64, Felt252)>,
+) -> Result<HashMap<u64, Felt252>, MemoryFileError> {
     let mut map = HashMap::new();
 
     for (address, value) in pairs.into_iter() {
-        assert!(!map.contains_key(&address));
-        map.insert(address, value);
+        if let Some(first) = map.insert(address, value) {
+            if first != value {
+                return Err(MemoryFileError::DuplicateAddress {
+                    address,
+                    first,
+                    second: value,
+                });
+            }
+        }
     }
 
-    map
+    Ok(map)
+}
+
+/// Lists the cells that differ between two memories, sorted by address.
+fn memory_diff(
+    actual: &HashMap<u64, Felt252>,
+    expected: &HashMap<u64, Felt252>,
+) -> Vec<MemoryCellDiff> {
+    let mut addresses: Vec<u64> = actual.keys().chain(expected.keys()).copied().collect();
+    addresses.sort_unstable();
+    addresses.dedup();
+
+    addresses
+        .into_iter()
+        .filter_map(|address| {
+            let actual = actual.get(&address).copied();
+            let expected = expected.get(&address).copied();
+            (actual != expected).then_some(MemoryCellDiff {
+                address,
+                actual,
+                expected,
+            })
+        })
+        .collect()
 }
 
 /// Checks that the two specified memory files describe the same memory, regardless of the Python vs Rust VM formats.
-pub fn assert_memory_eq(actual: &Vec<u8>, expected: &Vec<u8>) {
-    assert_eq!(actual.len() % 40, 0);
-    assert_eq!(expected.len() % 40, 0);
+///
+/// On mismatch, the error lists up to `MAX_MEMORY_DIFF_ENTRIES` differing addresses.
+pub fn assert_memory_eq(actual: &[u8], expected: &[u8]) -> Result<(), MemoryFileError> {
+    assert_memory_eq_with_layout(actual, expected, MemoryLayout::DEFAULT)
+}
 
-    let actual_memory_pairs = read_memory_pairs(actual.as_slice(), 8, 32);
-    let expected_memory_pairs = read_memory_pairs(expected.as_slice(), 8, 32);
+/// Same as `assert_memory_eq`, for memory files with a non-default layout.
+pub fn assert_memory_eq_with_layout(
+    actual: &[u8],
+    expected: &[u8],
+    layout: MemoryLayout,
+) -> Result<(), MemoryFileError> {
+    let actual_memory = memory_pairs_to_hashmap(read_memory_pairs(actual, layout)?)?;
+    let expected_memory = memory_pairs_to_hashmap(read_memory_pairs(expected, layout)?)?;
 
-    let actual_memory = memory_pairs_to_hashmap(actual_memory_pairs);
-    let expected_memory = memory_pairs_to_hashmap(expected_memory_pairs);
+    let mut differences = memory_diff(&actual_memory, &expected_memory);
+    if differences.is_empty() {
+        return Ok(());
+    }
+
+    let total = differences.len();
+    differences.truncate(MAX_MEMORY_DIFF_ENTRIES);
+    Err(MemoryFileError::Mismatch { differences, total })
+}
+
+/// Encodes (address, value) pairs as a memory file.
+fn write_memory_pairs(pairs: &[(u64, Felt252)], layout: MemoryLayout) -> Vec<u8> {
+    let mut memory = Vec::with_capacity(pairs.len() * layout.pair_size());
+    for (address, value) in pairs {
+        let mut address_bytes = address.to_le_bytes().to_vec();
+        address_bytes.resize(layout.addr_size, 0);
+        memory.extend(address_bytes);
+        memory.extend(&value.to_bytes_le()[..layout.felt_size]);
+    }
+    memory
+}
+
+/// Converts a memory file to its canonical form: cells sorted by address, without duplicates.
+///
+/// The Python and Rust VMs list the same cells in a different order, some external tools
+/// require the canonical form. Fails if an address appears more than once with different
+/// values.
+pub fn convert_memory(memory: &[u8], layout: MemoryLayout) -> Result<Vec<u8>, MemoryFileError> {
+    let pairs = canonical_memory_pairs(memory, layout)?;
+    Ok(write_memory_pairs(&pairs, layout))
+}
 
-    assert_eq!(actual_memory, expected_memory);
+fn canonical_memory_pairs(
+    memory: &[u8],
+    layout: MemoryLayout,
+) -> Result<Vec<(u64, Felt252)>, MemoryFileError> {
+    let memory = memory_pairs_to_hashmap(read_memory_pairs(memory, layout)?)?;
+    let mut pairs: Vec<(u64, Felt252)> = memory.into_iter().collect();
+    pairs.sort_unstable_by_key(|(address, _)| *address);
+    Ok(pairs)
 }
 
+/// Keccak-256 hash of the canonical form of a memory file, in the default layout. Two memory
+/// files describe the same memory if and only if they have the same canonical hash.
+pub fn canonical_memory_hash(
+    memory: &[u8],
+    layout: MemoryLayout,
+) -> Result<[u8; 32], MemoryFileError> {
+    let pairs = canonical_memory_pairs(memory, layout)?;
+    let canonical_memory = write_memory_pairs(&pairs, MemoryLayout::DEFAULT);
+    Ok(Keccak256::digest(canonical_memory).into())
+}
+
+/// Checks that two private inputs are equal, ignoring the output builtin.
 pub fn assert_private_input_eq(actual: AirPrivateInput, expected: AirPrivateInput) {
-    let actual_map = {
-        let mut map = actual.0;
-        map.remove(OUTPUT_BUILTIN_NAME);
-        map
-    };
+    let builtins = artifacts::private_input_diff(&actual, &expected);
+    assert!(
+        builtins.is_empty(),
+        "Private input differs for builtin(s): {}",
+        builtins.join(", ")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_pair(address: u64, value: u64) -> Vec<u8> {
+        let mut pair = address.to_le_bytes().to_vec();
+        pair.extend(value.to_le_bytes());
+        pair.extend([0u8; 24]);
+        pair
+    }
+
+    #[test]
+    fn test_read_memory_pairs() {
+        let memory = [memory_pair(1, 5), memory_pair(2, 7)].concat();
+
+        let pairs = read_memory_pairs(memory.as_slice(), MemoryLayout::DEFAULT).unwrap();
+
+        assert_eq!(
+            pairs,
+            vec![(1, Felt252::from(5u64)), (2, Felt252::from(7u64))]
+        );
+    }
+
+    #[test]
+    fn test_read_memory_pairs_truncated() {
+        let memory = [memory_pair(1, 5), memory_pair(2, 7)].concat();
+
+        let result = read_memory_pairs(&memory[..50], MemoryLayout::DEFAULT);
+
+        assert!(matches!(
+            result,
+            Err(MemoryFileError::UnexpectedEof {
+                offset: 40,
+                expected: 40,
+                actual: 10
+            })
+        ));
+    }
 
-    assert_eq!(actual_map, expected.0);
+    #[test]
+    fn test_assert_memory_eq_duplicate_address() {
+        let memory = [memory_pair(1, 5), memory_pair(1, 7)].concat();
+
+        let result = assert_memory_eq(&memory, &memory);
+
+        assert!(matches!(
+            result,
+            Err(MemoryFileError::DuplicateAddress { address: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_assert_memory_eq_ignores_order() {
+        let actual = [memory_pair(1, 5), memory_pair(2, 7)].concat();
+        let expected = [memory_pair(2, 7), memory_pair(1, 5)].concat();
+
+        assert!(assert_memory_eq(&actual, &expected).is_ok());
+    }
+
+    /// The fibonacci memory fixture, with its cells in reverse order.
+    fn shuffled_fibonacci_memory() -> (Vec<u8>, Vec<u8>) {
+        let memory =
+            std::fs::read(get_test_case_file_path("fibonacci/fibonacci_memory.bin")).unwrap();
+        let shuffled_memory: Vec<u8> = memory
+            .chunks(MemoryLayout::DEFAULT.pair_size())
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        assert_ne!(memory, shuffled_memory);
+        (memory, shuffled_memory)
+    }
+
+    #[test]
+    fn test_convert_memory() {
+        let (memory, shuffled_memory) = shuffled_fibonacci_memory();
+
+        let converted_memory = convert_memory(&memory, MemoryLayout::DEFAULT).unwrap();
+        let converted_shuffled_memory =
+            convert_memory(&shuffled_memory, MemoryLayout::DEFAULT).unwrap();
+
+        assert_eq!(converted_memory, converted_shuffled_memory);
+        assert!(assert_memory_eq(&converted_memory, &memory).is_ok());
+        // The conversion is idempotent
+        assert_eq!(
+            convert_memory(&converted_memory, MemoryLayout::DEFAULT).unwrap(),
+            converted_memory
+        );
+    }
+
+    #[test]
+    fn test_convert_memory_sorts_and_deduplicates() {
+        let memory = [memory_pair(2, 7), memory_pair(1, 5), memory_pair(2, 7)].concat();
+
+        let converted_memory = convert_memory(&memory, MemoryLayout::DEFAULT).unwrap();
+
+        assert_eq!(
+            converted_memory,
+            [memory_pair(1, 5), memory_pair(2, 7)].concat()
+        );
+    }
+
+    #[test]
+    fn test_convert_memory_conflicting_duplicates() {
+        let memory = [memory_pair(1, 5), memory_pair(1, 7)].concat();
+
+        let result = convert_memory(&memory, MemoryLayout::DEFAULT);
+
+        assert!(matches!(
+            result,
+            Err(MemoryFileError::DuplicateAddress { address: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_convert_memory_with_layout() {
+        let layout = MemoryLayout {
+            addr_size: 4,
+            felt_size: 8,
+        };
+        let pair = |address: u32, value: u64| {
+            [
+                address.to_le_bytes().as_slice(),
+                value.to_le_bytes().as_slice(),
+            ]
+            .concat()
+        };
+        let memory = [pair(2, 7), pair(1, 5)].concat();
+
+        let converted_memory = convert_memory(&memory, layout).unwrap();
+
+        assert_eq!(converted_memory, [pair(1, 5), pair(2, 7)].concat());
+    }
+
+    #[test]
+    fn test_canonical_memory_hash() {
+        let (memory, shuffled_memory) = shuffled_fibonacci_memory();
+
+        assert_eq!(
+            canonical_memory_hash(&memory, MemoryLayout::DEFAULT).unwrap(),
+            canonical_memory_hash(&shuffled_memory, MemoryLayout::DEFAULT).unwrap()
+        );
+
+        let other_memory = memory_pair(1, 5);
+        assert_ne!(
+            canonical_memory_hash(&memory, MemoryLayout::DEFAULT).unwrap(),
+            canonical_memory_hash(&other_memory, MemoryLayout::DEFAULT).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_assert_memory_eq_bounded_diff() {
+        let n_cells = MAX_MEMORY_DIFF_ENTRIES as u64 + 5;
+        let actual: Vec<u8> = (0..n_cells).flat_map(|i| memory_pair(i, i)).collect();
+        let expected: Vec<u8> = (0..n_cells).flat_map(|i| memory_pair(i, i + 1)).collect();
+
+        let error = assert_memory_eq(&actual, &expected).unwrap_err();
+
+        match &error {
+            MemoryFileError::Mismatch { differences, total } => {
+                assert_eq!(*total, n_cells as usize);
+                assert_eq!(differences.len(), MAX_MEMORY_DIFF_ENTRIES);
+                assert_eq!(differences[0].address, 0);
+            }
+            _ => panic!("unexpected error: {error}"),
+        }
+        assert!(error.to_string().ends_with("... and 5 more"));
+    }
 }